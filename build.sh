@@ -44,6 +44,7 @@ for arg in "$@"; do
     --ios)     PLATFORM="ios" ;;
     --android) PLATFORM="android" ;;
     --windows) PLATFORM="windows" ;;
+    --dnssd)   CARGO_ARGS+=("--features" "dnssd") ;;
     --help|-h)
       echo "Usage: ./build.sh [OPTIONS]"
       echo ""
@@ -0,0 +1,1520 @@
+//! `MdnsAdvertiser` — announces a service so other devices can find this machine.
+
+use crate::backend;
+use crate::browser::{self, SHUTDOWN_ERROR};
+use crate::cache::{self, CachedService};
+use crate::daemon::{self, DaemonPoll};
+use crate::interfaces::{self, ScopedAddress};
+use crate::ipv6_privacy;
+use crate::lifecycle;
+use crate::main_thread;
+use crate::naming::{self, NameSuffix};
+use godot::classes::notify::NodeNotification;
+use godot::classes::Engine;
+use godot::prelude::*;
+use mdns_sd::{DaemonEvent, IfKind, ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Advertises an mDNS service so that other nodes/devices on the LAN can
+/// discover this machine via [`MdnsBrowser`].
+///
+/// ## GDScript example
+/// ```gdscript
+/// var adv := MdnsAdvertiser.new()
+/// add_child(adv)
+/// adv.advertise_error.connect(func(msg): push_error("mDNS: " + msg))
+///
+/// # Announce the Nakama server port so clients on the LAN can find it
+/// var ok := adv.advertise("My Game Server", "_mygame._tcp.local.", 7350, {
+///     "version": "1.0",
+///     "region": "eu-west",
+/// })
+/// if ok:
+///     print("mDNS service registered")
+/// ```
+///
+/// Alternatively fill in `instance_name`, `service_type` and `port` in the
+/// inspector and tick `autostart`; the node then advertises on `ready()`.
+/// Explicit `advertise()` calls always win: one made before `ready()`
+/// suppresses the autostart, and later ones replace the registration.  In
+/// the editor (the class is a tool class) `autostart` is ignored.
+#[derive(GodotClass)]
+#[class(tool, base = Node)]
+pub struct MdnsAdvertiser {
+    /// Instance name used by `autostart`, e.g. `"My Game Server"`.
+    #[export]
+    instance_name: GString,
+    /// Service type used by `autostart`, e.g. `"_mygame._tcp.local."`.
+    #[export]
+    service_type: GString,
+    /// Port used by `autostart`.
+    #[export]
+    port: i64,
+    /// Call `advertise()` with the exported values on `ready()`.
+    #[export]
+    autostart: bool,
+    /// Clone of the shared daemon.  Kept alive so the service stays registered.
+    /// Dropped (without `shutdown()`) in `stop_advertising()`.
+    daemon: Option<ServiceDaemon>,
+    /// Registration on the system DNS-SD backend; see `MdnsManager.set_backend()`.
+    system: Option<backend::Advertisement>,
+    /// Shared-daemon generation the registration belongs to.  See `daemon::reset()`.
+    shared_generation: Option<u64>,
+    /// The registration being re-established follows `daemon::recover()`: emit
+    /// `recovered` once it succeeds.
+    recovering: bool,
+    /// Set while `registration` waits for the shared daemon to finish being
+    /// created in the background.
+    pending_daemon: Option<PendingDaemon>,
+    fullname: Option<String>,
+    /// Parameters of the active registration, kept so TXT updates can re-announce.
+    registration: Option<Registration>,
+    /// Explicit addresses from `set_advertise_scoped_addresses()`; empty means
+    /// let `mdns-sd` pick every local address.
+    scoped_addresses: Vec<ScopedAddress>,
+    /// Address families from `set_advertise_families()`.
+    families: Families,
+    /// Suffix scheme from `set_name_suffix_scheme()`.
+    name_suffix: NameSuffix,
+    /// Host name from `set_custom_host_fqdn()`, checked and normalized;
+    /// `None` = `"<this machine>.local."`.
+    custom_host: Option<String>,
+    /// Minimum spacing between TXT-driven re-announcements.
+    min_reannounce_interval: Duration,
+    /// When the service was last (re-)registered with the daemon.
+    last_announce: Option<Instant>,
+    /// A TXT update arrived inside the throttle window and still has to be announced.
+    reannounce_pending: bool,
+    /// Daemon monitor subscription, used to observe answered queries.
+    monitor: Option<mdns_sd::Receiver<DaemonEvent>>,
+    /// Answered-query counts per interface name for the current rate-limit window.
+    query_counts: BTreeMap<String, i64>,
+    /// Start of the current `query_received` window; `None` until a query arrives.
+    query_window_start: Option<Instant>,
+    /// In-flight `verify_advertisement()` self-browse.
+    verification: Option<Verification>,
+    /// Watch for foreign hosts answering for our name; see `set_hijack_watch()`.
+    watch_hijack: bool,
+    /// Browse backing the hijack watch while advertising.
+    hijack_watch: Option<HijackWatch>,
+    /// From `set_description()`; empty = none.
+    description: String,
+    /// From `set_available()`; `None` until first called, announcing no
+    /// status entry at all.
+    available: Option<bool>,
+    /// Port of this node's own daemon; see `set_daemon_port()`.
+    daemon_port: Option<u16>,
+    /// Port of `daemon` when it is this node's own rather than the shared one.
+    own_port: Option<u16>,
+    /// Handle `APPLICATION_PAUSED` / `RESUMED`; see `set_handle_app_lifecycle()`.
+    app_lifecycle: bool,
+    /// When the app was paused, while it is.
+    app_paused_at: Option<Instant>,
+    /// Re-announce on resume; see `set_reannounce_on_resume()`.
+    reannounce_on_resume: bool,
+    base: Base<Node>,
+}
+
+/// Minimum interval between two `query_received` emissions for the same interface.
+const QUERY_SIGNAL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Default for `set_min_reannounce_interval_ms()`.
+const DEFAULT_MIN_REANNOUNCE_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// A registration waiting for the shared daemon; see `daemon::poll_shared_daemon()`.
+struct PendingDaemon {
+    /// Creation attempt being waited on.
+    attempt: u64,
+    /// Emit `daemon_reset` once registered, because this wait follows a reset.
+    after_reset: bool,
+}
+
+/// Default for `verify_advertisement()` when `timeout_ms` is not positive.
+const DEFAULT_VERIFY_TIMEOUT_MS: u64 = 3000;
+
+/// A `verify_advertisement()` in progress.
+///
+/// Runs on a throwaway private daemon rather than the shared one: `mdns-sd`
+/// keeps a single listener per browsed type, so a self-browse on the shared
+/// daemon would steal the events of any `MdnsBrowser` browsing the same type.
+struct Verification {
+    daemon: ServiceDaemon,
+    receiver: mdns_sd::Receiver<ServiceEvent>,
+    service_type: String,
+    deadline: Instant,
+}
+
+impl Verification {
+    fn stop(self) {
+        let _ = self.daemon.stop_browse(&self.service_type);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// The browse behind `set_hijack_watch()`.
+///
+/// Like `Verification` it runs on a private daemon, for the same reason; it
+/// only browses, so the advertisement itself stays on the shared daemon.
+struct HijackWatch {
+    daemon: ServiceDaemon,
+    receiver: mdns_sd::Receiver<ServiceEvent>,
+    service_type: String,
+    /// Foreign addresses last reported, so each change is reported once.
+    reported: Vec<IpAddr>,
+}
+
+impl HijackWatch {
+    fn stop(self) {
+        let _ = self.daemon.stop_browse(&self.service_type);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// Everything needed to rebuild the `ServiceInfo` for a re-announcement.
+struct Registration {
+    service_type: String,
+    instance_name: String,
+    /// `"hostname.local."` for this machine, or `set_custom_host_fqdn()`'s.
+    hostname: String,
+    port: u16,
+    txt: Vec<(String, String)>,
+    /// Addresses to announce; empty = all local addresses.
+    addresses: Vec<ScopedAddress>,
+    families: Families,
+}
+
+/// Address families to announce; see `set_advertise_families()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Families {
+    Both,
+    V4Only,
+    V6Only,
+}
+
+impl Families {
+    fn from_mode(mode: i64) -> Option<Self> {
+        match mode {
+            MdnsAdvertiser::ADDRESS_FAMILY_BOTH => Some(Self::Both),
+            MdnsAdvertiser::ADDRESS_FAMILY_IPV4 => Some(Self::V4Only),
+            MdnsAdvertiser::ADDRESS_FAMILY_IPV6 => Some(Self::V6Only),
+            _ => None,
+        }
+    }
+
+    fn as_mode(self) -> i64 {
+        match self {
+            Self::Both => MdnsAdvertiser::ADDRESS_FAMILY_BOTH,
+            Self::V4Only => MdnsAdvertiser::ADDRESS_FAMILY_IPV4,
+            Self::V6Only => MdnsAdvertiser::ADDRESS_FAMILY_IPV6,
+        }
+    }
+
+    fn admits(self, ip: &IpAddr) -> bool {
+        match self {
+            Self::Both => true,
+            Self::V4Only => ip.is_ipv4(),
+            Self::V6Only => ip.is_ipv6(),
+        }
+    }
+
+    /// Address selection for automatic mode that leaves out temporary IPv6
+    /// privacy addresses: every IPv4 address (if wanted) plus each stable
+    /// IPv6 one by value.  `None` if there is no temporary address to leave
+    /// out, keeping the plain family selection that follows address changes.
+    fn without_temporary_ipv6(self) -> Option<Vec<IfKind>> {
+        if self == Self::V4Only {
+            return None;
+        }
+        let temporary = ipv6_privacy::temporary_addresses();
+        if temporary.is_empty() {
+            return None;
+        }
+        let mut kinds = Vec::new();
+        if self == Self::Both {
+            kinds.push(IfKind::IPv4);
+        }
+        kinds.extend(
+            interfaces::local_addresses()
+                .into_iter()
+                .filter(|ip| matches!(ip, IpAddr::V6(v6) if !temporary.contains(v6)))
+                .map(IfKind::Addr),
+        );
+        Some(kinds)
+    }
+}
+
+impl Registration {
+    fn to_service_info(&self) -> Result<ServiceInfo, String> {
+        let props: Vec<(&str, &str)> = self
+            .txt
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        let addresses: Vec<&ScopedAddress> = self
+            .addresses
+            .iter()
+            .filter(|a| self.families.admits(&a.ip))
+            .collect();
+        if addresses.is_empty() && !self.addresses.is_empty() {
+            return Err(format!(
+                "none of the explicit addresses is {}",
+                if self.families == Families::V4Only { "IPv4" } else { "IPv6" }
+            ));
+        }
+        let ips: Vec<IpAddr> = addresses.iter().map(|a| a.ip).collect();
+        let mut info = ServiceInfo::new(
+            &self.service_type,
+            &self.instance_name,
+            &self.hostname,
+            ips.as_slice(),
+            self.port,
+            props.as_slice(),
+        )
+        .map_err(|e| format!("Failed to build ServiceInfo: {e}"))?;
+        if addresses.is_empty() {
+            // No explicit addresses → mdns-sd fills in (and keeps up to date)
+            // the addresses of every local interface of the wanted families.
+            info = info.enable_addr_auto();
+            if let Some(kinds) = self.families.without_temporary_ipv6() {
+                info.set_interfaces(kinds);
+            } else {
+                match self.families {
+                    Families::Both => {}
+                    Families::V4Only => info.set_interfaces(vec![IfKind::IPv4]),
+                    Families::V6Only => info.set_interfaces(vec![IfKind::IPv6]),
+                }
+            }
+        } else if addresses.iter().any(|a| a.scope.is_some()) {
+            // A zone only means something if the service is confined to the
+            // zoned interfaces; unzoned addresses keep the interface that owns them.
+            info.set_interfaces(addresses.iter().map(|a| a.interface()).collect());
+        }
+        Ok(info)
+    }
+}
+
+#[godot_api]
+impl INode for MdnsAdvertiser {
+    fn init(base: Base<Node>) -> Self {
+        Self {
+            instance_name: GString::new(),
+            service_type: GString::new(),
+            port: 0,
+            autostart: false,
+            daemon: None,
+            system: None,
+            shared_generation: None,
+            recovering: false,
+            pending_daemon: None,
+            fullname: None,
+            registration: None,
+            scoped_addresses: Vec::new(),
+            families: Families::Both,
+            name_suffix: NameSuffix::None,
+            custom_host: None,
+            min_reannounce_interval: DEFAULT_MIN_REANNOUNCE_INTERVAL,
+            last_announce: None,
+            reannounce_pending: false,
+            monitor: None,
+            query_counts: BTreeMap::new(),
+            query_window_start: None,
+            verification: None,
+            watch_hijack: false,
+            hijack_watch: None,
+            description: String::new(),
+            available: None,
+            daemon_port: None,
+            own_port: None,
+            app_lifecycle: lifecycle::DEFAULT_ENABLED,
+            app_paused_at: None,
+            reannounce_on_resume: true,
+            base,
+        }
+    }
+
+    /// Advertise the exported `instance_name` / `service_type` / `port` if
+    /// `autostart` is set, except in the editor.  An `advertise()` issued
+    /// before the node entered the tree takes precedence.
+    fn ready(&mut self) {
+        if !self.autostart || self.is_advertising() || Engine::singleton().is_editor_hint() {
+            return;
+        }
+        if self.instance_name.is_empty() || self.service_type.is_empty() || self.port <= 0 {
+            self.emit_adv_error(
+                "autostart: instance_name, service_type and port must all be set".to_string(),
+            );
+            return;
+        }
+        let (instance_name, service_type) = (self.instance_name.clone(), self.service_type.clone());
+        self.advertise(instance_name, service_type, self.port, VarDictionary::new());
+    }
+
+    /// Poll the daemon monitor channel every frame for answered queries, and
+    /// flush any throttled TXT update whose interval has elapsed.
+    fn process(&mut self, _delta: f64) {
+        self.drain_system();
+        self.follow_daemon_reset();
+        self.complete_pending_registration();
+        self.drain_monitor();
+        self.drain_verification();
+        self.drain_hijack_watch();
+        if self.reannounce_pending && self.reannounce_allowed() && self.app_paused_at.is_none() {
+            self.reannounce();
+        }
+    }
+
+    fn on_notification(&mut self, what: NodeNotification) {
+        if what == NodeNotification::APPLICATION_PAUSED && self.app_lifecycle {
+            self.app_paused_at.get_or_insert_with(Instant::now);
+        }
+        if what == NodeNotification::APPLICATION_RESUMED {
+            self.resume_after_pause();
+        }
+    }
+
+    /// Automatically unregister and clean up when the node leaves the tree.
+    fn exit_tree(&mut self) {
+        self.stop_advertising();
+    }
+}
+
+#[godot_api]
+impl MdnsAdvertiser {
+    // ── Constants ────────────────────────────────────────────────────────────
+
+    /// `set_advertise_families()`: announce IPv4 and IPv6 addresses (default).
+    #[constant]
+    const ADDRESS_FAMILY_BOTH: i64 = 0;
+    /// `set_advertise_families()`: announce IPv4 addresses only.
+    #[constant]
+    const ADDRESS_FAMILY_IPV4: i64 = 1;
+    /// `set_advertise_families()`: announce IPv6 addresses only.
+    #[constant]
+    const ADDRESS_FAMILY_IPV6: i64 = 2;
+    /// `set_name_suffix_scheme()`: register the instance name as given (default).
+    #[constant]
+    const NAME_SUFFIX_NONE: i64 = 0;
+    /// `set_name_suffix_scheme()`: append four random hex digits, `"Server (3fa9)"`.
+    #[constant]
+    const NAME_SUFFIX_RANDOM: i64 = 1;
+    /// `set_name_suffix_scheme()`: append the short host name, `"Server (build-07)"`.
+    #[constant]
+    const NAME_SUFFIX_HOSTNAME: i64 = 2;
+    /// `set_name_suffix_scheme()`: append a per-process counter, `"Server (2)"`.
+    #[constant]
+    const NAME_SUFFIX_COUNTER: i64 = 3;
+
+    // ── Signals ──────────────────────────────────────────────────────────────
+
+    /// Emitted if registration or any internal mDNS error occurs.  A message
+    /// starting with `"shutdown"` means `MdnsManager.shutdown_all()` ended
+    /// the advertisement; the node is idle until the next `advertise()`.
+    #[signal]
+    fn advertise_error(message: GString);
+
+    /// Emitted when the daemon has answered queries from the LAN while this
+    /// node is advertising — i.e. someone is actively looking for us.
+    ///
+    /// Rate-limited to at most one emission per second per interface; `count`
+    /// is the number of responses sent on that interface since the last one.
+    ///
+    /// Level of detail: `mdns-sd` only reports *that* a multicast response was
+    /// sent and on which interface.  It does not say which registered service
+    /// the question matched or who asked, so with several advertisers in one
+    /// process every advertiser sees every answered query.
+    ///
+    /// Parameters:
+    ///   from_interface — interface name the response went out on, e.g. "en0"
+    ///   count          — responses sent on that interface in this window
+    #[signal]
+    fn query_received(from_interface: GString, count: i64);
+
+    /// Emitted after `MdnsManager.reset_shared_daemon()` once this node has
+    /// re-registered its service on the new daemon (or failed to, in which
+    /// case `advertise_error` precedes it and the node stops advertising).
+    #[signal]
+    fn daemon_reset();
+
+    /// Emitted after `daemon_reset` when the reset was an automatic recovery
+    /// from a failed daemon (see `MdnsManager.set_auto_recover()`) and the
+    /// service is registered again.  Peers may have timed it out meanwhile.
+    #[signal]
+    fn recovered();
+
+    /// Result of `verify_advertisement()`: `true` once the service was seen
+    /// answering on the network, `false` on timeout or if advertising stopped
+    /// before it was seen.
+    #[signal]
+    fn advertisement_verified(visible: bool);
+
+    /// Emitted while `set_hijack_watch(true)` is on when our registered name
+    /// resolves to addresses that do not belong to this machine — another
+    /// device is advertising the same fullname, by accident or on purpose.
+    /// Emitted again whenever that set of foreign addresses changes.
+    ///
+    /// Parameters:
+    ///   name              — our registered fullname
+    ///   foreign_addresses — the addresses that are not ours, IPv4 first
+    #[signal]
+    fn name_hijacked(name: GString, foreign_addresses: PackedStringArray);
+
+    // ── Methods ──────────────────────────────────────────────────────────────
+
+    /// Register an mDNS service.
+    ///
+    /// - `instance_name` — human-readable label, e.g. `"Mark's Server"`.  
+    ///   Must be unique among instances of the same `service_type` on the LAN.
+    /// - `service_type`  — e.g. `"_mygame._tcp.local."` (trailing dot required).
+    /// - `port`          — the port your service actually listens on.
+    /// - `txt_records`   — optional String→String Dictionary added to the TXT record.
+    ///
+    /// Returns `true` on success. On failure, `false` is returned and
+    /// `advertise_error` is emitted with a description.
+    ///
+    /// Calling `advertise()` while already advertising quietly stops the
+    /// previous registration first.
+    ///
+    /// If the shared daemon is still being created in the background (first
+    /// use, or after `MdnsManager.warm_up()`), `true` means the service is
+    /// valid and will be registered on the first frame after the daemon is
+    /// ready; a creation failure is reported through `advertise_error`.
+    #[func]
+    pub(crate) fn advertise(
+        &mut self,
+        instance_name: GString,
+        service_type: GString,
+        port: i64,
+        txt_records: VarDictionary,
+    ) -> bool {
+        // Build TXT record properties.
+        let txt: Vec<(String, String)> = txt_records
+            .iter_shared()
+            .filter_map(|(k, v)| {
+                let key = k.try_to::<GString>().ok()?.to_string();
+                let val = v.try_to::<GString>().ok()?.to_string();
+                Some((key, val))
+            })
+            .collect();
+
+        let mut txt = txt;
+        if !self.description.is_empty() {
+            set_txt_entry(&mut txt, cache::DESCRIPTION_KEY, &self.description);
+        }
+        if let Some(available) = self.available {
+            set_txt_entry(&mut txt, cache::STATUS_KEY, status_value(available));
+        }
+        self.register_new(
+            service_type.to_string(),
+            instance_name.to_string(),
+            port.clamp(1, 65535) as u16,
+            txt,
+        )
+    }
+
+    /// Re-advertise a service discovered by an `MdnsBrowser` in the same scene
+    /// tree under this host's name and addresses — same instance name, type,
+    /// port and TXT records.  This is the building block for a simple mDNS
+    /// relay bridging two LAN segments (e.g. restrict this host's addresses
+    /// with `set_advertise_scoped_addresses()` to the other segment).
+    ///
+    /// `name` is the fullname as reported by `service_discovered`.  Requires a
+    /// browser that currently has the service cached; returns `false` and
+    /// emits `advertise_error` otherwise.  Replaces any active registration.
+    ///
+    /// Re-advertising on the segment the service came from collides with the
+    /// original and should be avoided.
+    #[func]
+    fn re_advertise_discovered(&mut self, name: GString) -> bool {
+        let fullname = name.to_string();
+        let Some(service) = self.find_discovered(&fullname) else {
+            self.emit_adv_error(format!(
+                "re_advertise_discovered: no MdnsBrowser in the scene tree has '{fullname}' cached"
+            ));
+            return false;
+        };
+        let Some(instance_name) = instance_name_of(&service.fullname, &service.service_type) else {
+            self.emit_adv_error(format!(
+                "re_advertise_discovered: '{fullname}' is not an instance of '{}'",
+                service.service_type
+            ));
+            return false;
+        };
+        self.register_new(service.service_type, instance_name, service.port, service.txt)
+    }
+
+    /// Set (or add) a single TXT record key while advertising, e.g. the current
+    /// player count, and re-announce the service with the new value.
+    ///
+    /// Re-announcements are throttled: at most one per
+    /// `set_min_reannounce_interval_ms()` interval.  Updates arriving inside the
+    /// interval are coalesced and the latest TXT state is announced as soon as
+    /// it elapses, so the final value always goes out even if updates stop.
+    ///
+    /// Returns `false` (and emits `advertise_error`) when not advertising.
+    #[func]
+    fn set_txt_record(&mut self, key: GString, value: GString) -> bool {
+        if !self.update_txt("set_txt_record", key, value) {
+            return false;
+        }
+        self.schedule_reannounce();
+        true
+    }
+
+    /// Like `set_txt_record()`, but announces right away, ignoring the
+    /// re-announce throttle — for values peers should see immediately, such
+    /// as the player count.
+    ///
+    /// Only the TXT record changes: the service is registered again under the
+    /// same fullname, port and addresses, which `mdns-sd` sends as an update
+    /// announcement.  Browsers see an in-place `service_updated`, never a
+    /// `service_removed` followed by `service_discovered`.  That behaviour is
+    /// part of `mdns-sd`'s protocol handling, not of the platform's sockets,
+    /// so it is the same everywhere; `t17` in `tests/mdns_loopback.rs` checks
+    /// it wherever same-machine loopback works.
+    ///
+    /// Returns `false` (and emits `advertise_error`) when not advertising.
+    #[func]
+    fn bump_txt(&mut self, key: GString, value: GString) -> bool {
+        if !self.update_txt("bump_txt", key, value) {
+            return false;
+        }
+        self.reannounce();
+        true
+    }
+
+    /// Announce a longer, human-readable description (e.g. `"Capture the
+    /// flag, 3/8 players, no mods"`) next to the short instance name.  It goes
+    /// out as the TXT entry `desc` — the key every game using this extension
+    /// agrees on — and browsers list it as `description` in
+    /// `get_discovered_services()`.
+    ///
+    /// Applies to every later `advertise()`, overriding a `desc` key in its
+    /// `txt_records`, and re-announces right away (subject to the re-announce
+    /// throttle) while advertising.  An empty `text` removes the entry.
+    /// Returns `false` (and emits `advertise_error`) if `text` is longer than
+    /// 250 bytes, the most one TXT entry can hold.
+    #[func]
+    fn set_description(&mut self, text: GString) -> bool {
+        let text = text.to_string();
+        if text.len() > cache::MAX_DESCRIPTION_LEN {
+            self.emit_adv_error(format!(
+                "set_description: {} bytes is longer than the {} a TXT entry can hold",
+                text.len(),
+                cache::MAX_DESCRIPTION_LEN
+            ));
+            return false;
+        }
+        self.description = text;
+        let Some(registration) = self.registration.as_mut() else {
+            return true;
+        };
+        if self.description.is_empty() {
+            registration
+                .txt
+                .retain(|(key, _)| !key.eq_ignore_ascii_case(cache::DESCRIPTION_KEY));
+        } else {
+            set_txt_entry(&mut registration.txt, cache::DESCRIPTION_KEY, &self.description);
+        }
+        self.schedule_reannounce();
+        true
+    }
+
+    /// Returns the `set_description()` text; empty if none.
+    #[func]
+    fn get_description(&self) -> GString {
+        GString::from(&self.description)
+    }
+
+    /// Mark the server as taking players or not — e.g. `false` during
+    /// maintenance — without leaving the list, so clients can grey it out
+    /// instead of dropping it.  Announced as the TXT entry `status`, `up` or
+    /// `maintenance`, which browsers list as the `available` bool in
+    /// `get_discovered_services()` (a service announcing no `status` counts
+    /// as available).
+    ///
+    /// Applies to every later `advertise()`, overriding a `status` key in its
+    /// `txt_records`.  While advertising only that entry changes and the
+    /// update goes out at once, as with `bump_txt()`.
+    #[func]
+    fn set_available(&mut self, available: bool) {
+        self.available = Some(available);
+        let Some(registration) = self.registration.as_mut() else {
+            return;
+        };
+        set_txt_entry(&mut registration.txt, cache::STATUS_KEY, status_value(available));
+        self.reannounce();
+    }
+
+    /// `false` after `set_available(false)`, until `set_available(true)`.
+    #[func]
+    fn is_available(&self) -> bool {
+        self.available.unwrap_or(true)
+    }
+
+    /// Announce exactly these addresses instead of every local address, for
+    /// multi-homed servers.  Entries are `"ip"` or `"ip%scope"` strings, e.g.
+    /// `"192.168.1.10"` or `"fe80::1%eth0"`; a zone confines the service to that
+    /// interface (by name — numeric zone ids are not supported).  An empty
+    /// array restores auto-detection.
+    ///
+    /// Takes effect on the next `advertise()`, or immediately (subject to the
+    /// re-announce throttle) while advertising.  If any entry fails to parse,
+    /// nothing changes, `advertise_error` lists every bad entry and `false` is
+    /// returned.
+    #[func]
+    fn set_advertise_scoped_addresses(&mut self, addresses: PackedStringArray) -> bool {
+        let specs: Vec<String> = addresses.as_slice().iter().map(GString::to_string).collect();
+        let parsed = match interfaces::parse_scoped_addresses(&specs) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                self.emit_adv_error(format!("set_advertise_scoped_addresses: {e}"));
+                return false;
+            }
+        };
+        self.scoped_addresses = parsed;
+        if let Some(registration) = self.registration.as_mut() {
+            registration.addresses = self.scoped_addresses.clone();
+            self.schedule_reannounce();
+        }
+        true
+    }
+
+    /// Restrict the announced addresses to one family, e.g. `ADDRESS_FAMILY_IPV4`
+    /// for a server only reachable over IPv4.  Applies to the auto-detected
+    /// addresses and equally filters `set_advertise_scoped_addresses()`; if that
+    /// leaves no explicit address, advertising fails with `advertise_error`.
+    ///
+    /// Auto-detected IPv6 addresses never include temporary privacy addresses
+    /// (detected on Linux, Android and Windows), which the OS rotates every
+    /// few hours; the interface's stable address is announced instead.  While
+    /// such addresses exist, IPv6 addresses that appear later are only picked
+    /// up by the next `advertise()` or re-announcement.
+    ///
+    /// Takes effect on the next `advertise()`, or immediately (subject to the
+    /// re-announce throttle) while advertising.  Returns `false` for an unknown
+    /// mode.
+    #[func]
+    fn set_advertise_families(&mut self, mode: i64) -> bool {
+        let Some(families) = Families::from_mode(mode) else {
+            self.emit_adv_error(format!("set_advertise_families: unknown mode {mode}"));
+            return false;
+        };
+        self.families = families;
+        if let Some(registration) = self.registration.as_mut() {
+            registration.families = families;
+            self.schedule_reannounce();
+        }
+        true
+    }
+
+    /// Returns the `ADDRESS_FAMILY_*` mode set by `set_advertise_families()`.
+    #[func]
+    fn get_advertise_families(&self) -> i64 {
+        self.families.as_mode()
+    }
+
+    /// Append a suffix to the instance name of every later `advertise()` /
+    /// `re_advertise_discovered()`, e.g. `NAME_SUFFIX_HOSTNAME` so a fleet of
+    /// servers sharing one label stays distinguishable.  The suffix is picked
+    /// once per registration and kept across daemon resets;
+    /// `get_registered_name()` reports the suffixed fullname.  Names that would
+    /// exceed 63 bytes are shortened to keep the suffix.
+    ///
+    /// Does not rename an active registration.  Returns `false` for an unknown
+    /// scheme.
+    #[func]
+    fn set_name_suffix_scheme(&mut self, scheme: i64) -> bool {
+        self.name_suffix = match scheme {
+            Self::NAME_SUFFIX_NONE => NameSuffix::None,
+            Self::NAME_SUFFIX_RANDOM => NameSuffix::Random,
+            Self::NAME_SUFFIX_HOSTNAME => NameSuffix::Hostname,
+            Self::NAME_SUFFIX_COUNTER => NameSuffix::Counter,
+            _ => {
+                self.emit_adv_error(format!("set_name_suffix_scheme: unknown scheme {scheme}"));
+                return false;
+            }
+        };
+        true
+    }
+
+    /// Returns the `NAME_SUFFIX_*` scheme set by `set_name_suffix_scheme()`.
+    #[func]
+    fn get_name_suffix_scheme(&self) -> i64 {
+        match self.name_suffix {
+            NameSuffix::None => Self::NAME_SUFFIX_NONE,
+            NameSuffix::Random => Self::NAME_SUFFIX_RANDOM,
+            NameSuffix::Hostname => Self::NAME_SUFFIX_HOSTNAME,
+            NameSuffix::Counter => Self::NAME_SUFFIX_COUNTER,
+        }
+    }
+
+    /// Announce `fqdn` as the service's host (the SRV target) instead of
+    /// `"<this machine>.local."`, e.g. `"arcade-01.local."` so clients keep
+    /// one stable name across the machines that take turns serving.  A
+    /// missing trailing dot is added; `""` goes back to the machine's name.
+    /// Takes effect on the next `advertise()`.
+    ///
+    /// The daemon answers for `fqdn` with this machine's addresses, so it
+    /// must be a name nothing else on the LAN claims, or two hosts answer
+    /// for it and clients connect to either.  The name has to be under
+    /// `.local.`: `mdns-sd` registers no other host name, so pointing the SRV
+    /// record at a unicast DNS name (a router-provided `*.lan` or
+    /// `*.home.arpa` name) is not possible.  Returns `false` (and emits
+    /// `advertise_error`) for a name that is not a valid DNS name under
+    /// `.local.`.
+    #[func]
+    fn set_custom_host_fqdn(&mut self, fqdn: GString) -> bool {
+        let fqdn = fqdn.to_string();
+        if fqdn.trim().is_empty() {
+            self.custom_host = None;
+            return true;
+        }
+        match naming::check_host_fqdn(&fqdn) {
+            Ok(host) => {
+                self.custom_host = Some(host);
+                true
+            }
+            Err(e) => {
+                self.emit_adv_error(format!("set_custom_host_fqdn: {e}"));
+                false
+            }
+        }
+    }
+
+    /// The host name set with `set_custom_host_fqdn()`, normalized, or `""`.
+    #[func]
+    fn get_custom_host_fqdn(&self) -> GString {
+        GString::from(self.custom_host.as_deref().unwrap_or(""))
+    }
+
+    /// Check that the advertised service is actually discoverable, e.g. before
+    /// showing a lobby code: browses for the service's type from a separate,
+    /// short-lived daemon with multicast loopback on, and emits
+    /// `advertisement_verified(true)` as soon as this service resolves, or
+    /// `advertisement_verified(false)` after `timeout_ms` (default 3000 ms when
+    /// `timeout_ms <= 0`).  The verification browse is torn down either way.
+    ///
+    /// Can be called right after `advertise()`.  A second call restarts the
+    /// check.  Returns `false` (and emits `advertise_error`) when not
+    /// advertising or if the verification browse could not be started.
+    #[func]
+    fn verify_advertisement(&mut self, timeout_ms: i64) -> bool {
+        // Restarting supersedes the previous check without reporting it.
+        if let Some(previous) = self.verification.take() {
+            previous.stop();
+        }
+        let Some(registration) = &self.registration else {
+            self.emit_adv_error("verify_advertisement: not advertising".to_string());
+            return false;
+        };
+        let service_type = registration.service_type.clone();
+        let timeout = if timeout_ms > 0 {
+            timeout_ms as u64
+        } else {
+            DEFAULT_VERIFY_TIMEOUT_MS
+        };
+        let started = daemon::private_daemon_on(Default::default(), self.own_port).and_then(|d| {
+            // The answer comes from our own shared daemon on this host, so the
+            // query and response must loop back through the local stack.
+            let _ = d.set_multicast_loop_v4(true);
+            let _ = d.set_multicast_loop_v6(true);
+            match d.browse(&service_type) {
+                Ok(receiver) => Ok((d, receiver)),
+                Err(e) => {
+                    let _ = d.shutdown();
+                    Err(format!("verify_advertisement: browse failed: {e}"))
+                }
+            }
+        });
+        match started {
+            Ok((daemon, receiver)) => {
+                self.verification = Some(Verification {
+                    daemon,
+                    receiver,
+                    service_type,
+                    deadline: Instant::now() + Duration::from_millis(timeout),
+                });
+                true
+            }
+            Err(e) => {
+                self.emit_adv_error(e);
+                false
+            }
+        }
+    }
+
+    /// Watch the network for another device answering for our registered
+    /// name and report it through `name_hijacked`.  Off by default; may be
+    /// toggled at any time and applies to every later `advertise()`.
+    ///
+    /// The watch browses our service type on a private daemon for as long as
+    /// this node advertises, which keeps a second mDNS socket open; the
+    /// advertisement itself is unaffected.  Returns `false` (and emits
+    /// `advertise_error`) if the watch could not be started.
+    #[func]
+    fn set_hijack_watch(&mut self, enabled: bool) -> bool {
+        self.watch_hijack = enabled;
+        if !enabled {
+            if let Some(watch) = self.hijack_watch.take() {
+                watch.stop();
+            }
+            return true;
+        }
+        self.start_hijack_watch()
+    }
+
+    /// Returns `true` if `set_hijack_watch()` is on.
+    #[func]
+    fn is_hijack_watch_enabled(&self) -> bool {
+        self.watch_hijack
+    }
+
+    /// Minimum interval in milliseconds between TXT-driven re-announcements
+    /// (default 1000).  `0` disables throttling.
+    #[func]
+    fn set_min_reannounce_interval_ms(&mut self, interval_ms: i64) {
+        self.min_reannounce_interval = Duration::from_millis(interval_ms.max(0) as u64);
+    }
+
+    /// Returns the re-announcement throttle interval in milliseconds.
+    #[func]
+    fn get_min_reannounce_interval_ms(&self) -> i64 {
+        self.min_reannounce_interval.as_millis() as i64
+    }
+
+    /// Handle `NOTIFICATION_APPLICATION_PAUSED` / `RESUMED`.  On by default
+    /// on Android and iOS, off elsewhere; turn it off if the game handles
+    /// its own lifecycle.
+    ///
+    /// While paused, throttled TXT updates wait instead of being announced
+    /// into a frozen process.  On resume the `verify_advertisement()`
+    /// deadline moves back by the time spent paused, `query_received`
+    /// counts from before the pause are dropped, and the service is
+    /// re-announced (see `set_reannounce_on_resume()`) with the latest TXT.
+    #[func]
+    fn set_handle_app_lifecycle(&mut self, enabled: bool) {
+        self.app_lifecycle = enabled;
+        if !enabled {
+            self.app_paused_at = None;
+        }
+    }
+
+    /// Returns `true` if pause/resume notifications are handled.
+    #[func]
+    fn is_handle_app_lifecycle_enabled(&self) -> bool {
+        self.app_lifecycle
+    }
+
+    /// Re-announce the service when the app resumes (see
+    /// `set_handle_app_lifecycle()`), so peers that expired it while the app
+    /// was frozen list it again at once.  On by default.  When off, a TXT
+    /// update held back during the pause is still announced.
+    #[func]
+    fn set_reannounce_on_resume(&mut self, enabled: bool) {
+        self.reannounce_on_resume = enabled;
+    }
+
+    /// Returns `true` if the service is re-announced on resume.
+    #[func]
+    fn is_reannounce_on_resume_enabled(&self) -> bool {
+        self.reannounce_on_resume
+    }
+
+    /// Advertise on a daemon of this node's own, bound to `port`, instead of
+    /// the shared one — e.g. to test without fighting a system mDNS
+    /// responder, or to isolate a few nodes on one machine.  `0` (the
+    /// default) goes back to the shared daemon.  Takes effect on the next
+    /// `advertise()`; `verify_advertisement()` and the hijack watch follow.
+    ///
+    /// A daemon on a port other than 5353 only interoperates with nodes using
+    /// the same port (`MdnsBrowser.set_daemon_port()` or
+    /// `MdnsManager.set_daemon_port()`); standard mDNS peers on the LAN
+    /// neither see it nor are seen by it, which is logged as a warning.  A
+    /// second daemon on 5353 shares the port with the shared one, see the
+    /// crate documentation.  Returns `false` (and emits `advertise_error`)
+    /// for a port outside 0..=65535.
+    #[func]
+    fn set_daemon_port(&mut self, port: i64) -> bool {
+        match daemon::node_daemon_port(port) {
+            Ok(port) => {
+                self.daemon_port = port;
+                true
+            }
+            Err(e) => {
+                self.emit_adv_error(e);
+                false
+            }
+        }
+    }
+
+    /// Returns the `set_daemon_port()` port, `0` when on the shared daemon.
+    #[func]
+    fn get_daemon_port(&self) -> i64 {
+        self.daemon_port.map_or(0, i64::from)
+    }
+
+    /// Unregister the advertised service and release this node's daemon handle.
+    ///
+    /// The shared daemon itself stays alive as long as any other clone exists
+    /// (e.g. a running `MdnsBrowser`).  Dropping the clone here does not shut
+    /// down the background thread.
+    ///
+    /// With `set_daemon_port()`, this node's own daemon is shut down instead,
+    /// blocking for up to half a second until its goodbye is sent and its
+    /// thread has exited, so `advertise()` may rebind the same port right
+    /// after.  A daemon that does not confirm in time is reported through
+    /// `advertise_error`.
+    ///
+    /// Called automatically from `exit_tree`; safe to call manually at any time.
+    #[func]
+    pub(crate) fn stop_advertising(&mut self) {
+        self.finish_verification(false);
+        if let Some(watch) = self.hijack_watch.take() {
+            watch.stop();
+        }
+        match (&self.daemon, &self.fullname) {
+            (Some(daemon), name) if self.own_port.is_some() => {
+                if let Err(e) = daemon::shutdown_private(daemon, name.as_deref()) {
+                    main_thread::emit(
+                        &mut self.base_mut(),
+                        "advertise_error",
+                        &[GString::from(&e).to_variant()],
+                    );
+                }
+            }
+            (Some(daemon), Some(name)) => daemon::unregister(daemon, name),
+            _ => {}
+        }
+        // Dropping the system registration withdraws it.
+        self.system = None;
+        self.own_port = None;
+        self.monitor = None;
+        self.query_counts.clear();
+        self.query_window_start = None;
+        self.fullname = None;
+        self.registration = None;
+        self.last_announce = None;
+        self.reannounce_pending = false;
+        // Drop clone — does not shutdown shared daemon.
+        self.daemon = None;
+        self.shared_generation = None;
+        self.recovering = false;
+        self.pending_daemon = None;
+    }
+
+    /// Returns `true` if the service is currently being advertised (or
+    /// waiting for the shared daemon to be created).
+    #[func]
+    pub(crate) fn is_advertising(&self) -> bool {
+        self.daemon.is_some() || self.system.is_some() || self.pending_daemon.is_some()
+    }
+
+    /// Returns the full mDNS service name that was registered, or an empty string.
+    #[func]
+    pub(crate) fn get_registered_name(&self) -> GString {
+        GString::from(self.fullname.as_deref().unwrap_or(""))
+    }
+
+    // ── Internal helpers ─────────────────────────────────────────────────────
+
+    /// The host name the current registration announces, for `LanLobby`.
+    pub(crate) fn registered_host(&self) -> Option<&str> {
+        self.registration.as_ref().map(|registration| registration.hostname.as_str())
+    }
+
+    /// Replaces any active registration with a new one for this host.
+    fn register_new(
+        &mut self,
+        service_type: String,
+        instance_name: String,
+        port: u16,
+        txt: Vec<(String, String)>,
+    ) -> bool {
+        self.stop_advertising();
+
+        let host = get_hostname();
+        let registration = Registration {
+            service_type,
+            instance_name: self.name_suffix.apply(&instance_name, &host),
+            // Build a "hostname.local." string for this machine, unless the
+            // game chose the host name itself.
+            hostname: self.custom_host.clone().unwrap_or_else(|| format!("{host}.local.")),
+            port,
+            txt,
+            addresses: self.scoped_addresses.clone(),
+            families: self.families,
+        };
+
+        let info = match registration.to_service_info() {
+            Ok(i) => i,
+            Err(e) => {
+                self.emit_adv_error(e);
+                return false;
+            }
+        };
+
+        self.fullname = Some(info.get_fullname().to_string());
+        self.registration = Some(registration);
+        if let Some(system) = backend::active_system() {
+            return self.register_system(system, info);
+        }
+        if let Some(port) = self.daemon_port {
+            return self.register_private(info, port);
+        }
+        self.register_shared(daemon::poll_shared_daemon(None), false)
+    }
+
+    /// Registers `info` on a new daemon of this node's own on `port`.
+    /// Returns `false` (after `advertise_error`) if that fails.
+    fn register_private(&mut self, info: ServiceInfo, port: u16) -> bool {
+        let registered = daemon::private_daemon_on(Default::default(), Some(port))
+            .and_then(|d| match daemon::register_private(&d, info) {
+                Ok(()) => Ok(d),
+                Err(e) => {
+                    let _ = d.shutdown();
+                    Err(e)
+                }
+            });
+        match registered {
+            Ok(d) => {
+                self.monitor = d.monitor().ok();
+                self.last_announce = Some(Instant::now());
+                self.reannounce_pending = false;
+                self.daemon = Some(d);
+                self.own_port = Some(port);
+                if self.watch_hijack {
+                    self.start_hijack_watch();
+                }
+                true
+            }
+            Err(e) => {
+                self.stop_advertising();
+                self.emit_adv_error(e);
+                false
+            }
+        }
+    }
+
+    /// Registers `info` on the system DNS-SD backend.  Returns `false` (after
+    /// `advertise_error`) if that fails.
+    fn register_system(&mut self, system: &dyn backend::DnsSd, info: ServiceInfo) -> bool {
+        match system.register(&info) {
+            Ok(advertisement) => {
+                self.system = Some(advertisement);
+                self.last_announce = Some(Instant::now());
+                self.reannounce_pending = false;
+                true
+            }
+            Err(e) => {
+                self.stop_advertising();
+                self.emit_adv_error(format!("system backend: {e}"));
+                false
+            }
+        }
+    }
+
+    /// Takes what the system backend reported about the registration: the
+    /// name it ended up with after a conflict, or why it was dropped.
+    fn drain_system(&mut self) {
+        let next = |system: &backend::Advertisement| system.next_outcome();
+        while let Some(outcome) = self.system.as_ref().and_then(next) {
+            match outcome {
+                Ok(fullname) => self.fullname = Some(fullname),
+                Err(e) => self.emit_adv_error(format!("system backend: {e}")),
+            }
+        }
+    }
+
+    /// Polls the shared-daemon creation this node is waiting on.
+    fn complete_pending_registration(&mut self) {
+        let Some(pending) = self.pending_daemon.take() else {
+            return;
+        };
+        let poll = daemon::poll_shared_daemon(Some(pending.attempt));
+        self.register_shared(poll, pending.after_reset);
+    }
+
+    /// Registers the current `registration` on the shared daemon once `poll`
+    /// says it is ready, keeps waiting while it is pending, or gives up on
+    /// failure.  `after_reset` defers the `daemon_reset` signal until the
+    /// outcome is known.  Returns `false` if advertising stopped.
+    fn register_shared(&mut self, poll: DaemonPoll, after_reset: bool) -> bool {
+        let Some(registration) = &self.registration else {
+            return false;
+        };
+        let registered = match poll {
+            DaemonPoll::Pending(attempt) => {
+                self.pending_daemon = Some(PendingDaemon {
+                    attempt,
+                    after_reset,
+                });
+                return true;
+            }
+            DaemonPoll::Ready(d) => registration
+                .to_service_info()
+                .and_then(|info| daemon::register(&d, info).map(|()| d)),
+            DaemonPoll::Failed(e) => Err(e),
+        };
+        let ok = match registered {
+            Ok(d) => {
+                // Failing to monitor only loses `query_received`; the registration stands.
+                self.monitor = d.monitor().ok();
+                self.last_announce = Some(Instant::now());
+                self.reannounce_pending = false;
+                self.shared_generation = Some(daemon::generation());
+                self.daemon = Some(d);
+                if self.watch_hijack {
+                    // A failed watch is reported but does not undo the registration.
+                    self.start_hijack_watch();
+                }
+                true
+            }
+            Err(e) => {
+                self.stop_advertising();
+                self.emit_adv_error(e);
+                false
+            }
+        };
+        if after_reset {
+            main_thread::emit(&mut self.base_mut(), "daemon_reset", &[]);
+        }
+        if std::mem::take(&mut self.recovering) && ok {
+            main_thread::emit(&mut self.base_mut(), "recovered", &[]);
+        }
+        ok
+    }
+
+    /// This advertiser's entry in `MdnsManager.get_debug_info()`.
+    pub(crate) fn debug_info(&self) -> VarDictionary {
+        let state = if self.daemon.is_some() || self.system.is_some() {
+            "registered"
+        } else if self.pending_daemon.is_some() {
+            "waiting for daemon"
+        } else {
+            "idle"
+        };
+        let mut dict = VarDictionary::new();
+        dict.set("path", self.base().get_path().to_string());
+        dict.set("fullname", self.fullname.as_deref().unwrap_or(""));
+        dict.set("state", state);
+        dict
+    }
+
+    /// Looks `fullname` up in the cache of every `MdnsBrowser` in the scene tree.
+    fn find_discovered(&self, fullname: &str) -> Option<CachedService> {
+        let tree = self.base().get_tree()?;
+        browser::browsers_in_tree(&tree)
+            .iter()
+            .find_map(|browser| browser.bind().cached_service(fullname))
+    }
+
+    /// If the shared daemon was reset since this node registered, register the
+    /// current `registration` again on the new daemon and emit `daemon_reset`.
+    /// If it was shut down because the extension is unloading, stop with an
+    /// `advertise_error`.
+    fn follow_daemon_reset(&mut self) {
+        let Some(seen) = self.shared_generation else {
+            return;
+        };
+        let current = daemon::generation();
+        if seen == current {
+            return;
+        }
+        if daemon::is_unloading() {
+            // The daemon is gone for good; there is nothing left to unregister.
+            self.daemon = None;
+            self.stop_advertising();
+            self.emit_adv_error("mDNS extension unloading; advertising stopped".to_string());
+            return;
+        }
+        if daemon::was_shut_down_since(seen) {
+            // shutdown_all() already sent the goodbye.
+            self.daemon = None;
+            self.stop_advertising();
+            self.emit_adv_error(format!(
+                "{SHUTDOWN_ERROR}: MdnsManager.shutdown_all() stopped advertising"
+            ));
+            return;
+        }
+        // The old daemon is gone, so there is nothing to unregister; just let
+        // go of the dead handles.
+        self.daemon = None;
+        self.monitor = None;
+        self.query_counts.clear();
+        self.query_window_start = None;
+        self.shared_generation = None;
+        self.recovering = daemon::was_recovered_since(seen);
+        self.register_shared(daemon::poll_shared_daemon(None), true);
+    }
+
+    /// Sets `key` in the current registration's TXT record.  Returns `false`
+    /// (and emits `advertise_error`) when not advertising.
+    fn update_txt(&mut self, caller: &str, key: GString, value: GString) -> bool {
+        let Some(registration) = self.registration.as_mut() else {
+            self.emit_adv_error(format!("{caller}: not advertising"));
+            return false;
+        };
+        set_txt_entry(&mut registration.txt, &key.to_string(), &value.to_string());
+        true
+    }
+
+    /// Ends a pause begun by `APPLICATION_PAUSED`; see
+    /// `set_handle_app_lifecycle()`.
+    fn resume_after_pause(&mut self) {
+        let Some(paused_at) = self.app_paused_at.take() else {
+            return;
+        };
+        if let Some(verification) = &mut self.verification {
+            verification.deadline += paused_at.elapsed();
+        }
+        self.query_counts.clear();
+        self.query_window_start = None;
+        if self.reannounce_on_resume && self.daemon.is_some() {
+            self.reannounce();
+        }
+    }
+
+    /// Re-announces now, or marks a re-announcement pending if the throttle
+    /// interval has not elapsed yet.
+    fn schedule_reannounce(&mut self) {
+        if self.reannounce_allowed() {
+            self.reannounce();
+        } else {
+            self.reannounce_pending = true;
+        }
+    }
+
+    fn reannounce_allowed(&self) -> bool {
+        self.last_announce
+            .is_none_or(|at| at.elapsed() >= self.min_reannounce_interval)
+    }
+
+    /// Re-register the current `registration` under the same fullname.
+    /// `mdns-sd` treats a repeated `register()` as an update announcement.
+    fn reannounce(&mut self) {
+        self.reannounce_pending = false;
+        if let (Some(system), Some(registration)) = (&self.system, &self.registration) {
+            let result = registration.to_service_info().and_then(|info| system.update(&info));
+            self.last_announce = Some(Instant::now());
+            if let Err(e) = result {
+                self.emit_adv_error(format!("system backend: {e}"));
+            }
+            return;
+        }
+        let (Some(daemon), Some(registration)) = (&self.daemon, &self.registration) else {
+            return;
+        };
+        let result = registration.to_service_info().and_then(|info| {
+            if self.own_port.is_some() {
+                daemon::register_private(daemon, info)
+            } else {
+                daemon::register(daemon, info)
+            }
+        });
+        self.last_announce = Some(Instant::now());
+        if let Err(e) = result {
+            self.emit_adv_error(e);
+        }
+    }
+
+    /// Non-blocking drain of the monitor channel.  Counts answered queries and
+    /// flushes them as `query_received` once the rate-limit window has elapsed.
+    fn drain_monitor(&mut self) {
+        while let Some(rx) = &self.monitor {
+            match rx.try_recv() {
+                Ok(DaemonEvent::Respond(if_name)) => {
+                    *self.query_counts.entry(if_name).or_insert(0) += 1;
+                    self.query_window_start.get_or_insert_with(Instant::now);
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
+
+        let window_elapsed = self
+            .query_window_start
+            .is_some_and(|start| start.elapsed() >= QUERY_SIGNAL_INTERVAL);
+        if window_elapsed {
+            self.query_window_start = None;
+            let counts = std::mem::take(&mut self.query_counts);
+            for (if_name, count) in counts {
+                main_thread::emit(
+                    &mut self.base_mut(),
+                    "query_received",
+                    &[GString::from(&if_name).to_variant(), count.to_variant()],
+                );
+            }
+        }
+    }
+
+    /// Non-blocking drain of the verification browse: succeeds once our own
+    /// fullname resolves, fails once the deadline passes.
+    fn drain_verification(&mut self) {
+        let Some(verification) = &self.verification else {
+            return;
+        };
+        let fullname = self.fullname.as_deref().unwrap_or_default();
+        let seen = verification.receiver.try_iter().any(|event| {
+            matches!(event, ServiceEvent::ServiceResolved(info)
+                if info.get_fullname().eq_ignore_ascii_case(fullname))
+        });
+        if seen || Instant::now() >= verification.deadline {
+            self.finish_verification(seen);
+        }
+    }
+
+    /// Starts the hijack-watch browse for the current registration unless it
+    /// is already running.  Not advertising yet is not an error: the watch
+    /// starts with the registration.
+    fn start_hijack_watch(&mut self) -> bool {
+        if self.hijack_watch.is_some() || self.daemon.is_none() {
+            return true;
+        }
+        let Some(registration) = &self.registration else {
+            return true;
+        };
+        let service_type = registration.service_type.clone();
+        let started = daemon::private_daemon_on(Default::default(), self.own_port).and_then(|d| {
+            match d.browse(&service_type) {
+                Ok(receiver) => Ok((d, receiver)),
+                Err(e) => {
+                    let _ = d.shutdown();
+                    Err(format!("set_hijack_watch: browse failed: {e}"))
+                }
+            }
+        });
+        match started {
+            Ok((daemon, receiver)) => {
+                self.hijack_watch = Some(HijackWatch {
+                    daemon,
+                    receiver,
+                    service_type,
+                    reported: Vec::new(),
+                });
+                true
+            }
+            Err(e) => {
+                self.emit_adv_error(e);
+                false
+            }
+        }
+    }
+
+    /// Non-blocking drain of the hijack watch: compares every resolution of
+    /// our fullname against this machine's addresses.
+    fn drain_hijack_watch(&mut self) {
+        let (Some(watch), Some(fullname)) = (&self.hijack_watch, &self.fullname) else {
+            return;
+        };
+        let resolved: Vec<_> = watch
+            .receiver
+            .try_iter()
+            .filter_map(|event| match event {
+                ServiceEvent::ServiceResolved(info)
+                    if info.get_fullname().eq_ignore_ascii_case(fullname) =>
+                {
+                    Some(info)
+                }
+                _ => None,
+            })
+            .collect();
+        let Some(latest) = resolved.last() else {
+            return;
+        };
+        let local = interfaces::local_addresses();
+        let foreign: Vec<IpAddr> = CachedService::from_resolved(latest)
+            .addresses
+            .into_iter()
+            .filter(|ip| !local.contains(ip))
+            .collect();
+        let name = GString::from(fullname);
+        let Some(watch) = self.hijack_watch.as_mut() else {
+            return;
+        };
+        if foreign.is_empty() || foreign == watch.reported {
+            return;
+        }
+        watch.reported = foreign.clone();
+        let mut addresses = PackedStringArray::new();
+        for ip in &foreign {
+            addresses.push(ip.to_string().as_str());
+        }
+        main_thread::emit(
+            &mut self.base_mut(),
+            "name_hijacked",
+            &[name.to_variant(), addresses.to_variant()],
+        );
+    }
+
+    /// Tears down the verification browse, if any, and reports `visible`.
+    fn finish_verification(&mut self, visible: bool) {
+        let Some(verification) = self.verification.take() else {
+            return;
+        };
+        verification.stop();
+        main_thread::emit(&mut self.base_mut(), "advertisement_verified", &[visible.to_variant()]);
+    }
+
+    fn emit_adv_error(&mut self, msg: String) {
+        main_thread::emit(
+            &mut self.base_mut(),
+            "advertise_error",
+            &[GString::from(msg.as_str()).to_variant()],
+        );
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// Every `MdnsAdvertiser` in `tree`, for `MdnsManager.get_debug_info()`.
+pub(crate) fn advertisers_in_tree(tree: &Gd<SceneTree>) -> Vec<Gd<MdnsAdvertiser>> {
+    let Some(root) = tree.get_root() else {
+        return Vec::new();
+    };
+    root.upcast::<Node>()
+        .find_children_ex("*")
+        .type_("MdnsAdvertiser")
+        .owned(false)
+        .done()
+        .iter_shared()
+        .filter_map(|node| node.try_cast::<MdnsAdvertiser>().ok())
+        .collect()
+}
+
+/// Sets `key` to `value` in `txt`, replacing an existing entry.  TXT keys
+/// are case-insensitive (RFC 6763 §6.4).
+fn set_txt_entry(txt: &mut Vec<(String, String)>, key: &str, value: &str) {
+    match txt.iter_mut().find(|(k, _)| k.eq_ignore_ascii_case(key)) {
+        Some(entry) => entry.1 = value.to_string(),
+        None => txt.push((key.to_string(), value.to_string())),
+    }
+}
+
+/// The [`cache::STATUS_KEY`] value announced by `set_available(available)`.
+fn status_value(available: bool) -> &'static str {
+    if available {
+        cache::STATUS_UP
+    } else {
+        cache::STATUS_MAINTENANCE
+    }
+}
+
+/// Extracts the instance label from `"<instance>.<service_type>"`.
+/// DNS names are case-insensitive, so the suffix match is too.
+fn instance_name_of(fullname: &str, service_type: &str) -> Option<String> {
+    let cut = fullname.len().checked_sub(service_type.len() + 1)?;
+    let (instance, suffix) = (fullname.get(..cut)?, fullname.get(cut..)?);
+    let suffix_matches =
+        suffix.starts_with('.') && suffix[1..].eq_ignore_ascii_case(service_type);
+    (suffix_matches && !instance.is_empty()).then(|| instance.to_string())
+}
+
+/// Returns the local machine hostname without a domain suffix, or a
+/// per-process `unknown-host-…` name if the OS reports none.
+pub(crate) fn get_hostname() -> String {
+    naming::hostname_or_fallback(hostname::get().ok().and_then(|h| h.into_string().ok()))
+}
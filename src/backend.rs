@@ -0,0 +1,282 @@
+//! DNS-SD backends `MdnsBrowser` and `MdnsAdvertiser` run on.
+//!
+//! The bundled `mdns-sd` daemon (see `daemon`) is the default and the only
+//! backend everywhere except where a [`DnsSd`] implementation of the
+//! operating system's responder is installed with [`install_system`]: the
+//! `dnssd` module on Apple platforms, built with the `dnssd` feature.  There
+//! it spares iOS apps the multicast entitlement, since the OS, not the app,
+//! opens the multicast sockets.
+//!
+//! A system backend only browses and registers.  Whatever needs `mdns-sd`
+//! itself — pinned interfaces, custom daemon ports, passive capture, the
+//! manager's cache and metrics tools — keeps using the bundled daemon.
+//!
+//! The record helpers at the end are shared by system backends and kept here,
+//! free of any C API, so they can be tested on every platform.
+
+use mdns_sd::{Receiver, ResolvedService, ServiceEvent, ServiceInfo, TxtProperties, TxtProperty};
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, OnceLock};
+
+/// Which backend the next `browse()` / `advertise()` starts on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// The bundled `mdns-sd` daemon (default).
+    MdnsSd,
+    /// The operating system's responder, see [`install_system`].
+    System,
+}
+
+/// A DNS-SD implementation other than the bundled daemon.
+///
+/// It reports with `mdns-sd`'s own event types, so the nodes handle its
+/// results exactly like the bundled daemon's.
+pub trait DnsSd: Send + Sync {
+    /// Starts browsing `service_type` (e.g. `"_game._udp.local."`, or a
+    /// `"_sub."` subtype of it) until the returned [`Browse`] is dropped.
+    fn browse(&self, service_type: &str) -> Result<Browse, String>;
+
+    /// Starts announcing `info` until the returned [`Advertisement`] is
+    /// dropped.  Addresses and host name are the responder's own.
+    fn register(&self, info: &ServiceInfo) -> Result<Advertisement, String>;
+}
+
+/// Ends a [`Browse`] or withdraws an [`Advertisement`].
+type Stop = Box<dyn FnOnce() + Send>;
+
+/// Re-announces an [`Advertisement`] with the TXT record of a new `ServiceInfo`.
+type Update = Box<dyn Fn(&ServiceInfo) -> Result<(), String> + Send>;
+
+/// A running browse of a [`DnsSd`] backend; dropping it stops the browse.
+pub struct Browse {
+    receiver: Receiver<ServiceEvent>,
+    stop: Option<Stop>,
+}
+
+#[cfg_attr(not(all(feature = "dnssd", target_vendor = "apple")), allow(dead_code))]
+impl Browse {
+    /// A browse whose events arrive on `receiver`; `stop` ends it.
+    pub fn new(receiver: Receiver<ServiceEvent>, stop: impl FnOnce() + Send + 'static) -> Self {
+        Self {
+            receiver,
+            stop: Some(Box::new(stop)),
+        }
+    }
+
+    /// Where the browse reports `SearchStarted`, found, resolved and removed
+    /// instances, as the bundled daemon would.
+    pub fn receiver(&self) -> &Receiver<ServiceEvent> {
+        &self.receiver
+    }
+}
+
+impl Drop for Browse {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
+/// A service a [`DnsSd`] backend announces; dropping it withdraws the
+/// service (with a goodbye).
+pub struct Advertisement {
+    outcomes: mpsc::Receiver<Result<String, String>>,
+    update: Update,
+    stop: Option<Stop>,
+}
+
+#[cfg_attr(not(all(feature = "dnssd", target_vendor = "apple")), allow(dead_code))]
+impl Advertisement {
+    /// An advertisement reporting on `outcomes`, re-announced with `update`
+    /// and withdrawn by `stop`.
+    pub fn new(
+        outcomes: mpsc::Receiver<Result<String, String>>,
+        update: impl Fn(&ServiceInfo) -> Result<(), String> + Send + 'static,
+        stop: impl FnOnce() + Send + 'static,
+    ) -> Self {
+        Self {
+            outcomes,
+            update: Box::new(update),
+            stop: Some(Box::new(stop)),
+        }
+    }
+
+    /// Announces the TXT record of `info` in place of the current one.
+    pub fn update(&self, info: &ServiceInfo) -> Result<(), String> {
+        (self.update)(info)
+    }
+
+    /// The next outcome the responder reported, if any: the fullname it
+    /// registered (a new one after a name conflict), or why it gave up.
+    pub fn next_outcome(&self) -> Option<Result<String, String>> {
+        self.outcomes.try_recv().ok()
+    }
+}
+
+impl Drop for Advertisement {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            stop();
+        }
+    }
+}
+
+/// The installed system backend, see [`install_system`].
+static SYSTEM: OnceLock<&'static dyn DnsSd> = OnceLock::new();
+
+/// `set(Backend::System)` is in effect.
+static USE_SYSTEM: AtomicBool = AtomicBool::new(false);
+
+/// Makes `backend` the one [`Backend::System`] selects.  Only the first call
+/// counts.
+#[cfg_attr(not(all(feature = "dnssd", target_vendor = "apple")), allow(dead_code))]
+pub fn install_system(backend: &'static dyn DnsSd) {
+    let _ = SYSTEM.set(backend);
+}
+
+/// Selects the backend for `browse()` / `advertise()` calls from now on;
+/// running browses and advertisements stay where they are.  Fails for
+/// [`Backend::System`] when no system backend is installed.
+pub fn set(backend: Backend) -> Result<(), String> {
+    if backend == Backend::System && SYSTEM.get().is_none() {
+        return Err(
+            "no system DNS-SD backend in this build (macOS / iOS with the `dnssd` feature only)"
+                .into(),
+        );
+    }
+    USE_SYSTEM.store(backend == Backend::System, Ordering::SeqCst);
+    Ok(())
+}
+
+/// The backend last given to [`set`].
+pub fn current() -> Backend {
+    if USE_SYSTEM.load(Ordering::SeqCst) {
+        Backend::System
+    } else {
+        Backend::MdnsSd
+    }
+}
+
+/// The system backend when it is the current one; `None` means the bundled
+/// daemon.
+pub fn active_system() -> Option<&'static dyn DnsSd> {
+    match current() {
+        Backend::System => SYSTEM.get().copied(),
+        Backend::MdnsSd => None,
+    }
+}
+
+// ── Record helpers ─────────────────────────────────────────────────────────
+
+/// Splits `service_type` into the registration type and domain the DNS-SD
+/// C API takes: `"_game._udp.local."` → `("_game._udp", "local.")`, and a
+/// subtype `"_ctf._sub._game._udp.local."` → `("_game._udp,_ctf", "local.")`.
+#[cfg_attr(not(all(feature = "dnssd", target_vendor = "apple")), allow(dead_code))]
+pub fn split_service_type(service_type: &str) -> Option<(String, String)> {
+    let (subtype, main) = match service_type.split_once("._sub.") {
+        Some((subtype, main)) => (Some(subtype), main),
+        None => (None, service_type),
+    };
+    // "_game" "_udp" "local." — the domain is whatever follows the protocol.
+    let mut labels = main.splitn(3, '.');
+    let (name, proto, domain) = (labels.next()?, labels.next()?, labels.next()?);
+    if !name.starts_with('_') || !matches!(proto, "_tcp" | "_udp") || domain.is_empty() {
+        return None;
+    }
+    let mut regtype = format!("{name}.{proto}");
+    if let Some(subtype) = subtype {
+        regtype = format!("{regtype},{subtype}");
+    }
+    let domain = if domain.ends_with('.') { domain.to_string() } else { format!("{domain}.") };
+    Some((regtype, domain))
+}
+
+/// The fullname `mdns-sd` gives instance `name` of `service_type`: dots and
+/// backslashes in the name escaped, subtype dropped.
+#[cfg_attr(not(all(feature = "dnssd", target_vendor = "apple")), allow(dead_code))]
+pub fn fullname(name: &str, service_type: &str) -> String {
+    let main = service_type.split_once("._sub.").map_or(service_type, |(_, main)| main);
+    let escaped = name.replace('\\', "\\\\").replace('.', "\\.");
+    format!("{escaped}.{main}")
+}
+
+/// The instance name of `fullname`, unescaped, as the DNS-SD C API takes it.
+#[cfg_attr(not(all(feature = "dnssd", target_vendor = "apple")), allow(dead_code))]
+pub fn instance_name(fullname: &str, service_type: &str) -> Option<String> {
+    let main = service_type.split_once("._sub.").map_or(service_type, |(_, main)| main);
+    let escaped = fullname.strip_suffix(main)?.strip_suffix('.')?;
+    let mut name = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        name.push(if c == '\\' { chars.next()? } else { c });
+    }
+    Some(name)
+}
+
+/// The properties of a wire-format TXT record.  Strings that are empty or
+/// whose key `mdns-sd` would reject are skipped; a repeated key keeps its
+/// first value (RFC 6763 §6.4).
+#[cfg_attr(not(all(feature = "dnssd", target_vendor = "apple")), allow(dead_code))]
+pub fn decode_txt(record: &[u8]) -> Vec<TxtProperty> {
+    let mut properties: Vec<TxtProperty> = Vec::new();
+    let mut rest = record;
+    while let Some((&len, tail)) = rest.split_first() {
+        let len = usize::from(len).min(tail.len());
+        let (string, tail) = tail.split_at(len);
+        rest = tail;
+        let (key, value) = match string.iter().position(|&b| b == b'=') {
+            Some(at) => (&string[..at], Some(&string[at + 1..])),
+            None => (string, None),
+        };
+        let Ok(key) = std::str::from_utf8(key) else {
+            continue;
+        };
+        if key.is_empty()
+            || !key.is_ascii()
+            || properties.iter().any(|p| p.key().eq_ignore_ascii_case(key))
+        {
+            continue;
+        }
+        properties.push(match value {
+            Some(value) => TxtProperty::from((key, value)),
+            None => TxtProperty::from(key),
+        });
+    }
+    properties
+}
+
+/// `properties` as a wire-format TXT record; an entry longer than a TXT
+/// string can hold is cut at 255 bytes, as `mdns-sd` does.
+#[cfg_attr(not(all(feature = "dnssd", target_vendor = "apple")), allow(dead_code))]
+pub fn encode_txt(properties: &TxtProperties) -> Vec<u8> {
+    let mut record = Vec::new();
+    for property in properties.iter() {
+        let mut string = property.key().as_bytes().to_vec();
+        if let Some(value) = property.val() {
+            string.push(b'=');
+            string.extend_from_slice(value);
+        }
+        string.truncate(usize::from(u8::MAX));
+        record.push(string.len() as u8);
+        record.extend(string);
+    }
+    record
+}
+
+/// The `ServiceResolved` payload for instance `name` of `service_type` as a
+/// system responder reports it, or `None` if `mdns-sd` would not accept it.
+#[cfg_attr(not(all(feature = "dnssd", target_vendor = "apple")), allow(dead_code))]
+pub fn resolved(
+    service_type: &str,
+    name: &str,
+    host: &str,
+    port: u16,
+    addresses: &[IpAddr],
+    txt: &[u8],
+) -> Option<ResolvedService> {
+    ServiceInfo::new(service_type, name, host, addresses, port, decode_txt(txt))
+        .ok()
+        .map(ServiceInfo::as_resolved_service)
+}
@@ -0,0 +1,135 @@
+//! Check of the `NSBonjourServices` Info.plist entry on iOS, run by
+//! `MdnsBrowser.browse()`.
+//!
+//! iOS 14+ drops browses for service types the app did not declare under
+//! `NSBonjourServices`, even with the Local Network permission granted, and
+//! says nothing about it: the list just stays empty.  The declared types are
+//! read from the main bundle once, through CoreFoundation, and each
+//! `browse()` compares its type against them; nothing runs per event.
+//! Elsewhere the check does nothing.
+//!
+//! Free of Godot types so the comparison can be tested without an engine
+//! (see `tests/bonjour_services.rs`).
+
+/// Leading word of the error reported for an undeclared service type.
+pub const BONJOUR_SERVICE_UNDECLARED_ERROR: &str = "bonjour_service_undeclared";
+
+/// The `browse_error` message for browsing `service_type`, or `None` if the
+/// app may browse it (always `None` off iOS).
+pub fn check(service_type: &str) -> Option<String> {
+    #[cfg(target_os = "ios")]
+    {
+        use std::sync::OnceLock;
+        static DECLARED: OnceLock<Option<Vec<String>>> = OnceLock::new();
+        undeclared_error(DECLARED.get_or_init(declared_services).as_deref(), service_type)
+    }
+    #[cfg(not(target_os = "ios"))]
+    {
+        let _ = service_type;
+        None
+    }
+}
+
+/// The `NSBonjourServices` item that covers `service_type`:
+/// `"_mygame._tcp.local."` and `"_lobby._sub._mygame._tcp.local."` both need
+/// `"_mygame._tcp"`.
+pub fn plist_entry(service_type: &str) -> String {
+    let name = service_type.trim_end_matches('.');
+    let name = match name.len().checked_sub(".local".len()) {
+        Some(at) if name.is_char_boundary(at) && name[at..].eq_ignore_ascii_case(".local") => {
+            &name[..at]
+        }
+        _ => name,
+    };
+    match name.rfind("._sub.") {
+        Some(at) => name[at + "._sub.".len()..].to_string(),
+        None => name.to_string(),
+    }
+}
+
+/// The error for browsing `service_type` given the declared `NSBonjourServices`
+/// items (`None` if the key is missing), or `None` if it is declared.  Items
+/// compare case-insensitively, with or without a trailing dot.
+#[cfg_attr(not(target_os = "ios"), allow(dead_code))]
+pub fn undeclared_error(declared: Option<&[String]>, service_type: &str) -> Option<String> {
+    let entry = plist_entry(service_type);
+    let Some(declared) = declared else {
+        return Some(format!(
+            "{BONJOUR_SERVICE_UNDECLARED_ERROR}: Info.plist has no NSBonjourServices array, so \
+             iOS silently blocks browsing {service_type}. Add \
+             <key>NSBonjourServices</key><array><string>{entry}</string></array> to the iOS \
+             export preset's Additional Plist Content"
+        ));
+    };
+    let listed = declared
+        .iter()
+        .any(|item| item.trim_end_matches('.').eq_ignore_ascii_case(&entry));
+    (!listed).then(|| {
+        format!(
+            "{BONJOUR_SERVICE_UNDECLARED_ERROR}: {entry} is not listed under NSBonjourServices \
+             in Info.plist, so iOS silently blocks browsing {service_type}. Add \
+             <string>{entry}</string> to the NSBonjourServices array in the iOS export \
+             preset's Additional Plist Content"
+        )
+    })
+}
+
+/// The string items of the main bundle's `NSBonjourServices` array, or `None`
+/// if the key is missing or not an array.
+#[cfg(target_os = "ios")]
+fn declared_services() -> Option<Vec<String>> {
+    use std::ffi::{c_char, c_void, CStr};
+
+    type CFTypeRef = *const c_void;
+    /// `kCFStringEncodingUTF8`
+    const UTF8: u32 = 0x0800_0100;
+
+    #[link(name = "CoreFoundation", kind = "framework")]
+    extern "C" {
+        fn CFBundleGetMainBundle() -> CFTypeRef;
+        fn CFBundleGetValueForInfoDictionaryKey(bundle: CFTypeRef, key: CFTypeRef) -> CFTypeRef;
+        fn CFStringCreateWithCString(alloc: CFTypeRef, s: *const c_char, enc: u32) -> CFTypeRef;
+        fn CFStringGetCString(s: CFTypeRef, buf: *mut c_char, size: isize, enc: u32) -> u8;
+        fn CFArrayGetCount(array: CFTypeRef) -> isize;
+        fn CFArrayGetValueAtIndex(array: CFTypeRef, index: isize) -> CFTypeRef;
+        fn CFGetTypeID(cf: CFTypeRef) -> usize;
+        fn CFArrayGetTypeID() -> usize;
+        fn CFStringGetTypeID() -> usize;
+        fn CFRelease(cf: CFTypeRef);
+    }
+
+    // SAFETY: every object passed to CoreFoundation below is either owned
+    // here (`key`, released once) or borrowed from the main bundle, which
+    // lives as long as the process; types are checked before use.
+    unsafe {
+        let bundle = CFBundleGetMainBundle();
+        if bundle.is_null() {
+            return None;
+        }
+        let key = CFStringCreateWithCString(
+            std::ptr::null(),
+            c"NSBonjourServices".as_ptr(),
+            UTF8,
+        );
+        if key.is_null() {
+            return None;
+        }
+        let array = CFBundleGetValueForInfoDictionaryKey(bundle, key);
+        CFRelease(key);
+        if array.is_null() || CFGetTypeID(array) != CFArrayGetTypeID() {
+            return None;
+        }
+        let mut items = Vec::new();
+        for index in 0..CFArrayGetCount(array) {
+            let item = CFArrayGetValueAtIndex(array, index);
+            if item.is_null() || CFGetTypeID(item) != CFStringGetTypeID() {
+                continue;
+            }
+            let mut buffer = [0 as c_char; 256];
+            if CFStringGetCString(item, buffer.as_mut_ptr(), buffer.len() as isize, UTF8) != 0 {
+                items.push(CStr::from_ptr(buffer.as_ptr()).to_string_lossy().into_owned());
+            }
+        }
+        Some(items)
+    }
+}
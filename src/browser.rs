@@ -0,0 +1,2777 @@
+//! `MdnsBrowser` — discovers services on the LAN.
+
+use crate::backend;
+use crate::bonjour_services;
+use crate::cache::{
+    self, CachedService, Change, ExportedService, ServiceCache, ServiceDiff, ServiceIds,
+};
+use crate::daemon::{self, DaemonPoll};
+use crate::interfaces::{self, InterfaceSelection, LocalInterface, Subnet};
+use crate::lifecycle;
+use crate::local_network::{self, Permission};
+use crate::main_thread;
+use godot::classes::notify::NodeNotification;
+use godot::classes::{Engine, Json};
+use godot::global::Error;
+use godot::prelude::*;
+use mdns_sd::{HostnameResolutionEvent, ResolvedService, ServiceDaemon, ServiceEvent};
+use std::collections::HashMap;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Browses the LAN for an mDNS service type and emits signals when services
+/// are discovered or removed.
+///
+/// ## GDScript example
+/// ```gdscript
+/// var browser := MdnsBrowser.new()
+/// add_child(browser)
+/// browser.service_discovered.connect(_on_service_discovered)
+/// browser.service_removed.connect(_on_service_removed)
+/// browser.browse("_mygame._tcp.local.")
+///
+/// func _on_service_discovered(name, host, addresses, port, txt):
+///     print("Found server: ", name, " at ", addresses, ":", port)
+///
+/// func _on_service_removed(name):
+///     print("Server gone: ", name)
+/// ```
+///
+/// Alternatively set `service_type` and tick `autostart` in the inspector and
+/// the browser starts on `ready()` without any script.
+///
+/// The class also runs in the editor, for `@tool` scripts and editor plugins
+/// listing servers on the LAN.  There only an explicit `browse()` touches
+/// the network: `autostart` is ignored, so a scene open in someone's editor
+/// stays quiet.
+#[derive(GodotClass)]
+#[class(tool, base = Node)]
+pub struct MdnsBrowser {
+    /// Clone of the shared daemon (or a private daemon when `iface_ips` or
+    /// `daemon_port` is set).
+    /// Holding a clone keeps the reference alive; dropping it without calling
+    /// `shutdown()` is safe — the daemon only stops when every clone is dropped.
+    daemon: Option<ServiceDaemon>,
+    /// Browse channel of the private daemon, when `iface_ips` or `daemon_port` is set.
+    receiver: Option<mdns_sd::Receiver<ServiceEvent>>,
+    /// Private half of a dual-daemon browse; see `set_dual_daemon()`.
+    pinned: Option<PinnedBrowse>,
+    /// This node's share of the shared daemon's browse of `browsing_type`.
+    /// Other browsers of the same type hold their own; see `daemon::subscribe()`.
+    subscription: Option<daemon::Subscription>,
+    /// Browse of the system DNS-SD backend; see `MdnsManager.set_backend()`.
+    system: Option<backend::Browse>,
+    /// Service type browsed automatically on `ready()` when `autostart` is set.
+    #[export]
+    service_type: GString,
+    /// Call `browse(service_type)` on `ready()`, so the node works straight
+    /// from the scene tree without any GDScript.
+    #[export]
+    autostart: bool,
+    /// The service type currently being browsed (e.g. `"_mygame._tcp.local."`).
+    /// Stored so `stop_browsing()` can call `daemon.stop_browse()` to clean up
+    /// the browse subscription in the shared daemon.
+    browsing_type: Option<String>,
+    /// IP addresses or names of the network interfaces to restrict the
+    /// daemon to; empty for every interface.  Set this before calling
+    /// `browse()`.  On Android the daemon
+    /// must be pinned to the WiFi interface because the driver will not
+    /// deliver multicast packets to sockets joined on the wrong interface even
+    /// after a MulticastLock is acquired; `browse()` picks it itself
+    /// (`auto_iface`) when this is empty.
+    ///
+    /// When set, a *private* daemon is created for this browser instead of
+    /// the shared one, because `disable_interface(All)` would affect any
+    /// co-running `MdnsAdvertiser`.  Android devices never run
+    /// `MdnsAdvertiser` so this is safe in practice.
+    iface_ips: Vec<String>,
+    /// The WLAN address `browse()` picked on Android because `iface_ips` was
+    /// empty; used the same way.
+    auto_iface: Option<String>,
+    /// Shared-daemon generation this browser's subscription belongs to; `None`
+    /// when idle or browsing on a private daemon.  See `daemon::reset()`.
+    shared_generation: Option<u64>,
+    /// The browse being re-established follows `daemon::recover()`: emit
+    /// `recovered` once it succeeds.
+    recovering: bool,
+    /// Set while `browsing_type` waits for the shared daemon to finish being
+    /// created in the background.
+    pending_daemon: Option<PendingDaemon>,
+    /// Services that are currently live (resolved and not yet removed), keyed
+    /// by fullname.  Cleared when browsing stops.
+    cache: ServiceCache,
+    /// Evict cached services not re-resolved within this window; `None` = off.
+    stale_eviction: Option<Duration>,
+    /// Per-frame counters for `discovery_frame_stats`; `None` while disabled.
+    frame_stats: Option<FrameStats>,
+    /// `service_updated` carries only what changed; see `set_sparse_updates()`.
+    sparse_updates: bool,
+    /// Drop `service_updated` for rotated IPv6 privacy addresses; see
+    /// `set_collapse_ipv6_churn()`.
+    collapse_ipv6_churn: bool,
+    /// `set_preserve_txt_empty()`.
+    preserve_txt_empty: bool,
+    /// Discoveries arrive as `service_discovered_compact`; see
+    /// `set_compact_emission()`.
+    compact_emission: bool,
+    /// Stable integer IDs of the services seen; see `get_service_id()`.
+    service_ids: ServiceIds,
+    /// Also emit the `_with_id` twins of the discovered/updated/removed
+    /// signals; see `set_service_id_emission()`.
+    service_id_emission: bool,
+    /// With `set_dedup_by_host()`, the services reported to scripts, each
+    /// with its [`cache::host_group`]; one per host.  `None` while off.
+    host_rows: Option<HashMap<String, String>>,
+    /// TXT key picking the service that stands for its host; see
+    /// `set_dedup_prefer_key()`.
+    dedup_prefer: Option<String>,
+    /// In-flight `resolve_hostname()` queries, polled alongside the browse channel.
+    hostname_lookups: Vec<HostnameLookup>,
+    /// `resolve_hostname()` calls waiting for the shared daemon to be created.
+    pending_lookups: Vec<PendingLookup>,
+    /// Hold resolutions back until `resolve_found()`; see `set_manual_resolve()`.
+    manual_resolve: bool,
+    /// Instances reported through `service_found` in manual mode, by fullname.
+    found: HashMap<String, FoundService>,
+    /// With `iface_ips` set, browse on the shared daemon as well as the private one.
+    dual_daemon: bool,
+    /// Port of a private daemon used instead of the shared one; see
+    /// `set_daemon_port()`.
+    daemon_port: Option<u16>,
+    /// `first_response_received` was emitted for the current browse.
+    answered: bool,
+    /// When a `browse_for()` browse ends.
+    browse_deadline: Option<Instant>,
+    /// When a browse that has not heard from its daemon yet counts as
+    /// unresponsive; cleared by the first event.  See `set_search_timeout_ms()`.
+    search_deadline: Option<Instant>,
+    /// Window for `search_deadline`; zero disables the check.
+    search_timeout: Duration,
+    /// When the current browse reports `no_services_found` if it has still
+    /// found nothing; cleared by the first discovery.
+    empty_deadline: Option<Instant>,
+    /// `set_empty_deadline_ms()`; zero disables `no_services_found`.
+    empty_timeout: Duration,
+    /// `set_port_range()`, inclusive; `None` lists every port.
+    port_range: Option<(u16, u16)>,
+    /// `set_subnet_filter()`; empty lists every address.
+    subnets: Vec<Subnet>,
+    /// `local_network_permission_denied` was emitted for the current browse.
+    permission_reported: bool,
+    /// Rebuild a private daemon after the app was suspended; see
+    /// `set_resume_recovery()`.
+    resume_recovery: bool,
+    /// When `process()` last ran, to notice the app was suspended.
+    last_process: Option<Instant>,
+    /// Handle `APPLICATION_PAUSED` / `RESUMED`; see `set_handle_app_lifecycle()`.
+    app_lifecycle: bool,
+    /// When the app was paused, while it is.
+    app_paused_at: Option<Instant>,
+    /// Ask listed services to re-confirm on resume; see `set_refresh_on_resume()`.
+    refresh_on_resume: bool,
+    /// Set at the start of `exit_tree`: the node may be being freed, so no
+    /// signal is emitted from then on, even for events still queued.
+    destroying: bool,
+    base: Base<Node>,
+}
+
+/// An instance announced by `service_found` while in manual-resolve mode.
+#[derive(Default)]
+struct FoundService {
+    /// `resolve_found()` was called; resolutions pass straight through.
+    released: bool,
+    /// Latest resolution received before release.
+    held: Option<Box<ResolvedService>>,
+}
+
+/// The private daemon of a dual-daemon browse, alongside the shared one.
+struct PinnedBrowse {
+    daemon: ServiceDaemon,
+    receiver: mdns_sd::Receiver<ServiceEvent>,
+}
+
+/// Emissions counted during one `process()` call.
+#[derive(Default)]
+struct FrameStats {
+    discovered: i64,
+    updated: i64,
+    removed: i64,
+}
+
+/// A browse waiting for the shared daemon; see `daemon::poll_shared_daemon()`.
+struct PendingDaemon {
+    /// Creation attempt being waited on.
+    attempt: u64,
+    /// Emit `daemon_reset` once subscribed, because this wait follows a reset.
+    after_reset: bool,
+}
+
+/// Leading word of the error a node reports when `MdnsManager.shutdown_all()`
+/// stopped it.
+pub(crate) const SHUTDOWN_ERROR: &str = "shutdown";
+
+/// Leading word of the error a node reports when its daemon sent nothing,
+/// not even `SearchStarted`, within the search timeout.
+pub(crate) const DAEMON_UNRESPONSIVE_ERROR: &str = "daemon_unresponsive";
+
+/// A gap this long between two `process()` calls means the app was
+/// suspended, e.g. with the screen off.
+const SUSPEND_GAP: Duration = Duration::from_secs(10);
+
+/// `format` of an `export_cache_json()` document.
+const CACHE_JSON_FORMAT: &str = "godot-mdns-cache";
+
+/// `version` of the `export_cache_json()` document this build writes.
+const CACHE_JSON_VERSION: i64 = 1;
+
+/// How long a listed service has to re-confirm itself after a resume; see
+/// `set_refresh_on_resume()`.
+const RESUME_VERIFY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long before its stale-eviction window runs out a listed service is
+/// asked to confirm itself, and how long it has to answer; capped at half
+/// the window.  See `set_stale_eviction_ms()`.
+const EVICTION_CONFIRM_LEAD: Duration = Duration::from_secs(10);
+
+/// Default for `set_search_timeout_ms()`.  The daemon sends `SearchStarted`
+/// with its first query, normally well under a second after the browse.
+const DEFAULT_SEARCH_TIMEOUT_MS: u64 = 3000;
+
+/// Default for `resolve_hostname()` when `timeout_ms` is not positive.
+const DEFAULT_HOSTNAME_TIMEOUT_MS: u64 = 3000;
+
+/// One pending `resolve_hostname()` call.
+struct HostnameLookup {
+    /// Hostname exactly as passed by the caller; echoed back in the signals.
+    requested: String,
+    /// Fully-qualified form sent to the daemon, e.g. `"somehost.local."`.
+    fqdn: String,
+    daemon: ServiceDaemon,
+    receiver: mdns_sd::Receiver<HostnameResolutionEvent>,
+}
+
+/// A `resolve_hostname()` call waiting for the shared daemon; see
+/// `daemon::poll_shared_daemon()`.
+struct PendingLookup {
+    requested: String,
+    fqdn: String,
+    timeout_ms: u64,
+    /// Creation attempt being waited on.
+    attempt: u64,
+}
+
+#[godot_api]
+impl INode for MdnsBrowser {
+    fn init(base: Base<Node>) -> Self {
+        Self {
+            daemon: None,
+            receiver: None,
+            pinned: None,
+            subscription: None,
+            system: None,
+            service_type: GString::new(),
+            autostart: false,
+            browsing_type: None,
+            iface_ips: Vec::new(),
+            auto_iface: None,
+            shared_generation: None,
+            recovering: false,
+            pending_daemon: None,
+            cache: ServiceCache::new(),
+            stale_eviction: None,
+            frame_stats: None,
+            sparse_updates: false,
+            collapse_ipv6_churn: false,
+            preserve_txt_empty: false,
+            compact_emission: false,
+            service_ids: ServiceIds::new(),
+            service_id_emission: false,
+            host_rows: None,
+            dedup_prefer: None,
+            hostname_lookups: Vec::new(),
+            pending_lookups: Vec::new(),
+            manual_resolve: false,
+            dual_daemon: false,
+            daemon_port: None,
+            answered: false,
+            browse_deadline: None,
+            search_deadline: None,
+            search_timeout: Duration::from_millis(DEFAULT_SEARCH_TIMEOUT_MS),
+            empty_deadline: None,
+            empty_timeout: Duration::ZERO,
+            port_range: None,
+            subnets: Vec::new(),
+            permission_reported: false,
+            resume_recovery: true,
+            last_process: None,
+            app_lifecycle: lifecycle::DEFAULT_ENABLED,
+            app_paused_at: None,
+            refresh_on_resume: false,
+            destroying: false,
+            found: HashMap::new(),
+            base,
+        }
+    }
+
+    /// Start the exported `service_type` browse if `autostart` is set, except
+    /// in the editor.  A `browse()` issued before the node entered the tree
+    /// takes precedence.
+    fn ready(&mut self) {
+        if Engine::singleton().is_editor_hint() {
+            return;
+        }
+        if self.autostart && !self.service_type.is_empty() && !self.is_browsing() {
+            let service_type = self.service_type.clone();
+            self.browse(service_type);
+        }
+    }
+
+    /// Poll the mDNS channel every frame — non-blocking, drains all pending events.
+    fn process(&mut self, _delta: f64) {
+        if self.app_paused_at.is_some() {
+            // Events wait in their channels and are coalesced on resume.
+            return;
+        }
+        let now = Instant::now();
+        if self.last_process.replace(now).is_some_and(|last| now - last >= SUSPEND_GAP) {
+            self.recover_after_suspend();
+        }
+        self.follow_daemon_reset();
+        self.complete_pending_browse();
+        self.drain_events();
+        self.check_search_timeout();
+        self.check_empty_deadline();
+        self.check_local_network_permission();
+        self.finish_timed_browse();
+        self.evict_stale();
+        self.emit_frame_stats();
+        self.complete_pending_lookups();
+        self.drain_hostname_lookups();
+    }
+
+    fn on_notification(&mut self, what: NodeNotification) {
+        if what == NodeNotification::APPLICATION_PAUSED && self.app_lifecycle {
+            self.app_paused_at.get_or_insert_with(Instant::now);
+        }
+        if what == NodeNotification::APPLICATION_RESUMED {
+            self.resume_after_pause();
+            self.recover_after_suspend();
+            // The frame gap that follows is the same suspension.
+            self.last_process = None;
+        }
+    }
+
+    /// Re-arms signals after `exit_tree`, for a node that was only moved.
+    fn enter_tree(&mut self) {
+        self.destroying = false;
+    }
+
+    /// Automatically stop browsing (and any hostname lookups) when the node is
+    /// removed from the scene tree.
+    fn exit_tree(&mut self) {
+        self.destroying = true;
+        self.stop_browsing();
+        for lookup in self.hostname_lookups.drain(..) {
+            let _ = lookup.daemon.stop_resolve_hostname(&lookup.fqdn);
+        }
+        self.pending_lookups.clear();
+    }
+}
+
+#[godot_api]
+impl MdnsBrowser {
+    // ── Signals ──────────────────────────────────────────────────────────────
+
+    /// Emitted when a service has been fully resolved (IP addresses are known)
+    /// for the first time.  Later changes arrive as `service_updated`.
+    ///
+    /// Parameters:
+    ///   name      — full service name, e.g. "My Server._mygame._tcp.local."
+    ///   host      — hostname, e.g. "marks-pc.local."
+    ///   addresses — array of IP address strings (IPv4 and/or IPv6)
+    ///   port      — TCP/UDP port as int
+    ///   txt       — VarDictionary of TXT record key→value strings (keys sent
+    ///               without a value map to `true` with
+    ///               `set_preserve_txt_empty(true)`)
+    #[signal]
+    fn service_discovered(
+        name: GString,
+        host: GString,
+        addresses: PackedStringArray,
+        port: i64,
+        txt: VarDictionary,
+    );
+
+    /// Emitted when an already-discovered service re-announces with different
+    /// records (host, port, addresses or TXT).  Re-announcements carrying the
+    /// same data — even with the addresses in a different order — are not
+    /// reported.  Same parameters as `service_discovered`, except with
+    /// `set_sparse_updates(true)`, where only what changed is filled in:
+    ///   host      — new hostname, or "" if unchanged
+    ///   addresses — new full address array, or empty if unchanged
+    ///   port      — new port, or -1 if unchanged
+    ///   txt       — only the added or changed keys, with their new value,
+    ///               and removed keys with `null`; empty if TXT is unchanged
+    #[signal]
+    fn service_updated(
+        name: GString,
+        host: GString,
+        addresses: PackedStringArray,
+        port: i64,
+        txt: VarDictionary,
+    );
+
+    /// Emitted when a previously discovered service disappears from the LAN.
+    ///
+    /// Parameters:
+    ///   name — full service name that was removed
+    #[signal]
+    fn service_removed(name: GString);
+
+    /// `service_discovered` with the service's stable ID (as from
+    /// `get_service_id()`) as a last argument; emitted right after it while
+    /// `set_service_id_emission(true)` is on.
+    #[signal]
+    fn service_discovered_with_id(
+        name: GString,
+        host: GString,
+        addresses: PackedStringArray,
+        port: i64,
+        txt: VarDictionary,
+        id: i64,
+    );
+
+    /// `service_updated` with the stable ID as a last argument; emitted
+    /// right after it while `set_service_id_emission(true)` is on.
+    #[signal]
+    fn service_updated_with_id(
+        name: GString,
+        host: GString,
+        addresses: PackedStringArray,
+        port: i64,
+        txt: VarDictionary,
+        id: i64,
+    );
+
+    /// `service_removed` with the stable ID as a last argument; emitted
+    /// right after it while `set_service_id_emission(true)` is on.
+    #[signal]
+    fn service_removed_with_id(name: GString, id: i64);
+
+    /// Emitted instead of `service_discovered` and `service_updated` while
+    /// `set_compact_emission(true)` is on: once when a service is resolved,
+    /// and again only when an update changes its connect string.
+    ///
+    /// Parameters:
+    ///   name           — full service name
+    ///   connect_string — `"address:port"` for the preferred address (IPv4
+    ///                    when there is one, IPv6 in brackets), or
+    ///                    `"host:port"` if no address is known
+    #[signal]
+    fn service_discovered_compact(name: GString, connect_string: GString);
+
+    /// Emitted when a `browse_for()` browse has run for its duration and
+    /// stopped.  `get_discovered_services()` lists what it found.
+    #[signal]
+    fn browse_finished(service_type: GString);
+
+    /// Emitted when the service list was emptied at once, without a
+    /// `service_removed` per service: by `stop_browsing()`, and so by a new
+    /// `browse()`, or when a browse could not be re-issued on the shared
+    /// daemon.  Not emitted if the list was already empty.
+    #[signal]
+    fn services_cleared();
+
+    /// Emitted once per `browse()` when nothing has been discovered within
+    /// `set_empty_deadline_ms()`, to turn a "searching…" state into "no
+    /// servers found".  Never emitted for a browse that discovered anything,
+    /// even if it was removed again.
+    #[signal]
+    fn no_services_found(service_type: GString);
+
+    /// Emitted once per `browse()`, when the first instance is found or
+    /// resolved: the network is answering.  If this never comes, the query
+    /// went out but nothing answered — nothing is offering the type, or
+    /// multicast is blocked.  Instances another browser of the same type had
+    /// already found count as answers too.
+    #[signal]
+    fn first_response_received(service_type: GString);
+
+    /// Emitted in manual-resolve mode (`set_manual_resolve(true)`) when a new
+    /// instance name is seen, before anything else is known about it.  Call
+    /// `resolve_found(name)` to get its `service_discovered`.  A found
+    /// instance that goes away is reported through `service_removed`.
+    #[signal]
+    fn service_found(name: GString);
+
+    /// Emitted if an internal mDNS error occurs.  A message starting with
+    /// `"shutdown"` means `MdnsManager.shutdown_all()` ended the browse; the
+    /// node is idle and a later `browse()` starts over on a new daemon.
+    /// One starting with `"daemon_unresponsive"` means the daemon sent
+    /// nothing within the search timeout (see `set_search_timeout_ms()`);
+    /// the browse stays active in case it catches up.  One starting with
+    /// `"bonjour_service_undeclared"` (iOS) means Info.plist does not declare
+    /// the type under `NSBonjourServices`; see `browse()`.
+    #[signal]
+    fn browse_error(message: GString);
+
+    /// Emitted at the end of any frame in which this browser emitted at least
+    /// one `service_discovered` / `service_updated` / `service_removed`,
+    /// summarising the frame.
+    /// Off by default; see `set_emit_frame_stats()`.
+    ///
+    /// Parameters:
+    ///   discovered — services reported for the first time
+    ///   updated    — already-known services whose records changed
+    ///   removed    — services removed, including stale evictions
+    #[signal]
+    fn discovery_frame_stats(discovered: i64, updated: i64, removed: i64);
+
+    /// Emitted after `MdnsManager.reset_shared_daemon()` once this browser has
+    /// re-issued its browse on the new daemon.  Services the new daemon finds
+    /// with unchanged records are not reported again.
+    #[signal]
+    fn daemon_reset();
+
+    /// Emitted after `daemon_reset` when the reset was an automatic recovery
+    /// from a failed daemon (see `MdnsManager.set_auto_recover()`).  Browsing
+    /// was cut off meanwhile, so services that left may still be cached:
+    /// `MdnsManager.verify_cache()` prunes them, or clear your own lists.
+    #[signal]
+    fn recovered();
+
+    /// Emitted on Android when a browse on a private daemon was rebuilt after
+    /// the app was suspended (see `set_resume_recovery()`).  Services still
+    /// on the network stay listed without being reported again.
+    #[signal]
+    fn browse_recovered();
+
+    /// Emitted when a `resolve_hostname()` query received an answer.
+    ///
+    /// Parameters:
+    ///   host      — hostname as passed to `resolve_hostname()`
+    ///   addresses — IP address strings, IPv4 first
+    #[signal]
+    fn hostname_resolved(host: GString, addresses: PackedStringArray);
+
+    /// Emitted when a `resolve_hostname()` query timed out without an answer
+    /// or could not be started.
+    #[signal]
+    fn hostname_resolve_failed(host: GString);
+
+    /// Emitted once per `browse()` on iOS (and macOS 15+) when the player has
+    /// denied the app Local Network access, so the browse cannot find
+    /// anything: show "enable Local Network access in Settings" instead of
+    /// an empty list.  See `MdnsManager.get_local_network_permission()`.
+    #[signal]
+    fn local_network_permission_denied();
+
+    /// Emitted on Android when `browse()` pinned this browser to the WiFi
+    /// interface by itself because `set_interface()` was not called.
+    ///
+    /// Parameters:
+    ///   ip — the address chosen, e.g. "192.168.1.20"
+    #[signal]
+    fn interface_selected(ip: GString);
+
+    // ── Methods ──────────────────────────────────────────────────────────────
+
+    /// Pin the daemon to a single network interface by its IP address string
+    /// (e.g. `"192.168.1.42"`) or name (e.g. `"wlan0"`), parsed the same way
+    /// as `MdnsManager.set_allowed_interfaces()`.  Call this **before**
+    /// `browse()`.  Passing an empty string clears any previously set hint and
+    /// reverts to all-interface auto-detection.
+    ///
+    /// On Android this is required because `mdns-sd`'s default all-interface
+    /// socket binding does not reliably receive multicast traffic through the
+    /// WiFi driver even when a MulticastLock is held.  Restricting to the
+    /// correct WiFi IP ensures the daemon's socket joins the 224.0.0.251
+    /// multicast group on exactly that interface.
+    ///
+    /// When an interface IP is set, this browser creates its own private daemon
+    /// rather than using the shared one.  To restrict *every* node instead, use
+    /// `MdnsManager.set_allowed_interfaces()`.
+    ///
+    /// On Android, `browse()` picks the WiFi address itself when this was not
+    /// called (see `interface_selected`); an interface set here takes
+    /// precedence.
+    #[func]
+    fn set_interface(&mut self, iface_ip: GString) {
+        let s = iface_ip.to_string();
+        self.iface_ips = if s.is_empty() { Vec::new() } else { vec![s] };
+    }
+
+    /// Like `set_interface()`, for several interfaces at once — e.g. a
+    /// tablet on both WiFi and USB Ethernet.  The private daemon is switched
+    /// off everywhere and then enabled on each entry.  A service heard on
+    /// more than one of them is still a single record, its addresses merged.
+    /// An empty array reverts to every interface.
+    ///
+    /// Entries are checked by `browse()`: each invalid one is reported
+    /// through its own `browse_error` (starting with `"set_interfaces"` and
+    /// quoting the entry) while the valid ones still take effect.  If none
+    /// is valid, nothing is browsed.
+    #[func]
+    fn set_interfaces(&mut self, ips: PackedStringArray) {
+        self.iface_ips = ips
+            .as_slice()
+            .iter()
+            .map(GString::to_string)
+            .filter(|ip| !ip.is_empty())
+            .collect();
+    }
+
+    /// Names of the interfaces the daemon behind this browser is bound to:
+    /// its private daemon while browsing with `set_interface()`, otherwise
+    /// the shared daemon (empty while that does not exist).  Unlike the OS
+    /// interface list, this leaves out what the allow/deny lists, exclusions
+    /// and IP version switched off.
+    ///
+    /// `mdns-sd` cannot report which multicast groups it joined, so this is
+    /// reconstructed as in `MdnsManager.get_bound_addresses()`; if that finds
+    /// nothing for a private daemon, the configured interface is returned.
+    #[func]
+    fn get_active_interfaces(&self) -> PackedStringArray {
+        let selection = self.private_daemon_selection();
+        let pinned = selection.is_some();
+        let mut names: Vec<String> = Vec::new();
+        for interface in daemon::bound_interfaces(selection) {
+            if !names.contains(&interface.name) {
+                names.push(interface.name);
+            }
+        }
+        if names.is_empty() && pinned {
+            names.extend(self.pinned_ifaces().into_iter().map(str::to_string));
+        }
+        names.iter().map(GString::from).collect()
+    }
+
+    /// Start browsing for `service_type`, e.g. `"_mygame._tcp.local."`.
+    ///
+    /// Calling `browse()` again while already browsing stops the previous search first.
+    /// The trailing dot in the service type is required by the mDNS spec.
+    ///
+    /// If the shared daemon is still being created in the background (first
+    /// use, or after `MdnsManager.warm_up()`), the browse starts on the first
+    /// frame after it is ready; `is_browsing()` is already `true` meanwhile
+    /// and a creation failure is reported through `browse_error`.
+    ///
+    /// Several browsers may browse the same type: they share one browse on
+    /// the daemon, a late one is told about the services already known, and
+    /// stopping one does not affect the others.
+    ///
+    /// On iOS, a type missing from `NSBonjourServices` in Info.plist is
+    /// reported through `browse_error` (`"bonjour_service_undeclared"`,
+    /// naming the item to add), since iOS then blocks the browse silently.
+    /// The browse is started anyway.
+    #[func]
+    pub(crate) fn browse(&mut self, service_type: GString) {
+        // Clean up any existing browse session.
+        self.stop_browsing();
+        self.auto_iface = None;
+        self.permission_reported = false;
+        if !self.empty_timeout.is_zero() {
+            self.empty_deadline = Some(Instant::now() + self.empty_timeout);
+        }
+        if cfg!(target_os = "android") && self.iface_ips.is_empty() {
+            self.select_wifi_interface();
+        }
+        if let Some(error) = bonjour_services::check(&service_type.to_string()) {
+            self.emit_browse_error(error);
+        }
+
+        if let Some(system) = backend::active_system() {
+            match system.browse(&service_type.to_string()) {
+                Ok(browse) => {
+                    self.browsing_type = Some(service_type.to_string());
+                    self.system = Some(browse);
+                    self.arm_search_timeout();
+                }
+                Err(e) => self.emit_browse_error(format!("system backend: {e}")),
+            }
+            return;
+        }
+
+        // Use the shared daemon to avoid dual-socket conflicts, unless an
+        // interface IP is pinned (Android path): then create a private daemon
+        // so we can restrict its interface without affecting the shared daemon
+        // that MdnsAdvertiser may be using.
+        if self.pinned_ifaces().is_empty() && self.daemon_port.is_none() {
+            self.browsing_type = Some(service_type.to_string());
+            self.browse_shared(daemon::poll_shared_daemon(None), false);
+            return;
+        }
+        let Some((daemon, receiver)) = self.start_private_browse(&service_type.to_string()) else {
+            return;
+        };
+        self.browsing_type = Some(service_type.to_string());
+        if self.dual_daemon && !self.pinned_ifaces().is_empty() && self.daemon_port.is_none() {
+            self.pinned = Some(PinnedBrowse { daemon, receiver });
+            self.browse_shared(daemon::poll_shared_daemon(None), false);
+            return;
+        }
+        self.daemon = Some(daemon);
+        self.receiver = Some(receiver);
+        self.arm_search_timeout();
+    }
+
+    /// Also browse on the shared all-interface daemon while `set_interface()`
+    /// pins this browser to a private daemon, merging both into one stream:
+    /// an instance seen through both is listed once, by fullname.  Off by
+    /// default; takes effect on the next `browse()`.  Ignored while
+    /// `set_daemon_port()` is set.
+    ///
+    /// **Warning:** this runs two daemons bound to port 5353 in one process —
+    /// the dual-socket setup the shared daemon exists to avoid.  Incoming
+    /// packets are split between the sockets (non-deterministically on
+    /// macOS), which is tolerable here only because both halves feed the same
+    /// browser; any `MdnsAdvertiser` on the shared daemon may miss queries
+    /// meanwhile.  A removal reported by either daemon removes the instance.
+    #[func]
+    fn set_dual_daemon(&mut self, enabled: bool) {
+        self.dual_daemon = enabled;
+    }
+
+    /// How long a new browse may go without any event from its daemon, not
+    /// even `SearchStarted`, before `browse_error` reports it as
+    /// `"daemon_unresponsive"` (and `MdnsManager.get_daemon_status()` calls
+    /// the shared daemon unresponsive).  Default 3000; 0 disables the check.
+    ///
+    /// The clock starts when the browse reaches the daemon and events are
+    /// drained before it is checked, so a long frame alone does not trip it.
+    /// A browse joining one another browser already runs is not checked:
+    /// its `SearchStarted` came before it joined.
+    #[func]
+    fn set_search_timeout_ms(&mut self, timeout_ms: i64) {
+        self.search_timeout = Duration::from_millis(timeout_ms.max(0) as u64);
+    }
+
+    /// Returns the `set_search_timeout_ms()` window in milliseconds.
+    #[func]
+    fn get_search_timeout_ms(&self) -> i64 {
+        self.search_timeout.as_millis() as i64
+    }
+
+    /// Emit `no_services_found` if a browse has discovered nothing `ms`
+    /// milliseconds after `browse()`.  In manual-resolve mode an instance
+    /// reported through `service_found` counts as found.  `0` (the default)
+    /// turns it off.  Takes effect on the next `browse()`.
+    #[func]
+    fn set_empty_deadline_ms(&mut self, ms: i64) {
+        self.empty_timeout = Duration::from_millis(ms.max(0) as u64);
+    }
+
+    /// Returns the `set_empty_deadline_ms()` window in milliseconds.
+    #[func]
+    fn get_empty_deadline_ms(&self) -> i64 {
+        self.empty_timeout.as_millis() as i64
+    }
+
+    /// Browse on a daemon of this node's own, bound to `port`, instead of the
+    /// shared one — e.g. to test without fighting a system mDNS responder,
+    /// or to isolate a few nodes on one machine.  `0` (the default) goes back
+    /// to the shared daemon.  Takes effect on the next `browse()`; combines
+    /// with `set_interface()`, while `set_dual_daemon()` is ignored.
+    ///
+    /// A daemon on a port other than 5353 only interoperates with nodes using
+    /// the same port (`MdnsAdvertiser.set_daemon_port()` or
+    /// `MdnsManager.set_daemon_port()`); standard mDNS peers on the LAN
+    /// neither see it nor are seen by it, which is logged as a warning.
+    /// Returns `false` (and emits `browse_error`) for a port outside
+    /// 0..=65535.
+    #[func]
+    fn set_daemon_port(&mut self, port: i64) -> bool {
+        match daemon::node_daemon_port(port) {
+            Ok(port) => {
+                self.daemon_port = port;
+                true
+            }
+            Err(e) => {
+                self.emit_browse_error(e);
+                false
+            }
+        }
+    }
+
+    /// Returns the `set_daemon_port()` port, `0` when on the shared daemon.
+    #[func]
+    fn get_daemon_port(&self) -> i64 {
+        self.daemon_port.map_or(0, i64::from)
+    }
+
+    /// Only list services whose port is within `min..=max` (inclusive), e.g.
+    /// 7000 and 7999 for deployments that give port ranges a meaning.
+    /// `0, 0` (the default) lists every port.
+    ///
+    /// Services outside the range are not cached either, so
+    /// `get_discovered_services()`, `get_live_count()` and
+    /// `is_service_present()` agree with the signals, and no
+    /// `service_removed` is emitted for a service that was never reported.
+    /// Listed services now outside the range are removed at once (with
+    /// `service_removed`), as is one that later moves out of it; services
+    /// that come into range are reported when `mdns-sd` next reports them —
+    /// call `browse()` again to relist at once.  Returns `false` (and emits
+    /// `browse_error`) unless both ends are within 0..=65535 and
+    /// `min <= max`.
+    #[func]
+    fn set_port_range(&mut self, min: i64, max: i64) -> bool {
+        let range = match (u16::try_from(min), u16::try_from(max)) {
+            (Ok(0), Ok(0)) => None,
+            (Ok(min), Ok(max)) if min <= max => Some((min, max)),
+            _ => {
+                self.emit_browse_error(format!(
+                    "set_port_range: {min}..={max} is not a port range within 0..=65535"
+                ));
+                return false;
+            }
+        };
+        self.port_range = range;
+        if let Some((min, max)) = range {
+            for service in self.cache.retain_ports(min, max) {
+                self.emit_removed(&service.fullname);
+            }
+        }
+        true
+    }
+
+    /// The `set_port_range()` bounds as `[min, max]`, `[0, 0]` without a
+    /// filter.
+    #[func]
+    fn get_port_range(&self) -> PackedInt64Array {
+        let (min, max) = self.port_range.unwrap_or((0, 0));
+        [i64::from(min), i64::from(max)].into_iter().collect()
+    }
+
+    /// Only list services with at least one address inside one of the
+    /// subnets in `cidr`, given in CIDR notation and separated by commas,
+    /// e.g. `"192.168.1.0/24"` or `"192.168.1.0/24, fd00::/8"`, to ignore
+    /// servers seen through a VPN or a virtual machine network.  `""` (the
+    /// default) lists every address.
+    ///
+    /// This filters what the browse reports rather than binding a socket, so
+    /// it works on the shared daemon and needs no local address, unlike
+    /// `set_interface()`.  Services outside are handled as by
+    /// `set_port_range()`: not cached, removed at once if listed, and
+    /// reported when `mdns-sd` next reports them once they match.  Returns
+    /// `false` (and emits `browse_error`) for a malformed subnet, keeping
+    /// the previous filter.
+    #[func]
+    fn set_subnet_filter(&mut self, cidr: GString) -> bool {
+        let subnets = match interfaces::parse_subnets(&cidr.to_string()) {
+            Ok(subnets) => subnets,
+            Err(e) => {
+                self.emit_browse_error(format!("set_subnet_filter: {e}"));
+                return false;
+            }
+        };
+        self.subnets = subnets;
+        let removed = self.cache.retain(|service| in_subnets(service, &self.subnets));
+        for service in removed {
+            self.emit_removed(&service.fullname);
+        }
+        true
+    }
+
+    /// The `set_subnet_filter()` subnets, normalized and separated by
+    /// `", "`; `""` without a filter.
+    #[func]
+    fn get_subnet_filter(&self) -> GString {
+        let subnets: Vec<String> = self.subnets.iter().map(Subnet::to_string).collect();
+        GString::from(&subnets.join(", "))
+    }
+
+    /// Rebuild the browse's private daemon when the app comes back from being
+    /// suspended (`NOTIFICATION_APPLICATION_RESUMED`, or no frame for 10 s),
+    /// then emit `browse_recovered`.  On by default; Android only.
+    ///
+    /// After the screen has been off for a while, several Android WiFi
+    /// drivers drop the daemon's multicast group membership and the browse
+    /// receives nothing until it is recreated.  Recovery picks the WiFi
+    /// address again (unless `set_interface()` fixed one), creates a new
+    /// daemon on it, re-issues the browse and has `MdnsManager` re-acquire
+    /// its `MulticastLock`.  The service list is kept.  Browses on the shared
+    /// daemon are left alone.  If the new daemon cannot be created the
+    /// browse stops with `browse_error`.
+    #[func]
+    fn set_resume_recovery(&mut self, enabled: bool) {
+        self.resume_recovery = enabled;
+    }
+
+    /// Returns `true` if private daemons are rebuilt after a suspension.
+    #[func]
+    fn is_resume_recovery_enabled(&self) -> bool {
+        self.resume_recovery
+    }
+
+    /// Handle `NOTIFICATION_APPLICATION_PAUSED` / `RESUMED`.  On by default
+    /// on Android and iOS, off elsewhere; turn it off if the game handles
+    /// its own lifecycle (e.g. stops browsing when paused).
+    ///
+    /// While paused, events are left queued and no signal is emitted.  On
+    /// resume the backlog is reduced to its net effect before it is
+    /// reported: a service that appeared and left meanwhile is not reported
+    /// at all, several updates arrive as the latest one, and a removal
+    /// followed by a reappearance as an update (or nothing).  The stale
+    /// eviction clock restarts for every listed service, and the
+    /// `set_search_timeout_ms()`, `set_empty_deadline_ms()` and
+    /// `browse_for()` deadlines are moved back by the time spent paused.
+    #[func]
+    fn set_handle_app_lifecycle(&mut self, enabled: bool) {
+        self.app_lifecycle = enabled;
+        if !enabled {
+            self.app_paused_at = None;
+        }
+    }
+
+    /// Returns `true` if pause/resume notifications are handled.
+    #[func]
+    fn is_handle_app_lifecycle_enabled(&self) -> bool {
+        self.app_lifecycle
+    }
+
+    /// On resume (see `set_handle_app_lifecycle()`), ask every listed service
+    /// to re-confirm itself (RFC 6762 §10.4); those that left while the app
+    /// was paused and stay silent for 10 seconds are reported through
+    /// `service_removed`.  Off by default.
+    #[func]
+    fn set_refresh_on_resume(&mut self, enabled: bool) {
+        self.refresh_on_resume = enabled;
+    }
+
+    /// Returns `true` if listed services are re-confirmed on resume.
+    #[func]
+    fn is_refresh_on_resume_enabled(&self) -> bool {
+        self.refresh_on_resume
+    }
+
+    /// Returns `true` if `set_dual_daemon()` is on.
+    #[func]
+    fn is_dual_daemon(&self) -> bool {
+        self.dual_daemon
+    }
+
+    /// Like `browse()`, but stops by itself after `duration_ms` milliseconds
+    /// and emits `browse_finished`, e.g. for a one-shot "scan for servers"
+    /// button.  Discovery signals are emitted as usual meanwhile; the services
+    /// found stay in `get_discovered_services()` until the next `browse()`.
+    ///
+    /// A browse that fails to start reports `browse_error` and never
+    /// finishes.  `stop_browsing()` cancels it without `browse_finished`.
+    #[func]
+    fn browse_for(&mut self, service_type: GString, duration_ms: i64) {
+        self.browse(service_type);
+        if self.is_browsing() {
+            let duration = Duration::from_millis(duration_ms.max(0) as u64);
+            self.browse_deadline = Some(Instant::now() + duration);
+        }
+    }
+
+    /// Every service currently listed, sorted by fullname, each as a
+    /// `Dictionary` with the parameters of `service_discovered`: `name`,
+    /// `host`, `addresses`, `port` and `txt`.  `description` is the `desc`
+    /// TXT entry set by `MdnsAdvertiser.set_description()`, or `""`, and
+    /// `available` is `false` while the server is marked unavailable with
+    /// `MdnsAdvertiser.set_available()`.  `addresses_scoped` has the
+    /// same addresses as `mdns-sd` reported them, every link-local IPv6 one
+    /// with its zone (`"fe80::1%en0"`), for custom socket binding.  With
+    /// `set_dedup_by_host(true)` only the service reported for each host is
+    /// listed, its `siblings` holding the fullnames of the others.
+    #[func]
+    pub(crate) fn get_discovered_services(&self) -> Array<VarDictionary> {
+        let mut out = Array::new();
+        for service in self.cache.services() {
+            if self.host_rows.as_ref().is_some_and(|rows| !rows.contains_key(&service.fullname)) {
+                continue;
+            }
+            out.push(&self.listed_dict(service));
+        }
+        out
+    }
+
+    /// The entry `get_discovered_services()` lists for `name`, or an empty
+    /// `Dictionary` if it is not listed.  Case and a missing trailing dot do
+    /// not matter.  With `set_compact_emission(true)` this is how to get the
+    /// TXT record and the full address list of one service.  A sibling
+    /// collapsed by `set_dedup_by_host()` is found too.
+    #[func]
+    fn get_service(&self, name: GString) -> VarDictionary {
+        self.cache
+            .find(&name.to_string())
+            .map(|service| self.listed_dict(service))
+            .unwrap_or_default()
+    }
+
+    /// `"[address%scope]:port"` for `name`'s first link-local IPv6 address,
+    /// with `scope` as its zone instead of the one `mdns-sd` reported, or
+    /// `""` if `name` is not listed or has no link-local IPv6 address.  An
+    /// empty `scope` keeps the reported zone.  Case and a missing trailing
+    /// dot in `name` do not matter.
+    ///
+    /// A link-local address needs the interface it is reached through, and
+    /// platforms spell that differently: `mdns-sd` reports the interface name
+    /// on Linux, Android, macOS and iOS (`"fe80::1%en0"`) but its numeric
+    /// index on Windows (`"fe80::1%12"`).  Linux, Android and Apple sockets
+    /// accept either form; Windows only accepts the index.  So pass the form
+    /// your socket API wants, e.g. the index from `IP.get_local_interfaces()`
+    /// when the reported zone is a name.
+    #[func]
+    fn get_scoped_connect_string(&self, name: GString, scope: GString) -> GString {
+        self.cache
+            .find(&name.to_string())
+            .and_then(|service| service.scoped_connect_string(&scope.to_string()))
+            .map(|connect| GString::from(&connect))
+            .unwrap_or_default()
+    }
+
+    /// Size in bytes of `name`'s TXT record as sent on the wire (RFC 6763
+    /// framing: one length byte per entry), or -1 if it is not listed.
+    /// RFC 6763 §6.2 recommends staying under 400 bytes; past about 1300 the
+    /// record no longer fits one Ethernet packet with the rest of the answer
+    /// and may arrive truncated.  Case and a missing trailing dot do not
+    /// matter.
+    #[func]
+    fn get_txt_byte_size(&self, name: GString) -> i64 {
+        self.cache
+            .find(&name.to_string())
+            .map_or(-1, |service| service.txt_byte_size() as i64)
+    }
+
+    /// The listed services as a JSON string, e.g. to save a "recent servers"
+    /// list or attach the discovery state to a bug report:
+    ///
+    /// ```json
+    /// {"format": "godot-mdns-cache", "version": 1, "exported_at": 1760600000.5,
+    ///  "services": [{"name": "...", "service_type": "...", "host": "...",
+    ///                "port": 7350, "addresses": ["192.168.1.20"],
+    ///                "txt": {"players": "3"}, "txt_flags": [],
+    ///                "last_seen": 1760599990.1, "stale": false}]}
+    /// ```
+    ///
+    /// `exported_at` and `last_seen` are Unix times in seconds; `addresses`
+    /// carry the `%zone` of link-local IPv6 ones; `txt_flags` lists the keys
+    /// sent without a value.  Services are sorted by fullname.
+    #[func]
+    fn export_cache_json(&self) -> GString {
+        let now = unix_now();
+        let mut services = VarArray::new();
+        for service in self.cache.services() {
+            let age = self
+                .cache
+                .last_seen(&service.fullname)
+                .map_or(0.0, |seen| seen.elapsed().as_secs_f64());
+            let addresses: PackedStringArray = service
+                .scoped_address_strings()
+                .iter()
+                .map(GString::from)
+                .collect();
+            let mut txt = VarDictionary::new();
+            for (key, value) in &service.txt {
+                txt.set(GString::from(key), GString::from(value));
+            }
+            let flags: PackedStringArray = service.txt_flags.iter().map(GString::from).collect();
+            let mut entry = VarDictionary::new();
+            entry.set("name", service.fullname.as_str());
+            entry.set("service_type", service.service_type.as_str());
+            entry.set("host", service.host.as_str());
+            entry.set("port", service.port as i64);
+            entry.set("addresses", addresses);
+            entry.set("txt", txt);
+            entry.set("txt_flags", flags);
+            entry.set("last_seen", now - age);
+            entry.set("stale", self.cache.is_stale(&service.fullname));
+            services.push(&entry.to_variant());
+        }
+        let mut root = VarDictionary::new();
+        root.set("format", CACHE_JSON_FORMAT);
+        root.set("version", CACHE_JSON_VERSION);
+        root.set("exported_at", now);
+        root.set("services", services);
+        Json::stringify_ex(&root.to_variant()).full_precision(true).done()
+    }
+
+    /// Restore services saved by `export_cache_json()` and report each one
+    /// not already listed through `service_discovered` (or
+    /// `service_discovered_compact`), in fullname order.  Services outside
+    /// `set_port_range()` or `set_subnet_filter()` are skipped; services
+    /// already listed keep their live data.
+    ///
+    /// With `mark_stale`, restored services are flagged `stale` (see
+    /// `is_service_stale()`) until `mdns-sd` reports them on the network
+    /// again.  Their `last_seen` time is kept either way, so
+    /// `set_stale_eviction_ms()` removes those that do not show up.
+    ///
+    /// The whole document is checked first: returns `false` with
+    /// `browse_error` and imports nothing if it is not valid JSON in this
+    /// format or any service is malformed.
+    #[func]
+    fn import_cache_json(&mut self, json: GString, mark_stale: bool) -> bool {
+        let services = match parse_cache_json(&json) {
+            Ok(services) => services,
+            Err(e) => {
+                self.emit_browse_error(format!("import_cache_json: {e}"));
+                return false;
+            }
+        };
+        let now = unix_now();
+        for (service, last_seen) in services {
+            if !self.passes_filters(&service) {
+                continue;
+            }
+            let age = Duration::try_from_secs_f64(now - last_seen).unwrap_or_default();
+            let seen = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+            if self.cache.restore(service.clone(), seen, mark_stale) {
+                self.emit_imported(&service);
+            }
+        }
+        true
+    }
+
+    /// Returns `true` if `name` was restored by `import_cache_json(json, true)`
+    /// and has not been seen on the network since.  Case and a missing
+    /// trailing dot do not matter.
+    #[func]
+    fn is_service_stale(&self, name: GString) -> bool {
+        self.cache
+            .find(&name.to_string())
+            .is_some_and(|service| self.cache.is_stale(&service.fullname))
+    }
+
+    /// Stop the active browse and release this node's daemon handle.
+    ///
+    /// For the shared daemon, dropping the clone does not shut down the background
+    /// thread — other users (e.g. `MdnsAdvertiser`) keep their own clones alive.
+    /// For the private Android daemon, dropping it here shuts it down because this
+    /// was the only clone.
+    #[func]
+    pub(crate) fn stop_browsing(&mut self) {
+        let cleared = !self.cache.is_empty();
+        // Tell a private daemon to stop the browse so it no longer sends
+        // multicast queries or queues events for this service type.
+        if let (Some(daemon), Some(svc_type), Some(_)) =
+            (&self.daemon, &self.browsing_type, &self.receiver)
+        {
+            daemon::stop_browse_private(daemon, svc_type);
+        }
+        self.stop_pinned();
+        // Drop receiver first so the browse channel flushes cleanly.  Dropping
+        // the subscription stops the shared browse only if no other browser
+        // of this type is left.
+        self.receiver = None;
+        self.subscription = None;
+        self.system = None;
+        self.browsing_type = None;
+        self.shared_generation = None;
+        self.recovering = false;
+        self.pending_daemon = None;
+        self.cache.clear();
+        self.clear_host_rows();
+        self.found.clear();
+        self.answered = false;
+        self.browse_deadline = None;
+        self.search_deadline = None;
+        self.empty_deadline = None;
+        // Drop daemon clone — does not shutdown shared daemon; only shuts down
+        // the private Android daemon (which has no other live clones).
+        self.daemon = None;
+        if cleared {
+            self.emit("services_cleared", &[]);
+        }
+    }
+
+    /// Returns `true` if a browse is currently active (or waiting for the
+    /// shared daemon to be created).
+    #[func]
+    pub(crate) fn is_browsing(&self) -> bool {
+        self.receiver.is_some()
+            || self.pinned.is_some()
+            || self.subscription.is_some()
+            || self.system.is_some()
+            || self.pending_daemon.is_some()
+    }
+
+    /// Returns the number of currently live services of `service_type`, or the
+    /// total across all types when `service_type` is empty.
+    ///
+    /// Returns `0` when this browser is not browsing `service_type`.
+    #[func]
+    fn get_live_count(&self, service_type: GString) -> i64 {
+        let ty = service_type.to_string();
+        if ty.is_empty() {
+            return self.cache.len() as i64;
+        }
+        match &self.browsing_type {
+            Some(browsing) if browsing.eq_ignore_ascii_case(&ty) => {
+                self.cache.count_of_type(&ty) as i64
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns `true` if the service `name` (a fullname as in
+    /// `service_discovered`, e.g. one saved from a previous session) is live
+    /// right now.  Case and a missing trailing dot do not matter.
+    ///
+    /// Only looks at what this browser has already resolved, so it answers
+    /// `false` until the browse has had a chance to hear the service: on a
+    /// cold start, `browse()` its type first and wait for
+    /// `service_discovered` or `first_response_received` (or a second or two)
+    /// before treating `false` as "gone".
+    #[func]
+    fn is_service_present(&self, name: GString) -> bool {
+        self.cache.contains(&name.to_string())
+    }
+
+    /// Emit `service_removed` for any cached service that has not been
+    /// (re-)resolved within `ms` milliseconds, regardless of its DNS TTL.
+    /// `0` (the default) turns eviction off and leaves removal entirely to
+    /// `mdns-sd`.
+    ///
+    /// This bounds how long a server that vanished without a goodbye stays
+    /// listed.  `mdns-sd` only reports a service again when one of its
+    /// records changes, so shortly before the window runs out (10 seconds,
+    /// or half a short window) the service is asked to confirm itself
+    /// (RFC 6762 §10.4).  One that answers stays listed and its window
+    /// starts over; one that stays silent is reported through
+    /// `service_removed` once the window has passed.  Advertisers need not
+    /// re-announce for it.  If the query cannot be sent, the service is
+    /// evicted (with `browse_error`).  An evicted service that is reported
+    /// again is re-discovered normally.
+    #[func]
+    pub(crate) fn set_stale_eviction_ms(&mut self, ms: i64) {
+        self.stale_eviction = (ms > 0).then(|| Duration::from_millis(ms as u64));
+    }
+
+    /// Returns the stale-eviction window in milliseconds (`0` = off).
+    #[func]
+    fn get_stale_eviction_ms(&self) -> i64 {
+        self.stale_eviction.map_or(0, |d| d.as_millis() as i64)
+    }
+
+    /// Enable or disable the per-frame `discovery_frame_stats` signal (off by default).
+    #[func]
+    fn set_emit_frame_stats(&mut self, enabled: bool) {
+        self.frame_stats = enabled.then(FrameStats::default);
+    }
+
+    /// Make `service_updated` carry only the changed fields (see the signal)
+    /// instead of a full snapshot, keeping the data crossing into GDScript
+    /// small when TXT records change often.  Off by default.
+    /// `get_discovered_services()` still returns complete entries.
+    #[func]
+    fn set_sparse_updates(&mut self, enabled: bool) {
+        self.sparse_updates = enabled;
+    }
+
+    /// Returns `true` if `service_updated` is sparse.
+    #[func]
+    fn is_sparse_updates_enabled(&self) -> bool {
+        self.sparse_updates
+    }
+
+    /// Skip `service_updated` when the only change is a temporary IPv6
+    /// privacy address rotated for another from the same /64 prefix, which
+    /// Windows and Linux hosts do every few hours.  Off by default.
+    /// `get_discovered_services()` still lists the current addresses.
+    #[func]
+    fn set_collapse_ipv6_churn(&mut self, enabled: bool) {
+        self.collapse_ipv6_churn = enabled;
+    }
+
+    /// Returns `true` if rotated IPv6 privacy addresses are not reported.
+    #[func]
+    fn is_collapse_ipv6_churn_enabled(&self) -> bool {
+        self.collapse_ipv6_churn
+    }
+
+    /// Tell TXT keys sent without a value apart from keys with an empty one.
+    /// RFC 6763 reads `key` as a boolean attribute that is set and `key=` as
+    /// an empty string; by default both come out as `""`.  When enabled,
+    /// valueless keys map to `true` instead, in `service_discovered`,
+    /// `service_updated` and `get_discovered_services()`.  Off by default.
+    #[func]
+    fn set_preserve_txt_empty(&mut self, enabled: bool) {
+        self.preserve_txt_empty = enabled;
+    }
+
+    /// Returns `true` if valueless TXT keys are reported as `true`.
+    #[func]
+    fn is_preserve_txt_empty_enabled(&self) -> bool {
+        self.preserve_txt_empty
+    }
+
+    /// Report discoveries through `service_discovered_compact(name,
+    /// connect_string)` instead of `service_discovered` / `service_updated`,
+    /// leaving the TXT record and address list on the Rust side, e.g. for a
+    /// server list that only shows names until one is picked.  Updates that
+    /// keep the connect string are not reported.  Off by default.
+    ///
+    /// The full data stays available: `get_service(name)` returns one
+    /// service's host, addresses, `addresses_scoped`, port, TXT and
+    /// description, and `get_discovered_services()` all of them.
+    /// `service_removed` is unaffected.
+    #[func]
+    fn set_compact_emission(&mut self, enabled: bool) {
+        self.compact_emission = enabled;
+    }
+
+    /// Returns `true` if discoveries arrive as `service_discovered_compact`.
+    #[func]
+    fn is_compact_emission_enabled(&self) -> bool {
+        self.compact_emission
+    }
+
+    /// Returns the stable integer ID of the service `name`, or -1 if this
+    /// browser has not reported it (case and a missing trailing dot do not
+    /// matter).
+    ///
+    /// IDs start at 1 and go up by one for every fullname seen for the first
+    /// time; an ID is never handed to another service.  A service keeps its
+    /// ID across updates, and one removed and rediscovered within ten minutes
+    /// gets it back; after that its ID is retired and it gets a new one.
+    /// The ID of a removed service stays readable until then.  `reset()`
+    /// forgets every ID and starts again from 1.
+    #[func]
+    fn get_service_id(&self, name: GString) -> i64 {
+        self.service_ids.get(&name.to_string()).unwrap_or(-1)
+    }
+
+    /// Also emit `service_discovered_with_id`, `service_updated_with_id` and
+    /// `service_removed_with_id`, which carry the service's stable ID (see
+    /// `get_service_id()`) as a last `id: int` argument, each right after
+    /// its plain counterpart.  The plain signals keep their parameters
+    /// either way.  Off by default.
+    #[func]
+    fn set_service_id_emission(&mut self, enabled: bool) {
+        self.service_id_emission = enabled;
+    }
+
+    /// Returns `true` if the service signals carry the stable ID.
+    #[func]
+    fn is_service_id_emission_enabled(&self) -> bool {
+        self.service_id_emission
+    }
+
+    /// Report one service per host instead of one per instance, for UIs
+    /// listing machines rather than, say, every room a server hosts.
+    /// Instances whose resolved host names match (case and trailing dot
+    /// aside) collapse into the first one seen, or the one
+    /// `set_dedup_prefer_key()` picks; only that one is reported through the
+    /// service signals and `get_discovered_services()`, whose entries then
+    /// list the others' fullnames under `siblings`.
+    ///
+    /// A sibling going away is not reported.  When the reported instance
+    /// goes away, or another one takes its place, `service_removed` is
+    /// emitted for it and `service_discovered` for the next one, so scripts
+    /// keep one row per host.  Switching this on or off while browsing
+    /// reports the difference the same way.  Off by default.
+    #[func]
+    fn set_dedup_by_host(&mut self, enabled: bool) {
+        if enabled == self.host_rows.is_some() {
+            return;
+        }
+        if enabled {
+            // Everything cached has been reported; hide what now collapses.
+            let rows: HashMap<String, String> = self
+                .cache
+                .services()
+                .into_iter()
+                .map(|service| (service.fullname.clone(), cache::host_group(service)))
+                .collect();
+            self.host_rows = Some(rows);
+            self.sync_all_hosts();
+            return;
+        }
+        let rows = self.host_rows.take().unwrap_or_default();
+        let hidden: Vec<CachedService> = self
+            .cache
+            .services()
+            .into_iter()
+            .filter(|service| !rows.contains_key(&service.fullname))
+            .cloned()
+            .collect();
+        for service in &hidden {
+            self.emit_discovered(service);
+        }
+    }
+
+    /// Returns `true` if `set_dedup_by_host()` is on.
+    #[func]
+    fn is_dedup_by_host_enabled(&self) -> bool {
+        self.host_rows.is_some()
+    }
+
+    /// With `set_dedup_by_host(true)`, let the instance with the largest
+    /// number in TXT entry `key` (e.g. `"players"`) stand for its host
+    /// instead of the first one seen, which still breaks ties.  Instances
+    /// without a number there rank last.  `""` (the default) keeps the first
+    /// one.  Applies at once to what is listed.
+    #[func]
+    fn set_dedup_prefer_key(&mut self, key: GString) {
+        let key = key.to_string();
+        self.dedup_prefer = if key.is_empty() { None } else { Some(key) };
+        self.sync_all_hosts();
+    }
+
+    /// The key set with `set_dedup_prefer_key()`; `""` if none.
+    #[func]
+    fn get_dedup_prefer_key(&self) -> GString {
+        GString::from(self.dedup_prefer.as_deref().unwrap_or(""))
+    }
+
+    /// Switch to two-phase discovery: instead of `service_discovered` for
+    /// every instance, emit `service_found(name)` and report an instance only
+    /// after `resolve_found(name)` — for UIs that list names first and show
+    /// details lazily.  Off by default.
+    ///
+    /// `mdns-sd` always resolves the instances it finds, so this does not
+    /// reduce network traffic; it limits the resolutions this node reports
+    /// (and your script processes) to the ones asked for.  Turning the mode
+    /// off releases every found instance at once.
+    #[func]
+    fn set_manual_resolve(&mut self, enabled: bool) {
+        self.manual_resolve = enabled;
+        if enabled {
+            return;
+        }
+        let mut held: Vec<_> = self.found.drain().filter_map(|(_, f)| f.held).collect();
+        held.sort_by(|a, b| a.fullname.cmp(&b.fullname));
+        for info in held {
+            self.on_service_resolved(info);
+        }
+    }
+
+    /// Returns `true` in manual-resolve mode.
+    #[func]
+    fn is_manual_resolve(&self) -> bool {
+        self.manual_resolve
+    }
+
+    /// Report `name` (as passed by `service_found`) through `service_discovered`
+    /// once it is resolved — immediately if it already is — and keep reporting
+    /// its updates.  Returns `false` if this browser has not found `name`, or
+    /// it was removed since.
+    #[func]
+    fn resolve_found(&mut self, name: GString) -> bool {
+        let name = name.to_string();
+        let Some(found) = self.found.get_mut(&name) else {
+            return false;
+        };
+        found.released = true;
+        if let Some(info) = found.held.take() {
+            self.on_service_resolved(info);
+        }
+        true
+    }
+
+    /// Return this node to its just-created behaviour without interrupting
+    /// the active browse, e.g. to reuse one browser across game modes.
+    ///
+    /// Resets to their defaults: `set_stale_eviction_ms()` (off),
+    /// `set_emit_frame_stats()` (off), `set_sparse_updates()` (off),
+    /// `set_collapse_ipv6_churn()` (off), `set_preserve_txt_empty()` (off),
+    /// `set_compact_emission()` (off), `set_service_id_emission()` (off,
+    /// forgetting every ID), `set_dedup_by_host()` (off, with no
+    /// `set_dedup_prefer_key()`), `set_port_range()` (every port),
+    /// `set_subnet_filter()` (every address),
+    /// `set_manual_resolve()` (off, dropping found and held instances),
+    /// `set_search_timeout_ms()` (3000) and `set_empty_deadline_ms()` (off,
+    /// cancelling a pending `no_services_found`).
+    ///
+    /// Empties `get_discovered_services()`, emitting `service_removed` for
+    /// each listed service (and each `service_found` instance not yet
+    /// released) under the settings in force before the reset.  While
+    /// browsing, the listed services are first asked to re-confirm
+    /// themselves, as on resume with `set_refresh_on_resume()`; those still
+    /// on the network come back through `service_discovered` when `mdns-sd`
+    /// reports them again — call `browse()` again to get the full list at
+    /// once.  `first_response_received` fires again.
+    ///
+    /// Left alone: the browse itself and its `browse_for()` deadline, the
+    /// exported `service_type` / `autostart`, `set_interface()`,
+    /// `set_interfaces()`, `set_dual_daemon()` and `set_daemon_port()` (they
+    /// describe the running browse), `set_handle_app_lifecycle()`,
+    /// `set_resume_recovery()` and `set_refresh_on_resume()` (they follow
+    /// the platform rather than the game mode), pending
+    /// `resolve_hostname()` lookups, and `MdnsManager`'s event history,
+    /// which is shared by every node.
+    #[func]
+    fn reset(&mut self) {
+        if self.browsing_type.is_some() && !self.cache.is_empty() {
+            if let Err(e) = self.refresh_listed(RESUME_VERIFY_TIMEOUT) {
+                self.emit_browse_error(format!("reset: {e}"));
+            }
+        }
+        self.clear_listed();
+        self.stale_eviction = None;
+        self.frame_stats = None;
+        self.sparse_updates = false;
+        self.collapse_ipv6_churn = false;
+        self.preserve_txt_empty = false;
+        self.compact_emission = false;
+        self.service_id_emission = false;
+        self.service_ids.clear();
+        self.host_rows = None;
+        self.dedup_prefer = None;
+        self.port_range = None;
+        self.subnets.clear();
+        self.manual_resolve = false;
+        self.found.clear();
+        self.search_timeout = Duration::from_millis(DEFAULT_SEARCH_TIMEOUT_MS);
+        self.empty_timeout = Duration::ZERO;
+        self.empty_deadline = None;
+        self.answered = false;
+    }
+
+    /// Look up the IP addresses of `host` directly, without service discovery —
+    /// e.g. for a `somehost.local.` typed in by the player or read from a QR code.
+    ///
+    /// `host` may be given as `"somehost"`, `"somehost.local"` or
+    /// `"somehost.local."`.  Emits `hostname_resolved` with the first answer, or
+    /// `hostname_resolve_failed` once `timeout_ms` elapses (default 3000 ms when
+    /// `timeout_ms <= 0`).  Works whether or not this node is browsing; a
+    /// second call for a host that is still pending is ignored.  Never blocks:
+    /// on an idle browser the query goes out once the shared daemon is up.
+    #[func]
+    fn resolve_hostname(&mut self, host: GString, timeout_ms: i64) {
+        let requested = host.to_string();
+        let fqdn = local_fqdn(&requested);
+        if self.hostname_lookups.iter().any(|l| l.fqdn == fqdn)
+            || self.pending_lookups.iter().any(|l| l.fqdn == fqdn)
+        {
+            return;
+        }
+        let timeout_ms = if timeout_ms > 0 {
+            timeout_ms as u64
+        } else {
+            DEFAULT_HOSTNAME_TIMEOUT_MS
+        };
+        let poll = match &self.daemon {
+            Some(d) => DaemonPoll::Ready(d.clone()),
+            None => daemon::poll_shared_daemon(None),
+        };
+        self.start_lookup(requested, fqdn, timeout_ms, poll);
+    }
+
+    // ── Internal helpers ─────────────────────────────────────────────────────
+
+    /// Cached entry for `fullname`, for `MdnsAdvertiser.re_advertise_discovered()`.
+    pub(crate) fn cached_service(&self, fullname: &str) -> Option<CachedService> {
+        self.cache.get(fullname).cloned()
+    }
+
+    /// Every cached service, sorted by fullname, for `LanLobby`.
+    pub(crate) fn listed_services(&self) -> Vec<CachedService> {
+        self.cache.services().into_iter().cloned().collect()
+    }
+
+    /// This browser's entry in `MdnsManager.get_debug_info()`.
+    pub(crate) fn debug_info(&self) -> VarDictionary {
+        let state = if self.subscription.is_some() && self.pinned.is_some() {
+            "browsing (shared + private daemon)"
+        } else if self.subscription.is_some() {
+            "browsing"
+        } else if self.receiver.is_some() {
+            "browsing (private daemon)"
+        } else if self.pending_daemon.is_some() {
+            "waiting for daemon"
+        } else {
+            "idle"
+        };
+        let queued = self.subscription.as_ref().map_or(0, |s| s.queued_events())
+            + self.private_receiver().map_or(0, |r| r.len());
+        let mut dict = VarDictionary::new();
+        dict.set("path", self.base().get_path().to_string());
+        dict.set("service_type", self.browsing_type.as_deref().unwrap_or(""));
+        dict.set("state", state);
+        dict.set("queued_events", queued as i64);
+        dict.set("cached_services", self.cache.len() as i64);
+        dict
+    }
+
+    /// Interface selection of the private daemon this browser browses on, as
+    /// `daemon::private_daemon()` completes it; `None` when not on one.
+    pub(crate) fn private_daemon_selection(&self) -> Option<InterfaceSelection> {
+        self.receiver.as_ref()?;
+        let ifaces = self.pinned_ifaces();
+        let selection = if ifaces.is_empty() {
+            InterfaceSelection::default()
+        } else {
+            pinned_selection(&ifaces).ok()?.0
+        };
+        Some(daemon::private_selection(selection))
+    }
+
+    /// Type browsed on the shared daemon, for attributing `MdnsManager`'s
+    /// `event` signal; `None` when idle or on a private daemon.
+    pub(crate) fn shared_browse_type(&self) -> Option<&str> {
+        self.subscription.as_ref()?;
+        self.browsing_type.as_deref()
+    }
+
+    /// Fullnames of cached services of `service_type`, for `MdnsManager.verify_cache()`.
+    pub(crate) fn cached_fullnames_of_type(&self, service_type: &str) -> Vec<String> {
+        self.cache.fullnames_of_type(service_type)
+    }
+
+    /// If the shared daemon was reset since this browser subscribed, re-issue
+    /// the browse on the new daemon and emit `daemon_reset`.  If it was shut
+    /// down because the extension is unloading, stop with a `browse_error`.  The cache is
+    /// kept so services still on the LAN are not reported as removed — unless
+    /// the reset was `MdnsManager.flush_cache()`, which reports every cached
+    /// service as removed so only what the new daemon finds is listed.
+    fn follow_daemon_reset(&mut self) {
+        let Some(seen) = self.shared_generation else {
+            return;
+        };
+        let current = daemon::generation();
+        if seen == current {
+            return;
+        }
+        if daemon::is_unloading() {
+            // The daemon is gone for good; drop the dead handles and go idle.
+            self.daemon = None;
+            self.stop_browsing();
+            self.emit_browse_error("mDNS extension unloading; browsing stopped".to_string());
+            return;
+        }
+        if daemon::was_shut_down_since(seen) {
+            // Report what the browse delivered before it was stopped.
+            self.drain_events();
+            self.daemon = None;
+            self.stop_browsing();
+            self.emit_browse_error(format!(
+                "{SHUTDOWN_ERROR}: MdnsManager.shutdown_all() stopped browsing"
+            ));
+            return;
+        }
+        let Some(service_type) = self.browsing_type.clone() else {
+            return;
+        };
+        if daemon::was_flushed_since(seen) {
+            self.clear_listed();
+        }
+        // The old daemon is already shut down and its bookkeeping dropped, so
+        // there is nothing to stop; just let go of the dead handles.
+        self.subscription = None;
+        self.daemon = None;
+        self.shared_generation = None;
+        self.recovering = daemon::was_recovered_since(seen);
+        self.browsing_type = Some(service_type);
+        self.browse_shared(daemon::poll_shared_daemon(None), true);
+    }
+
+    /// Polls the shared-daemon creation this browser is waiting on.
+    fn complete_pending_browse(&mut self) {
+        let Some(pending) = self.pending_daemon.take() else {
+            return;
+        };
+        let poll = daemon::poll_shared_daemon(Some(pending.attempt));
+        self.browse_shared(poll, pending.after_reset);
+    }
+
+    /// Subscribes `browsing_type` on the shared daemon once `poll` says it is
+    /// ready, keeps waiting while it is pending, or gives up on failure.
+    /// `after_reset` defers the `daemon_reset` signal until the outcome is known.
+    fn browse_shared(&mut self, poll: DaemonPoll, after_reset: bool) {
+        let Some(service_type) = self.browsing_type.clone() else {
+            return;
+        };
+        let subscribed = match poll {
+            DaemonPoll::Pending(attempt) => {
+                self.pending_daemon = Some(PendingDaemon {
+                    attempt,
+                    after_reset,
+                });
+                return;
+            }
+            DaemonPoll::Ready(d) => daemon::subscribe(&service_type).map(|sub| (d, sub)),
+            DaemonPoll::Failed(e) => Err(e),
+        };
+        match subscribed {
+            Ok((d, sub)) => {
+                if sub.started_browse() {
+                    self.arm_search_timeout();
+                }
+                self.daemon = Some(d);
+                self.subscription = Some(sub);
+                self.shared_generation = Some(daemon::generation());
+            }
+            Err(e) => {
+                self.stop_pinned();
+                self.browsing_type = None;
+                let cleared = !self.cache.is_empty();
+                self.cache.clear();
+                self.clear_host_rows();
+                if cleared {
+                    self.emit("services_cleared", &[]);
+                }
+                self.emit_browse_error(e);
+            }
+        }
+        if after_reset {
+            self.emit("daemon_reset", &[]);
+        }
+        if std::mem::take(&mut self.recovering) && self.subscription.is_some() {
+            self.emit("recovered", &[]);
+        }
+    }
+
+    /// Non-blocking drain — processes all queued events without blocking the main thread.
+    fn drain_events(&mut self) {
+        if let Some(subscription) = &self.subscription {
+            for event in subscription.take_events() {
+                self.handle_event(event);
+            }
+        }
+        while let Some(event) = self.next_private_event() {
+            self.handle_event(event);
+        }
+    }
+
+    /// The next event of the private daemon, if one is queued, published to
+    /// `MdnsManager`'s event bus on the way.
+    fn next_private_event(&mut self) -> Option<ServiceEvent> {
+        // Empty or disconnected — nothing more to process.
+        let event = self.private_receiver()?.try_recv().ok()?;
+        if daemon::wants_private_events() {
+            let service_type = self.browsing_type.clone().unwrap_or_default();
+            let owner = self.base().get_path().to_string();
+            daemon::publish_private(&service_type, owner, &event);
+        }
+        Some(event)
+    }
+
+    /// Ends a pause begun by `APPLICATION_PAUSED`: reports the backlog
+    /// coalesced and moves the timers past the frozen time; see
+    /// `set_handle_app_lifecycle()`.
+    fn resume_after_pause(&mut self) {
+        let Some(paused_at) = self.app_paused_at.take() else {
+            return;
+        };
+        let paused = paused_at.elapsed();
+        let mut backlog = self
+            .subscription
+            .as_ref()
+            .map(|subscription| subscription.take_events())
+            .unwrap_or_default();
+        while let Some(event) = self.next_private_event() {
+            backlog.push(event);
+        }
+        let events = lifecycle::coalesce(backlog, |fullname| {
+            self.cache.contains(fullname) || self.found.contains_key(fullname)
+        });
+        for event in events {
+            self.handle_event(event);
+        }
+        self.cache.touch_all(Instant::now());
+        for deadline in [
+            &mut self.search_deadline,
+            &mut self.empty_deadline,
+            &mut self.browse_deadline,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *deadline += paused;
+        }
+        if self.refresh_on_resume {
+            if let Err(e) = self.refresh_listed(RESUME_VERIFY_TIMEOUT) {
+                self.emit_browse_error(format!("refresh on resume: {e}"));
+            }
+        }
+    }
+
+    /// Asks every listed service to re-confirm itself within `timeout` on the
+    /// daemon serving the browse, for `set_refresh_on_resume()` and
+    /// `MdnsManager.refresh_all()`.  Services that answer with changed
+    /// records are reported through `service_updated`, silent ones are
+    /// removed once `timeout` has passed; the cache is left alone meanwhile.
+    pub(crate) fn refresh_listed(&self, timeout: Duration) -> Result<(), String> {
+        let fullnames: Vec<String> =
+            self.cache.services().iter().map(|service| service.fullname.clone()).collect();
+        self.verify_on_daemon(&fullnames, timeout)
+    }
+
+    /// Asks `fullnames` to re-confirm themselves within `timeout` on the
+    /// daemon serving the browse: this node's own, or the shared one.  A
+    /// system backend browse needs no asking.
+    pub(crate) fn verify_on_daemon(
+        &self,
+        fullnames: &[String],
+        timeout: Duration,
+    ) -> Result<(), String> {
+        if self.system.is_some() {
+            // The system responder confirms its own records and reports the
+            // instances that stop answering; there is nothing to ask.
+            return Ok(());
+        }
+        match (&self.daemon, &self.receiver) {
+            (Some(private), Some(_)) => fullnames.iter().try_for_each(|fullname| {
+                private
+                    .verify(fullname.clone(), timeout)
+                    .map_err(|e| format!("verify({fullname}) failed: {e}"))
+            }),
+            _ => daemon::verify(fullnames, timeout),
+        }
+    }
+
+    /// The type being browsed, if any.
+    pub(crate) fn browsing_type(&self) -> Option<&str> {
+        self.browsing_type.as_deref()
+    }
+
+    /// Starts the `set_search_timeout_ms()` clock for a browse that just
+    /// reached its daemon, unless one is already running.
+    fn arm_search_timeout(&mut self) {
+        if self.search_timeout.is_zero() || self.search_deadline.is_some() {
+            return;
+        }
+        self.search_deadline = Some(Instant::now() + self.search_timeout);
+    }
+
+    /// Reports a browse whose daemon stayed silent past its deadline.  Runs
+    /// after `drain_events()`, so anything delivered this frame counts.
+    fn check_search_timeout(&mut self) {
+        let Some(deadline) = self.search_deadline else {
+            return;
+        };
+        if Instant::now() < deadline {
+            return;
+        }
+        self.search_deadline = None;
+        if self.subscription.is_some() {
+            daemon::report_silent_browse();
+        }
+        let service_type = self.browsing_type.clone().unwrap_or_default();
+        self.emit_browse_error(format!(
+            "{DAEMON_UNRESPONSIVE_ERROR}: no response from the mDNS daemon within {} ms \
+             of browsing {service_type}",
+            self.search_timeout.as_millis()
+        ));
+    }
+
+    /// Emits `no_services_found` for a browse that is past its
+    /// `set_empty_deadline_ms()` window without having found anything.
+    fn check_empty_deadline(&mut self) {
+        let Some(deadline) = self.empty_deadline else {
+            return;
+        };
+        if !self.is_browsing() {
+            // Failed after starting, e.g. the daemon could not be created.
+            self.empty_deadline = None;
+            return;
+        }
+        if Instant::now() < deadline {
+            return;
+        }
+        self.empty_deadline = None;
+        if self.cache.is_empty() && self.found.is_empty() {
+            let service_type = GString::from(self.browsing_type.as_deref().unwrap_or(""));
+            self.emit("no_services_found", &[service_type.to_variant()]);
+        }
+    }
+
+    /// Browse channel of this browser's private daemon, alone or as the
+    /// pinned half of a dual-daemon browse, or of its system backend browse.
+    fn private_receiver(&self) -> Option<&mdns_sd::Receiver<ServiceEvent>> {
+        self.receiver
+            .as_ref()
+            .or_else(|| self.pinned.as_ref().map(|pinned| &pinned.receiver))
+            .or_else(|| self.system.as_ref().map(backend::Browse::receiver))
+    }
+
+    /// Creates the private daemon for `set_interface()` / `set_daemon_port()`
+    /// and starts browsing `service_type` on it.  Reports failures, and each
+    /// invalid interface skipped, through `browse_error`.
+    fn start_private_browse(
+        &mut self,
+        service_type: &str,
+    ) -> Option<(ServiceDaemon, mdns_sd::Receiver<ServiceEvent>)> {
+        let ifaces = self.pinned_ifaces();
+        let (selection, skipped) = if ifaces.is_empty() {
+            (Ok(InterfaceSelection::default()), Vec::new())
+        } else {
+            match pinned_selection(&ifaces) {
+                Ok((selection, skipped)) => (Ok(selection), skipped),
+                Err(e) => (Err(format!("set_interfaces: {e}")), Vec::new()),
+            }
+        };
+        for e in skipped {
+            self.emit_browse_error(format!("set_interfaces: {e}"));
+        }
+        let port = self.daemon_port;
+        let started = selection
+            .and_then(|selection| daemon::private_daemon_on(selection, port))
+            .and_then(|daemon| {
+                // On failure the private daemon is dropped here (shared one lives on).
+                let receiver = daemon::browse_private(&daemon, service_type)?;
+                Ok((daemon, receiver))
+            });
+        match started {
+            Ok(started) => Some(started),
+            Err(e) => {
+                self.emit_browse_error(e);
+                None
+            }
+        }
+    }
+
+    /// Emits `local_network_permission_denied` the first time during this
+    /// browse that the manager's probe found the permission denied.
+    fn check_local_network_permission(&mut self) {
+        if self.permission_reported || !local_network::applies() || !self.is_browsing() {
+            return;
+        }
+        if daemon::local_network_permission() == Permission::Denied {
+            self.permission_reported = true;
+            self.emit("local_network_permission_denied", &[]);
+        }
+    }
+
+    /// The interfaces the private daemon is pinned to: those given to
+    /// `set_interfaces()`, else the one picked on Android, else none.
+    fn pinned_ifaces(&self) -> Vec<&str> {
+        if self.iface_ips.is_empty() {
+            self.auto_iface.as_deref().into_iter().collect()
+        } else {
+            self.iface_ips.iter().map(String::as_str).collect()
+        }
+    }
+
+    /// Pins the next browse to the WiFi address, as `set_interface()` would,
+    /// and emits `interface_selected`; warns and leaves the browse on every
+    /// interface if there is none.
+    fn select_wifi_interface(&mut self) {
+        let Some(ip) = interfaces::pick_wifi_address(&LocalInterface::enumerate()) else {
+            godot_warn!(
+                "godot-mdns: no WiFi interface (wlan*/swlan* with a private IPv4 address) \
+                 is up, so the browse uses every interface, where Android may deliver no \
+                 multicast. Connect to WiFi, or call set_interface() with the right address."
+            );
+            return;
+        };
+        let ip = ip.to_string();
+        self.auto_iface = Some(ip.clone());
+        self.emit("interface_selected", &[GString::from(&ip).to_variant()]);
+    }
+
+    /// Replaces the private daemon of an active browse with a new one after
+    /// the app was suspended; see `set_resume_recovery()`.
+    fn recover_after_suspend(&mut self) {
+        if !cfg!(target_os = "android") || !self.resume_recovery || self.destroying {
+            return;
+        }
+        let Some(service_type) = self.browsing_type.clone() else {
+            return;
+        };
+        let dual = self.pinned.is_some();
+        if !dual && self.receiver.is_none() {
+            // Shared daemon: nothing of this node's own to rebuild.
+            return;
+        }
+        // Keep what the old daemon delivered, then let it go for good.
+        self.drain_events();
+        let old = if dual {
+            self.pinned.take().map(|pinned| pinned.daemon)
+        } else {
+            self.receiver = None;
+            self.daemon.take()
+        };
+        if let Some(old) = old {
+            daemon::stop_browse_private(&old, &service_type);
+            let _ = daemon::shutdown_private(&old, None);
+        }
+        if self.iface_ips.is_empty() {
+            // The WiFi address may have changed while asleep.
+            self.auto_iface = None;
+            self.select_wifi_interface();
+        }
+        daemon::request_multicast_relock();
+        let Some((new, receiver)) = self.start_private_browse(&service_type) else {
+            self.stop_browsing();
+            return;
+        };
+        if dual {
+            self.pinned = Some(PinnedBrowse {
+                daemon: new,
+                receiver,
+            });
+        } else {
+            self.daemon = Some(new);
+            self.receiver = Some(receiver);
+        }
+        self.emit("browse_recovered", &[]);
+    }
+
+    /// Stops the private half of a dual-daemon browse, if any.
+    fn stop_pinned(&mut self) {
+        if let (Some(pinned), Some(service_type)) = (self.pinned.take(), &self.browsing_type) {
+            daemon::stop_browse_private(&pinned.daemon, service_type);
+        }
+    }
+
+    /// Drops cached services older than the stale-eviction window and reports
+    /// them as removed.
+    fn evict_stale(&mut self) {
+        let Some(max_age) = self.stale_eviction else {
+            return;
+        };
+        if !self.is_browsing() {
+            // A finished `browse_for()` result is a snapshot; nothing refreshes it.
+            return;
+        }
+        let now = Instant::now();
+        self.cache.finish_confirmations(now);
+        let lead = EVICTION_CONFIRM_LEAD.min(max_age / 2);
+        let due = self.cache.due_for_confirmation(now, max_age, lead);
+        if !due.is_empty() {
+            match self.verify_on_daemon(&due, lead) {
+                Ok(()) => self.cache.start_confirmation(&due, now + lead),
+                Err(e) => {
+                    // Nobody can confirm them: evict them as they are.
+                    self.emit_browse_error(format!("stale eviction: {e}"));
+                    let evicted = due.iter().filter_map(|name| self.cache.remove(name)).collect();
+                    self.report_removed(evicted);
+                }
+            }
+        }
+        let evicted = self.cache.evict_stale(now, max_age);
+        self.report_removed(evicted);
+    }
+
+    /// Emits `service_removed` for services already dropped from the cache.
+    fn report_removed(&mut self, services: Vec<CachedService>) {
+        for service in services {
+            self.emit_removed(&service.fullname);
+        }
+    }
+
+    /// Empties the service list, emitting `service_removed` for every listed
+    /// service and every instance announced through `service_found` but not
+    /// released yet.
+    fn clear_listed(&mut self) {
+        let listed = self.cache.take_all();
+        self.report_removed(listed);
+        let mut unreleased: Vec<String> = self
+            .found
+            .drain()
+            .filter(|(_, found)| !found.released)
+            .map(|(name, _)| name)
+            .collect();
+        unreleased.sort();
+        for name in unreleased {
+            self.emit_service_removed(&name);
+        }
+    }
+
+    /// Emits and resets this frame's `discovery_frame_stats`, if enabled and non-empty.
+    fn emit_frame_stats(&mut self) {
+        let Some(stats) = self.frame_stats.as_mut() else {
+            return;
+        };
+        let FrameStats {
+            discovered,
+            updated,
+            removed,
+        } = std::mem::take(stats);
+        if discovered + updated + removed > 0 {
+            self.emit(
+                "discovery_frame_stats",
+                &[discovered.to_variant(), updated.to_variant(), removed.to_variant()],
+            );
+        }
+    }
+
+    /// Starts the query of a `resolve_hostname()` call once `poll` has a
+    /// daemon, keeps it waiting while the shared one is being created, or
+    /// reports the failure.
+    fn start_lookup(
+        &mut self,
+        requested: String,
+        fqdn: String,
+        timeout_ms: u64,
+        poll: DaemonPoll,
+    ) {
+        let started = match poll {
+            DaemonPoll::Pending(attempt) => {
+                self.pending_lookups.push(PendingLookup {
+                    requested,
+                    fqdn,
+                    timeout_ms,
+                    attempt,
+                });
+                return;
+            }
+            DaemonPoll::Ready(daemon) => daemon
+                .resolve_hostname(&fqdn, Some(timeout_ms))
+                .map(|receiver| (daemon, receiver))
+                .map_err(|e| format!("resolve_hostname({fqdn}) failed: {e}")),
+            DaemonPoll::Failed(e) => Err(e),
+        };
+        match started {
+            Ok((daemon, receiver)) => self.hostname_lookups.push(HostnameLookup {
+                requested,
+                fqdn,
+                daemon,
+                receiver,
+            }),
+            Err(e) => {
+                self.emit_browse_error(e);
+                self.emit("hostname_resolve_failed", &[GString::from(&requested).to_variant()]);
+            }
+        }
+    }
+
+    /// Starts the lookups that were waiting for the shared daemon, once its
+    /// creation has finished.
+    fn complete_pending_lookups(&mut self) {
+        for pending in std::mem::take(&mut self.pending_lookups) {
+            let poll = daemon::poll_shared_daemon(Some(pending.attempt));
+            self.start_lookup(pending.requested, pending.fqdn, pending.timeout_ms, poll);
+        }
+    }
+
+    /// Non-blocking poll of every pending hostname lookup.  A lookup finishes
+    /// on its first `AddressesFound` or when the daemon reports the timeout.
+    fn drain_hostname_lookups(&mut self) {
+        let mut i = 0;
+        while i < self.hostname_lookups.len() {
+            let lookup = &self.hostname_lookups[i];
+            let mut outcome = None;
+            loop {
+                match lookup.receiver.try_recv() {
+                    Ok(HostnameResolutionEvent::AddressesFound(_, addrs)) => {
+                        outcome = Some(Some(cache::ipv4_first(&addrs)));
+                        break;
+                    }
+                    Ok(HostnameResolutionEvent::SearchTimeout(_))
+                    | Ok(HostnameResolutionEvent::SearchStopped(_)) => {
+                        outcome = Some(None);
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(_) => {
+                        if lookup.receiver.is_disconnected() {
+                            outcome = Some(None);
+                        }
+                        break;
+                    }
+                }
+            }
+            let Some(result) = outcome else {
+                i += 1;
+                continue;
+            };
+
+            let lookup = self.hostname_lookups.remove(i);
+            let _ = lookup.daemon.stop_resolve_hostname(&lookup.fqdn);
+            let host = GString::from(&lookup.requested).to_variant();
+            match result {
+                Some(addrs) => {
+                    let mut addresses = PackedStringArray::new();
+                    for addr in &addrs {
+                        addresses.push(addr.to_string().as_str());
+                    }
+                    self.emit("hostname_resolved", &[host, addresses.to_variant()]);
+                }
+                None => {
+                    self.emit("hostname_resolve_failed", &[host]);
+                }
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: ServiceEvent) {
+        self.search_deadline = None;
+        if self.destroying {
+            return;
+        }
+        if !self.answered
+            && matches!(
+                event,
+                ServiceEvent::ServiceFound(..) | ServiceEvent::ServiceResolved(_)
+            )
+        {
+            self.answered = true;
+            daemon::note_network_heard();
+            let service_type = GString::from(self.browsing_type.as_deref().unwrap_or(""));
+            self.emit("first_response_received", &[service_type.to_variant()]);
+        }
+        match event {
+            ServiceEvent::ServiceFound(_, fullname) if self.manual_resolve => {
+                self.note_found(&fullname);
+            }
+            ServiceEvent::ServiceResolved(info) => {
+                if self.manual_resolve {
+                    // A resolution may overtake its own ServiceFound.
+                    let found = self.note_found(&info.fullname);
+                    if !found.released {
+                        found.held = Some(info);
+                        return;
+                    }
+                }
+                self.on_service_resolved(info);
+            }
+            ServiceEvent::ServiceRemoved(_, fullname) => {
+                let found = self.found.remove(&fullname).is_some();
+                let listed = self.cache.remove(&fullname).is_some();
+                // Only what scripts were told about: a listed service, or an
+                // instance announced through `service_found`.  Anything else
+                // was filtered out, evicted or already cleared.
+                if found || listed {
+                    self.emit_removed(&fullname);
+                }
+            }
+            // SearchStarted / SearchStopped (and ServiceFound outside manual
+            // mode) are informational; ignored here.
+            _ => {}
+        }
+    }
+
+    /// Emits `service_found` the first time `fullname` is seen in manual mode.
+    fn note_found(&mut self, fullname: &str) -> &mut FoundService {
+        if !self.found.contains_key(fullname) {
+            self.found.insert(fullname.to_string(), FoundService::default());
+            self.emit("service_found", &[GString::from(fullname).to_variant()]);
+        }
+        self.found.get_mut(fullname).expect("inserted above")
+    }
+
+    fn on_service_resolved(&mut self, info: Box<ResolvedService>) {
+        if self.destroying {
+            return;
+        }
+        let service = CachedService::from_resolved(&info);
+        if !self.passes_filters(&service) {
+            // Moved out of the filters: it was listed, so report it gone.
+            if self.cache.remove(&service.fullname).is_some() {
+                self.emit_removed(&service.fullname);
+            }
+            return;
+        }
+        let name = GString::from(&service.fullname);
+        let host = GString::from(&service.host);
+        let port = service.port as i64;
+        let addresses = address_array(&service);
+        let txt = txt_dict(&service, self.preserve_txt_empty);
+        let diff = if self.sparse_updates {
+            self.cache.get(&service.fullname).map(|previous| previous.diff(&service))
+        } else {
+            None
+        };
+        let flags = if self.preserve_txt_empty { service.txt_flags.clone() } else { Vec::new() };
+        let connect = service.connect_string();
+        let connect_changed = self
+            .cache
+            .get(&service.fullname)
+            .is_none_or(|previous| previous.connect_string() != connect);
+        let churn = self.collapse_ipv6_churn
+            && self
+                .cache
+                .get(&service.fullname)
+                .is_some_and(|previous| previous.only_ipv6_rotated(&service));
+
+        let id = self.service_ids.assign(&service.fullname, Instant::now());
+        let group = cache::host_group(&service);
+        let change = self.cache.upsert(service);
+        if change == Change::Discovered {
+            self.empty_deadline = None;
+        }
+        if churn {
+            // Cached for get_discovered_services(), not worth a signal.
+            return;
+        }
+        if self.host_rows.is_some() && !self.keeps_host_row(&name.to_string(), &group) {
+            return;
+        }
+        if let Some(stats) = self.frame_stats.as_mut() {
+            match change {
+                Change::Discovered => stats.discovered += 1,
+                Change::Updated => stats.updated += 1,
+                Change::Unchanged => {}
+            }
+        }
+        if self.compact_emission {
+            if change != Change::Unchanged && connect_changed {
+                self.emit(
+                    "service_discovered_compact",
+                    &[name.to_variant(), GString::from(&connect).to_variant()],
+                );
+            }
+            return;
+        }
+        let signal = match change {
+            Change::Discovered => "service_discovered",
+            Change::Updated => "service_updated",
+            // Same data re-announced (possibly reordered): nothing to report.
+            Change::Unchanged => return,
+        };
+        if let (Change::Updated, Some(diff)) = (change, diff) {
+            self.emit_sparse_update(name, diff, &flags, id);
+            return;
+        }
+
+        let args = vec![
+            name.to_variant(),
+            host.to_variant(),
+            addresses.to_variant(),
+            port.to_variant(),
+            txt.to_variant(),
+        ];
+        self.emit_with_id(signal, args, id);
+    }
+
+    /// Returns `true` if `service` is within `set_port_range()` and
+    /// `set_subnet_filter()`.
+    fn passes_filters(&self, service: &CachedService) -> bool {
+        self.port_range
+            .is_none_or(|(min, max)| (min..=max).contains(&service.port))
+            && in_subnets(service, &self.subnets)
+    }
+
+    /// Reports a service restored by `import_cache_json()` as discovered.
+    fn emit_imported(&mut self, service: &CachedService) {
+        self.empty_deadline = None;
+        if self.host_rows.is_some() {
+            self.sync_host(&cache::host_group(service));
+            return;
+        }
+        self.emit_discovered(service);
+    }
+
+    /// Emits `service_discovered` (or `service_discovered_compact`) for a
+    /// cached `service`, counting it for frame stats.
+    fn emit_discovered(&mut self, service: &CachedService) {
+        let id = self.service_ids.assign(&service.fullname, Instant::now());
+        if let Some(stats) = self.frame_stats.as_mut() {
+            stats.discovered += 1;
+        }
+        let name = GString::from(&service.fullname);
+        if self.compact_emission {
+            let connect = GString::from(&service.connect_string());
+            self.emit("service_discovered_compact", &[name.to_variant(), connect.to_variant()]);
+            return;
+        }
+        let args = vec![
+            name.to_variant(),
+            GString::from(&service.host).to_variant(),
+            address_array(service).to_variant(),
+            (service.port as i64).to_variant(),
+            txt_dict(service, self.preserve_txt_empty).to_variant(),
+        ];
+        self.emit_with_id("service_discovered", args, id);
+    }
+
+    /// Emits `service_updated` with only the fields in `diff`; the keys in
+    /// `flags` were sent without a value and are reported as `true`.
+    fn emit_sparse_update(
+        &mut self,
+        name: GString,
+        diff: ServiceDiff,
+        flags: &[String],
+        id: i64,
+    ) {
+        let host = GString::from(diff.host.as_deref().unwrap_or(""));
+        let addresses: PackedStringArray = diff
+            .addresses
+            .unwrap_or_default()
+            .iter()
+            .map(GString::from)
+            .collect();
+        let port = diff.port.map_or(-1, i64::from);
+        let mut txt = VarDictionary::new();
+        for (key, value) in &diff.txt {
+            let value = match value {
+                None => Variant::nil(),
+                Some(_) if flags.contains(key) => true.to_variant(),
+                Some(v) => GString::from(v).to_variant(),
+            };
+            txt.set(GString::from(key), value);
+        }
+        let args = vec![
+            name.to_variant(),
+            host.to_variant(),
+            addresses.to_variant(),
+            port.to_variant(),
+            txt.to_variant(),
+        ];
+        self.emit_with_id("service_updated", args, id);
+    }
+
+    /// Ends a `browse_for()` browse whose time is up, keeping what it found.
+    fn finish_timed_browse(&mut self) {
+        let Some(deadline) = self.browse_deadline else {
+            return;
+        };
+        if !self.is_browsing() {
+            // Failed after starting, e.g. the daemon could not be created.
+            self.browse_deadline = None;
+            return;
+        }
+        if Instant::now() < deadline {
+            return;
+        }
+        let service_type = GString::from(self.browsing_type.as_deref().unwrap_or(""));
+        let found = std::mem::take(&mut self.cache);
+        let rows = self.host_rows.clone();
+        self.stop_browsing();
+        self.cache = found;
+        self.host_rows = rows;
+        self.emit("browse_finished", &[service_type.to_variant()]);
+    }
+
+    /// Emits `service_removed` for `fullname`, counting it for frame stats.
+    /// With `set_dedup_by_host()` only a reported service is, and the next
+    /// instance on its host takes its place.
+    fn emit_removed(&mut self, fullname: &str) {
+        if let Some(rows) = self.host_rows.as_mut() {
+            let Some(group) = rows.remove(fullname) else {
+                // A sibling scripts were never told about.
+                self.service_ids.release(fullname, Instant::now());
+                return;
+            };
+            self.signal_removed(fullname);
+            self.sync_host(&group);
+            return;
+        }
+        self.signal_removed(fullname);
+    }
+
+    fn signal_removed(&mut self, fullname: &str) {
+        if let Some(stats) = self.frame_stats.as_mut() {
+            stats.removed += 1;
+        }
+        self.emit_service_removed(fullname);
+    }
+
+    /// Brings the reported service of host `group` in line with the cache
+    /// under `set_dedup_by_host()`: reports others of the group as removed
+    /// and its representative as discovered if it was not reported yet.
+    fn sync_host(&mut self, group: &str) {
+        let Some(rows) = &self.host_rows else {
+            return;
+        };
+        let representative = self
+            .cache
+            .host_representative(group, self.dedup_prefer.as_deref())
+            .cloned();
+        let wanted = representative.as_ref().map(|service| service.fullname.as_str());
+        let mut replaced: Vec<String> = rows
+            .iter()
+            .filter(|(fullname, row)| *row == group && Some(fullname.as_str()) != wanted)
+            .map(|(fullname, _)| fullname.clone())
+            .collect();
+        replaced.sort();
+        let missing = wanted.is_some_and(|fullname| !rows.contains_key(fullname));
+        for fullname in replaced {
+            if let Some(rows) = self.host_rows.as_mut() {
+                rows.remove(&fullname);
+            }
+            self.signal_removed(&fullname);
+        }
+        if let (true, Some(service)) = (missing, representative) {
+            if let Some(rows) = self.host_rows.as_mut() {
+                rows.insert(service.fullname.clone(), group.to_string());
+            }
+            self.emit_discovered(&service);
+        }
+    }
+
+    /// [`sync_host`](Self::sync_host) for every host listed or cached.
+    fn sync_all_hosts(&mut self) {
+        let Some(rows) = &self.host_rows else {
+            return;
+        };
+        let mut groups: Vec<String> = rows.values().cloned().collect();
+        groups.extend(self.cache.services().into_iter().map(cache::host_group));
+        groups.sort();
+        groups.dedup();
+        for group in groups {
+            self.sync_host(&group);
+        }
+    }
+
+    /// Handles a resolution of `fullname`, now cached under host `group`,
+    /// under `set_dedup_by_host()`.  Returns `true` if it was reported before
+    /// and still stands for its host, so the usual update signal applies;
+    /// otherwise what changed has been reported here.
+    fn keeps_host_row(&mut self, fullname: &str, group: &str) -> bool {
+        let Some(rows) = self.host_rows.as_mut() else {
+            return true;
+        };
+        let previous = rows.remove(fullname);
+        let representative = self
+            .cache
+            .host_representative(group, self.dedup_prefer.as_deref())
+            .is_some_and(|service| service.fullname == fullname);
+        let kept = previous.is_some() && representative;
+        if kept {
+            rows.insert(fullname.to_string(), group.to_string());
+        } else if previous.is_some() {
+            self.signal_removed(fullname);
+        }
+        self.sync_host(group);
+        if let Some(old) = previous.filter(|old| old != group) {
+            self.sync_host(&old);
+        }
+        kept
+    }
+
+    /// Drops the `set_dedup_by_host()` rows along with the cache.
+    fn clear_host_rows(&mut self) {
+        if let Some(rows) = self.host_rows.as_mut() {
+            rows.clear();
+        }
+    }
+
+    /// [`service_dict`] plus, under `set_dedup_by_host()`, the `siblings`
+    /// sharing the service's host.
+    fn listed_dict(&self, service: &CachedService) -> VarDictionary {
+        let mut dict = service_dict(service, self.preserve_txt_empty);
+        if self.host_rows.is_some() {
+            let siblings: PackedStringArray = self
+                .cache
+                .host_siblings(&service.fullname)
+                .iter()
+                .map(GString::from)
+                .collect();
+            dict.set("siblings", siblings);
+        }
+        dict
+    }
+
+    /// Emits `service_removed` for `fullname`, starting the reuse window of
+    /// its ID.
+    fn emit_service_removed(&mut self, fullname: &str) {
+        let id = self.service_ids.release(fullname, Instant::now());
+        let args = vec![GString::from(fullname).to_variant()];
+        self.emit_with_id("service_removed", args, id.unwrap_or(-1));
+    }
+
+    /// Emits `signal` with `args`, then, with `set_service_id_emission()`,
+    /// its `_with_id` twin with `id` appended.
+    fn emit_with_id(&mut self, signal: &str, mut args: Vec<Variant>, id: i64) {
+        self.emit(signal, &args);
+        if self.service_id_emission {
+            args.push(id.to_variant());
+            self.emit(&format!("{signal}_with_id"), &args);
+        }
+    }
+
+    fn emit_browse_error(&mut self, msg: String) {
+        self.emit("browse_error", &[GString::from(msg.as_str()).to_variant()]);
+    }
+
+    /// Every signal of the node goes out through here, so none does once
+    /// `exit_tree` has begun; see `destroying`.
+    fn emit(&mut self, signal: &str, args: &[Variant]) {
+        if self.destroying {
+            return;
+        }
+        main_thread::emit(&mut self.base_mut(), signal, args);
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Helpers
+// ---------------------------------------------------------------------------
+
+/// The addresses of `service` as `service_discovered` reports them.
+pub(crate) fn address_array(service: &CachedService) -> PackedStringArray {
+    service
+        .address_strings()
+        .iter()
+        .map(GString::from)
+        .collect()
+}
+
+/// Returns `true` if `service` has an address inside one of `subnets`, or
+/// `subnets` is empty.
+fn in_subnets(service: &CachedService, subnets: &[Subnet]) -> bool {
+    subnets.is_empty()
+        || service
+            .addresses
+            .iter()
+            .any(|ip| subnets.iter().any(|subnet| subnet.contains(ip)))
+}
+
+/// `service` as `get_discovered_services()` lists it.
+fn service_dict(service: &CachedService, preserve_empty: bool) -> VarDictionary {
+    let mut dict = VarDictionary::new();
+    dict.set("name", service.fullname.as_str());
+    dict.set("host", service.host.as_str());
+    dict.set("addresses", address_array(service));
+    let scoped: PackedStringArray = service
+        .scoped_address_strings()
+        .iter()
+        .map(GString::from)
+        .collect();
+    dict.set("addresses_scoped", scoped);
+    dict.set("port", service.port as i64);
+    dict.set("txt", txt_dict(service, preserve_empty));
+    dict.set("description", service.description());
+    dict.set("available", service.is_available());
+    dict
+}
+
+/// Current Unix time in seconds.
+fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |since| since.as_secs_f64())
+}
+
+/// Parses and checks an `export_cache_json()` document, returning each
+/// service (sorted by fullname) with its `last_seen` Unix time.
+fn parse_cache_json(json: &GString) -> Result<Vec<(CachedService, f64)>, String> {
+    let mut parser = Json::new_gd();
+    if parser.parse(json) != Error::OK {
+        return Err(format!(
+            "invalid JSON at line {}: {}",
+            parser.get_error_line(),
+            parser.get_error_message()
+        ));
+    }
+    let root: VarDictionary = parser
+        .get_data()
+        .try_to()
+        .map_err(|_| "the document is not a JSON object".to_string())?;
+    if root.get("format").and_then(|f| f.try_to::<GString>().ok())
+        != Some(GString::from(CACHE_JSON_FORMAT))
+    {
+        return Err(format!("\"format\" is not \"{CACHE_JSON_FORMAT}\""));
+    }
+    let version = json_number(&root, "version")?;
+    if version != CACHE_JSON_VERSION as f64 {
+        return Err(format!("unsupported version {version}"));
+    }
+    let entries: VarArray = root
+        .get("services")
+        .and_then(|services| services.try_to().ok())
+        .ok_or_else(|| "\"services\" is not an array".to_string())?;
+    let mut services = Vec::new();
+    for (index, entry) in entries.iter_shared().enumerate() {
+        let parsed = entry
+            .try_to::<VarDictionary>()
+            .map_err(|_| "not an object".to_string())
+            .and_then(|entry| exported_service(&entry));
+        services.push(parsed.map_err(|e| format!("services[{index}]: {e}"))?);
+    }
+    services.sort_by(|a, b| a.0.fullname.cmp(&b.0.fullname));
+    if let Some(pair) = services.windows(2).find(|pair| pair[0].0.fullname == pair[1].0.fullname) {
+        return Err(format!("{} is listed twice", pair[0].0.fullname));
+    }
+    Ok(services)
+}
+
+/// One entry of `export_cache_json()`'s `services` array.
+fn exported_service(entry: &VarDictionary) -> Result<(CachedService, f64), String> {
+    let text = |key: &str| {
+        entry
+            .get(key)
+            .and_then(|value| value.try_to::<GString>().ok())
+            .map(|value| value.to_string())
+            .ok_or_else(|| format!("\"{key}\" is not a string"))
+    };
+    let strings = |key: &str| -> Result<Vec<String>, String> {
+        let array: VarArray = entry
+            .get(key)
+            .and_then(|value| value.try_to().ok())
+            .ok_or_else(|| format!("\"{key}\" is not an array"))?;
+        array
+            .iter_shared()
+            .map(|item| item.try_to::<GString>().map(|item| item.to_string()))
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("\"{key}\" holds a non-string"))
+    };
+    let port = json_number(entry, "port")?;
+    if port.fract() != 0.0 {
+        return Err(format!("invalid port {port}"));
+    }
+    let txt_dict: VarDictionary = entry
+        .get("txt")
+        .and_then(|value| value.try_to().ok())
+        .ok_or_else(|| "\"txt\" is not an object".to_string())?;
+    let mut txt = Vec::new();
+    for (key, value) in txt_dict.iter_shared() {
+        match (key.try_to::<GString>(), value.try_to::<GString>()) {
+            (Ok(key), Ok(value)) => txt.push((key.to_string(), value.to_string())),
+            _ => return Err("\"txt\" holds a non-string value".to_string()),
+        }
+    }
+    let service = CachedService::from_exported(ExportedService {
+        service_type: text("service_type")?,
+        fullname: text("name")?,
+        host: text("host")?,
+        port: port as i64,
+        addresses: strings("addresses")?,
+        txt,
+        txt_flags: strings("txt_flags")?,
+    })?;
+    Ok((service, json_number(entry, "last_seen")?))
+}
+
+/// The number under `key`; JSON numbers parse as floats.
+fn json_number(dict: &VarDictionary, key: &str) -> Result<f64, String> {
+    dict.get(key)
+        .and_then(|value| value.try_to::<f64>().ok())
+        .filter(|number| number.is_finite())
+        .ok_or_else(|| format!("\"{key}\" is not a number"))
+}
+
+/// The TXT record of `service` as a `String → String` dictionary.  With
+/// `preserve_empty`, keys sent without a value map to `true` instead of `""`.
+pub(crate) fn txt_dict(service: &CachedService, preserve_empty: bool) -> VarDictionary {
+    let mut txt = VarDictionary::new();
+    for (key, val) in &service.txt {
+        if preserve_empty && service.txt_flags.contains(key) {
+            txt.set(GString::from(key), true);
+        } else {
+            txt.set(GString::from(key), GString::from(val));
+        }
+    }
+    txt
+}
+
+/// The selection of the private daemon for `set_interfaces(ifaces)`: those
+/// interfaces only, plus the errors of the entries left out.  Fails if no
+/// entry is valid.
+fn pinned_selection(ifaces: &[&str]) -> Result<(InterfaceSelection, Vec<String>), String> {
+    let (allowed, skipped) = interfaces::parse_valid_interfaces(ifaces);
+    if allowed.is_empty() {
+        return Err(skipped.join("; "));
+    }
+    let selection = InterfaceSelection {
+        allowed,
+        ..Default::default()
+    };
+    Ok((selection, skipped))
+}
+
+/// Every `MdnsBrowser` in `tree`, for the helpers on `MdnsAdvertiser` and
+/// `MdnsManager` that look at what the browsers have discovered.
+pub(crate) fn browsers_in_tree(tree: &Gd<SceneTree>) -> Vec<Gd<MdnsBrowser>> {
+    let Some(root) = tree.get_root() else {
+        return Vec::new();
+    };
+    root.upcast::<Node>()
+        .find_children_ex("*")
+        .type_("MdnsBrowser")
+        .owned(false)
+        .done()
+        .iter_shared()
+        .filter_map(|node| node.try_cast::<MdnsBrowser>().ok())
+        .collect()
+}
+
+/// Normalises `"host"`, `"host.local"` and `"host.local."` to `"host.local."`,
+/// the only form `mdns-sd` accepts for hostname queries.
+fn local_fqdn(host: &str) -> String {
+    let host = host.trim().trim_end_matches('.');
+    let stem = match host.len().checked_sub(".local".len()) {
+        Some(at) if host.is_char_boundary(at) && host[at..].eq_ignore_ascii_case(".local") => {
+            &host[..at]
+        }
+        _ => host,
+    };
+    format!("{stem}.local.")
+}
@@ -0,0 +1,823 @@
+//! Discovered-service cache used by `MdnsBrowser`.
+//!
+//! This module is deliberately free of Godot types so it can be exercised by the
+//! integration tests without an engine (see `tests/service_cache.rs`).  The
+//! browser converts entries to `GString` / `VarDictionary` only at emission time.
+
+use mdns_sd::{ResolvedService, ScopedIp};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// TXT key of the longer, human-readable description that goes with the
+/// short instance name; see `MdnsAdvertiser.set_description()`.
+pub const DESCRIPTION_KEY: &str = "desc";
+
+/// Longest description that fits one TXT entry (255 bytes, `desc=` included).
+pub const MAX_DESCRIPTION_LEN: usize = 255 - DESCRIPTION_KEY.len() - 1;
+
+/// TXT key telling whether a server takes players; see
+/// `MdnsAdvertiser.set_available()`.
+pub const STATUS_KEY: &str = "status";
+
+/// [`STATUS_KEY`] value of a server that takes players.
+pub const STATUS_UP: &str = "up";
+
+/// [`STATUS_KEY`] value of a server that is listed but not taking players.
+pub const STATUS_MAINTENANCE: &str = "maintenance";
+
+/// A resolved service as last seen by the browser.
+#[derive(Debug, Clone)]
+pub struct CachedService {
+    /// Service type including the domain, e.g. `"_mygame._tcp.local."`.
+    pub service_type: String,
+    /// Full instance name, e.g. `"My Server._mygame._tcp.local."`.
+    pub fullname: String,
+    pub host: String,
+    pub port: u16,
+    /// Addresses sorted IPv4-first (see [`sorted_addresses`]).
+    pub addresses: Vec<IpAddr>,
+    /// Zone of each link-local IPv6 address in `addresses`, as the platform
+    /// writes it after the `%`: the interface name, or its index on Windows.
+    pub zones: BTreeMap<IpAddr, String>,
+    /// TXT key/value pairs in the order they appeared on the wire.  A key
+    /// sent without a value is listed with an empty one.
+    pub txt: Vec<(String, String)>,
+    /// Keys of `txt` that were sent without a value (`key` rather than
+    /// `key=`), which RFC 6763 reads as boolean attributes.
+    pub txt_flags: Vec<String>,
+}
+
+impl CachedService {
+    pub fn from_resolved(info: &ResolvedService) -> Self {
+        Self {
+            service_type: info.ty_domain.clone(),
+            fullname: info.get_fullname().to_string(),
+            host: info.get_hostname().to_string(),
+            port: info.get_port(),
+            addresses: sorted_addresses(info),
+            zones: link_local_zones(info.get_addresses()),
+            txt: info
+                .get_properties()
+                .iter()
+                .map(|p| (p.key().to_string(), p.val_str().to_string()))
+                .collect(),
+            txt_flags: info
+                .get_properties()
+                .iter()
+                .filter(|p| p.val().is_none())
+                .map(|p| p.key().to_string())
+                .collect(),
+        }
+    }
+
+    /// Rebuilds a service saved by `MdnsBrowser.export_cache_json()`,
+    /// checking every field: `fullname` must be an instance of
+    /// `service_type`, `port` a valid port, each address an IP (link-local
+    /// IPv6 ones optionally with their `%zone`), and each TXT flag a TXT key.
+    pub fn from_exported(exported: ExportedService) -> Result<Self, String> {
+        let ExportedService {
+            service_type,
+            fullname,
+            host,
+            port,
+            addresses,
+            txt,
+            txt_flags,
+        } = exported;
+        let type_part = service_type.trim_end_matches('.');
+        if !type_part.starts_with('_') || !type_part.contains("._") {
+            return Err(format!("\"{service_type}\" is not a service type"));
+        }
+        let cut = fullname.len().checked_sub(service_type.len() + 1);
+        let instance_ok = cut.is_some_and(|cut| {
+            cut > 0
+                && fullname.get(cut..).is_some_and(|suffix| {
+                    suffix.starts_with('.') && suffix[1..].eq_ignore_ascii_case(&service_type)
+                })
+        });
+        if !instance_ok {
+            return Err(format!("\"{fullname}\" is not an instance of {service_type}"));
+        }
+        if host.is_empty() {
+            return Err(format!("{fullname}: empty host"));
+        }
+        let port = u16::try_from(port).map_err(|_| format!("{fullname}: invalid port {port}"))?;
+        let mut ips = Vec::new();
+        let mut zones = BTreeMap::new();
+        for address in &addresses {
+            let (ip, zone) = match address.split_once('%') {
+                Some((ip, zone)) => (ip, Some(zone)),
+                None => (address.as_str(), None),
+            };
+            let ip = IpAddr::from_str(ip)
+                .map_err(|_| format!("{fullname}: \"{address}\" is not an IP address"))?;
+            if let Some(zone) = zone {
+                if !ip.is_ipv6() || zone.is_empty() {
+                    return Err(format!("{fullname}: invalid zone in \"{address}\""));
+                }
+                zones.insert(ip, zone.to_string());
+            }
+            ips.push(ip);
+        }
+        ips.sort_by_key(|ip| (ip.is_ipv6(), *ip));
+        ips.dedup();
+        if txt.iter().any(|(key, _)| key.is_empty()) {
+            return Err(format!("{fullname}: empty TXT key"));
+        }
+        let is_key = |flag: &String| txt.iter().any(|(key, _)| key == flag);
+        if let Some(flag) = txt_flags.iter().find(|flag| !is_key(flag)) {
+            return Err(format!("{fullname}: TXT flag \"{flag}\" is not a TXT key"));
+        }
+        Ok(Self {
+            service_type,
+            fullname,
+            host,
+            port,
+            addresses: ips,
+            zones,
+            txt,
+            txt_flags,
+        })
+    }
+
+    /// Returns `true` if `key` was sent without a value (matched
+    /// case-insensitively, like every TXT key).
+    pub fn is_txt_flag(&self, key: &str) -> bool {
+        self.txt_flags.iter().any(|flag| flag.eq_ignore_ascii_case(key))
+    }
+
+    /// Returns `true` if `other` carries the same records.  Addresses and TXT
+    /// pairs compare as sets: `mdns-sd` hands addresses over in `HashSet`
+    /// order, so a re-announcement of identical data may arrive reordered.
+    pub fn same_records(&self, other: &CachedService) -> bool {
+        fn sorted<T: Ord + Clone>(items: &[T]) -> Vec<T> {
+            let mut items = items.to_vec();
+            items.sort();
+            items.dedup();
+            items
+        }
+        self.host == other.host
+            && self.port == other.port
+            && sorted(&self.addresses) == sorted(&other.addresses)
+            && self.zones == other.zones
+            && sorted(&self.txt) == sorted(&other.txt)
+            && sorted(&self.txt_flags) == sorted(&other.txt_flags)
+    }
+
+    /// Value of the [`DESCRIPTION_KEY`] TXT entry (matched case-insensitively,
+    /// first one wins); empty if there is none.
+    pub fn description(&self) -> &str {
+        self.txt_value(DESCRIPTION_KEY).unwrap_or("")
+    }
+
+    /// Value of the TXT entry `key`, matched case-insensitively, first one
+    /// wins.
+    pub fn txt_value(&self, key: &str) -> Option<&str> {
+        self.txt
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// `false` if the [`STATUS_KEY`] TXT entry (matched as
+    /// [`description`](Self::description)) holds anything but [`STATUS_UP`],
+    /// in any case; a service without one is available.
+    pub fn is_available(&self) -> bool {
+        self.txt_value(STATUS_KEY)
+            .is_none_or(|value| value.eq_ignore_ascii_case(STATUS_UP))
+    }
+
+    /// Size of the TXT record's RDATA on the wire (RFC 6763 §6): one length
+    /// byte per entry plus `key=value`, or just `key` for a valueless one.
+    /// An empty record is sent as a single empty string, one byte.
+    pub fn txt_byte_size(&self) -> usize {
+        if self.txt.is_empty() {
+            return 1;
+        }
+        self.txt
+            .iter()
+            .map(|(key, value)| {
+                let body = if self.is_txt_flag(key) { 0 } else { 1 + value.len() };
+                1 + key.len() + body
+            })
+            .sum()
+    }
+
+    /// Returns `true` if `newer` differs from `self` only in IPv6 addresses
+    /// replaced by others from the same /64 prefix — the hourly rotation of
+    /// temporary privacy addresses (RFC 8981).  Host, port, TXT and every
+    /// IPv4 address must be unchanged.
+    pub fn only_ipv6_rotated(&self, newer: &CachedService) -> bool {
+        fn sorted<T: Ord + Clone>(items: &[T]) -> Vec<T> {
+            let mut items = items.to_vec();
+            items.sort();
+            items.dedup();
+            items
+        }
+        if self.host != newer.host
+            || self.port != newer.port
+            || sorted(&self.txt) != sorted(&newer.txt)
+        {
+            return false;
+        }
+        let old: BTreeSet<IpAddr> = self.addresses.iter().copied().collect();
+        let new: BTreeSet<IpAddr> = newer.addresses.iter().copied().collect();
+        let prefixes = |changed: BTreeSet<&IpAddr>| -> Option<BTreeSet<[u16; 4]>> {
+            changed
+                .into_iter()
+                .map(|ip| match ip {
+                    IpAddr::V6(v6) => {
+                        let s = v6.segments();
+                        Some([s[0], s[1], s[2], s[3]])
+                    }
+                    IpAddr::V4(_) => None,
+                })
+                .collect()
+        };
+        let gone = prefixes(old.difference(&new).collect());
+        let added = prefixes(new.difference(&old).collect());
+        old != new && gone.is_some() && gone == added
+    }
+
+    /// `addresses` as the strings the browser emits.
+    ///
+    /// A service reachable over IPv6 only — typically an IoT-style peer with
+    /// nothing but an `fe80::` address — is unusable without the interface
+    /// its link-local addresses belong to, so for such services those carry
+    /// their zone (`"fe80::1%en0"`).  Services with any IPv4 address keep
+    /// plain strings, since `addresses[0]` is IPv4 anyway.
+    pub fn address_strings(&self) -> Vec<String> {
+        let ipv6_only = self.addresses.iter().all(IpAddr::is_ipv6);
+        self.addresses
+            .iter()
+            .map(|ip| match self.zones.get(ip) {
+                Some(zone) if ipv6_only => format!("{ip}%{zone}"),
+                _ => ip.to_string(),
+            })
+            .collect()
+    }
+
+    /// `"address:port"` to connect to: the first of
+    /// [`address_strings`](Self::address_strings) (IPv4 when there is one),
+    /// bracketed if IPv6 (`"[fe80::1%en0]:4000"`), or the host name without
+    /// its trailing dot if no address is known.
+    pub fn connect_string(&self) -> String {
+        match self.address_strings().first() {
+            Some(address) if address.contains(':') => format!("[{address}]:{}", self.port),
+            Some(address) => format!("{address}:{}", self.port),
+            None => format!("{}:{}", self.host.trim_end_matches('.'), self.port),
+        }
+    }
+
+    /// `addresses` in the form `mdns-sd` reported them: every link-local
+    /// IPv6 address carries its zone, whatever other addresses there are.
+    pub fn scoped_address_strings(&self) -> Vec<String> {
+        self.addresses
+            .iter()
+            .map(|ip| match self.zones.get(ip) {
+                Some(zone) => format!("{ip}%{zone}"),
+                None => ip.to_string(),
+            })
+            .collect()
+    }
+
+    /// `"[address%scope]:port"` for the first link-local IPv6 address, with
+    /// `scope` in place of the zone `mdns-sd` reported (a leading `%` is
+    /// dropped).  An empty `scope` keeps the reported zone, if any.  `None`
+    /// if the service has no link-local IPv6 address.
+    pub fn scoped_connect_string(&self, scope: &str) -> Option<String> {
+        let ip = self.addresses.iter().find(|ip| match ip {
+            IpAddr::V6(v6) => v6.is_unicast_link_local(),
+            IpAddr::V4(_) => false,
+        })?;
+        let scope = scope.trim().trim_start_matches('%');
+        let zone = match scope {
+            "" => self.zones.get(ip).map(String::as_str),
+            scope => Some(scope),
+        };
+        Some(match zone {
+            Some(zone) => format!("[{ip}%{zone}]:{}", self.port),
+            None => format!("[{ip}]:{}", self.port),
+        })
+    }
+
+    /// What `newer` changes relative to `self`.  Addresses (with their
+    /// zones) compare as sets, as in [`same_records`](Self::same_records).
+    pub fn diff(&self, newer: &CachedService) -> ServiceDiff {
+        fn sorted(mut items: Vec<String>) -> Vec<String> {
+            items.sort();
+            items.dedup();
+            items
+        }
+        let old_txt: BTreeMap<&str, &str> = self
+            .txt
+            .iter()
+            .rev()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let new_keys: HashSet<&str> = newer.txt.iter().map(|(key, _)| key.as_str()).collect();
+        let mut seen = HashSet::new();
+        let mut txt: Vec<(String, Option<String>)> = newer
+            .txt
+            .iter()
+            .filter(|(key, _)| seen.insert(key.as_str()))
+            .filter(|(key, value)| {
+                old_txt.get(key.as_str()) != Some(&value.as_str())
+                    || self.is_txt_flag(key) != newer.is_txt_flag(key)
+            })
+            .map(|(key, value)| (key.clone(), Some(value.clone())))
+            .collect();
+        txt.extend(
+            old_txt
+                .keys()
+                .filter(|key| !new_keys.contains(*key))
+                .map(|key| (key.to_string(), None)),
+        );
+        let moved = sorted(self.scoped_address_strings()) != sorted(newer.scoped_address_strings());
+        ServiceDiff {
+            host: (self.host != newer.host).then(|| newer.host.clone()),
+            port: (self.port != newer.port).then_some(newer.port),
+            addresses: moved.then(|| newer.address_strings()),
+            txt,
+        }
+    }
+}
+
+/// What changed between two records of one service, for the sparse
+/// `service_updated` of `MdnsBrowser.set_sparse_updates()`.  `None` / empty
+/// means unchanged.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ServiceDiff {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    /// The new [`CachedService::address_strings`], if the address set changed.
+    pub addresses: Option<Vec<String>>,
+    /// Added or changed TXT keys with their new value, and removed keys with
+    /// `None`; in the order of the newer record, removals last.  A key that
+    /// only gained or lost its `=` counts as changed.
+    pub txt: Vec<(String, Option<String>)>,
+}
+
+/// What [`ServiceCache::upsert`] did with a resolved service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Not cached before.
+    Discovered,
+    /// Cached before with different records.
+    Updated,
+    /// Cached before with the same records; only the last-seen time moved.
+    Unchanged,
+}
+
+/// The fields of a service read back from `MdnsBrowser.export_cache_json()`,
+/// not yet checked; see [`CachedService::from_exported`].
+#[derive(Debug, Clone, Default)]
+pub struct ExportedService {
+    pub service_type: String,
+    pub fullname: String,
+    pub host: String,
+    pub port: i64,
+    /// As [`CachedService::scoped_address_strings`] writes them.
+    pub addresses: Vec<String>,
+    pub txt: Vec<(String, String)>,
+    pub txt_flags: Vec<String>,
+}
+
+/// Live services keyed by fullname.
+#[derive(Debug, Default)]
+pub struct ServiceCache {
+    entries: HashMap<String, Entry>,
+    /// Arrival number of the next service not cached yet.
+    next_arrival: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    service: CachedService,
+    /// When `mdns-sd` last reported this service as resolved.
+    last_seen: Instant,
+    /// Restored from a saved list and not confirmed on the network since;
+    /// see [`ServiceCache::restore`].
+    stale: bool,
+    /// Order in which services were first cached; kept across updates.
+    arrival: u64,
+    /// While `mdns-sd` is asked to confirm the service before it would be
+    /// evicted, when that ends; see [`ServiceCache::start_confirmation`].
+    confirm_until: Option<Instant>,
+}
+
+impl ServiceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts or replaces the entry for `service.fullname`, returning the
+    /// previous one.  Marks the service as seen now.
+    pub fn insert(&mut self, service: CachedService) -> Option<CachedService> {
+        let arrival = match self.entries.get(&service.fullname) {
+            Some(previous) => previous.arrival,
+            None => self.take_arrival(),
+        };
+        let entry = Entry {
+            service,
+            last_seen: Instant::now(),
+            stale: false,
+            arrival,
+            confirm_until: None,
+        };
+        self.entries
+            .insert(entry.service.fullname.clone(), entry)
+            .map(|prev| prev.service)
+    }
+
+    /// Like [`insert`](Self::insert), but reports whether anything the
+    /// browser would emit actually changed.
+    pub fn upsert(&mut self, service: CachedService) -> Change {
+        let unchanged = self
+            .get(&service.fullname)
+            .map(|previous| previous.same_records(&service));
+        self.insert(service);
+        match unchanged {
+            None => Change::Discovered,
+            Some(false) => Change::Updated,
+            Some(true) => Change::Unchanged,
+        }
+    }
+
+    /// Adds a service saved earlier, last seen at `last_seen`, unless
+    /// `fullname` is already cached (a live entry is newer).  `stale` flags
+    /// it as unconfirmed until `mdns-sd` reports it again.  Returns `true` if
+    /// it was added.
+    pub fn restore(&mut self, service: CachedService, last_seen: Instant, stale: bool) -> bool {
+        if self.contains(&service.fullname) {
+            return false;
+        }
+        let entry = Entry {
+            service,
+            last_seen,
+            stale,
+            arrival: self.take_arrival(),
+            confirm_until: None,
+        };
+        self.entries.insert(entry.service.fullname.clone(), entry);
+        true
+    }
+
+    /// Returns `true` if `fullname` was restored as stale and has not been
+    /// reported since.
+    pub fn is_stale(&self, fullname: &str) -> bool {
+        self.entries.get(fullname).is_some_and(|entry| entry.stale)
+    }
+
+    /// When `mdns-sd` last reported `fullname`.
+    pub fn last_seen(&self, fullname: &str) -> Option<Instant> {
+        self.entries.get(fullname).map(|entry| entry.last_seen)
+    }
+
+    pub fn get(&self, fullname: &str) -> Option<&CachedService> {
+        self.entries.get(fullname).map(|entry| &entry.service)
+    }
+
+    /// Like [`get`](Self::get), but DNS names are case-insensitive and the
+    /// trailing dot is optional, so `"Host._game._udp.local"` finds
+    /// `"host._game._udp.local."`.
+    pub fn find(&self, fullname: &str) -> Option<&CachedService> {
+        if let Some(service) = self.get(fullname) {
+            return Some(service);
+        }
+        let wanted = fullname.trim_end_matches('.');
+        self.entries
+            .iter()
+            .find(|(cached, _)| cached.trim_end_matches('.').eq_ignore_ascii_case(wanted))
+            .map(|(_, entry)| &entry.service)
+    }
+
+    /// Returns `true` if `fullname` is cached, matched as by
+    /// [`find`](Self::find).
+    pub fn contains(&self, fullname: &str) -> bool {
+        self.find(fullname).is_some()
+    }
+
+    pub fn remove(&mut self, fullname: &str) -> Option<CachedService> {
+        self.entries.remove(fullname).map(|entry| entry.service)
+    }
+
+    /// Every cached service, sorted by fullname.
+    pub fn services(&self) -> Vec<&CachedService> {
+        let mut all: Vec<&CachedService> = self.entries.values().map(|e| &e.service).collect();
+        all.sort_by(|a, b| a.fullname.cmp(&b.fullname));
+        all
+    }
+
+    /// Fullnames of the cached services of `service_type`, sorted.
+    pub fn fullnames_of_type(&self, service_type: &str) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .entries
+            .values()
+            .filter(|entry| entry.service.service_type.eq_ignore_ascii_case(service_type))
+            .map(|entry| entry.service.fullname.clone())
+            .collect();
+        names.sort();
+        names
+    }
+
+    /// Removes and returns every cached service, sorted by fullname.
+    pub fn take_all(&mut self) -> Vec<CachedService> {
+        let mut all: Vec<CachedService> =
+            self.entries.drain().map(|(_, entry)| entry.service).collect();
+        all.sort_by(|a, b| a.fullname.cmp(&b.fullname));
+        all
+    }
+
+    /// Removes and returns every service last seen more than `max_age` before
+    /// `now`, sorted by fullname so removals are reported in a stable order.
+    /// Services being confirmed are kept until the confirmation ends.
+    pub fn evict_stale(&mut self, now: Instant, max_age: Duration) -> Vec<CachedService> {
+        let stale: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                entry.confirm_until.is_none()
+                    && now.saturating_duration_since(entry.last_seen) > max_age
+            })
+            .map(|(fullname, _)| fullname.clone())
+            .collect();
+        let mut evicted: Vec<CachedService> =
+            stale.iter().filter_map(|fullname| self.remove(fullname)).collect();
+        evicted.sort_by(|a, b| a.fullname.cmp(&b.fullname));
+        evicted
+    }
+
+    /// Fullnames of the services that [`evict_stale`](Self::evict_stale)
+    /// would drop within `lead` of `now` and that are not being confirmed,
+    /// sorted.  Services restored from a saved list and not reported since
+    /// are left out: `mdns-sd` has nothing to confirm for them.
+    pub fn due_for_confirmation(
+        &self,
+        now: Instant,
+        max_age: Duration,
+        lead: Duration,
+    ) -> Vec<String> {
+        let age = max_age.saturating_sub(lead);
+        let mut due: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| {
+                !entry.stale
+                    && entry.confirm_until.is_none()
+                    && now.saturating_duration_since(entry.last_seen) >= age
+            })
+            .map(|(fullname, _)| fullname.clone())
+            .collect();
+        due.sort();
+        due
+    }
+
+    /// Marks `fullnames` as being confirmed by `mdns-sd` until `until`.  One
+    /// that does not answer is removed through the browse; one still cached
+    /// afterwards answered, and [`finish_confirmations`](Self::finish_confirmations)
+    /// counts it as seen.
+    pub fn start_confirmation(&mut self, fullnames: &[String], until: Instant) {
+        for fullname in fullnames {
+            if let Some(entry) = self.entries.get_mut(fullname) {
+                entry.confirm_until = Some(until);
+            }
+        }
+    }
+
+    /// Ends every confirmation that ran out by `now`, marking those services
+    /// as seen at `now`: they are still cached, so they answered.  An update
+    /// meanwhile ends a confirmation too.
+    pub fn finish_confirmations(&mut self, now: Instant) {
+        for entry in self.entries.values_mut() {
+            if entry.confirm_until.is_some_and(|until| until <= now) {
+                entry.confirm_until = None;
+                entry.last_seen = now;
+            }
+        }
+    }
+
+    /// Removes and returns every service whose port is outside `min..=max`,
+    /// sorted by fullname.
+    pub fn retain_ports(&mut self, min: u16, max: u16) -> Vec<CachedService> {
+        self.retain(|service| (min..=max).contains(&service.port))
+    }
+
+    /// Removes and returns every service `keep` rejects, sorted by fullname.
+    pub fn retain(&mut self, mut keep: impl FnMut(&CachedService) -> bool) -> Vec<CachedService> {
+        let rejected: Vec<String> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| !keep(&entry.service))
+            .map(|(fullname, _)| fullname.clone())
+            .collect();
+        let mut removed: Vec<CachedService> =
+            rejected.iter().filter_map(|fullname| self.remove(fullname)).collect();
+        removed.sort_by(|a, b| a.fullname.cmp(&b.fullname));
+        removed
+    }
+
+    /// The service standing for every instance of host `group` (see
+    /// [`host_group`]) under `MdnsBrowser.set_dedup_by_host()`: the one with
+    /// the largest number in TXT entry `prefer` if given, else, and among
+    /// equals, the one cached first.  Entries without a number rank last.
+    pub fn host_representative(&self, group: &str, prefer: Option<&str>) -> Option<&CachedService> {
+        let rank = |entry: &Entry| {
+            prefer
+                .and_then(|key| entry.service.txt_value(key))
+                .and_then(|value| value.trim().parse::<f64>().ok())
+                .filter(|value| !value.is_nan())
+                .unwrap_or(f64::NEG_INFINITY)
+        };
+        self.entries
+            .values()
+            .filter(|entry| host_group(&entry.service) == group)
+            .max_by(|a, b| rank(a).total_cmp(&rank(b)).then(b.arrival.cmp(&a.arrival)))
+            .map(|entry| &entry.service)
+    }
+
+    /// Fullnames of the other services on the host of `fullname`, in the
+    /// order they were cached; empty if it is not cached.
+    pub fn host_siblings(&self, fullname: &str) -> Vec<String> {
+        let Some(service) = self.get(fullname) else {
+            return Vec::new();
+        };
+        let group = host_group(service);
+        let mut siblings: Vec<&Entry> = self
+            .entries
+            .values()
+            .filter(|entry| entry.service.fullname != fullname)
+            .filter(|entry| host_group(&entry.service) == group)
+            .collect();
+        siblings.sort_by_key(|entry| entry.arrival);
+        siblings.iter().map(|entry| entry.service.fullname.clone()).collect()
+    }
+
+    fn take_arrival(&mut self) -> u64 {
+        self.next_arrival += 1;
+        self.next_arrival
+    }
+
+    /// Marks every service as seen at `now`, restarting stale eviction, e.g.
+    /// after the process was frozen and nothing could be heard.
+    pub fn touch_all(&mut self, now: Instant) {
+        for entry in self.entries.values_mut() {
+            entry.last_seen = now;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Number of cached services whose type matches `service_type`.
+    /// DNS names are case-insensitive, so the comparison is too.
+    pub fn count_of_type(&self, service_type: &str) -> usize {
+        self.entries
+            .values()
+            .map(|entry| &entry.service)
+            .filter(|s| s.service_type.eq_ignore_ascii_case(service_type))
+            .count()
+    }
+}
+
+/// How long the ID of a removed service is kept for it to get back if it
+/// reappears; see [`ServiceIds`].
+pub const ID_REUSE_WINDOW: Duration = Duration::from_secs(600);
+
+/// Stable integer IDs for discovered services, for games that key their UI
+/// or lobby lists by something cheaper than the fullname.
+///
+/// IDs start at 1 and increase by one per fullname never seen before; they
+/// are never handed to a different name.  A service keeps its ID across
+/// updates, and one that is removed and reappears within
+/// [`ID_REUSE_WINDOW`] gets the same ID back.  After that its ID is retired,
+/// not reused, and a reappearance gets a new one.  Fullnames match as by
+/// [`ServiceCache::find`].
+#[derive(Debug)]
+pub struct ServiceIds {
+    ids: HashMap<String, IdEntry>,
+    next: i64,
+}
+
+#[derive(Debug)]
+struct IdEntry {
+    id: i64,
+    /// When the service was removed, or `None` while it is live.
+    removed: Option<Instant>,
+}
+
+impl Default for ServiceIds {
+    fn default() -> Self {
+        Self {
+            ids: HashMap::new(),
+            next: 1,
+        }
+    }
+}
+
+impl ServiceIds {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The ID of `fullname`, allocating one if it has none, and marks it live.
+    pub fn assign(&mut self, fullname: &str, now: Instant) -> i64 {
+        self.prune(now);
+        let next = &mut self.next;
+        let entry = self.ids.entry(id_key(fullname)).or_insert_with(|| {
+            let id = *next;
+            *next += 1;
+            IdEntry { id, removed: None }
+        });
+        entry.removed = None;
+        entry.id
+    }
+
+    /// Marks `fullname` removed, starting its reuse window, and returns its
+    /// ID (`None` if it never had one).
+    pub fn release(&mut self, fullname: &str, now: Instant) -> Option<i64> {
+        let entry = self.ids.get_mut(&id_key(fullname))?;
+        entry.removed.get_or_insert(now);
+        Some(entry.id)
+    }
+
+    /// The ID of `fullname`, live or within its reuse window.
+    pub fn get(&self, fullname: &str) -> Option<i64> {
+        self.ids.get(&id_key(fullname)).map(|entry| entry.id)
+    }
+
+    /// Retires the IDs of services removed more than [`ID_REUSE_WINDOW`]
+    /// before `now`.
+    pub fn prune(&mut self, now: Instant) {
+        self.ids.retain(|_, entry| {
+            entry
+                .removed
+                .is_none_or(|removed| now.saturating_duration_since(removed) <= ID_REUSE_WINDOW)
+        });
+    }
+
+    /// Forgets every ID and starts again from 1.
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// The host `service` is grouped under by `MdnsBrowser.set_dedup_by_host()`:
+/// its host name, case and trailing dot aside.  A service without one is a
+/// group of its own.
+pub fn host_group(service: &CachedService) -> String {
+    let host = service.host.trim_end_matches('.');
+    if host.is_empty() {
+        format!("\0{}", service.fullname)
+    } else {
+        host.to_ascii_lowercase()
+    }
+}
+
+fn id_key(fullname: &str) -> String {
+    fullname.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// Collects the addresses of `info` and sorts them so IPv4 always comes before IPv6.
+///
+/// `get_addresses()` iterates a HashSet whose order is non-deterministic;
+/// without this sort `addresses[0]` can be an IPv6 link-local address
+/// (fe80::…) that Godot/Nakama cannot use as a plain host string.
+/// mdns-sd 0.18+ returns ScopedIp; convert to plain IpAddr for Godot strings.
+pub fn sorted_addresses(info: &ResolvedService) -> Vec<IpAddr> {
+    ipv4_first(info.get_addresses())
+}
+
+/// Zones of the link-local IPv6 addresses in `addresses` that came with one.
+/// `mdns-sd`'s own formatting already picks name or index per platform.
+pub fn link_local_zones(addresses: &HashSet<ScopedIp>) -> BTreeMap<IpAddr, String> {
+    addresses
+        .iter()
+        .filter_map(|addr| {
+            let formatted = addr.to_string();
+            let (_, zone) = formatted.split_once('%')?;
+            Some((addr.to_ip_addr(), zone.to_string()))
+        })
+        .collect()
+}
+
+/// Converts a set of scoped addresses (as delivered by `mdns-sd`) to plain
+/// `IpAddr`s, IPv4 first and then by value, so the same set always yields the
+/// same order.  Shared by service and hostname resolution.
+pub fn ipv4_first(addresses: &HashSet<ScopedIp>) -> Vec<IpAddr> {
+    let mut addrs: Vec<IpAddr> = addresses.iter().map(|a| a.to_ip_addr()).collect();
+    addrs.sort_by_key(|a| (a.is_ipv6(), *a));
+    addrs.dedup();
+    addrs
+}
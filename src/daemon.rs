@@ -0,0 +1,1508 @@
+//! Shared daemon layer.
+//!
+//! Owns the process-global `ServiceDaemon` (see the crate docs for why there must
+//! only be one) together with the bookkeeping that makes it introspectable:
+//! global configuration applied at creation time, and which browse
+//! subscriptions and registrations are currently live.
+//!
+//! `MdnsBrowser` and `MdnsAdvertiser` route every browse/register call through
+//! the functions here instead of talking to the daemon directly, and
+//! `MdnsManager` exposes the state to GDScript.
+
+use crate::devloop;
+use crate::fanout::BrowseFanOut;
+use crate::history::{EventHistory, HistoryEntry};
+use crate::interfaces::{Excluded, Exclusions, InterfaceSelection, IpVersion, LocalInterface};
+use crate::local_network::{LocalNetwork, Permission};
+use crate::netwatch::{NetworkChange, NetworkMonitor};
+use crate::passive::{OverheardService, PassiveCapture};
+use crate::platform;
+use crate::portcheck::{self, PortReport};
+use crate::sync;
+use godot::global::godot_warn;
+use mdns_sd::{DaemonEvent, ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+use std::time::{Duration, Instant};
+
+// ---------------------------------------------------------------------------
+// Configuration
+// ---------------------------------------------------------------------------
+
+/// Settings applied when the shared daemon is created.
+///
+/// Setters on `MdnsManager` write here and are rejected once the daemon
+/// exists, because the values only take effect at creation time.
+#[derive(Debug, Default, Clone)]
+pub struct DaemonConfig {
+    /// Overrides `mdns-sd`'s RFC 6763 limit of 15 bytes for the service name
+    /// (the `_mygame` part of `_mygame._tcp.local.`).  `None` keeps the default.
+    pub service_name_len_max: Option<u8>,
+    /// UDP port the daemon binds and sends to instead of 5353.  Only peers
+    /// configured with the same port can see each other.
+    pub port: Option<u16>,
+    /// Multicast loopback on both the IPv4 and IPv6 sockets.  `None` keeps the
+    /// `mdns-sd` default.  Can also be changed on a live daemon.
+    pub multicast_loopback: Option<bool>,
+    /// Interfaces the daemon may use.  Unlike the other fields this can also
+    /// be changed on a live daemon, via [`set_interface_selection`].
+    pub interfaces: InterfaceSelection,
+}
+
+// ---------------------------------------------------------------------------
+// Shared state
+// ---------------------------------------------------------------------------
+
+#[derive(Default)]
+struct SharedState {
+    daemon: Option<ServiceDaemon>,
+    /// Monitor subscription opened when the daemon is created; drained by
+    /// `MdnsManager` once per frame via [`drain_monitor`].
+    monitor: Option<mdns_sd::Receiver<DaemonEvent>>,
+    config: DaemonConfig,
+    /// Browses on the shared daemon, one per service type, each fanned out to
+    /// every subscribed node.  See [`subscribe`].
+    browses: BTreeMap<String, SharedBrowse>,
+    /// Queues of browses ended by [`shutdown_all`], holding the events their
+    /// subscribers have not taken yet; each entry goes once its last
+    /// [`Subscription`] is dropped.
+    closed_browses: BTreeMap<String, BrowseFanOut>,
+    /// Browses running on private daemons (`MdnsBrowser.set_interface()`).
+    private_browses: BTreeMap<String, usize>,
+    /// Fullnames of services currently registered through this layer.
+    registrations: BTreeSet<String>,
+    /// Fullnames registered on private daemons
+    /// (`MdnsAdvertiser.set_daemon_port()`); not touched by shutdowns.
+    private_registrations: BTreeSet<String>,
+    /// Outstanding `get_metrics()` request, answered asynchronously by the daemon thread.
+    metrics_pending: Option<mdns_sd::Receiver<mdns_sd::Metrics>>,
+    /// Most recent metrics snapshot received.
+    metrics_latest: Option<BTreeMap<String, i64>>,
+    /// Last error reported on the monitor channel, and when it was drained.
+    last_error: Option<(String, Instant)>,
+    /// The last `RECENT_EVENT_LIMIT` monitor events, oldest first, for
+    /// [`debug_snapshot`].  Kept across resets.
+    recent_events: VecDeque<(String, Instant)>,
+    /// Browse events waiting for `MdnsManager`'s `event` signal, see
+    /// [`take_bus_events`].  Only filled while the bus is enabled.
+    bus_events: Vec<BusEvent>,
+    /// Ring buffer of `MdnsManager.get_recent_events()`; records nothing
+    /// until given a size.  Kept across resets.
+    history: EventHistory,
+    /// Background creation of the daemon, see [`poll_shared_daemon`].
+    creation: Creation,
+    /// Id of the most recent creation attempt.
+    creation_attempts: u64,
+    /// Outcome of the last background creation, until [`progress_creation`]
+    /// hands it to `MdnsManager`.
+    creation_notice: Option<Result<(), String>>,
+    /// Liveness pings of the current daemon, see [`watchdog_tick`].
+    watchdog: Watchdog,
+    /// A browse on the current daemon got no event at all in time, see
+    /// [`report_silent_browse`].  Cleared by the next browse event.
+    silent_browse: bool,
+    /// Receive-only listener behind [`set_passive_capture`].  Independent of
+    /// the daemon: it survives `shutdown_all()` and resets.
+    passive: Option<PassiveCapture>,
+    /// Interface snapshots behind [`network_tick`].  Independent of the
+    /// daemon: it survives `shutdown_all()` and resets.
+    network: NetworkMonitor,
+    /// Local Network permission probes, see [`local_network_tick`].  Kept
+    /// across resets.
+    local_network: LocalNetwork,
+    /// A browser recovered from the device sleeping, see
+    /// [`request_multicast_relock`].
+    relock_multicast: bool,
+}
+
+/// Liveness check of the shared daemon's thread.
+#[derive(Default)]
+struct Watchdog {
+    /// When the outstanding ping was first attempted.
+    ping_sent: Option<Instant>,
+    /// Answer channel of the outstanding ping; `None` while the daemon's
+    /// command channel refuses it (full, e.g. because the thread is stuck).
+    ping: Option<mdns_sd::Receiver<mdns_sd::DaemonStatus>>,
+    /// When the last ping was answered.
+    last_answer: Option<Instant>,
+    /// A ping went unanswered for `WATCHDOG_TIMEOUT`.
+    stalled: bool,
+}
+
+/// One service type browsed on the shared daemon.
+struct SharedBrowse {
+    receiver: mdns_sd::Receiver<ServiceEvent>,
+    fanout: BrowseFanOut,
+}
+
+/// State of the background thread that builds the shared daemon.
+#[derive(Default)]
+enum Creation {
+    #[default]
+    Idle,
+    Running {
+        attempt: u64,
+        receiver: std::sync::mpsc::Receiver<Result<ServiceDaemon, String>>,
+    },
+    /// Kept until the next attempt so every node waiting on it sees the error.
+    Failed { attempt: u64, error: String },
+}
+
+/// How long [`metrics_snapshot`] waits for the very first snapshot of a daemon.
+/// Later calls never block: they return the previous snapshot and queue a new request.
+const FIRST_METRICS_WAIT: Duration = Duration::from_millis(50);
+
+/// Process-global mDNS daemon shared by both `MdnsBrowser` and `MdnsAdvertiser`,
+/// plus its bookkeeping.  Lazily initialised on first use.
+static SHARED_DAEMON: OnceLock<Mutex<SharedState>> = OnceLock::new();
+
+/// Bumped by every [`reset`].  Nodes holding the shared daemon remember the
+/// value they saw and re-establish their browse/registration when it changes.
+/// Atomic so the per-frame check does not take the mutex.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Generation created by the most recent [`flush`], or 0.  Nodes that
+/// subscribed before it drop what they learned from the old daemon.
+static LAST_FLUSH: AtomicU64 = AtomicU64::new(0);
+
+/// Generation created by the most recent [`recover`], or 0.  Nodes that
+/// re-establish themselves across it tell their scripts with `recovered`.
+static LAST_RECOVERY: AtomicU64 = AtomicU64::new(0);
+
+/// Generation created by the most recent [`shutdown_all`], or 0.  Nodes that
+/// subscribed or registered before it go idle instead of re-establishing.
+static LAST_SHUTDOWN: AtomicU64 = AtomicU64::new(0);
+
+/// Source of [`Subscription`] ids; never reused, so a subscription left
+/// over from a reset daemon cannot touch a newer browse of the same type.
+static NEXT_SUBSCRIPTION: AtomicU64 = AtomicU64::new(1);
+
+/// Set by [`shutdown_for_unload`]; once set, no new shared daemon is created.
+static UNLOADING: AtomicBool = AtomicBool::new(false);
+
+/// Set by [`set_event_bus`]; while clear, nothing is copied for the bus.
+static EVENT_BUS: AtomicBool = AtomicBool::new(false);
+
+/// Set while the event history has a non-zero size, see [`set_history_size`].
+static HISTORY: AtomicBool = AtomicBool::new(false);
+
+/// Most service types browsed at once, see [`set_max_browse_types`].
+static MAX_BROWSE_TYPES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_BROWSE_TYPES);
+
+/// Port check made before the shared daemon was first created; see
+/// [`startup_report`].
+static STARTUP_REPORT: OnceLock<PortReport> = OnceLock::new();
+
+/// Set once [`take_startup_warning`] has handed out the warning.
+static STARTUP_WARNED: AtomicBool = AtomicBool::new(false);
+
+/// Standard mDNS port, used when no `DaemonConfig::port` is set.
+const MDNS_PORT: u16 = 5353;
+
+/// Default of [`set_max_browse_types`].
+pub const DEFAULT_MAX_BROWSE_TYPES: usize = 16;
+
+/// Leading word of the error a browse gets past [`set_max_browse_types`].
+pub const TOO_MANY_TYPES_ERROR: &str = "too_many_types";
+
+const UNLOADING_ERROR: &str = "the mDNS extension is unloading; the shared daemon is gone";
+
+/// How long [`shutdown_all`] and [`shutdown_for_unload`] wait for the daemon
+/// thread to confirm.
+const SHUTDOWN_WAIT: Duration = Duration::from_millis(500);
+
+/// Locks the shared state.
+///
+/// If a panic poisoned the mutex (say inside `ServiceDaemon::new()`), the
+/// daemon and its bookkeeping may be half-updated, so they are shut down and
+/// the [`generation`] is bumped: nodes re-subscribe on a freshly created
+/// daemon as after a [`reset`].  Configuration and passive capture are kept.
+fn lock_state() -> MutexGuard<'static, SharedState> {
+    let mutex = SHARED_DAEMON.get_or_init(|| Mutex::new(SharedState::default()));
+    let (state, recovered) = sync::lock_or_recover(mutex, |state| {
+        let _ = shutdown_locked(state);
+        GENERATION.fetch_add(1, Ordering::SeqCst);
+    });
+    if recovered {
+        godot_warn!(
+            "godot-mdns: recovered from a panic in the shared daemon layer; \
+             the daemon will be recreated"
+        );
+    }
+    state
+}
+
+/// Returns a clone of the shared `ServiceDaemon`, creating it on first call.
+///
+/// Blocks until the daemon exists: if an asynchronous creation (see
+/// [`poll_shared_daemon`]) is already running it waits for that one instead
+/// of starting a second.  The extension itself only uses
+/// [`poll_shared_daemon`], so it never blocks a frame; this is for the
+/// engine-free tests, which need a daemon right now.
+///
+/// Returns `Err` with a description string if the daemon could not be created.
+#[allow(dead_code)]
+pub fn shared_daemon() -> Result<ServiceDaemon, String> {
+    if is_unloading() {
+        return Err(UNLOADING_ERROR.to_string());
+    }
+    if let Some(e) = platform::unsupported_error() {
+        return Err(e);
+    }
+    let mut state = lock_state();
+    if state.daemon.is_none() {
+        let result = match std::mem::take(&mut state.creation) {
+            Creation::Running { receiver, .. } => receiver
+                .recv()
+                .unwrap_or_else(|_| Err("daemon creation thread died".to_string())),
+            _ => create_daemon(state.config.port),
+        };
+        install(&mut state, result?)?;
+    }
+    Ok(state.daemon.as_ref().unwrap().clone())
+}
+
+/// Outcome of [`poll_shared_daemon`].
+pub enum DaemonPoll {
+    /// The shared daemon exists.
+    Ready(ServiceDaemon),
+    /// Creation attempt with this id is still running on its background thread.
+    Pending(u64),
+    /// The attempt the caller was waiting on failed.
+    Failed(String),
+}
+
+/// Non-blocking variant of [`shared_daemon`].
+///
+/// `ServiceDaemon::new()` binds sockets, enumerates interfaces and spawns a
+/// thread, which can take long enough to hitch a frame on machines with many
+/// adapters, so the first caller starts the construction on a short-lived
+/// thread and everybody polls until it finishes.
+///
+/// `waiting_on` is the attempt id from a previous `Pending` answer.  A failed
+/// attempt is reported to every caller waiting on it; a caller that was not
+/// waiting on it starts a new one.
+pub fn poll_shared_daemon(waiting_on: Option<u64>) -> DaemonPoll {
+    if is_unloading() {
+        return DaemonPoll::Failed(UNLOADING_ERROR.to_string());
+    }
+    if let Some(e) = platform::unsupported_error() {
+        return DaemonPoll::Failed(e);
+    }
+    let mut state = lock_state();
+    if let Some(daemon) = &state.daemon {
+        return DaemonPoll::Ready(daemon.clone());
+    }
+    if let Some(Ok(daemon)) = progress_locked(&mut state) {
+        return DaemonPoll::Ready(daemon);
+    }
+    match &state.creation {
+        Creation::Running { attempt, .. } => DaemonPoll::Pending(*attempt),
+        Creation::Failed { attempt, error } if waiting_on == Some(*attempt) => {
+            DaemonPoll::Failed(error.clone())
+        }
+        Creation::Idle | Creation::Failed { .. } => DaemonPoll::Pending(start_creation(&mut state)),
+    }
+}
+
+/// Starts creating the shared daemon in the background unless it exists or
+/// is already being created.  Never blocks.
+pub fn warm_up() -> Result<(), String> {
+    if is_unloading() {
+        return Err(UNLOADING_ERROR.to_string());
+    }
+    if let Some(e) = platform::unsupported_error() {
+        return Err(e);
+    }
+    let mut state = lock_state();
+    if state.daemon.is_none() && !matches!(state.creation, Creation::Running { .. }) {
+        start_creation(&mut state);
+    }
+    Ok(())
+}
+
+/// Finishes a background creation that has completed, without starting a new
+/// one.  Called by `MdnsManager` every frame so a `warm_up()` completes even
+/// when no node is polling.
+///
+/// Returns the outcome of a background creation that finished since the last
+/// call (whether this call or a node's poll finished it), exactly once.
+pub fn progress_creation() -> Option<Result<(), String>> {
+    let mut state = lock_state();
+    progress_locked(&mut state);
+    state.creation_notice.take()
+}
+
+fn progress_locked(state: &mut SharedState) -> Option<Result<ServiceDaemon, String>> {
+    let Creation::Running { attempt, receiver } = &state.creation else {
+        return None;
+    };
+    let attempt = *attempt;
+    let result = match receiver.try_recv() {
+        Ok(result) => result,
+        Err(std::sync::mpsc::TryRecvError::Empty) => return None,
+        Err(std::sync::mpsc::TryRecvError::Disconnected) => {
+            Err("daemon creation thread died".to_string())
+        }
+    };
+    state.creation = Creation::Idle;
+    let result = result.and_then(|daemon| {
+        install(state, daemon)?;
+        Ok(state.daemon.as_ref().unwrap().clone())
+    });
+    if let Err(error) = &result {
+        state.creation = Creation::Failed {
+            attempt,
+            error: error.clone(),
+        };
+    }
+    state.creation_notice = Some(result.as_ref().map(drop).map_err(Clone::clone));
+    Some(result)
+}
+
+fn start_creation(state: &mut SharedState) -> u64 {
+    state.creation_attempts += 1;
+    let attempt = state.creation_attempts;
+    let port = state.config.port;
+    let (tx, receiver) = std::sync::mpsc::channel();
+    let spawned = std::thread::Builder::new()
+        .name("godot-mdns-create".to_string())
+        .spawn(move || {
+            let result = create_daemon(port);
+            if let Err(std::sync::mpsc::SendError(Ok(daemon))) = tx.send(result) {
+                // Nobody is waiting any more (shutdown or unload); don't leak the thread.
+                let _ = daemon.shutdown();
+            }
+        });
+    state.creation = match spawned {
+        Ok(_) => Creation::Running { attempt, receiver },
+        Err(e) => Creation::Failed {
+            attempt,
+            error: format!("Failed to spawn daemon creation thread: {e}"),
+        },
+    };
+    attempt
+}
+
+fn create_daemon(port: Option<u16>) -> Result<ServiceDaemon, String> {
+    STARTUP_REPORT.get_or_init(|| portcheck::probe(port.unwrap_or(MDNS_PORT)));
+    match port {
+        Some(port) => ServiceDaemon::new_with_port(port),
+        None => ServiceDaemon::new(),
+    }
+    .map_err(|e| format!("Failed to create shared mDNS daemon: {e}"))
+}
+
+/// Applies the configuration to a freshly created daemon and makes it the
+/// shared one.  Runs on the main thread so configuration changed while the
+/// daemon was being built is not lost; every call here is just a channel send.
+fn install(state: &mut SharedState, daemon: ServiceDaemon) -> Result<(), String> {
+    let configured = (|| {
+        if let Some(len_max) = state.config.service_name_len_max {
+            daemon
+                .set_service_name_len_max(len_max)
+                .map_err(|e| format!("set_service_name_len_max({len_max}) failed: {e}"))?;
+        }
+        if !state.config.interfaces.is_default() {
+            state.config.interfaces.apply(&daemon)?;
+        }
+        if let Some(enabled) = devloop::effective(state.config.multicast_loopback) {
+            apply_multicast_loopback(&daemon, enabled)?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = configured {
+        let _ = daemon.shutdown();
+        return Err(e);
+    }
+    // Without a monitor the daemon still works; its errors just go unreported.
+    state.monitor = daemon.monitor().ok();
+    state.daemon = Some(daemon);
+    Ok(())
+}
+
+/// Creates a standalone daemon (not the shared one) restricted to `selection`,
+/// for the Android `iface_ips` path of `MdnsBrowser` and the self-checks.
+///
+/// Honours the configured port, multicast loopback, virtual-adapter
+/// exclusions and IP version; the global allow/deny lists are not applied,
+/// `selection` replaces them.
+pub fn private_daemon(selection: InterfaceSelection) -> Result<ServiceDaemon, String> {
+    private_daemon_on(selection, None)
+}
+
+/// Like [`private_daemon`], on `port` instead of the configured one if set
+/// (the nodes' `set_daemon_port()`).
+pub fn private_daemon_on(
+    selection: InterfaceSelection,
+    port: Option<u16>,
+) -> Result<ServiceDaemon, String> {
+    if let Some(e) = platform::unsupported_error() {
+        return Err(e);
+    }
+    let config = lock_state().config.clone();
+    let selection = private_selection(selection);
+    let daemon = match port.or(config.port) {
+        Some(port) => ServiceDaemon::new_with_port(port),
+        None => ServiceDaemon::new(),
+    }
+    .map_err(|e| format!("Failed to create mDNS daemon: {e}"))?;
+    let configured = (|| {
+        if !selection.is_default() {
+            selection.apply(&daemon)?;
+        }
+        if let Some(enabled) = devloop::effective(config.multicast_loopback) {
+            apply_multicast_loopback(&daemon, enabled)?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = configured {
+        let _ = daemon.shutdown();
+        return Err(e);
+    }
+    Ok(daemon)
+}
+
+/// Checks a node's `set_daemon_port()` argument: `0` selects the shared
+/// daemon (`None`).  A port other than 5353 is logged as a warning, since
+/// standard mDNS peers never use it.
+pub fn node_daemon_port(port: i64) -> Result<Option<u16>, String> {
+    match port {
+        0 => Ok(None),
+        1..=65535 => {
+            if port != i64::from(MDNS_PORT) {
+                godot_warn!(
+                    "godot-mdns: a daemon on port {port} only interoperates with nodes \
+                     using the same port, not with standard mDNS peers on the LAN"
+                );
+            }
+            Ok(Some(port as u16))
+        }
+        _ => Err(format!("set_daemon_port: {port} is outside 0..=65535")),
+    }
+}
+
+/// `selection` with the global settings [`private_daemon`] adds to it.
+pub fn private_selection(mut selection: InterfaceSelection) -> InterfaceSelection {
+    let config = lock_state().config.interfaces.clone();
+    selection.exclusions = config.exclusions;
+    selection.ip_version = config.ip_version;
+    selection
+}
+
+/// The local interface addresses a daemon using `selection` is bound to,
+/// reconstructed from the selection and the current interface list.  For
+/// the shared daemon (`None`) this is empty while the daemon does not exist.
+pub fn bound_interfaces(selection: Option<InterfaceSelection>) -> Vec<LocalInterface> {
+    let selection = match selection {
+        Some(selection) => selection,
+        None => {
+            let state = lock_state();
+            if state.daemon.is_none() {
+                return Vec::new();
+            }
+            state.config.interfaces.clone()
+        }
+    };
+    let interfaces = LocalInterface::enumerate();
+    let binds = selection.binds(&interfaces);
+    interfaces
+        .into_iter()
+        .zip(binds)
+        .filter_map(|(interface, bound)| bound.then_some(interface))
+        .collect()
+}
+
+/// The port check made just before the shared daemon was first created, or
+/// `None` if it has never been created.
+pub fn startup_report() -> Option<PortReport> {
+    STARTUP_REPORT.get().cloned()
+}
+
+/// The startup check's warning, the first time it is asked for; `None` after
+/// that or if there is nothing to warn about.
+pub fn take_startup_warning() -> Option<String> {
+    let warning = STARTUP_REPORT.get()?.warning()?;
+    (!STARTUP_WARNED.swap(true, Ordering::SeqCst)).then_some(warning)
+}
+
+/// Returns `true` once the shared daemon has been created (and not shut down).
+pub fn is_created() -> bool {
+    lock_state().daemon.is_some()
+}
+
+/// Runs `f` against the configuration, provided the daemon does not exist yet.
+///
+/// Returns `Err` if the daemon has already been created, since the change
+/// would silently have no effect.
+pub fn configure(f: impl FnOnce(&mut DaemonConfig)) -> Result<(), String> {
+    let mut state = lock_state();
+    if state.daemon.is_some() || matches!(state.creation, Creation::Running { .. }) {
+        return Err("the shared mDNS daemon already exists or is being created; configure it \
+                    before the first browse()/advertise()/warm_up() call"
+            .to_string());
+    }
+    f(&mut state.config);
+    Ok(())
+}
+
+/// The configured IP version.
+pub fn ip_version() -> IpVersion {
+    lock_state().config.interfaces.ip_version
+}
+
+/// Receive and send buffer sizes, in bytes, the OS gives a new UDP socket —
+/// what the daemon's sockets get, since `mdns-sd` never changes them.  `None`
+/// if no probe socket could be opened.
+pub fn default_socket_buffer_sizes() -> Option<(usize, usize)> {
+    use socket2::{Domain, Protocol, Socket, Type};
+    if !platform::is_supported() {
+        return None;
+    }
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).ok()?;
+    Some((socket.recv_buffer_size().ok()?, socket.send_buffer_size().ok()?))
+}
+
+/// Interfaces the configured exclusions switch off on this machine right now.
+pub fn excluded_interfaces() -> Vec<Excluded> {
+    let selection = lock_state().config.interfaces.clone();
+    selection.excluded_now()
+}
+
+/// The configured virtual-adapter exclusions.
+pub fn exclusions() -> Exclusions {
+    lock_state().config.interfaces.exclusions.clone()
+}
+
+/// Updates the interface selection and, if the daemon already exists, applies
+/// it to the live daemon straight away.
+///
+/// Returns `Ok(true)` when a live daemon was reconfigured, `Ok(false)` when the
+/// selection was only stored for the next creation.
+pub fn set_interface_selection(f: impl FnOnce(&mut InterfaceSelection)) -> Result<bool, String> {
+    let mut state = lock_state();
+    f(&mut state.config.interfaces);
+    match &state.daemon {
+        Some(daemon) => state.config.interfaces.apply(daemon).map(|()| true),
+        None => Ok(false),
+    }
+}
+
+/// Stops every tracked browse, unregisters every tracked service, and shuts
+/// the shared daemon down, waiting up to `SHUTDOWN_WAIT` for the goodbyes and
+/// the thread's confirmation.  The next `shared_daemon()` call creates a
+/// fresh one; configuration and passive capture are kept.
+///
+/// Bumps the [`generation`] so nodes notice on their next frame: they see
+/// [`was_shut_down_since`], take the final events of their browse (kept here
+/// until they do) and go idle instead of re-subscribing.  Browses on private
+/// daemons belong to their nodes and are not touched.
+pub fn shutdown_all() -> Result<(), String> {
+    let receipts = {
+        let mut state = lock_state();
+        let shut_down = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+        LAST_SHUTDOWN.store(shut_down, Ordering::SeqCst);
+        shutdown_locked(&mut state)?
+    };
+    let Some(mut receipts) = receipts else {
+        return Ok(());
+    };
+    let confirmed = await_shutdown(&receipts);
+    // The thread has handled the stop_browse()s by now (or is stuck): whatever
+    // it delivered is final.
+    let mut state = lock_state();
+    for (service_type, mut browse) in std::mem::take(&mut receipts.browses) {
+        for event in browse.receiver.try_iter() {
+            browse.fanout.dispatch(event);
+        }
+        state.closed_browses.insert(service_type, browse.fanout);
+    }
+    confirmed
+}
+
+/// Returns `true` if a [`shutdown_all`] happened after generation `seen`.
+pub fn was_shut_down_since(seen: u64) -> bool {
+    LAST_SHUTDOWN.load(Ordering::SeqCst) > seen
+}
+
+/// Remembers the multicast-loopback preference for every daemon created
+/// from now on and applies it to the shared daemon if it exists.  Never
+/// creates or waits for the daemon: one being built picks the preference up
+/// in [`install`].
+pub fn set_multicast_loopback(enabled: bool) -> Result<(), String> {
+    let mut state = lock_state();
+    state.config.multicast_loopback = Some(enabled);
+    match &state.daemon {
+        Some(daemon) => apply_multicast_loopback(daemon, enabled),
+        None => Ok(()),
+    }
+}
+
+fn apply_multicast_loopback(daemon: &ServiceDaemon, enabled: bool) -> Result<(), String> {
+    daemon
+        .set_multicast_loop_v4(enabled)
+        .map_err(|e| format!("set_multicast_loop_v4({enabled}) failed: {e}"))?;
+    daemon
+        .set_multicast_loop_v6(enabled)
+        .map_err(|e| format!("set_multicast_loop_v6({enabled}) failed: {e}"))
+}
+
+/// Like [`shutdown_all`], but also bumps the [`generation`] so nodes that were
+/// using the old daemon transparently re-issue their browse / registration on
+/// a freshly created one.  Use this to recover from a daemon whose sockets died.
+///
+/// Waits up to `SHUTDOWN_WAIT` for the goodbyes and the old thread's exit, so
+/// on `Ok` its sockets are closed before anything rebinds the port.
+pub fn reset() -> Result<(), String> {
+    replace_daemon(None)
+}
+
+/// Like [`reset`], but also tells nodes to forget what the old daemon
+/// discovered (see [`was_flushed_since`]).
+///
+/// `mdns-sd` has no call to drop its record cache, so the only way to flush
+/// it is to replace the daemon: the new one starts empty.
+pub fn flush() -> Result<(), String> {
+    replace_daemon(Some(&LAST_FLUSH))
+}
+
+/// Returns `true` if a [`flush`] happened after generation `seen`.
+pub fn was_flushed_since(seen: u64) -> bool {
+    LAST_FLUSH.load(Ordering::SeqCst) > seen
+}
+
+/// Like [`reset`], for a daemon that failed on its own (see [`watchdog_tick`])
+/// rather than one the game asked to replace.  Nodes re-browse and re-register
+/// as after a reset, and additionally learn that they were cut off for a
+/// while (see [`was_recovered_since`]): services may have left unnoticed.
+pub fn recover() -> Result<(), String> {
+    replace_daemon(Some(&LAST_RECOVERY))
+}
+
+/// Body of [`reset`], [`flush`] and [`recover`]: shuts the shared daemon down
+/// and bumps the [`generation`] (recording it in `mark`, if given) under the
+/// lock, then waits for the shutdown after releasing it.
+fn replace_daemon(mark: Option<&AtomicU64>) -> Result<(), String> {
+    let receipts = {
+        let mut state = lock_state();
+        let receipts = shutdown_locked(&mut state);
+        // Bump even if shutdown failed: the old daemon is gone from the slot either way.
+        let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(mark) = mark {
+            mark.store(generation, Ordering::SeqCst);
+        }
+        receipts?
+    };
+    match receipts {
+        Some(receipts) => await_shutdown(&receipts),
+        None => Ok(()),
+    }
+}
+
+/// Returns `true` if a [`recover`] happened after generation `seen`.
+pub fn was_recovered_since(seen: u64) -> bool {
+    LAST_RECOVERY.load(Ordering::SeqCst) > seen
+}
+
+/// Asks the shared daemon to re-confirm each instance in `fullnames` (RFC 6762
+/// §10.4).  Instances that do not answer within `timeout` are dropped from its
+/// cache and reported as removed to browsers.  Never creates the daemon.
+pub fn verify(fullnames: &[String], timeout: Duration) -> Result<(), String> {
+    let daemon = lock_state()
+        .daemon
+        .clone()
+        .ok_or_else(|| "the shared mDNS daemon is not running".to_string())?;
+    for fullname in fullnames {
+        daemon
+            .verify(fullname.clone(), timeout)
+            .map_err(|e| format!("verify({fullname}) failed: {e}"))?;
+    }
+    Ok(())
+}
+
+/// Current shared-daemon generation; see [`reset`].
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+/// Shuts the shared daemon down for good because the extension library is
+/// being unloaded (an editor hot-reload, or plain process exit), waiting
+/// briefly for the daemon thread to confirm so its port-5353 socket is closed
+/// before the new library creates its own.
+///
+/// Every registration still tracked here is unregistered first, so peers get
+/// goodbye packets even when the owning nodes never saw `exit_tree()` (e.g. a
+/// crash-to-quit path that skips the scene teardown).  The whole wait — for
+/// the goodbyes and then the thread — is bounded by `SHUTDOWN_WAIT`, so
+/// a wedged socket thread cannot hang the process exit.
+///
+/// Bumps the [`generation`] so surviving nodes notice on their next frame;
+/// they see [`is_unloading`] and go idle with an error instead of
+/// re-subscribing.
+pub fn shutdown_for_unload() -> Result<(), String> {
+    UNLOADING.store(true, Ordering::SeqCst);
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    let (passive, receipts) = {
+        let mut state = lock_state();
+        // A daemon still under construction must not outlive the library
+        // either: wait for it and shut it down with the rest.
+        if let Creation::Running { receiver, .. } = std::mem::take(&mut state.creation) {
+            if let Ok(Ok(daemon)) = receiver.recv_timeout(SHUTDOWN_WAIT) {
+                let _ = daemon.shutdown();
+            }
+        }
+        (state.passive.take(), shutdown_locked(&mut state))
+    };
+    // Joins the capture thread, so it cannot outlive the library either.  Not
+    // under the lock: that takes up to a receive poll.
+    drop(passive);
+    let Some(receipts) = receipts? else {
+        return Ok(());
+    };
+    await_shutdown(&receipts)
+}
+
+/// Unregisters `fullname` (if any) from a node's own daemon, shuts that
+/// daemon down and waits up to `SHUTDOWN_WAIT` for the goodbye and the
+/// thread's exit.  Blocks the caller for that long at most; on `Ok` the
+/// daemon's sockets are closed, so a new daemon can take its port at once.
+pub fn shutdown_private(daemon: &ServiceDaemon, fullname: Option<&str>) -> Result<(), String> {
+    let mut goodbyes = Vec::new();
+    if let Some(fullname) = fullname {
+        goodbyes.extend(daemon.unregister(fullname).ok());
+        lock_state().private_registrations.remove(fullname);
+    }
+    let status = daemon
+        .shutdown()
+        .map_err(|e| format!("Failed to shut down private mDNS daemon: {e}"))?;
+    await_shutdown(&ShutdownReceipts {
+        goodbyes,
+        status,
+        browses: BTreeMap::new(),
+    })
+}
+
+/// Waits up to `SHUTDOWN_WAIT` in total for the goodbyes and then the
+/// daemon thread's confirmation in `receipts`.
+fn await_shutdown(receipts: &ShutdownReceipts) -> Result<(), String> {
+    let deadline = Instant::now() + SHUTDOWN_WAIT;
+    let remaining = || deadline.saturating_duration_since(Instant::now());
+    let unconfirmed = receipts
+        .goodbyes
+        .iter()
+        .filter(|rx| rx.recv_timeout(remaining()).is_err())
+        .count();
+    match receipts.status.recv_timeout(remaining()) {
+        Ok(mdns_sd::DaemonStatus::Shutdown) => {}
+        Ok(other) => return Err(format!("daemon reported {other:?} after shutdown")),
+        Err(e) => return Err(format!("daemon did not confirm shutdown: {e}")),
+    }
+    if unconfirmed > 0 {
+        return Err(format!("{unconfirmed} service(s) may not have sent a goodbye"));
+    }
+    Ok(())
+}
+
+/// Starts or stops the passive capture socket on the configured port.
+/// Starting it while it runs keeps the instances heard so far.
+pub fn set_passive_capture(enabled: bool) -> Result<(), String> {
+    let mut state = lock_state();
+    if !enabled {
+        let stopped = state.passive.take();
+        drop(state);
+        // Joining the capture thread takes up to a receive poll; the lock is
+        // released first so other nodes are not held up meanwhile.
+        drop(stopped);
+        return Ok(());
+    }
+    if state.passive.is_none() {
+        if is_unloading() {
+            return Err(UNLOADING_ERROR.to_string());
+        }
+        if let Some(e) = platform::unsupported_error() {
+            return Err(e);
+        }
+        let port = state.config.port.unwrap_or(MDNS_PORT);
+        state.passive = Some(PassiveCapture::start(port)?);
+    }
+    Ok(())
+}
+
+/// Instances heard by the passive capture, or `None` while it is off.
+pub fn overheard_services() -> Option<Vec<OverheardService>> {
+    lock_state().passive.as_ref().map(PassiveCapture::snapshot)
+}
+
+/// Returns `true` once [`shutdown_for_unload`] has run.
+pub fn is_unloading() -> bool {
+    UNLOADING.load(Ordering::SeqCst)
+}
+
+/// Channels on which the daemon thread confirms a [`shutdown_locked`].
+struct ShutdownReceipts {
+    /// One per unregistered service, answered once its goodbye went out.
+    goodbyes: Vec<mdns_sd::Receiver<mdns_sd::UnregisterStatus>>,
+    status: mdns_sd::Receiver<mdns_sd::DaemonStatus>,
+    /// The browses that were stopped, with events still in their channels.
+    browses: BTreeMap<String, SharedBrowse>,
+}
+
+/// Shuts down and forgets the shared daemon, returning the confirmation
+/// channels (if there was a daemon).
+fn shutdown_locked(state: &mut SharedState) -> Result<Option<ShutdownReceipts>, String> {
+    let browses = std::mem::take(&mut state.browses);
+    let registrations = std::mem::take(&mut state.registrations);
+    state.monitor = None;
+    state.metrics_pending = None;
+    state.metrics_latest = None;
+    state.last_error = None;
+    state.watchdog = Watchdog::default();
+    state.silent_browse = false;
+    // Dropping the receiver makes a still-running creation thread shut its
+    // daemon down itself once it finishes.
+    state.creation = Creation::Idle;
+    if let Some(daemon) = state.daemon.take() {
+        for service_type in browses.keys() {
+            let _ = daemon.stop_browse(service_type);
+        }
+        let goodbyes = registrations
+            .iter()
+            .filter_map(|fullname| daemon.unregister(fullname).ok())
+            .collect();
+        return daemon
+            .shutdown()
+            .map(|status| {
+                Some(ShutdownReceipts {
+                    goodbyes,
+                    status,
+                    browses,
+                })
+            })
+            .map_err(|e| format!("Failed to shut down shared mDNS daemon: {e}"));
+    }
+    Ok(None)
+}
+
+/// Returns every event queued on the shared daemon's monitor channel without blocking.
+///
+/// Errors are also remembered for [`health`].
+pub fn drain_monitor() -> Vec<DaemonEvent> {
+    let mut state = lock_state();
+    let events: Vec<DaemonEvent> = state
+        .monitor
+        .as_ref()
+        .map(|rx| rx.try_iter().collect())
+        .unwrap_or_default();
+    if let Some(e) = events.iter().rev().find_map(|ev| match ev {
+        DaemonEvent::Error(e) => Some(e.to_string()),
+        _ => None,
+    }) {
+        state.last_error = Some((e, Instant::now()));
+    }
+    let now = Instant::now();
+    for event in &events {
+        state.history.record_daemon(event);
+        if state.recent_events.len() == RECENT_EVENT_LIMIT {
+            state.recent_events.pop_front();
+        }
+        state.recent_events.push_back((format!("{event:?}"), now));
+    }
+    events
+}
+
+/// Monitor events kept for [`debug_snapshot`].
+const RECENT_EVENT_LIMIT: usize = 16;
+
+/// What the shared layer knows about itself, for `MdnsManager.get_debug_info()`.
+#[derive(Debug, Clone)]
+pub struct DebugSnapshot {
+    pub shared_daemon: bool,
+    /// Browses on private daemons, one daemon each.
+    pub private_browses: usize,
+    /// Browsed service types and their subscriber counts.
+    pub browses: Vec<(String, usize)>,
+    /// Fullnames registered on the shared daemon.
+    pub registrations: Vec<String>,
+    /// Recent monitor events, oldest first, with their age.
+    pub recent_events: Vec<(String, Duration)>,
+}
+
+/// Takes a [`DebugSnapshot`].  Never creates the daemon.
+pub fn debug_snapshot() -> DebugSnapshot {
+    let state = lock_state();
+    DebugSnapshot {
+        shared_daemon: state.daemon.is_some(),
+        private_browses: state.private_browses.values().sum(),
+        browses: state
+            .browses
+            .iter()
+            .map(|(service_type, browse)| (service_type.clone(), browse.fanout.len()))
+            .collect(),
+        registrations: state.registrations.iter().cloned().collect(),
+        recent_events: state
+            .recent_events
+            .iter()
+            .map(|(event, at)| (event.clone(), at.elapsed()))
+            .collect(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Event bus
+// ---------------------------------------------------------------------------
+
+/// A browse event copied for `MdnsManager`'s catch-all `event` signal.
+#[derive(Debug, Clone)]
+pub struct BusEvent {
+    pub service_type: String,
+    /// Node path of the browser that received it on a private daemon; `None`
+    /// for the shared daemon, whose events go to every subscriber of the type.
+    pub owner: Option<String>,
+    pub event: ServiceEvent,
+}
+
+/// Starts or stops copying browse events for the bus.  Disabling drops the
+/// events not taken yet.
+pub fn set_event_bus(enabled: bool) {
+    EVENT_BUS.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        lock_state().bus_events.clear();
+    }
+}
+
+/// Whether [`set_event_bus`] is on.
+pub fn is_event_bus_enabled() -> bool {
+    EVENT_BUS.load(Ordering::Relaxed)
+}
+
+/// Returns `true` if browsers on a private daemon should pass their events
+/// to [`publish_private`]: the bus or the history is on.
+pub fn wants_private_events() -> bool {
+    is_event_bus_enabled() || HISTORY.load(Ordering::Relaxed)
+}
+
+/// Copies an event of a private-daemon browse owned by the node at `owner`
+/// for the bus and the history, whichever is on.
+pub fn publish_private(service_type: &str, owner: String, event: &ServiceEvent) {
+    let mut state = lock_state();
+    state.history.record_service(service_type, event);
+    if is_event_bus_enabled() {
+        state.bus_events.push(BusEvent {
+            service_type: service_type.to_string(),
+            owner: Some(owner),
+            event: event.clone(),
+        });
+    }
+}
+
+/// Takes the browse events copied since the last call, oldest first.  Each
+/// daemon event appears once, however many nodes it was fanned out to.
+pub fn take_bus_events() -> Vec<BusEvent> {
+    if !is_event_bus_enabled() {
+        return Vec::new();
+    }
+    std::mem::take(&mut lock_state().bus_events)
+}
+
+/// Keeps the last `size` events for [`recent_events`]; 0 (the default)
+/// turns the history off and drops it.
+pub fn set_history_size(size: usize) {
+    lock_state().history.set_limit(size);
+    HISTORY.store(size > 0, Ordering::SeqCst);
+}
+
+/// The size last given to [`set_history_size`].
+pub fn history_size() -> usize {
+    lock_state().history.limit()
+}
+
+/// The recorded events, oldest first.
+pub fn recent_events() -> Vec<HistoryEntry> {
+    lock_state().history.entries().cloned().collect()
+}
+
+pub fn clear_history() {
+    lock_state().history.clear();
+}
+
+// ---------------------------------------------------------------------------
+// Health
+// ---------------------------------------------------------------------------
+
+/// How long the daemon thread gets to answer a status request before it is
+/// considered unresponsive.
+const STATUS_TIMEOUT: Duration = Duration::from_millis(50);
+
+/// Spacing of watchdog pings while the daemon keeps answering.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A watchdog ping unanswered for this long marks the daemon unresponsive.
+const WATCHDOG_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A monitor error younger than this marks the daemon unhealthy.
+const RECENT_ERROR_WINDOW: Duration = Duration::from_secs(10);
+
+/// Result of [`health`].
+#[derive(Debug, Clone)]
+pub struct DaemonHealth {
+    pub healthy: bool,
+    /// `"ok"`, `"not started"`, `"shut down"`, `"unresponsive"` or `"recent error"`.
+    pub reason: &'static str,
+    /// Last monitor error and how long ago it was drained.
+    pub last_error: Option<(String, Duration)>,
+}
+
+/// Checks whether the shared daemon is functional: it must exist, its thread
+/// must answer a status request promptly with `Running`, and its monitor must
+/// not have reported an error within the last few seconds.
+///
+/// Never creates the daemon.  Blocks for at most a few milliseconds.
+pub fn health() -> DaemonHealth {
+    let (daemon, last_error, stalled, silent) = {
+        let state = lock_state();
+        (
+            state.daemon.clone(),
+            state.last_error.as_ref().map(|(e, at)| (e.clone(), at.elapsed())),
+            state.watchdog.stalled,
+            state.silent_browse,
+        )
+    };
+    let reason = match daemon {
+        None => "not started",
+        Some(_) if stalled || silent => "unresponsive",
+        Some(daemon) => match daemon.status().map(|rx| rx.recv_timeout(STATUS_TIMEOUT)) {
+            Ok(Ok(mdns_sd::DaemonStatus::Running)) => {
+                match &last_error {
+                    Some((_, age)) if *age < RECENT_ERROR_WINDOW => "recent error",
+                    _ => "ok",
+                }
+            }
+            Ok(Ok(_)) => "shut down",
+            Ok(Err(_)) | Err(_) => "unresponsive",
+        },
+    };
+    DaemonHealth {
+        healthy: reason == "ok",
+        reason,
+        last_error,
+    }
+}
+
+/// What [`watchdog_tick`] found wrong with the shared daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchdogAlarm {
+    /// The thread has not answered a ping for `WATCHDOG_TIMEOUT`.
+    Stalled,
+    /// The thread is gone (it panicked or exited): its command channel is
+    /// closed.  The daemon will never work again and must be replaced.
+    Died,
+}
+
+/// Re-enumerates the interfaces when the poll interval has passed and
+/// returns what changed since the previous poll.  Called once per frame by
+/// `MdnsManager`; anything that reacts to network changes should hang off
+/// it rather than poll on its own.
+pub fn network_tick() -> Option<NetworkChange> {
+    if !platform::is_supported() {
+        return None;
+    }
+    lock_state().network.poll(Instant::now())
+}
+
+/// Sets the time between two interface polls; `None` turns the monitor off.
+pub fn set_network_poll_interval(interval: Option<Duration>) {
+    lock_state().network.set_interval(interval);
+}
+
+/// The time between two interface polls, or `None` while the monitor is off.
+pub fn network_poll_interval() -> Option<Duration> {
+    lock_state().network.interval()
+}
+
+/// Probes the Local Network permission on Apple platforms while anything
+/// browses or advertises and it is not known to be granted, at most every
+/// few seconds; call once per frame.  Returns the permission when it changed.
+pub fn local_network_tick() -> Option<Permission> {
+    let mut guard = lock_state();
+    let state = &mut *guard;
+    let active = !state.browses.is_empty()
+        || !state.private_browses.is_empty()
+        || !state.registrations.is_empty()
+        || !state.private_registrations.is_empty();
+    let port = state.config.port.unwrap_or(MDNS_PORT);
+    state.local_network.poll(Instant::now(), active, port)
+}
+
+/// Asks `MdnsManager` to release and re-acquire Android's `MulticastLock`
+/// on its next frame: a lock held across a long sleep may no longer let
+/// multicast through.
+pub fn request_multicast_relock() {
+    lock_state().relock_multicast = true;
+}
+
+/// Returns `true` once after [`request_multicast_relock`].
+pub fn take_multicast_relock() -> bool {
+    std::mem::take(&mut lock_state().relock_multicast)
+}
+
+/// The port daemons bind: 5353 unless `set_daemon_port()` changed it.
+pub fn configured_port() -> u16 {
+    lock_state().config.port.unwrap_or(MDNS_PORT)
+}
+
+/// The Local Network permission as known now; see [`local_network_tick`].
+pub fn local_network_permission() -> Permission {
+    lock_state().local_network.permission()
+}
+
+/// Records that a browse heard from the network, which proves the Local
+/// Network permission granted.
+pub fn note_network_heard() {
+    lock_state().local_network.note_heard();
+}
+
+/// Pings the shared daemon's thread through its command channel every
+/// `WATCHDOG_INTERVAL` and checks the answer without blocking; call once per
+/// frame.  Returns an alarm on the frame a ping has gone unanswered for
+/// `WATCHDOG_TIMEOUT`, after which [`health`] reports `"unresponsive"` until
+/// the daemon answers again or is replaced, or as soon as the thread is found
+/// dead.
+///
+/// While the daemon is healthy this costs one status request per interval.
+pub fn watchdog_tick() -> Option<WatchdogAlarm> {
+    let mut guard = lock_state();
+    let state = &mut *guard;
+    let daemon = state.daemon.as_ref()?;
+    let now = Instant::now();
+    let dog = &mut state.watchdog;
+    let Some(sent) = dog.ping_sent else {
+        if dog.last_answer.is_none_or(|at| now - at >= WATCHDOG_INTERVAL) {
+            dog.ping_sent = Some(now);
+            match send_ping(daemon) {
+                Ok(ping) => dog.ping = ping,
+                Err(alarm) => return Some(alarm),
+            }
+        }
+        return None;
+    };
+    match &dog.ping {
+        Some(ping) => {
+            if ping.try_recv().is_ok() {
+                *dog = Watchdog {
+                    last_answer: Some(now),
+                    ..Default::default()
+                };
+                return None;
+            }
+            // The thread dropped the command holding our sender unanswered.
+            if ping.is_disconnected() {
+                return Some(WatchdogAlarm::Died);
+            }
+        }
+        None => match send_ping(daemon) {
+            Ok(ping) => dog.ping = ping,
+            Err(alarm) => return Some(alarm),
+        },
+    }
+    if !dog.stalled && now - sent >= WATCHDOG_TIMEOUT {
+        dog.stalled = true;
+        return Some(WatchdogAlarm::Stalled);
+    }
+    None
+}
+
+/// Records that a browse on the shared daemon got no event, not even
+/// `SearchStarted`, within its window: [`health`] reports the daemon as
+/// unresponsive until the next browse event or a new daemon.
+pub fn report_silent_browse() {
+    lock_state().silent_browse = true;
+}
+
+/// Sends one watchdog ping.  `Ok(None)` if the command channel is full (the
+/// thread may just be busy), `Err(Died)` if it is closed.
+fn send_ping(
+    daemon: &ServiceDaemon,
+) -> Result<Option<mdns_sd::Receiver<mdns_sd::DaemonStatus>>, WatchdogAlarm> {
+    match daemon.status() {
+        Ok(ping) => Ok(Some(ping)),
+        Err(mdns_sd::Error::Again) => Ok(None),
+        Err(_) => Err(WatchdogAlarm::Died),
+    }
+}
+
+/// Returns the latest metrics snapshot of the shared daemon, keyed by the
+/// counter names `mdns-sd` uses, or `None` if the daemon does not exist.
+///
+/// `mdns-sd` answers each `get_metrics()` request once, so a request is kept
+/// in flight between calls: every call collects the answer to the previous
+/// one and immediately issues the next.
+pub fn metrics_snapshot() -> Option<BTreeMap<String, i64>> {
+    let mut guard = lock_state();
+    let state = &mut *guard;
+    let daemon = state.daemon.as_ref()?;
+    if state.metrics_pending.is_none() {
+        state.metrics_pending = daemon.get_metrics().ok();
+    }
+    if let Some(rx) = &state.metrics_pending {
+        let received = if state.metrics_latest.is_none() {
+            rx.recv_timeout(FIRST_METRICS_WAIT).ok()
+        } else {
+            rx.try_recv().ok()
+        };
+        if let Some(metrics) = received {
+            state.metrics_latest = Some(metrics.into_iter().collect());
+            // Queue the next request so the following call has a fresher answer.
+            state.metrics_pending = daemon.get_metrics().ok();
+        } else if rx.is_disconnected() {
+            state.metrics_pending = None;
+        }
+    }
+    Some(state.metrics_latest.clone().unwrap_or_default())
+}
+
+// ---------------------------------------------------------------------------
+// Browse / register routing
+// ---------------------------------------------------------------------------
+
+/// A node's share of a browse on the shared daemon.  Dropping it
+/// unsubscribes; the daemon-side browse stops when the last subscriber of
+/// the type is gone, so nodes browsing the same type never cut each other off.
+pub struct Subscription {
+    service_type: String,
+    id: u64,
+    /// This subscription started the daemon-side browse rather than joining one.
+    started: bool,
+}
+
+impl Subscription {
+    /// Takes the events queued for this subscriber, after moving whatever
+    /// the daemon has delivered into every subscriber's queue.  Empty once the
+    /// daemon this subscription belonged to was shut down, except for the
+    /// final events of a browse ended by [`shutdown_all`].
+    pub fn take_events(&self) -> Vec<ServiceEvent> {
+        let mut state = lock_state();
+        if let Some(closed) = state.closed_browses.get_mut(&self.service_type) {
+            let events = closed.take(self.id);
+            if !events.is_empty() {
+                return events;
+            }
+        }
+        let state = &mut *state;
+        let Some(browse) = state.browses.get_mut(&self.service_type) else {
+            return Vec::new();
+        };
+        let bus = is_event_bus_enabled();
+        if !browse.receiver.is_empty() {
+            state.silent_browse = false;
+        }
+        for event in browse.receiver.try_iter() {
+            state.history.record_service(&self.service_type, &event);
+            if bus {
+                state.bus_events.push(BusEvent {
+                    service_type: self.service_type.clone(),
+                    owner: None,
+                    event: event.clone(),
+                });
+            }
+            browse.fanout.dispatch(event);
+        }
+        browse.fanout.take(self.id)
+    }
+
+    /// Returns `true` if this subscription started the daemon-side browse, so
+    /// the daemon's `SearchStarted` for it is still to come.
+    pub fn started_browse(&self) -> bool {
+        self.started
+    }
+
+    /// Number of events waiting for this subscriber: those already in its
+    /// queue plus those still in the daemon's channel.
+    pub fn queued_events(&self) -> usize {
+        let state = lock_state();
+        let closed = state
+            .closed_browses
+            .get(&self.service_type)
+            .map_or(0, |closed| closed.queued(self.id));
+        let live = state.browses.get(&self.service_type).map_or(0, |browse| {
+            browse.fanout.queued(self.id) + browse.receiver.len()
+        });
+        closed + live
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        let mut state = lock_state();
+        if let Some(closed) = state.closed_browses.get_mut(&self.service_type) {
+            if closed.unsubscribe(self.id) {
+                state.closed_browses.remove(&self.service_type);
+            }
+        }
+        let Some(browse) = state.browses.get_mut(&self.service_type) else {
+            return;
+        };
+        if browse.fanout.unsubscribe(self.id) {
+            state.browses.remove(&self.service_type);
+            if let Some(daemon) = &state.daemon {
+                let _ = daemon.stop_browse(&self.service_type);
+            }
+        }
+    }
+}
+
+/// Subscribes to `service_type` on the shared daemon, which must exist.
+///
+/// The first subscriber starts the daemon-side browse; later ones join it
+/// and get the instances already known replayed into their queue.
+pub fn subscribe(service_type: &str) -> Result<Subscription, String> {
+    let mut state = lock_state();
+    let id = NEXT_SUBSCRIPTION.fetch_add(1, Ordering::SeqCst);
+    let started = !state.browses.contains_key(service_type);
+    if let Some(browse) = state.browses.get_mut(service_type) {
+        browse.fanout.subscribe(id);
+    } else {
+        check_type_limit(&state, service_type)?;
+        let daemon = state
+            .daemon
+            .as_ref()
+            .ok_or_else(|| "the shared mDNS daemon is not running".to_string())?;
+        let receiver = daemon
+            .browse(service_type)
+            .map_err(|e| format!("Failed to start mDNS browse: {e}"))?;
+        let mut fanout = BrowseFanOut::new();
+        fanout.subscribe(id);
+        state
+            .browses
+            .insert(service_type.to_string(), SharedBrowse { receiver, fanout });
+    }
+    Ok(Subscription {
+        service_type: service_type.to_string(),
+        id,
+        started,
+    })
+}
+
+/// Starts browsing `service_type` on a private `daemon` and counts it.
+pub fn browse_private(
+    daemon: &ServiceDaemon,
+    service_type: &str,
+) -> Result<mdns_sd::Receiver<ServiceEvent>, String> {
+    check_type_limit(&lock_state(), service_type)?;
+    let receiver = daemon
+        .browse(service_type)
+        .map_err(|e| format!("Failed to start mDNS browse: {e}"))?;
+    *lock_state()
+        .private_browses
+        .entry(service_type.to_string())
+        .or_default() += 1;
+    Ok(receiver)
+}
+
+/// Stops a browse started with [`browse_private`].
+pub fn stop_browse_private(daemon: &ServiceDaemon, service_type: &str) {
+    let _ = daemon.stop_browse(service_type);
+    let mut state = lock_state();
+    if let Some(count) = state.private_browses.get_mut(service_type) {
+        *count -= 1;
+        if *count == 0 {
+            state.private_browses.remove(service_type);
+        }
+    }
+}
+
+/// Registers `info` on `daemon` and records its fullname.
+pub fn register(daemon: &ServiceDaemon, info: ServiceInfo) -> Result<(), String> {
+    let fullname = info.get_fullname().to_string();
+    daemon
+        .register(info)
+        .map_err(|e| format!("Failed to register mDNS service: {e}"))?;
+    lock_state().registrations.insert(fullname);
+    Ok(())
+}
+
+/// Registers `info` on a private `daemon` and records its fullname apart from
+/// the shared daemon's, so shutdowns leave it alone.
+pub fn register_private(daemon: &ServiceDaemon, info: ServiceInfo) -> Result<(), String> {
+    let fullname = info.get_fullname().to_string();
+    daemon
+        .register(info)
+        .map_err(|e| format!("Failed to register mDNS service: {e}"))?;
+    lock_state().private_registrations.insert(fullname);
+    Ok(())
+}
+
+/// Unregisters `fullname` on `daemon` and forgets it.
+pub fn unregister(daemon: &ServiceDaemon, fullname: &str) {
+    let _ = daemon.unregister(fullname);
+    lock_state().registrations.remove(fullname);
+}
+
+/// Number of live browse subscriptions across all nodes.
+pub fn active_browse_count() -> usize {
+    let state = lock_state();
+    state.private_browses.values().sum::<usize>()
+        + state.browses.values().map(|b| b.fanout.len()).sum::<usize>()
+}
+
+/// Service types browsed by at least one node, on the shared daemon or a
+/// private one, sorted.
+pub fn active_browse_types() -> Vec<String> {
+    browsed_types(&lock_state()).into_iter().cloned().collect()
+}
+
+fn browsed_types(state: &SharedState) -> BTreeSet<&String> {
+    let shared = state
+        .browses
+        .iter()
+        .filter(|(_, browse)| !browse.fanout.is_empty())
+        .map(|(service_type, _)| service_type);
+    shared.chain(state.private_browses.keys()).collect()
+}
+
+/// Caps the service types browsed at once across all nodes, shared and
+/// private daemons together; 0 lifts the cap.  Every type is a standing
+/// query on the wire, so a game that browses whatever a server list hands it
+/// stays a good neighbour.  Browses already running are kept when the cap is
+/// lowered below their count.
+pub fn set_max_browse_types(max: usize) {
+    MAX_BROWSE_TYPES.store(max, Ordering::SeqCst);
+}
+
+/// The cap last given to [`set_max_browse_types`].
+pub fn max_browse_types() -> usize {
+    MAX_BROWSE_TYPES.load(Ordering::SeqCst)
+}
+
+/// Refuses a browse of `service_type` when it would be one type too many.
+/// Joining a type something already browses is always allowed.
+fn check_type_limit(state: &SharedState, service_type: &str) -> Result<(), String> {
+    let max = max_browse_types();
+    let types = browsed_types(state);
+    if max == 0 || types.len() < max || types.iter().any(|t| t.as_str() == service_type) {
+        return Ok(());
+    }
+    Err(format!(
+        "{TOO_MANY_TYPES_ERROR}: already browsing {} service types, the limit set by \
+         MdnsManager.set_max_browse_types(); not browsing {service_type}",
+        types.len()
+    ))
+}
+
+/// Fullnames of the services currently registered through this layer, on
+/// the shared daemon or a private one, sorted.
+pub fn active_registrations() -> Vec<String> {
+    let state = lock_state();
+    let names: BTreeSet<&String> = state
+        .registrations
+        .iter()
+        .chain(&state.private_registrations)
+        .collect();
+    names.into_iter().cloned().collect()
+}
+
+/// Number of services currently registered through this layer.
+pub fn active_registration_count() -> usize {
+    let state = lock_state();
+    state.registrations.len() + state.private_registrations.len()
+}
@@ -0,0 +1,37 @@
+//! `GODOT_MDNS_FORCE_LOOPBACK`: a development aid that turns multicast
+//! loopback on for every daemon this extension creates, so a browser and an
+//! advertiser on the same machine see each other without calling
+//! `MdnsManager.set_multicast_loopback()` — handy when running two game
+//! instances side by side, and for the network round-trip tests.
+//!
+//! Not meant for release builds: loopback only adds traffic on a real LAN.
+//!
+//! Free of Godot types so it can be tested without an engine
+//! (see `tests/force_loopback.rs`).
+
+/// Environment variable read whenever a daemon is created.
+pub const FORCE_LOOPBACK_VAR: &str = "GODOT_MDNS_FORCE_LOOPBACK";
+
+/// Returns `true` for the values that switch the override on: `1`, `true`,
+/// `yes` or `on` (any case, surrounding blanks ignored).
+pub fn is_enabled_value(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+/// Returns `true` if [`FORCE_LOOPBACK_VAR`] is set to an enabling value.
+pub fn forced() -> bool {
+    std::env::var(FORCE_LOOPBACK_VAR).is_ok_and(|value| is_enabled_value(&value))
+}
+
+/// The multicast-loopback setting to apply to a new daemon: on when
+/// [`forced`], otherwise `configured` (`None` keeps the `mdns-sd` default).
+pub fn effective(configured: Option<bool>) -> Option<bool> {
+    if forced() {
+        Some(true)
+    } else {
+        configured
+    }
+}
@@ -0,0 +1,247 @@
+//! `MdnsDiagnostics` — one-button "test my LAN" self-check.
+
+use crate::advertiser;
+use crate::cache;
+use crate::daemon;
+use godot::prelude::*;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::time::{Duration, Instant};
+
+/// Advertises a throwaway service and browses for it at the same time,
+/// timing how long until this machine sees itself — the same probe the
+/// integration tests use to decide whether network tests can run, packaged
+/// for players.
+///
+/// ## GDScript example
+/// ```gdscript
+/// var diag := MdnsDiagnostics.new()
+/// add_child(diag)
+/// diag.diagnostics_complete.connect(func(r): print("LAN ok: ", r.self_visible))
+/// diag.run(5000)
+/// ```
+///
+/// The probe runs on a private daemon with multicast loopback on, so it never
+/// touches the browses and registrations of other nodes.  The temporary
+/// advertisement and browse are removed when the run ends, is cancelled, or
+/// the node leaves the tree.
+#[derive(GodotClass)]
+#[class(tool, base = Node)]
+pub struct MdnsDiagnostics {
+    run: Option<Probe>,
+    base: Base<Node>,
+}
+
+/// A `run()` in progress.
+struct Probe {
+    daemon: ServiceDaemon,
+    receiver: mdns_sd::Receiver<ServiceEvent>,
+    fullname: String,
+    started: Instant,
+    deadline: Instant,
+}
+
+/// Service type of the probe.  Short enough for the default 15-byte limit and
+/// unlikely to be browsed by anything else.
+const PROBE_SERVICE_TYPE: &str = "_gmdns-diag._udp.local.";
+
+/// Default for `run()` when `timeout_ms` is not positive.
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+
+/// Port announced by the probe (discard); nothing ever connects to it.
+const PROBE_PORT: u16 = 9;
+
+/// DNS labels are limited to 63 bytes.
+const MAX_INSTANCE_LEN: usize = 63;
+
+#[godot_api]
+impl INode for MdnsDiagnostics {
+    fn init(base: Base<Node>) -> Self {
+        Self { run: None, base }
+    }
+
+    /// Watch for the probe's own service every frame.
+    fn process(&mut self, _delta: f64) {
+        self.poll_probe();
+    }
+
+    /// Remove the temporary advertisement when the node leaves the tree.
+    fn exit_tree(&mut self) {
+        if let Some(probe) = self.run.take() {
+            probe.stop();
+        }
+    }
+}
+
+#[godot_api]
+impl MdnsDiagnostics {
+    // ── Signals ──────────────────────────────────────────────────────────────
+
+    /// Emitted when a `run()` finishes (or is cancelled).  `results` holds:
+    ///
+    /// - `self_visible` (bool) — this machine discovered its own probe
+    /// - `time_to_discover_ms` (int) — `-1` if it never did
+    /// - `addresses_seen` (PackedStringArray) — addresses the probe resolved
+    ///   to, IPv4 first; these are the addresses peers would connect to
+    /// - `error` (String) — why the run could not complete, or `""`
+    #[signal]
+    fn diagnostics_complete(results: VarDictionary);
+
+    // ── Methods ──────────────────────────────────────────────────────────────
+
+    /// Start the self-test, giving up after `timeout_ms` (default 5000 ms when
+    /// `timeout_ms <= 0`).  The result arrives via `diagnostics_complete`.
+    ///
+    /// Returns `false` if a run is already in progress; a probe that cannot be
+    /// started still reports through `diagnostics_complete` with `error` set.
+    #[func]
+    fn run(&mut self, timeout_ms: i64) -> bool {
+        if self.run.is_some() {
+            return false;
+        }
+        let timeout = if timeout_ms > 0 {
+            timeout_ms as u64
+        } else {
+            DEFAULT_TIMEOUT_MS
+        };
+        match Probe::start(Duration::from_millis(timeout)) {
+            Ok(probe) => self.run = Some(probe),
+            Err(e) => self.complete(None, &[], &e),
+        }
+        true
+    }
+
+    /// Abort a run in progress; `diagnostics_complete` reports it as not visible.
+    #[func]
+    fn cancel(&mut self) {
+        if let Some(probe) = self.run.take() {
+            probe.stop();
+            self.complete(None, &[], "cancelled");
+        }
+    }
+
+    /// Returns `true` while a run is in progress.
+    #[func]
+    fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    // ── Internal helpers ─────────────────────────────────────────────────────
+
+    /// Non-blocking drain of the probe browse.
+    fn poll_probe(&mut self) {
+        let Some(probe) = &self.run else {
+            return;
+        };
+        let found = probe.receiver.try_iter().find_map(|event| match event {
+            ServiceEvent::ServiceResolved(info)
+                if info.get_fullname().eq_ignore_ascii_case(&probe.fullname) =>
+            {
+                Some(cache::ipv4_first(info.get_addresses()))
+            }
+            _ => None,
+        });
+        let elapsed = probe.started.elapsed();
+        let timed_out = Instant::now() >= probe.deadline;
+        if found.is_none() && !timed_out {
+            return;
+        }
+        if let Some(probe) = self.run.take() {
+            probe.stop();
+        }
+        match found {
+            Some(addresses) => {
+                let addresses: Vec<String> = addresses.iter().map(|a| a.to_string()).collect();
+                self.complete(Some(elapsed), &addresses, "");
+            }
+            None => self.complete(None, &[], ""),
+        }
+    }
+
+    fn complete(&mut self, discovered_after: Option<Duration>, addresses: &[String], error: &str) {
+        let mut seen = PackedStringArray::new();
+        for addr in addresses {
+            seen.push(addr.as_str());
+        }
+        let mut results = VarDictionary::new();
+        results.set("self_visible", discovered_after.is_some());
+        results.set(
+            "time_to_discover_ms",
+            discovered_after.map_or(-1, |d| d.as_millis() as i64),
+        );
+        results.set("addresses_seen", seen);
+        results.set("error", error);
+        self.base_mut()
+            .emit_signal("diagnostics_complete", &[results.to_variant()]);
+    }
+}
+
+impl Probe {
+    /// Creates the private daemon, starts the browse, then registers a
+    /// uniquely named instance of [`PROBE_SERVICE_TYPE`].
+    fn start(timeout: Duration) -> Result<Self, String> {
+        let daemon = daemon::private_daemon(Default::default())?;
+        let started = (|| {
+            daemon
+                .set_multicast_loop_v4(true)
+                .and_then(|()| daemon.set_multicast_loop_v6(true))
+                .map_err(|e| format!("enabling multicast loopback failed: {e}"))?;
+            let receiver = daemon
+                .browse(PROBE_SERVICE_TYPE)
+                .map_err(|e| format!("probe browse failed: {e}"))?;
+            let host = advertiser::get_hostname();
+            let info = ServiceInfo::new(
+                PROBE_SERVICE_TYPE,
+                &probe_instance_name(&host),
+                &format!("{host}.local."),
+                "",
+                PROBE_PORT,
+                None,
+            )
+            .map_err(|e| format!("invalid probe service: {e}"))?
+            .enable_addr_auto();
+            let fullname = info.get_fullname().to_string();
+            daemon
+                .register(info)
+                .map_err(|e| format!("probe registration failed: {e}"))?;
+            Ok((receiver, fullname))
+        })();
+        match started {
+            Ok((receiver, fullname)) => {
+                let now = Instant::now();
+                Ok(Self {
+                    daemon,
+                    receiver,
+                    fullname,
+                    started: now,
+                    deadline: now + timeout,
+                })
+            }
+            Err(e) => {
+                let _ = daemon.shutdown();
+                Err(e)
+            }
+        }
+    }
+
+    /// Withdraws the probe (with a goodbye) and shuts its daemon down.
+    fn stop(self) {
+        let _ = self.daemon.unregister(&self.fullname);
+        let _ = self.daemon.stop_browse(PROBE_SERVICE_TYPE);
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// `"diag-<host>-<pid>-<nanos>"`, so concurrent runs on one LAN never collide.
+fn probe_instance_name(host: &str) -> String {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let suffix = format!("-{}-{nanos}", std::process::id());
+    let budget = MAX_INSTANCE_LEN - "diag-".len() - suffix.len();
+    let host: String = host
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .take(budget)
+        .collect();
+    format!("diag-{host}{suffix}")
+}
@@ -0,0 +1,653 @@
+//! The system DNS-SD backend on Apple platforms: browses and registers
+//! through mDNSResponder's C API (`dns_sd.h`, part of libSystem) instead of
+//! the bundled daemon.  See `backend` for what it covers.
+//!
+//! Each browse and each registration gets a thread of its own that owns its
+//! `DNSServiceRef`s and waits on their sockets — the C API is not
+//! thread-safe, so nothing else touches them.  Stopping the handle stops the
+//! thread, which deallocates the references; for a registration,
+//! mDNSResponder then sends the goodbye.
+
+use crate::backend::{self, Advertisement, Browse, DnsSd};
+use mdns_sd::{ServiceEvent, ServiceInfo};
+use std::collections::HashMap;
+use std::ffi::{c_char, c_int, c_short, c_uint, c_void, CStr, CString};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// The backend `lib.rs` installs with `backend::install_system()`.
+pub struct Dnssd;
+
+/// How long a worker waits on its sockets before checking whether to stop.
+const POLL_INTERVAL_MS: c_int = 100;
+
+// ── dns_sd.h / poll.h ──────────────────────────────────────────────────────
+
+type ServiceRef = *mut c_void;
+
+const NO_ERROR: i32 = 0;
+const FLAG_MORE_COMING: u32 = 0x1;
+const FLAG_ADD: u32 = 0x2;
+const PROTOCOL_IPV4: u32 = 0x1;
+const PROTOCOL_IPV6: u32 = 0x2;
+const INTERFACE_ANY: u32 = 0;
+
+const AF_INET: u8 = 2;
+const AF_INET6: u8 = 30;
+const POLLIN: c_short = 0x1;
+
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+type BrowseReply = unsafe extern "C" fn(
+    ServiceRef,
+    u32,
+    u32,
+    i32,
+    *const c_char,
+    *const c_char,
+    *const c_char,
+    *mut c_void,
+);
+type ResolveReply = unsafe extern "C" fn(
+    ServiceRef,
+    u32,
+    u32,
+    i32,
+    *const c_char,
+    *const c_char,
+    u16,
+    u16,
+    *const u8,
+    *mut c_void,
+);
+type AddrInfoReply =
+    unsafe extern "C" fn(ServiceRef, u32, u32, i32, *const c_char, *const u8, u32, *mut c_void);
+type RegisterReply = unsafe extern "C" fn(
+    ServiceRef,
+    u32,
+    i32,
+    *const c_char,
+    *const c_char,
+    *const c_char,
+    *mut c_void,
+);
+
+extern "C" {
+    fn DNSServiceBrowse(
+        sd_ref: *mut ServiceRef,
+        flags: u32,
+        interface_index: u32,
+        regtype: *const c_char,
+        domain: *const c_char,
+        callback: BrowseReply,
+        context: *mut c_void,
+    ) -> i32;
+    fn DNSServiceResolve(
+        sd_ref: *mut ServiceRef,
+        flags: u32,
+        interface_index: u32,
+        name: *const c_char,
+        regtype: *const c_char,
+        domain: *const c_char,
+        callback: ResolveReply,
+        context: *mut c_void,
+    ) -> i32;
+    fn DNSServiceGetAddrInfo(
+        sd_ref: *mut ServiceRef,
+        flags: u32,
+        interface_index: u32,
+        protocol: u32,
+        hostname: *const c_char,
+        callback: AddrInfoReply,
+        context: *mut c_void,
+    ) -> i32;
+    fn DNSServiceRegister(
+        sd_ref: *mut ServiceRef,
+        flags: u32,
+        interface_index: u32,
+        name: *const c_char,
+        regtype: *const c_char,
+        domain: *const c_char,
+        host: *const c_char,
+        port: u16,
+        txt_len: u16,
+        txt_record: *const c_void,
+        callback: Option<RegisterReply>,
+        context: *mut c_void,
+    ) -> i32;
+    fn DNSServiceUpdateRecord(
+        sd_ref: ServiceRef,
+        record_ref: *mut c_void,
+        flags: u32,
+        rdlen: u16,
+        rdata: *const c_void,
+        ttl: u32,
+    ) -> i32;
+    fn DNSServiceRefSockFD(sd_ref: ServiceRef) -> c_int;
+    fn DNSServiceProcessResult(sd_ref: ServiceRef) -> i32;
+    fn DNSServiceRefDeallocate(sd_ref: ServiceRef);
+    fn poll(fds: *mut PollFd, nfds: c_uint, timeout: c_int) -> c_int;
+}
+
+impl DnsSd for Dnssd {
+    fn browse(&self, service_type: &str) -> Result<Browse, String> {
+        let (regtype, domain) = c_service_type(service_type)?;
+        let (sender, receiver) = flume::unbounded();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (started, start) = mpsc::channel();
+        let service_type = service_type.to_string();
+        let worker_stop = Arc::clone(&stop);
+        thread::Builder::new()
+            .name("godot-mdns-dnssd-browse".into())
+            .spawn(move || {
+                let state = BrowseState {
+                    service_type,
+                    sender,
+                    instances: HashMap::new(),
+                    retired: Vec::new(),
+                };
+                browse_worker(state, &regtype, &domain, &started, &worker_stop);
+            })
+            .map_err(|e| format!("failed to start the browse thread: {e}"))?;
+        start.recv().map_err(|_| "the browse thread exited".to_string())??;
+        // Not joined: the thread lets go of its references within one poll.
+        Ok(Browse::new(receiver, move || stop.store(true, Ordering::SeqCst)))
+    }
+
+    fn register(&self, info: &ServiceInfo) -> Result<Advertisement, String> {
+        let service = info.clone().as_resolved_service();
+        let service_type = service.sub_ty_domain.as_deref().unwrap_or(&service.ty_domain);
+        let (regtype, domain) = c_service_type(service_type)?;
+        let name = backend::instance_name(&service.fullname, &service.ty_domain)
+            .and_then(|name| CString::new(name).ok())
+            .ok_or_else(|| format!("invalid instance name in {}", service.fullname))?;
+        let txt = backend::encode_txt(&service.txt_properties);
+        let (outcome, outcomes) = mpsc::channel();
+        let (update, updates) = mpsc::channel::<Vec<u8>>();
+        let stop = Arc::new(AtomicBool::new(false));
+        let (started, start) = mpsc::channel();
+        let request = RegisterRequest {
+            name,
+            regtype,
+            domain,
+            port: service.port,
+            txt,
+        };
+        let state = RegisterState {
+            service_type: service.ty_domain.clone(),
+            outcome,
+        };
+        let worker_stop = Arc::clone(&stop);
+        let worker = thread::Builder::new()
+            .name("godot-mdns-dnssd-register".into())
+            .spawn(move || register_worker(state, request, &updates, &started, &worker_stop))
+            .map_err(|e| format!("failed to start the registration thread: {e}"))?;
+        start.recv().map_err(|_| "the registration thread exited".to_string())??;
+        Ok(Advertisement::new(
+            outcomes,
+            move |info: &ServiceInfo| {
+                let service = info.clone().as_resolved_service();
+                update
+                    .send(backend::encode_txt(&service.txt_properties))
+                    .map_err(|_| "the registration thread exited".to_string())
+            },
+            // Joined, so re-advertising the same name right after does not
+            // collide with the registration being withdrawn.
+            move || {
+                stop.store(true, Ordering::SeqCst);
+                let _ = worker.join();
+            },
+        ))
+    }
+}
+
+/// `service_type` as the registration type and domain strings the C API takes.
+fn c_service_type(service_type: &str) -> Result<(CString, CString), String> {
+    backend::split_service_type(service_type)
+        .and_then(|(regtype, domain)| {
+            Some((CString::new(regtype).ok()?, CString::new(domain).ok()?))
+        })
+        .ok_or_else(|| format!("invalid service type {service_type:?}"))
+}
+
+// ── Browsing ───────────────────────────────────────────────────────────────
+
+/// One instance a browse has found, and the queries resolving it.
+struct Instance {
+    name: String,
+    /// Interfaces it was found on; it is removed when the last one drops it.
+    seen: u32,
+    resolve: Option<ServiceRef>,
+    addr_query: Option<ServiceRef>,
+    host: String,
+    port: u16,
+    txt: Vec<u8>,
+    addresses: Vec<IpAddr>,
+}
+
+/// What a browse thread's callbacks share through their context pointer.
+struct BrowseState {
+    service_type: String,
+    sender: flume::Sender<ServiceEvent>,
+    /// Instances by fullname.
+    instances: HashMap<String, Instance>,
+    /// References to deallocate once the callback running now has returned.
+    retired: Vec<ServiceRef>,
+}
+
+impl BrowseState {
+    /// The resolve and address queries of every instance.
+    fn queries(&self) -> Vec<ServiceRef> {
+        self.instances
+            .values()
+            .flat_map(|instance| instance.resolve.into_iter().chain(instance.addr_query))
+            .collect()
+    }
+
+    /// The fullname of the instance `sd_ref` is a query of.
+    fn fullname_of(&self, sd_ref: ServiceRef) -> Option<String> {
+        self.instances
+            .iter()
+            .find(|(_, instance)| {
+                instance.resolve == Some(sd_ref) || instance.addr_query == Some(sd_ref)
+            })
+            .map(|(fullname, _)| fullname.clone())
+    }
+
+    /// Reports instance `fullname` resolved, once it has an address.
+    fn report(&self, fullname: &str) {
+        let Some(instance) = self.instances.get(fullname) else {
+            return;
+        };
+        if instance.addresses.is_empty() {
+            return;
+        }
+        if let Some(service) = backend::resolved(
+            &self.service_type,
+            &instance.name,
+            &instance.host,
+            instance.port,
+            &instance.addresses,
+            &instance.txt,
+        ) {
+            let _ = self.sender.send(ServiceEvent::ServiceResolved(Box::new(service)));
+        }
+    }
+
+    fn release_retired(&mut self) {
+        for sd_ref in self.retired.drain(..) {
+            unsafe { DNSServiceRefDeallocate(sd_ref) };
+        }
+    }
+}
+
+fn browse_worker(
+    state: BrowseState,
+    regtype: &CStr,
+    domain: &CStr,
+    started: &mpsc::Sender<Result<(), String>>,
+    stop: &AtomicBool,
+) {
+    // Only reached through raw pointers from here on: the callbacks modify
+    // it while `DNSServiceProcessResult` runs.
+    let state = Box::into_raw(Box::new(state));
+    let mut browse: ServiceRef = ptr::null_mut();
+    let error = unsafe {
+        DNSServiceBrowse(
+            &mut browse,
+            0,
+            INTERFACE_ANY,
+            regtype.as_ptr(),
+            domain.as_ptr(),
+            on_browse,
+            state.cast(),
+        )
+    };
+    if error != NO_ERROR {
+        let _ = started.send(Err(format!("DNSServiceBrowse failed ({error})")));
+        drop(unsafe { Box::from_raw(state) });
+        return;
+    }
+    let _ = started.send(Ok(()));
+    unsafe {
+        let _ = (*state).sender.send(ServiceEvent::SearchStarted((*state).service_type.clone()));
+    }
+    while !stop.load(Ordering::SeqCst) {
+        let mut refs = vec![browse];
+        refs.extend(unsafe { (*state).queries() });
+        for sd_ref in ready(&refs) {
+            // An earlier reply this round may have retired it.
+            if unsafe { (*state).retired.contains(&sd_ref) } {
+                continue;
+            }
+            let error = unsafe { DNSServiceProcessResult(sd_ref) };
+            if error == NO_ERROR {
+                continue;
+            }
+            if sd_ref == browse {
+                log::warn!("godot-mdns: mDNSResponder ended the browse ({error})");
+                stop.store(true, Ordering::SeqCst);
+            } else if let Some(instance) = unsafe {
+                (*state).fullname_of(sd_ref).and_then(|name| (*state).instances.get_mut(&name))
+            } {
+                // Give up on this query only; the instance stays listed.
+                if instance.resolve == Some(sd_ref) {
+                    instance.resolve = None;
+                } else {
+                    instance.addr_query = None;
+                }
+                unsafe { (*state).retired.push(sd_ref) };
+            }
+        }
+        unsafe { (*state).release_retired() };
+    }
+    let mut state = unsafe { Box::from_raw(state) };
+    let queries = state.queries();
+    state.retired.extend(queries);
+    state.release_retired();
+    unsafe { DNSServiceRefDeallocate(browse) };
+}
+
+/// The references of `refs` with a reply waiting, after at most
+/// `POLL_INTERVAL_MS`.
+fn ready(refs: &[ServiceRef]) -> Vec<ServiceRef> {
+    let mut fds: Vec<PollFd> = refs
+        .iter()
+        .map(|&sd_ref| PollFd {
+            fd: unsafe { DNSServiceRefSockFD(sd_ref) },
+            events: POLLIN,
+            revents: 0,
+        })
+        .collect();
+    if unsafe { poll(fds.as_mut_ptr(), fds.len() as c_uint, POLL_INTERVAL_MS) } <= 0 {
+        return Vec::new();
+    }
+    refs.iter()
+        .zip(&fds)
+        .filter(|(_, fd)| fd.revents != 0)
+        .map(|(&sd_ref, _)| sd_ref)
+        .collect()
+}
+
+unsafe extern "C" fn on_browse(
+    _sd_ref: ServiceRef,
+    flags: u32,
+    _interface_index: u32,
+    error: i32,
+    name: *const c_char,
+    regtype: *const c_char,
+    domain: *const c_char,
+    context: *mut c_void,
+) {
+    if error != NO_ERROR {
+        return;
+    }
+    let state = &mut *context.cast::<BrowseState>();
+    let instance_name = CStr::from_ptr(name).to_string_lossy().into_owned();
+    let fullname = backend::fullname(&instance_name, &state.service_type);
+    if flags & FLAG_ADD == 0 {
+        let Some(instance) = state.instances.get_mut(&fullname) else {
+            return;
+        };
+        instance.seen -= 1;
+        if instance.seen > 0 {
+            return;
+        }
+        if let Some(instance) = state.instances.remove(&fullname) {
+            state.retired.extend(instance.resolve.into_iter().chain(instance.addr_query));
+        }
+        let _ = state
+            .sender
+            .send(ServiceEvent::ServiceRemoved(state.service_type.clone(), fullname));
+        return;
+    }
+    if let Some(instance) = state.instances.get_mut(&fullname) {
+        instance.seen += 1;
+        return;
+    }
+    let _ = state
+        .sender
+        .send(ServiceEvent::ServiceFound(state.service_type.clone(), fullname.clone()));
+    let mut resolve: ServiceRef = ptr::null_mut();
+    let error = DNSServiceResolve(
+        &mut resolve,
+        0,
+        INTERFACE_ANY,
+        name,
+        regtype,
+        domain,
+        on_resolve,
+        context,
+    );
+    state.instances.insert(
+        fullname,
+        Instance {
+            name: instance_name,
+            seen: 1,
+            resolve: (error == NO_ERROR).then_some(resolve),
+            addr_query: None,
+            host: String::new(),
+            port: 0,
+            txt: Vec::new(),
+            addresses: Vec::new(),
+        },
+    );
+}
+
+unsafe extern "C" fn on_resolve(
+    sd_ref: ServiceRef,
+    _flags: u32,
+    _interface_index: u32,
+    error: i32,
+    _fullname: *const c_char,
+    host: *const c_char,
+    port: u16,
+    txt_len: u16,
+    txt: *const u8,
+    context: *mut c_void,
+) {
+    if error != NO_ERROR {
+        return;
+    }
+    let state = &mut *context.cast::<BrowseState>();
+    let host = CStr::from_ptr(host).to_string_lossy().into_owned();
+    let txt = if txt.is_null() {
+        Vec::new()
+    } else {
+        std::slice::from_raw_parts(txt, usize::from(txt_len)).to_vec()
+    };
+    let Some(fullname) = state.fullname_of(sd_ref) else {
+        return;
+    };
+    let Some(instance) = state.instances.get_mut(&fullname) else {
+        return;
+    };
+    instance.port = u16::from_be(port);
+    instance.txt = txt;
+    if instance.host == host && instance.addr_query.is_some() {
+        // Same host: a new TXT record or port.
+        state.report(&fullname);
+        return;
+    }
+    let retired = instance.addr_query.take();
+    instance.host = host.clone();
+    instance.addresses.clear();
+    let Ok(c_host) = CString::new(host) else {
+        return;
+    };
+    let mut addr_query: ServiceRef = ptr::null_mut();
+    let error = DNSServiceGetAddrInfo(
+        &mut addr_query,
+        0,
+        INTERFACE_ANY,
+        PROTOCOL_IPV4 | PROTOCOL_IPV6,
+        c_host.as_ptr(),
+        on_addr_info,
+        context,
+    );
+    if error == NO_ERROR {
+        instance.addr_query = Some(addr_query);
+    }
+    state.retired.extend(retired);
+}
+
+unsafe extern "C" fn on_addr_info(
+    sd_ref: ServiceRef,
+    flags: u32,
+    _interface_index: u32,
+    error: i32,
+    _hostname: *const c_char,
+    address: *const u8,
+    _ttl: u32,
+    context: *mut c_void,
+) {
+    if error != NO_ERROR || address.is_null() {
+        return;
+    }
+    let state = &mut *context.cast::<BrowseState>();
+    let Some(fullname) = state.fullname_of(sd_ref) else {
+        return;
+    };
+    let Some(instance) = state.instances.get_mut(&fullname) else {
+        return;
+    };
+    if let Some(ip) = sockaddr_ip(address) {
+        if flags & FLAG_ADD != 0 {
+            if !instance.addresses.contains(&ip) {
+                instance.addresses.push(ip);
+            }
+        } else {
+            instance.addresses.retain(|known| *known != ip);
+        }
+    }
+    if flags & FLAG_MORE_COMING == 0 {
+        state.report(&fullname);
+    }
+}
+
+/// The address of a `sockaddr_in` / `sockaddr_in6` (BSD layout: length byte,
+/// family byte, port, then the address).
+unsafe fn sockaddr_ip(address: *const u8) -> Option<IpAddr> {
+    match *address.add(1) {
+        AF_INET => {
+            let octets: [u8; 4] = std::slice::from_raw_parts(address.add(4), 4).try_into().ok()?;
+            Some(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        AF_INET6 => {
+            let octets: [u8; 16] =
+                std::slice::from_raw_parts(address.add(8), 16).try_into().ok()?;
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+// ── Registering ────────────────────────────────────────────────────────────
+
+/// What `DNSServiceRegister` is called with.
+struct RegisterRequest {
+    name: CString,
+    regtype: CString,
+    domain: CString,
+    port: u16,
+    txt: Vec<u8>,
+}
+
+/// What a registration thread's callback shares through its context pointer.
+struct RegisterState {
+    service_type: String,
+    outcome: mpsc::Sender<Result<String, String>>,
+}
+
+fn register_worker(
+    state: RegisterState,
+    request: RegisterRequest,
+    updates: &mpsc::Receiver<Vec<u8>>,
+    started: &mpsc::Sender<Result<(), String>>,
+    stop: &AtomicBool,
+) {
+    let Ok(txt_len) = u16::try_from(request.txt.len()) else {
+        let _ = started.send(Err("TXT record longer than 65535 bytes".into()));
+        return;
+    };
+    let state = Box::into_raw(Box::new(state));
+    let mut registration: ServiceRef = ptr::null_mut();
+    let error = unsafe {
+        DNSServiceRegister(
+            &mut registration,
+            0,
+            INTERFACE_ANY,
+            request.name.as_ptr(),
+            request.regtype.as_ptr(),
+            request.domain.as_ptr(),
+            // mDNSResponder's own host name and addresses.
+            ptr::null(),
+            request.port.to_be(),
+            txt_len,
+            request.txt.as_ptr().cast(),
+            Some(on_register),
+            state.cast(),
+        )
+    };
+    if error != NO_ERROR {
+        let _ = started.send(Err(format!("DNSServiceRegister failed ({error})")));
+        drop(unsafe { Box::from_raw(state) });
+        return;
+    }
+    let _ = started.send(Ok(()));
+    while !stop.load(Ordering::SeqCst) {
+        while let Ok(txt) = updates.try_recv() {
+            let Ok(txt_len) = u16::try_from(txt.len()) else {
+                continue;
+            };
+            let error = unsafe {
+                let rdata = txt.as_ptr().cast();
+                DNSServiceUpdateRecord(registration, ptr::null_mut(), 0, txt_len, rdata, 0)
+            };
+            if error != NO_ERROR {
+                let message = format!("DNSServiceUpdateRecord failed ({error})");
+                let _ = unsafe { (*state).outcome.send(Err(message)) };
+            }
+        }
+        if ready(&[registration]).is_empty() {
+            continue;
+        }
+        let error = unsafe { DNSServiceProcessResult(registration) };
+        if error != NO_ERROR {
+            let message = format!("mDNSResponder ended the registration ({error})");
+            let _ = unsafe { (*state).outcome.send(Err(message)) };
+            break;
+        }
+    }
+    unsafe {
+        DNSServiceRefDeallocate(registration);
+        drop(Box::from_raw(state));
+    }
+}
+
+unsafe extern "C" fn on_register(
+    _sd_ref: ServiceRef,
+    _flags: u32,
+    error: i32,
+    name: *const c_char,
+    _regtype: *const c_char,
+    _domain: *const c_char,
+    context: *mut c_void,
+) {
+    let state = &*context.cast::<RegisterState>();
+    let outcome = if error == NO_ERROR {
+        let name = CStr::from_ptr(name).to_string_lossy();
+        Ok(backend::fullname(&name, &state.service_type))
+    } else {
+        Err(format!("mDNSResponder rejected the registration ({error})"))
+    };
+    let _ = state.outcome.send(outcome);
+}
@@ -0,0 +1,231 @@
+//! Network environment probes behind `MdnsManager.run_environment_diagnostics()`.
+//!
+//! The integration tests learned the hard way that some machines cannot
+//! discover their own services: Windows with Hyper-V or WSL virtual switches
+//! (and some VPN adapters) never deliver an mDNS packet back to the machine
+//! that sent it, so hosting and joining on one PC finds nothing although LAN
+//! discovery works.  These are the probes `tests/mdns_loopback.rs` runs to
+//! decide whether its network tests can run, packaged for the shipped game:
+//!
+//! - raw UDP multicast loopback on an ephemeral socket;
+//! - the same per IPv4 interface, joining the mDNS group on each;
+//! - a throwaway service registered and browsed on a private daemon, which is
+//!   the definitive answer.
+//!
+//! They take a few seconds, so [`spawn`] runs them on a thread of their own.
+//!
+//! Free of Godot types so the report can be tested without an engine
+//! (see `tests/environment.rs`).
+
+use crate::interfaces::LocalInterface;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, UdpSocket};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Port of the self-resolve daemon; off 5353 so the shared daemon and other
+/// responders are not disturbed.
+const DAEMON_PORT: u16 = 15353;
+
+/// Port announced by the self-resolve probe (discard); nothing connects to it.
+const PROBE_PORT: u16 = 9;
+
+/// Service type of the self-resolve probe.
+const PROBE_SERVICE_TYPE: &str = "_gmdns-env._udp.local.";
+
+/// How long a raw multicast packet may take to loop back.
+const LOOPBACK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long the self-resolve probe waits to see its own service.
+const SELF_RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The mDNS IPv4 group.
+const MDNS_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+/// Raw multicast loopback through one interface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceProbe {
+    pub name: String,
+    pub address: Ipv4Addr,
+    /// The packet came back.
+    pub looped: bool,
+    /// Why the probe could not run or nothing came back; empty if it did.
+    pub error: String,
+}
+
+/// What the probes found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnvironmentReport {
+    /// Raw multicast loops back on the default interface.
+    pub multicast_loopback: bool,
+    /// Why it does not; empty if it does.
+    pub multicast_error: String,
+    pub interfaces: Vec<InterfaceProbe>,
+    /// How long the throwaway service took to be seen, or `None` if it never was.
+    pub self_resolve: Option<Duration>,
+    /// How long all the probes took.
+    pub duration: Duration,
+}
+
+impl EnvironmentReport {
+    /// Returns `true` if a browser should find an advertiser in the same
+    /// process or on the same machine.
+    pub fn same_machine_discovery_expected(&self) -> bool {
+        self.self_resolve.is_some()
+    }
+
+    /// A few sentences for a player or QA: what works, and what to do if not.
+    pub fn summary(&self) -> String {
+        if let Some(took) = self.self_resolve {
+            return format!(
+                "Same-machine discovery works: this machine saw its own test service \
+                 after {} ms.",
+                took.as_millis()
+            );
+        }
+        if !self.multicast_loopback {
+            return format!(
+                "This machine does not deliver multicast packets back to itself ({}), so \
+                 mDNS cannot work here. Check that a firewall allows UDP multicast for this \
+                 program and that a network adapter is connected.",
+                self.multicast_error
+            );
+        }
+        let mut summary = String::from(
+            "Multicast loops back, but this machine could not discover its own test \
+             service. This is normal on Windows with Hyper-V or WSL virtual switches and \
+             with some VPN adapters: hosting and joining on one PC will not find the host, \
+             while discovery between two machines on the LAN is unaffected. Connect to \
+             127.0.0.1 to play on one PC.",
+        );
+        let failed: Vec<String> = self
+            .interfaces
+            .iter()
+            .filter(|probe| !probe.looped)
+            .map(|probe| format!("{} ({})", probe.name, probe.address))
+            .collect();
+        if !failed.is_empty() {
+            summary.push_str(&format!(
+                " Multicast did not loop back on: {}.",
+                failed.join(", ")
+            ));
+        }
+        summary
+    }
+}
+
+/// Runs [`run`] on a thread of its own; the report arrives on the receiver.
+pub fn spawn() -> io::Result<mpsc::Receiver<EnvironmentReport>> {
+    let (tx, receiver) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("godot-mdns-env".to_string())
+        .spawn(move || {
+            let _ = tx.send(run());
+        })?;
+    Ok(receiver)
+}
+
+/// Runs every probe, blocking for up to about ten seconds.
+pub fn run() -> EnvironmentReport {
+    let started = Instant::now();
+    let (multicast_loopback, multicast_error) =
+        match loop_back(Ipv4Addr::new(239, 255, 77, 88), Ipv4Addr::UNSPECIFIED) {
+            Ok(()) => (true, String::new()),
+            Err(e) => (false, e.to_string()),
+        };
+    let interfaces = LocalInterface::enumerate()
+        .into_iter()
+        .filter_map(|interface| match interface.ip {
+            IpAddr::V4(address) => Some((interface.name, address)),
+            IpAddr::V6(_) => None,
+        })
+        .map(|(name, address)| {
+            let result = loop_back(MDNS_GROUP, address);
+            InterfaceProbe {
+                name,
+                address,
+                looped: result.is_ok(),
+                error: result.err().map(|e| e.to_string()).unwrap_or_default(),
+            }
+        })
+        .collect();
+    let self_resolve = self_resolve();
+    EnvironmentReport {
+        multicast_loopback,
+        multicast_error,
+        interfaces,
+        self_resolve,
+        duration: started.elapsed(),
+    }
+}
+
+/// Sends one packet to `group` through `interface` and waits for it to come
+/// back to the same socket.
+fn loop_back(group: Ipv4Addr, interface: Ipv4Addr) -> io::Result<()> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    let port = socket.local_addr()?.port();
+    socket.join_multicast_v4(&group, &interface)?;
+    socket.set_multicast_loop_v4(true)?;
+    socket.set_read_timeout(Some(LOOPBACK_TIMEOUT))?;
+    let message = b"GODOT_MDNS_LOOPBACK_PROBE";
+    socket.send_to(message, (group, port))?;
+    let mut buffer = [0u8; 64];
+    let deadline = Instant::now() + LOOPBACK_TIMEOUT;
+    // Other programs may send to the group too; only our own packet counts.
+    while Instant::now() < deadline {
+        let (len, _) = socket.recv_from(&mut buffer)?;
+        if &buffer[..len] == message {
+            return Ok(());
+        }
+    }
+    Err(io::Error::new(io::ErrorKind::TimedOut, "the packet did not come back"))
+}
+
+/// Registers a throwaway service on a private daemon with multicast loopback
+/// on and browses for it, returning how long until it resolved.
+fn self_resolve() -> Option<Duration> {
+    let daemon = ServiceDaemon::new_with_port(DAEMON_PORT).ok()?;
+    let _ = daemon.set_multicast_loop_v4(true);
+    let _ = daemon.set_multicast_loop_v6(true);
+    let resolved = browse_own_service(&daemon);
+    let _ = daemon.shutdown();
+    resolved
+}
+
+fn browse_own_service(daemon: &ServiceDaemon) -> Option<Duration> {
+    let receiver = daemon.browse(PROBE_SERVICE_TYPE).ok()?;
+    std::thread::sleep(Duration::from_millis(300));
+    let host = hostname::get()
+        .ok()
+        .and_then(|name| name.into_string().ok())
+        .unwrap_or_else(|| "godot-mdns-probe".to_string());
+    let instance = format!("env-probe-{}", std::process::id());
+    let info = ServiceInfo::new(
+        PROBE_SERVICE_TYPE,
+        &instance,
+        &format!("{host}.local."),
+        "",
+        PROBE_PORT,
+        &[] as &[(&str, &str)],
+    )
+    .ok()?
+    .enable_addr_auto();
+    let fullname = info.get_fullname().to_string();
+    let started = Instant::now();
+    daemon.register(info).ok()?;
+    let deadline = started + SELF_RESOLVE_TIMEOUT;
+    let mut resolved = None;
+    while let Some(left) = deadline.checked_duration_since(Instant::now()) {
+        match receiver.recv_timeout(left) {
+            Ok(ServiceEvent::ServiceResolved(service)) if service.get_fullname() == fullname => {
+                resolved = Some(started.elapsed());
+                break;
+            }
+            Ok(_) => {}
+            Err(_) => break,
+        }
+    }
+    let _ = daemon.unregister(&fullname);
+    resolved
+}
@@ -0,0 +1,96 @@
+//! Fan-out of one browse channel to every node browsing the same type.
+//!
+//! `mdns-sd` keeps a single listener per service type: a second `browse()` of
+//! a type replaces the first caller's channel, and `stop_browse()` ends the
+//! search for everyone.  The shared daemon layer therefore browses each type
+//! once and gives every interested node its own queue, fed from that one
+//! channel; the daemon-side browse is stopped when the last node leaves.
+//!
+//! Free of Godot types so it can be tested without an engine
+//! (see `tests/fanout.rs`).
+
+use mdns_sd::ServiceEvent;
+use std::collections::{BTreeMap, VecDeque};
+
+/// Per-subscriber queues for one browsed type, plus what is needed to bring
+/// a late subscriber up to date.
+#[derive(Default)]
+pub struct BrowseFanOut {
+    queues: BTreeMap<u64, VecDeque<ServiceEvent>>,
+    /// Every instance found and not removed since, by fullname, with the
+    /// `ServiceFound` that announced it.
+    found: BTreeMap<String, ServiceEvent>,
+    /// Latest `ServiceResolved` of each live instance, by fullname.
+    resolved: BTreeMap<String, ServiceEvent>,
+}
+
+impl BrowseFanOut {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds subscriber `id`.  Its queue starts with the `ServiceFound` and
+    /// latest `ServiceResolved` of every live instance, as a fresh browse on
+    /// the daemon would have reported them.
+    pub fn subscribe(&mut self, id: u64) {
+        let replay = self
+            .found
+            .values()
+            .chain(self.resolved.values())
+            .cloned()
+            .collect();
+        self.queues.insert(id, replay);
+    }
+
+    /// Removes subscriber `id` and its queue.  Returns `true` if nobody is
+    /// left, i.e. the daemon-side browse should be stopped.
+    pub fn unsubscribe(&mut self, id: u64) -> bool {
+        self.queues.remove(&id);
+        self.is_empty()
+    }
+
+    /// Number of subscribers.
+    pub fn len(&self) -> usize {
+        self.queues.len()
+    }
+
+    /// Returns `true` if there are no subscribers.
+    pub fn is_empty(&self) -> bool {
+        self.queues.is_empty()
+    }
+
+    /// Queues `event` for every subscriber and updates the replay state.
+    pub fn dispatch(&mut self, event: ServiceEvent) {
+        match &event {
+            ServiceEvent::ServiceFound(_, fullname) => {
+                self.found.insert(fullname.clone(), event.clone());
+            }
+            ServiceEvent::ServiceResolved(info) => {
+                self.resolved
+                    .insert(info.get_fullname().to_string(), event.clone());
+            }
+            ServiceEvent::ServiceRemoved(_, fullname) => {
+                self.found.remove(fullname);
+                self.resolved.remove(fullname);
+            }
+            _ => {}
+        }
+        for queue in self.queues.values_mut() {
+            queue.push_back(event.clone());
+        }
+    }
+
+    /// Number of events queued for subscriber `id`; 0 for unknown ids.
+    pub fn queued(&self, id: u64) -> usize {
+        self.queues.get(&id).map_or(0, VecDeque::len)
+    }
+
+    /// Takes every event queued for subscriber `id`, oldest first.  Empty for
+    /// unknown ids.
+    pub fn take(&mut self, id: u64) -> Vec<ServiceEvent> {
+        self.queues
+            .get_mut(&id)
+            .map(|queue| queue.drain(..).collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,225 @@
+//! Windows Defender Firewall check behind `MdnsManager.check_firewall()`.
+//!
+//! The most common cause of one-way discovery on Windows: the first time the
+//! game listens, Windows asks whether to allow it, the player ticks only
+//! "Private networks" (or clicks Cancel), and on a network Windows classed as
+//! Public every inbound mDNS packet is dropped.  The game can still send, so
+//! its own queries and announcements go out and other machines see it, but it
+//! hears no answers.
+//!
+//! The firewall is queried through PowerShell's `NetSecurity` cmdlets, whose
+//! property names and values do not depend on the system language (unlike
+//! `netsh` output).  Only rules naming this executable are considered; rules
+//! for every program or built from service names are not.
+//!
+//! Free of Godot types so the evaluation can be tested without an engine
+//! (see `tests/firewall.rs`).
+
+/// An enabled inbound rule for this executable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirewallRule {
+    pub name: String,
+    /// `Allow` rather than `Block`.
+    pub allow: bool,
+    /// `UDP`, `TCP`, `Any`, …
+    pub protocol: String,
+    /// `Any`, or ports and ranges separated by commas (`"5353,7000-7100"`).
+    pub local_port: String,
+    /// `Any`, or profiles separated by commas (`"Private, Public"`).
+    pub profiles: String,
+}
+
+impl FirewallRule {
+    /// Returns `true` if the rule covers inbound UDP to `port` on `profile`.
+    pub fn covers(&self, profile: &str, port: u16) -> bool {
+        let listed = |list: &str, wanted: &dyn Fn(&str) -> bool| {
+            list.split(',')
+                .map(str::trim)
+                .any(|item| item.eq_ignore_ascii_case("Any") || wanted(item))
+        };
+        listed(&self.protocol, &|protocol| protocol.eq_ignore_ascii_case("UDP"))
+            && listed(&self.local_port, &|ports| match ports.split_once('-') {
+                Some((low, high)) => low.parse().is_ok_and(|low: u16| low <= port)
+                    && high.parse().is_ok_and(|high: u16| port <= high),
+                None => ports.parse() == Ok(port),
+            })
+            && listed(&self.profiles, &|listed| listed.eq_ignore_ascii_case(profile))
+    }
+}
+
+/// What the firewall does with inbound mDNS for this executable.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FirewallReport {
+    /// Category of the active network: `Public`, `Private` or `Domain`;
+    /// empty if there is no connected network.
+    pub profile: String,
+    /// The firewall is on for that profile.
+    pub enabled: bool,
+    /// The profile blocks inbound traffic no rule allows (the default).
+    pub default_block: bool,
+    /// The profile ignores allow rules ("Block all incoming connections").
+    pub block_all: bool,
+    /// Enabled inbound rules for this executable.
+    pub rules: Vec<FirewallRule>,
+}
+
+impl FirewallReport {
+    /// The first rule allowing inbound mDNS on the active profile.
+    pub fn allow_rule(&self, port: u16) -> Option<&FirewallRule> {
+        self.rules.iter().find(|rule| rule.allow && rule.covers(&self.profile, port))
+    }
+
+    /// The first rule blocking inbound mDNS on the active profile; block
+    /// rules win over allow rules.
+    pub fn block_rule(&self, port: u16) -> Option<&FirewallRule> {
+        self.rules.iter().find(|rule| !rule.allow && rule.covers(&self.profile, port))
+    }
+
+    /// Returns `true` if inbound mDNS on `port` is dropped.
+    pub fn inbound_blocked(&self, port: u16) -> bool {
+        self.enabled
+            && (self.block_all
+                || self.block_rule(port).is_some()
+                || (self.default_block && self.allow_rule(port).is_none()))
+    }
+
+    /// What is wrong and how to fix it, or `""` if nothing is.
+    pub fn message(&self, port: u16, program: &str) -> String {
+        if !self.inbound_blocked(port) {
+            return String::new();
+        }
+        let profile = &self.profile;
+        let cause = if self.block_all {
+            format!("the {profile} firewall profile blocks all incoming connections")
+        } else if let Some(rule) = self.block_rule(port) {
+            format!("the firewall rule \"{}\" blocks it on {profile} networks", rule.name)
+        } else {
+            format!("no firewall rule allows it on {profile} networks")
+        };
+        format!(
+            "Windows Firewall drops incoming mDNS (UDP {port}) for {program}: {cause}, so \
+             this game can be found but finds nothing. Allow the game in Windows Security → \
+             Firewall & network protection → Allow an app through firewall (tick \
+             \"{profile}\"), switch the network to Private, or call \
+             MdnsManager.request_firewall_rule()"
+        )
+    }
+}
+
+/// Reads `key=value` lines printed by [`SCRIPT`]; unknown lines are ignored.
+#[cfg_attr(not(windows), allow(dead_code))]
+pub fn parse_report(text: &str) -> FirewallReport {
+    let mut report = FirewallReport::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let yes = value.eq_ignore_ascii_case("True");
+        match key {
+            // Domain networks report as `DomainAuthenticated`.
+            "profile" => report.profile = value.trim_end_matches("Authenticated").to_string(),
+            "enabled" => report.enabled = yes,
+            // `NotConfigured` falls back to Windows' default, which is Block.
+            "default_inbound" => report.default_block = !value.eq_ignore_ascii_case("Allow"),
+            "allow_inbound_rules" => report.block_all = value.eq_ignore_ascii_case("False"),
+            "rule" => {
+                let fields: Vec<&str> = value.split('|').collect();
+                if let [name, action, protocol, local_port, profiles] = fields[..] {
+                    report.rules.push(FirewallRule {
+                        name: name.to_string(),
+                        allow: action.eq_ignore_ascii_case("Allow"),
+                        protocol: protocol.to_string(),
+                        local_port: local_port.to_string(),
+                        profiles: profiles.to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    report
+}
+
+/// Prints the state of the active profile and the enabled inbound rules of
+/// the program in `$exe` as `key=value` lines.
+#[cfg_attr(not(windows), allow(dead_code))]
+const SCRIPT: &str = r#"
+$ErrorActionPreference = 'SilentlyContinue'
+$category = [string](Get-NetConnectionProfile | Select-Object -First 1).NetworkCategory
+"profile=$category"
+$name = $category -replace 'Authenticated', ''
+if ($name) {
+    $fw = Get-NetFirewallProfile -PolicyStore ActiveStore -Name $name
+    "enabled=$($fw.Enabled)"
+    "default_inbound=$($fw.DefaultInboundAction)"
+    "allow_inbound_rules=$($fw.AllowInboundRules)"
+}
+Get-NetFirewallApplicationFilter -PolicyStore ActiveStore |
+    Where-Object { $_.Program -eq $exe } |
+    Get-NetFirewallRule |
+    Where-Object { $_.Direction -eq 'Inbound' -and $_.Enabled -eq 'True' } |
+    ForEach-Object {
+        $ports = $_ | Get-NetFirewallPortFilter
+        $local = $ports.LocalPort -join ','
+        "rule=$($_.DisplayName)|$($_.Action)|$($ports.Protocol)|$local|$($_.Profile)"
+    }
+"#;
+
+/// Queries the firewall for `program`, blocking for a second or more.
+#[cfg(windows)]
+pub fn query(program: &str) -> Result<FirewallReport, String> {
+    let script = format!("$exe = '{}'\n{SCRIPT}", program.replace('\'', "''"));
+    let output = powershell(&script)
+        .output()
+        .map_err(|e| format!("could not run PowerShell: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "PowerShell failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(parse_report(&String::from_utf8_lossy(&output.stdout)))
+}
+
+#[cfg(not(windows))]
+pub fn query(_program: &str) -> Result<FirewallReport, String> {
+    Err("Windows Firewall is only checked on Windows".to_string())
+}
+
+/// Starts an elevated `netsh` that adds an inbound allow rule for UDP `port`
+/// to `program` on every profile.  Windows shows the UAC prompt; this
+/// returns once it is up, without waiting for the answer.
+#[cfg(windows)]
+pub fn request_rule(program: &str, port: u16) -> Result<(), String> {
+    let rule = format!(
+        "advfirewall firewall add rule name=\"godot-mdns ({port}/udp)\" dir=in action=allow \
+         protocol=UDP localport={port} profile=any program=\"{program}\""
+    );
+    let script = format!(
+        "Start-Process netsh -Verb RunAs -WindowStyle Hidden -ArgumentList '{}'",
+        rule.replace('\'', "''")
+    );
+    powershell(&script)
+        .spawn()
+        .map(drop)
+        .map_err(|e| format!("could not run PowerShell: {e}"))
+}
+
+#[cfg(not(windows))]
+pub fn request_rule(_program: &str, _port: u16) -> Result<(), String> {
+    Err("Windows Firewall rules can only be added on Windows".to_string())
+}
+
+#[cfg(windows)]
+fn powershell(script: &str) -> std::process::Command {
+    use std::os::windows::process::CommandExt;
+    /// `CREATE_NO_WINDOW`: no console flashes up over the game.
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+    let mut command = std::process::Command::new("powershell.exe");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-ExecutionPolicy", "Bypass", "-Command"])
+        .arg(script)
+        .creation_flags(CREATE_NO_WINDOW);
+    command
+}
@@ -0,0 +1,163 @@
+//! Bounded record of recent mDNS events behind
+//! `MdnsManager.set_event_history_size()` / `get_recent_events()`, for bug
+//! reports filed after the signals are long gone.
+//!
+//! Entries are plain strings, formatted when recorded; Godot objects are
+//! only built on retrieval.  Free of Godot types so it can be tested without
+//! an engine (see `tests/history.rs`).
+
+use mdns_sd::{DaemonEvent, ServiceEvent};
+use std::collections::VecDeque;
+use std::time::SystemTime;
+
+/// Longest `summary` kept; longer ones are cut on a character boundary.
+pub const MAX_SUMMARY_LEN: usize = 160;
+
+/// One recorded event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryEntry {
+    pub at: SystemTime,
+    /// `"service_resolved"`, `"daemon_error"`, …: the kinds of
+    /// `MdnsManager`'s `event` signal.
+    pub kind: &'static str,
+    /// Browsed type; empty for daemon events.
+    pub service_type: String,
+    /// Instance the event is about; empty if none.
+    pub fullname: String,
+    /// Short human-readable details, e.g. `"host.local.:7350 [192.168.1.42] txt=3"`.
+    pub summary: String,
+}
+
+/// The last `limit` events, oldest first.  A limit of 0 records nothing.
+#[derive(Debug, Default)]
+pub struct EventHistory {
+    limit: usize,
+    entries: VecDeque<HistoryEntry>,
+}
+
+impl EventHistory {
+    pub fn limit(&self) -> usize {
+        self.limit
+    }
+
+    /// Changes the limit, dropping the oldest entries that no longer fit.
+    pub fn set_limit(&mut self, limit: usize) {
+        self.limit = limit;
+        while self.entries.len() > limit {
+            self.entries.pop_front();
+        }
+        if limit == 0 {
+            self.entries = VecDeque::new();
+        }
+    }
+
+    /// Appends `entry`, evicting the oldest one when full.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if self.limit == 0 {
+            return;
+        }
+        if self.entries.len() == self.limit {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// Records a browse event of `service_type`.
+    pub fn record_service(&mut self, service_type: &str, event: &ServiceEvent) {
+        if self.limit == 0 {
+            return;
+        }
+        if let Some((kind, fullname, summary)) = describe_service_event(event) {
+            self.push(entry(kind, service_type, fullname, summary));
+        }
+    }
+
+    /// Records a monitor event of the shared daemon.  `respond` events are
+    /// skipped: one comes with every response sent and would push everything
+    /// else out.
+    pub fn record_daemon(&mut self, event: &DaemonEvent) {
+        if self.limit == 0 || matches!(event, DaemonEvent::Respond(_)) {
+            return;
+        }
+        if let Some((kind, fullname, summary)) = describe_daemon_event(event) {
+            self.push(entry(kind, "", fullname, summary));
+        }
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn entry(kind: &'static str, service_type: &str, fullname: String, summary: String) -> HistoryEntry {
+    HistoryEntry {
+        at: SystemTime::now(),
+        kind,
+        service_type: service_type.to_string(),
+        fullname,
+        summary: shorten(summary),
+    }
+}
+
+/// Cuts `text` to [`MAX_SUMMARY_LEN`] bytes, marking the cut with `…`.
+fn shorten(mut text: String) -> String {
+    if text.len() <= MAX_SUMMARY_LEN {
+        return text;
+    }
+    let mut cut = MAX_SUMMARY_LEN - '…'.len_utf8();
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    text.truncate(cut);
+    text.push('…');
+    text
+}
+
+/// Kind, fullname and summary of a browse event; `None` for kinds this
+/// version of the extension does not know.
+pub fn describe_service_event(event: &ServiceEvent) -> Option<(&'static str, String, String)> {
+    Some(match event {
+        ServiceEvent::SearchStarted(detail) => ("search_started", String::new(), detail.clone()),
+        ServiceEvent::SearchStopped(detail) => ("search_stopped", String::new(), detail.clone()),
+        ServiceEvent::ServiceFound(_, fullname) => ("service_found", fullname.clone(), String::new()),
+        ServiceEvent::ServiceRemoved(_, fullname) => {
+            ("service_removed", fullname.clone(), String::new())
+        }
+        ServiceEvent::ServiceResolved(info) => {
+            let mut addresses: Vec<String> =
+                info.get_addresses().iter().map(ToString::to_string).collect();
+            addresses.sort();
+            let summary = format!(
+                "{}:{} [{}] txt={}",
+                info.get_hostname(),
+                info.get_port(),
+                addresses.join(", "),
+                info.get_properties().len()
+            );
+            ("service_resolved", info.get_fullname().to_string(), summary)
+        }
+        _ => return None,
+    })
+}
+
+/// Kind, fullname and summary of a monitor event; `None` for kinds this
+/// version of the extension does not know.
+pub fn describe_daemon_event(event: &DaemonEvent) -> Option<(&'static str, String, String)> {
+    Some(match event {
+        DaemonEvent::Error(e) => ("daemon_error", String::new(), e.to_string()),
+        DaemonEvent::Announce(fullname, detail) => ("announce", fullname.clone(), detail.clone()),
+        DaemonEvent::IpAdd(ip) => ("ip_added", String::new(), ip.to_string()),
+        DaemonEvent::IpDel(ip) => ("ip_removed", String::new(), ip.to_string()),
+        DaemonEvent::NameChange(change) => (
+            "name_changed",
+            change.new_name.clone(),
+            format!("renamed from {}", change.original),
+        ),
+        DaemonEvent::Respond(interface) => ("respond", String::new(), interface.clone()),
+        _ => return None,
+    })
+}
@@ -0,0 +1,527 @@
+//! Interface selection shared by the global daemon configuration
+//! (`MdnsManager.set_allowed_interfaces()` / `set_denied_interfaces()`) and the
+//! private daemon `MdnsBrowser.set_interface()` creates on Android, plus the
+//! `ip%scope` parsing behind `MdnsAdvertiser.set_advertise_scoped_addresses()`.
+//!
+//! Free of Godot types so the parsing rules can be tested without an engine
+//! (see `tests/interfaces.rs`).
+
+use mdns_sd::{IfKind, ServiceDaemon};
+use std::net::IpAddr;
+
+/// Parses a user-supplied interface spec: an IP address (`"192.168.1.42"`,
+/// `"fe80::1"`) selects the interface owning that address, anything else is
+/// taken as an interface name (`"eth0"`, `"en0"`, `"Wi-Fi"`).
+pub fn parse_interface(spec: &str) -> Result<IfKind, String> {
+    let spec = spec.trim();
+    if spec.is_empty() {
+        return Err("empty interface name".to_string());
+    }
+    if let Ok(ip) = spec.parse::<IpAddr>() {
+        return Ok(IfKind::Addr(ip));
+    }
+    if spec.chars().any(char::is_control) {
+        return Err(format!("invalid interface name '{}'", spec.escape_debug()));
+    }
+    Ok(IfKind::Name(spec.to_string()))
+}
+
+/// Like [`parse_interface`], but also accepts `"all"`, `"ipv4"` and `"ipv6"`
+/// (any case) for every interface, every IPv4 one and every IPv6 one.
+pub fn parse_interface_or_group(spec: &str) -> Result<IfKind, String> {
+    match spec.trim().to_ascii_lowercase().as_str() {
+        "all" => Ok(IfKind::All),
+        "ipv4" => Ok(IfKind::IPv4),
+        "ipv6" => Ok(IfKind::IPv6),
+        _ => parse_interface(spec),
+    }
+}
+
+/// Parses every entry of `specs`, failing on the first invalid one.
+pub fn parse_interfaces<S: AsRef<str>>(specs: &[S]) -> Result<Vec<IfKind>, String> {
+    specs.iter().map(|s| parse_interface(s.as_ref())).collect()
+}
+
+/// Parses every entry of `specs`, keeping the valid ones (duplicates
+/// dropped) and an error per invalid one, quoting it.
+pub fn parse_valid_interfaces<S: AsRef<str>>(specs: &[S]) -> (Vec<IfKind>, Vec<String>) {
+    let mut seen: Vec<&str> = Vec::new();
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for spec in specs {
+        let spec = spec.as_ref();
+        match parse_interface(spec) {
+            Ok(_) if seen.contains(&spec.trim()) => {}
+            Ok(kind) => {
+                seen.push(spec.trim());
+                parsed.push(kind);
+            }
+            Err(e) => errors.push(format!("'{}': {e}", spec.escape_debug())),
+        }
+    }
+    (parsed, errors)
+}
+
+/// An address to advertise, optionally pinned to one interface by its zone
+/// (`"fe80::1%en0"`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScopedAddress {
+    pub ip: IpAddr,
+    /// Interface name from the `%scope` suffix, if any.
+    pub scope: Option<String>,
+}
+
+impl ScopedAddress {
+    /// The interface this address must be announced on: its zone if given,
+    /// otherwise whichever interface owns the address.
+    pub fn interface(&self) -> IfKind {
+        match &self.scope {
+            Some(name) => IfKind::Name(name.clone()),
+            None => IfKind::Addr(self.ip),
+        }
+    }
+}
+
+/// Parses `"ip"` or `"ip%scope"`.
+///
+/// `mdns-sd` selects interfaces by name, so numeric zone ids (Windows-style
+/// `fe80::1%12`) are rejected; use the adapter name instead.
+pub fn parse_scoped_address(spec: &str) -> Result<ScopedAddress, String> {
+    let spec = spec.trim();
+    let (ip, scope) = match spec.split_once('%') {
+        Some((ip, scope)) => (ip, Some(scope)),
+        None => (spec, None),
+    };
+    let ip: IpAddr = ip
+        .parse()
+        .map_err(|_| format!("'{spec}': not an IP address"))?;
+    let scope = match scope {
+        None => None,
+        Some("") => return Err(format!("'{spec}': empty zone after '%'")),
+        Some(zone) if zone.bytes().all(|b| b.is_ascii_digit()) => {
+            return Err(format!(
+                "'{spec}': numeric zone ids are not supported, use the interface name"
+            ))
+        }
+        Some(zone) => match parse_interface(zone).map_err(|e| format!("'{spec}': {e}"))? {
+            IfKind::Name(name) => Some(name),
+            _ => return Err(format!("'{spec}': zone must be an interface name")),
+        },
+    };
+    Ok(ScopedAddress { ip, scope })
+}
+
+/// Parses every entry of `specs`.  On failure, the error lists *every*
+/// invalid entry rather than just the first.
+pub fn parse_scoped_addresses<S: AsRef<str>>(specs: &[S]) -> Result<Vec<ScopedAddress>, String> {
+    let mut parsed = Vec::new();
+    let mut errors = Vec::new();
+    for spec in specs {
+        match parse_scoped_address(spec.as_ref()) {
+            Ok(addr) => parsed.push(addr),
+            Err(e) => errors.push(e),
+        }
+    }
+    if errors.is_empty() {
+        Ok(parsed)
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+/// An IP network in CIDR notation, e.g. `192.168.1.0/24`, behind
+/// `MdnsBrowser.set_subnet_filter()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subnet {
+    /// The network address, host bits cleared.
+    pub network: IpAddr,
+    pub prefix: u8,
+}
+
+impl Subnet {
+    /// Returns `true` if `ip` is inside the network.  Addresses of the other
+    /// family never are.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        self.network == masked(*ip, self.prefix) && self.network.is_ipv4() == ip.is_ipv4()
+    }
+}
+
+impl std::fmt::Display for Subnet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// `ip` with every bit after the first `prefix` cleared.
+fn masked(ip: IpAddr, prefix: u8) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::from((u32::from(v4) & mask).to_be_bytes())
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+            IpAddr::from((u128::from(v6) & mask).to_be_bytes())
+        }
+    }
+}
+
+/// Parses a subnet in CIDR notation (`"192.168.1.0/24"`, `"fd00::/8"`).  Set
+/// host bits are ignored; a bare address is a single-host subnet.
+pub fn parse_subnet(spec: &str) -> Result<Subnet, String> {
+    let spec = spec.trim();
+    let (address, prefix) = spec.split_once('/').unwrap_or((spec, ""));
+    let ip: IpAddr = address
+        .parse()
+        .map_err(|_| format!("'{}' is not an IP address", address.escape_debug()))?;
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+    let prefix = match prefix {
+        "" if !spec.contains('/') => max,
+        _ => prefix
+            .parse::<u8>()
+            .ok()
+            .filter(|prefix| *prefix <= max)
+            .ok_or_else(|| format!("'{}' is not a prefix length within 0..={max}", prefix))?,
+    };
+    Ok(Subnet {
+        network: masked(ip, prefix),
+        prefix,
+    })
+}
+
+/// Parses a list of subnets separated by commas or blanks, failing on the
+/// first invalid one.  An empty list parses to no subnet.
+pub fn parse_subnets(specs: &str) -> Result<Vec<Subnet>, String> {
+    specs
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|spec| !spec.is_empty())
+        .map(parse_subnet)
+        .collect()
+}
+
+/// Every address currently assigned to a local interface, loopback included.
+/// Empty if the interface list cannot be read.
+pub fn local_addresses() -> Vec<IpAddr> {
+    if_addrs::get_if_addrs()
+        .map(|ifaces| ifaces.iter().map(|iface| iface.ip()).collect())
+        .unwrap_or_default()
+}
+
+/// IP families a daemon may use at all (`MdnsManager.set_ip_version()`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum IpVersion {
+    #[default]
+    Both,
+    V4Only,
+    V6Only,
+}
+
+impl IpVersion {
+    /// The family to switch off, if any.
+    pub fn disabled_family(self) -> Option<IfKind> {
+        match self {
+            Self::Both => None,
+            Self::V4Only => Some(IfKind::IPv6),
+            Self::V6Only => Some(IfKind::IPv4),
+        }
+    }
+}
+
+/// Name patterns of VPN, container and VM adapters, excluded by default:
+/// WireGuard/Tailscale/OpenVPN tunnels, Hyper-V switches, Docker bridges,
+/// VirtualBox host-only networks and ZeroTier.  `*` matches any run of
+/// characters; matching ignores ASCII case.
+pub const DEFAULT_EXCLUSION_PATTERNS: [&str; 8] = [
+    "utun*", "tun*", "tap*", "vEthernet*", "docker*", "br-*", "vboxnet*", "zt*",
+];
+
+/// Returns `true` if `name` matches `pattern`, where `*` stands for any run
+/// of characters (including none).  ASCII case is ignored.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name) = (pattern.to_ascii_lowercase(), name.to_ascii_lowercase());
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No `*`: the whole name must match.
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Returns `true` for addresses VPN overlays hand out: the CGNAT block
+/// 100.64.0.0/10 (Tailscale's IPv4 range) and Tailscale's IPv6 prefix
+/// fd7a:115c:a1e0::/48.
+pub fn is_vpn_overlay_address(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            a == 100 && (64..128).contains(&b)
+        }
+        IpAddr::V6(v6) => {
+            let [a, b, c, ..] = v6.segments();
+            (a, b, c) == (0xfd7a, 0x115c, 0xa1e0)
+        }
+    }
+}
+
+/// The address `MdnsBrowser` pins itself to on Android when no interface was
+/// given: a private (RFC 1918) IPv4 address on an interface named like WLAN
+/// (`wlan*`, or `swlan*` for the hotspot), preferring `wlan0`, then other
+/// `wlan*`, then `swlan*`.  `interfaces` lists only interfaces that are up
+/// (see [`LocalInterface::enumerate`]); the OS list has no multicast flag,
+/// but Android's WLAN interfaces always are.
+pub fn pick_wifi_address(interfaces: &[LocalInterface]) -> Option<IpAddr> {
+    let rank = |name: &str| match name {
+        "wlan0" => Some(0),
+        _ if name.starts_with("wlan") => Some(1),
+        _ if name.starts_with("swlan") => Some(2),
+        _ => None,
+    };
+    interfaces
+        .iter()
+        .filter(|i| !i.loopback && matches!(i.ip, IpAddr::V4(v4) if v4.is_private()))
+        .filter_map(|i| Some((rank(&i.name)?, i)))
+        .min_by_key(|(rank, i)| (*rank, i.name.clone(), i.ip))
+        .map(|(_, i)| i.ip)
+}
+
+/// Heuristic exclusion of virtual adapters (`MdnsManager.set_interface_exclusions()`).
+#[derive(Debug, Clone)]
+pub struct Exclusions {
+    pub enabled: bool,
+    /// Interface-name patterns, see [`glob_match`].
+    pub patterns: Vec<String>,
+}
+
+impl Default for Exclusions {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            patterns: DEFAULT_EXCLUSION_PATTERNS.iter().map(|p| p.to_string()).collect(),
+        }
+    }
+}
+
+/// An interface the exclusion heuristic switched off, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Excluded {
+    pub name: String,
+    /// `"pattern utun*"` or `"VPN address 100.101.1.2"`.
+    pub reason: String,
+}
+
+/// Returns `true` if `a` and `b` select the same interfaces.  `IfKind` has no
+/// `PartialEq`.
+fn same_kind(a: &IfKind, b: &IfKind) -> bool {
+    match (a, b) {
+        (IfKind::Name(a), IfKind::Name(b)) => a == b,
+        (IfKind::Addr(a), IfKind::Addr(b)) => a == b,
+        _ => std::mem::discriminant(a) == std::mem::discriminant(b),
+    }
+}
+
+/// One `MdnsManager.enable_interface()` / `disable_interface()` call.
+#[derive(Debug, Clone)]
+pub struct InterfaceToggle {
+    pub kind: IfKind,
+    pub enabled: bool,
+}
+
+/// Which interfaces a daemon may use.
+///
+/// An empty `allowed` list means "all interfaces"; `denied` is applied on top,
+/// so an interface that is both allowed and denied stays off.  Then
+/// `exclusions` switch off virtual adapters that are not explicitly allowed,
+/// `toggles` follow in the order they were made, and `ip_version` is applied
+/// last and overrides everything.
+#[derive(Debug, Default, Clone)]
+pub struct InterfaceSelection {
+    pub allowed: Vec<IfKind>,
+    pub denied: Vec<IfKind>,
+    pub exclusions: Exclusions,
+    pub toggles: Vec<InterfaceToggle>,
+    pub ip_version: IpVersion,
+}
+
+impl InterfaceSelection {
+    /// Returns `true` when the selection leaves the daemon's defaults untouched.
+    pub fn is_default(&self) -> bool {
+        self.allowed.is_empty()
+            && self.denied.is_empty()
+            && !self.exclusions.enabled
+            && self.toggles.is_empty()
+            && self.ip_version == IpVersion::Both
+    }
+
+    /// The interfaces among `interfaces` (name and address pairs, one per
+    /// address) that the exclusion heuristic switches off, by name.  An
+    /// interface the allow-list names, by name or address, is kept.
+    pub fn excluded(&self, interfaces: &[(String, IpAddr)]) -> Vec<Excluded> {
+        if !self.exclusions.enabled {
+            return Vec::new();
+        }
+        let allowed = |name: &str| {
+            self.allowed.iter().any(|kind| match kind {
+                IfKind::Name(allowed) => allowed == name,
+                IfKind::Addr(ip) => interfaces.iter().any(|(n, a)| n == name && a == ip),
+                _ => false,
+            })
+        };
+        let mut excluded: Vec<Excluded> = Vec::new();
+        for (name, ip) in interfaces {
+            if allowed(name) || excluded.iter().any(|e| e.name == *name) {
+                continue;
+            }
+            let reason = match self.exclusions.patterns.iter().find(|p| glob_match(p, name)) {
+                Some(pattern) => format!("pattern {pattern}"),
+                None if is_vpn_overlay_address(ip) => format!("VPN address {ip}"),
+                None => continue,
+            };
+            excluded.push(Excluded {
+                name: name.clone(),
+                reason,
+            });
+        }
+        excluded
+    }
+
+    /// [`excluded`](Self::excluded) for this machine's current interfaces.
+    pub fn excluded_now(&self) -> Vec<Excluded> {
+        let interfaces: Vec<(String, IpAddr)> = if_addrs::get_if_addrs()
+            .map(|ifaces| ifaces.into_iter().map(|i| (i.name.clone(), i.ip())).collect())
+            .unwrap_or_default();
+        self.excluded(&interfaces)
+    }
+
+    /// Records an enable/disable of `kind`.  Earlier toggles it supersedes —
+    /// of the same interface, or all of them for `IfKind::All` — are dropped,
+    /// so repeated toggling does not grow the list.
+    pub fn toggle(&mut self, kind: IfKind, enabled: bool) {
+        if matches!(kind, IfKind::All) {
+            self.toggles.clear();
+        } else {
+            self.toggles.retain(|toggle| !same_kind(&toggle.kind, &kind));
+        }
+        self.toggles.push(InterfaceToggle { kind, enabled });
+    }
+
+    /// The `enable_interface` (`true`) / `disable_interface` (`false`) calls
+    /// [`apply`](Self::apply) makes, in order, given the interfaces the
+    /// exclusion heuristic switches off.
+    pub fn plan(&self, excluded: &[Excluded]) -> Vec<(Vec<IfKind>, bool)> {
+        let mut plan = vec![(vec![IfKind::All], true)];
+        if !self.allowed.is_empty() {
+            plan.push((vec![IfKind::All], false));
+            plan.push((self.allowed.clone(), true));
+        }
+        if !self.denied.is_empty() {
+            plan.push((self.denied.clone(), false));
+        }
+        if !excluded.is_empty() {
+            let names = excluded.iter().map(|e| IfKind::Name(e.name.clone())).collect();
+            plan.push((names, false));
+        }
+        for toggle in &self.toggles {
+            plan.push((vec![toggle.kind.clone()], toggle.enabled));
+        }
+        if let Some(family) = self.ip_version.disabled_family() {
+            // Disabling a family closes its sockets, not just filters records.
+            plan.push((vec![family], false));
+        }
+        plan
+    }
+
+    /// Applies the selection to `daemon`.  Exclusions are evaluated against
+    /// the interfaces present now; an adapter that appears later is only
+    /// excluded by the next call.
+    ///
+    /// `mdns-sd` evaluates interface selections in the order they were issued
+    /// and the last match wins, so re-enabling `All` first resets whatever a
+    /// previous call configured.  That makes this safe to call on a live daemon.
+    pub fn apply(&self, daemon: &ServiceDaemon) -> Result<(), String> {
+        for (kinds, enabled) in self.plan(&self.excluded_now()) {
+            let result = if enabled {
+                daemon.enable_interface(kinds.clone())
+            } else {
+                daemon.disable_interface(kinds.clone())
+            };
+            let call = if enabled { "enable_interface" } else { "disable_interface" };
+            result.map_err(|e| format!("{call}({kinds:?}) failed: {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Which of `interfaces` a daemon configured with this selection binds,
+    /// replaying [`plan`](Self::plan) the way `mdns-sd` does: everything
+    /// starts enabled and the last matching call wins.  `mdns-sd` has no way
+    /// to ask the daemon itself.
+    pub fn binds(&self, interfaces: &[LocalInterface]) -> Vec<bool> {
+        let pairs: Vec<(String, IpAddr)> =
+            interfaces.iter().map(|i| (i.name.clone(), i.ip)).collect();
+        let plan = self.plan(&self.excluded(&pairs));
+        interfaces
+            .iter()
+            .map(|interface| {
+                plan.iter()
+                    .rev()
+                    .find(|(kinds, _)| kinds.iter().any(|kind| interface.matches(kind)))
+                    .is_none_or(|(_, enabled)| *enabled)
+            })
+            .collect()
+    }
+}
+
+/// One address of a local interface, as `mdns-sd` considers binding it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalInterface {
+    pub name: String,
+    pub ip: IpAddr,
+    pub loopback: bool,
+}
+
+impl LocalInterface {
+    /// The interfaces `mdns-sd` would consider now: up, not point-to-point,
+    /// and not Apple's AWDL/LLW peer-to-peer links.
+    pub fn enumerate() -> Vec<LocalInterface> {
+        if_addrs::get_if_addrs()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|i| {
+                i.is_oper_up()
+                    && !i.is_p2p()
+                    && !i.name.starts_with("awdl")
+                    && !i.name.starts_with("llw")
+            })
+            .map(|i| LocalInterface {
+                loopback: i.is_loopback(),
+                ip: i.ip(),
+                name: i.name,
+            })
+            .collect()
+    }
+
+    /// Returns `true` if `kind` selects this address, as in `mdns-sd`.
+    pub fn matches(&self, kind: &IfKind) -> bool {
+        match kind {
+            IfKind::All => true,
+            IfKind::IPv4 => self.ip.is_ipv4(),
+            IfKind::IPv6 => self.ip.is_ipv6(),
+            IfKind::Name(name) => *name == self.name,
+            IfKind::Addr(ip) => *ip == self.ip,
+            IfKind::LoopbackV4 => self.loopback && self.ip.is_ipv4(),
+            IfKind::LoopbackV6 => self.loopback && self.ip.is_ipv6(),
+            // Kinds added by later `mdns-sd` versions: assume no match.
+            _ => false,
+        }
+    }
+}
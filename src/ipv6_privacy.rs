@@ -0,0 +1,116 @@
+//! Detection of temporary IPv6 privacy addresses (RFC 8981), which Windows
+//! and most Linux distributions rotate every few hours.
+//!
+//! `MdnsAdvertiser` leaves them out of its automatically chosen addresses so
+//! peers are not handed one that expires mid-session; the stable address of
+//! the same interface (EUI-64 or RFC 7217 stable-privacy) is announced
+//! instead.  `if-addrs`, which `mdns-sd` enumerates interfaces with, does not
+//! expose the flags, so they are read from the OS directly:
+//!
+//! - Linux and Android: `/proc/net/if_inet6` (`IFA_F_TEMPORARY`,
+//!   `IFA_F_DEPRECATED`).  Android 10+ may deny apps access; nothing is
+//!   detected then.
+//! - Windows: `GetAdaptersAddresses()` (random suffix origin, deprecated DAD
+//!   state).
+//! - Elsewhere (macOS, iOS): not detected; every address counts as stable.
+//!
+//! Free of Godot types so the parsing can be tested without an engine
+//! (see `tests/ipv6_privacy.rs`).
+
+use std::net::Ipv6Addr;
+
+/// `IFA_F_TEMPORARY` from `linux/if_addr.h`.
+pub const IFA_F_TEMPORARY: u32 = 0x01;
+/// `IFA_F_DEPRECATED` from `linux/if_addr.h`.
+pub const IFA_F_DEPRECATED: u32 = 0x20;
+
+/// Local IPv6 addresses that are temporary or deprecated right now.  Empty
+/// where detection is not supported.
+pub fn temporary_addresses() -> Vec<Ipv6Addr> {
+    detect()
+}
+
+/// The temporary or deprecated addresses listed in the contents of
+/// `/proc/net/if_inet6`.  Lines that do not parse are skipped.
+///
+/// Each line is `<32 hex digits> <ifindex> <prefix len> <scope> <flags>
+/// <name>`, every number in hex.
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+pub fn parse_if_inet6(text: &str) -> Vec<Ipv6Addr> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (hex, flags) = (fields.first()?, fields.get(4)?);
+            let flags = u32::from_str_radix(flags, 16).ok()?;
+            if flags & (IFA_F_TEMPORARY | IFA_F_DEPRECATED) == 0 || hex.len() != 32 {
+                return None;
+            }
+            u128::from_str_radix(hex, 16).ok().map(Ipv6Addr::from)
+        })
+        .collect()
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn detect() -> Vec<Ipv6Addr> {
+    std::fs::read_to_string("/proc/net/if_inet6")
+        .map(|text| parse_if_inet6(&text))
+        .unwrap_or_default()
+}
+
+#[cfg(windows)]
+fn detect() -> Vec<Ipv6Addr> {
+    use windows_sys::Win32::Foundation::{ERROR_BUFFER_OVERFLOW, NO_ERROR};
+    use windows_sys::Win32::NetworkManagement::IpHelper::{
+        GetAdaptersAddresses, GAA_FLAG_SKIP_ANYCAST, GAA_FLAG_SKIP_DNS_SERVER,
+        GAA_FLAG_SKIP_MULTICAST, IP_ADAPTER_ADDRESSES_LH,
+    };
+    use windows_sys::Win32::Networking::WinSock::{
+        IpDadStateDeprecated, IpSuffixOriginRandom, AF_INET6, SOCKADDR_IN6,
+    };
+
+    let flags = GAA_FLAG_SKIP_ANYCAST | GAA_FLAG_SKIP_MULTICAST | GAA_FLAG_SKIP_DNS_SERVER;
+    let mut size: u32 = 16 * 1024;
+    // The list is rebuilt if it grew between the two calls.
+    for _ in 0..3 {
+        // u64 elements keep the buffer aligned for the structures written into it.
+        let mut buffer = vec![0u64; (size as usize).div_ceil(8)];
+        let first = buffer.as_mut_ptr().cast::<IP_ADAPTER_ADDRESSES_LH>();
+        // SAFETY: `first` points to `size` writable bytes, as the call requires.
+        let rc = unsafe {
+            GetAdaptersAddresses(AF_INET6 as u32, flags, std::ptr::null(), first, &mut size)
+        };
+        if rc == ERROR_BUFFER_OVERFLOW {
+            continue;
+        }
+        if rc != NO_ERROR {
+            return Vec::new();
+        }
+        let mut found = Vec::new();
+        // SAFETY: on success the buffer holds linked lists of valid
+        // structures, terminated by null pointers, that live as long as it.
+        unsafe {
+            let mut adapter = first.cast_const();
+            while let Some(a) = adapter.as_ref() {
+                let mut unicast = a.FirstUnicastAddress.cast_const();
+                while let Some(u) = unicast.as_ref() {
+                    let sockaddr = u.Address.lpSockaddr;
+                    let temporary = u.SuffixOrigin == IpSuffixOriginRandom
+                        || u.DadState == IpDadStateDeprecated;
+                    if temporary && !sockaddr.is_null() && (*sockaddr).sa_family == AF_INET6 {
+                        let sin6 = &*sockaddr.cast::<SOCKADDR_IN6>();
+                        found.push(Ipv6Addr::from(sin6.sin6_addr.u.Byte));
+                    }
+                    unicast = u.Next.cast_const();
+                }
+                adapter = a.Next.cast_const();
+            }
+        }
+        return found;
+    }
+    Vec::new()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", windows)))]
+fn detect() -> Vec<Ipv6Addr> {
+    Vec::new()
+}
@@ -0,0 +1,65 @@
+//! Application pause/resume handling shared by `MdnsBrowser` and
+//! `MdnsAdvertiser`; see their `set_handle_app_lifecycle()`.
+//!
+//! Android and iOS freeze the process while the app is in the background.
+//! The daemon thread keeps queueing browse events meanwhile (or wakes to a
+//! burst of them), and every `Instant`-based timer of the nodes counts the
+//! frozen time as elapsed.  On resume the browser drains the backlog through
+//! [`coalesce`], so a service that came and went while the app slept is not
+//! reported at all and one that changed five times is reported once.
+//!
+//! Free of Godot types so the coalescing can be tested without an engine
+//! (see `tests/lifecycle.rs`).
+
+use mdns_sd::ServiceEvent;
+use std::collections::HashMap;
+
+/// Whether the nodes handle pause/resume unless told otherwise: on where the
+/// OS suspends apps in the background, off on desktop.
+pub const DEFAULT_ENABLED: bool = cfg!(any(target_os = "android", target_os = "ios"));
+
+/// Reduces a backlog of browse events to their net effect, keeping order.
+///
+/// Per fullname only the last `ServiceResolved` / `ServiceRemoved` is kept,
+/// so updates collapse into the latest one and a removal followed by a
+/// resolution becomes that resolution.  A service `known` did not list
+/// before the backlog and whose last event is a removal loses every event,
+/// its `ServiceFound` included.  Other events pass through.
+pub fn coalesce(events: Vec<ServiceEvent>, known: impl Fn(&str) -> bool) -> Vec<ServiceEvent> {
+    let mut last: HashMap<String, usize> = HashMap::new();
+    for (index, event) in events.iter().enumerate() {
+        if let Some(fullname) = state_fullname(event) {
+            last.insert(fullname.to_string(), index);
+        }
+    }
+    let vanished = |fullname: &str| {
+        last.get(fullname).is_some_and(|&index| {
+            matches!(events[index], ServiceEvent::ServiceRemoved(..)) && !known(fullname)
+        })
+    };
+    let keep: Vec<bool> = events
+        .iter()
+        .enumerate()
+        .map(|(index, event)| match event {
+            ServiceEvent::ServiceFound(_, fullname) => !vanished(fullname),
+            _ => match state_fullname(event) {
+                Some(fullname) => last[fullname] == index && !vanished(fullname),
+                None => true,
+            },
+        })
+        .collect();
+    events
+        .into_iter()
+        .zip(keep)
+        .filter_map(|(event, keep)| keep.then_some(event))
+        .collect()
+}
+
+/// The fullname of a resolution or removal.
+fn state_fullname(event: &ServiceEvent) -> Option<&str> {
+    match event {
+        ServiceEvent::ServiceResolved(info) => Some(&info.fullname),
+        ServiceEvent::ServiceRemoved(_, fullname) => Some(fullname),
+        _ => None,
+    }
+}
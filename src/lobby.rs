@@ -0,0 +1,396 @@
+//! `LanLobby` — host a game lobby and list the others on the LAN in one node.
+
+use crate::advertiser::MdnsAdvertiser;
+use crate::browser::{self, MdnsBrowser};
+use crate::cache::CachedService;
+use crate::interfaces;
+use crate::main_thread;
+use crate::naming;
+use godot::classes::node::InternalMode;
+use godot::classes::ProjectSettings;
+use godot::prelude::*;
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+/// Announces this machine's lobby and keeps the list of the other lobbies on
+/// the LAN — the advertise/browse/peer-list wiring most LAN games repeat,
+/// built on an `MdnsAdvertiser` and an `MdnsBrowser` kept as internal
+/// children.
+///
+/// ## GDScript example
+/// ```gdscript
+/// var lobby := LanLobby.new()
+/// add_child(lobby)
+/// lobby.lobby_added.connect(func(l): print("Lobby ", l.name, " at ", l.address, ":", l.port))
+/// lobby.search()
+///
+/// # On the machine that hosts:
+/// lobby.host("Mark's game", 7350, {"map": "dust", "players": 1})
+/// ```
+///
+/// Every lobby is reported once, however many interfaces and addresses it
+/// is heard on, and this node's own lobby is never listed.  Lobbies leave
+/// the list when their host stops (it says goodbye), when their records
+/// expire, or after `set_lobby_timeout_ms()`.  Leaving the tree stops
+/// hosting and searching.
+#[derive(GodotClass)]
+#[class(tool, base = Node)]
+pub struct LanLobby {
+    /// From `set_service_type()`; `None` derives it from the project name.
+    service_type: Option<String>,
+    /// From `set_lobby_timeout_ms()`; 0 leaves expiry to the record TTLs.
+    lobby_timeout_ms: i64,
+    advertiser: Option<Gd<MdnsAdvertiser>>,
+    browser: Option<Gd<MdnsBrowser>>,
+    /// Lobbies reported through `lobby_added` and not removed since, by
+    /// fullname.
+    lobbies: BTreeMap<String, CachedService>,
+    /// Fullnames this node has hosted under, each with the host name it
+    /// announced, both lowercased without the trailing dot.  An entry stays
+    /// after `stop_hosting()` so the lobby does not show up while its
+    /// goodbye is on the way, and is dropped once the browser no longer
+    /// lists it.
+    own: BTreeMap<String, String>,
+    base: Base<Node>,
+}
+
+#[godot_api]
+impl INode for LanLobby {
+    fn init(base: Base<Node>) -> Self {
+        Self {
+            service_type: None,
+            lobby_timeout_ms: 0,
+            advertiser: None,
+            browser: None,
+            lobbies: BTreeMap::new(),
+            own: BTreeMap::new(),
+            base,
+        }
+    }
+
+    /// Reports what the browser changed this frame; the children process
+    /// first (see [`LanLobby::adopt`]).
+    fn process(&mut self, _delta: f64) {
+        self.sync_lobbies();
+    }
+
+    /// Stop hosting and searching when the node leaves the tree.
+    fn exit_tree(&mut self) {
+        self.stop_hosting();
+        if let Some(browser) = &mut self.browser {
+            browser.bind_mut().stop_browsing();
+        }
+        self.lobbies.clear();
+    }
+}
+
+#[godot_api]
+impl LanLobby {
+    // ── Signals ──────────────────────────────────────────────────────────────
+
+    /// Emitted when a lobby shows up on the LAN.  `lobby` holds:
+    ///
+    /// - `name` (String) — the lobby name its host gave to `host()`
+    /// - `fullname` (String) — the mDNS instance, e.g. `"Mark's game._my-game._udp.local."`
+    /// - `host` (String) — the host's name, e.g. `"marks-pc.local."`
+    /// - `address` (String) — the address to connect to, IPv4 if there is one
+    /// - `addresses` (PackedStringArray) — every address, IPv4 first
+    /// - `port` (int)
+    /// - `metadata` (Dictionary) — the host's `metadata`, every value a String
+    /// - `available` (bool) — `false` while the host is marked unavailable
+    ///   (see `MdnsAdvertiser.set_available()`)
+    #[signal]
+    fn lobby_added(lobby: VarDictionary);
+
+    /// Emitted when a listed lobby changes, e.g. its metadata; `lobby` as in
+    /// `lobby_added`.
+    #[signal]
+    fn lobby_updated(lobby: VarDictionary);
+
+    /// Emitted when a listed lobby goes away; `lobby` is its last state.
+    #[signal]
+    fn lobby_removed(lobby: VarDictionary);
+
+    /// Emitted when hosting or searching fails; `message` is the
+    /// `advertise_error` / `browse_error` of the node underneath.
+    #[signal]
+    fn lobby_error(message: GString);
+
+    // ── Methods ──────────────────────────────────────────────────────────────
+
+    /// Announce a lobby called `lobby_name` for players to join on `port`,
+    /// with `metadata` (map, mode, player count…) sent as TXT entries — so
+    /// keep it small, a few hundred bytes at most.  Values are converted to
+    /// strings.  Hosting again replaces the announcement.
+    ///
+    /// Returns `false` (and emits `lobby_error`) if the announcement could
+    /// not be made, e.g. for an invalid name or a port outside 1..=65535.
+    #[func]
+    fn host(&mut self, lobby_name: GString, port: i64, metadata: VarDictionary) -> bool {
+        if !(1..=65535).contains(&port) {
+            let message = GString::from(&format!("host: port {port} is outside 1..=65535"));
+            main_thread::emit(&mut self.base_mut(), "lobby_error", &[message.to_variant()]);
+            return false;
+        }
+        let service_type = GString::from(&self.effective_service_type());
+        let mut txt = VarDictionary::new();
+        for (key, value) in metadata.iter_shared() {
+            txt.set(key.stringify(), value.stringify());
+        }
+        let mut advertiser = self.advertiser();
+        let hosting = advertiser.bind_mut().advertise(lobby_name, service_type, port, txt);
+        self.note_own_name();
+        hosting
+    }
+
+    /// Withdraw the announcement made by `host()`; peers drop the lobby at
+    /// once.
+    #[func]
+    fn stop_hosting(&mut self) {
+        if let Some(advertiser) = &mut self.advertiser {
+            advertiser.bind_mut().stop_advertising();
+        }
+    }
+
+    /// Returns `true` while a `host()` announcement is up.
+    #[func]
+    fn is_hosting(&self) -> bool {
+        self.advertiser.as_ref().is_some_and(|advertiser| advertiser.bind().is_advertising())
+    }
+
+    /// Start listing the lobbies on the LAN.  Searching again starts over:
+    /// the list is emptied without `lobby_removed` and lobbies are reported
+    /// anew.
+    #[func]
+    fn search(&mut self) {
+        let service_type = GString::from(&self.effective_service_type());
+        let timeout_ms = self.lobby_timeout_ms;
+        let mut browser = self.browser();
+        self.lobbies.clear();
+        let mut browser = browser.bind_mut();
+        browser.set_stale_eviction_ms(timeout_ms);
+        browser.browse(service_type);
+    }
+
+    /// Stop listing lobbies.  `get_lobbies()` is empty afterwards; no
+    /// `lobby_removed` is emitted.
+    #[func]
+    fn stop_search(&mut self) {
+        if let Some(browser) = &mut self.browser {
+            browser.bind_mut().stop_browsing();
+        }
+        self.lobbies.clear();
+    }
+
+    /// Returns `true` while `search()` is running.
+    #[func]
+    fn is_searching(&self) -> bool {
+        self.browser.as_ref().is_some_and(|browser| browser.bind().is_browsing())
+    }
+
+    /// The listed lobbies, sorted by fullname, as in `lobby_added`.
+    #[func]
+    fn get_lobbies(&self) -> Array<VarDictionary> {
+        let mut out = Array::new();
+        for service in self.lobbies.values() {
+            out.push(&lobby_dict(service));
+        }
+        out
+    }
+
+    /// Use `service_type` (e.g. `"_mygame._udp.local."`) instead of the one
+    /// derived from the project name, which is the name's letters and digits
+    /// joined by hyphens and cut to 15 characters: *My Game!* gives
+    /// `"_my-game._udp.local."`.  Hosts and searchers must agree on it, so
+    /// set it when two projects should see each other's lobbies or two
+    /// unrelated ones derive the same type.  `""` goes back to the derived
+    /// one.  Takes effect on the next `host()` / `search()`.
+    #[func]
+    fn set_service_type(&mut self, service_type: GString) {
+        let service_type = service_type.to_string();
+        self.service_type = if service_type.is_empty() { None } else { Some(service_type) };
+    }
+
+    /// The service type `host()` and `search()` use, set or derived.
+    #[func]
+    fn get_service_type(&self) -> GString {
+        GString::from(&self.effective_service_type())
+    }
+
+    /// Drop a lobby whose host stops answering within about `ms`
+    /// milliseconds, for hosts that vanish without a goodbye (a crash, a
+    /// pulled cable).  `0` (the default) leaves that to the record TTLs,
+    /// which can take over an hour.  Lobbies are asked to confirm
+    /// themselves before they would be dropped (see
+    /// `MdnsBrowser.set_stale_eviction_ms()`), so a host that is still up
+    /// stays listed without re-announcing.  Takes effect on the next
+    /// `search()`.
+    #[func]
+    fn set_lobby_timeout_ms(&mut self, ms: i64) {
+        self.lobby_timeout_ms = ms.max(0);
+    }
+
+    /// The timeout set with `set_lobby_timeout_ms()`.
+    #[func]
+    fn get_lobby_timeout_ms(&self) -> i64 {
+        self.lobby_timeout_ms
+    }
+
+    // ── Internal helpers ─────────────────────────────────────────────────────
+
+    fn effective_service_type(&self) -> String {
+        if let Some(service_type) = &self.service_type {
+            return service_type.clone();
+        }
+        let project = ProjectSettings::singleton()
+            .get_setting("application/config/name")
+            .try_to::<GString>()
+            .map(|name| name.to_string())
+            .unwrap_or_default();
+        naming::lobby_service_type(&project)
+    }
+
+    /// The advertiser child, created on first use.
+    fn advertiser(&mut self) -> Gd<MdnsAdvertiser> {
+        if let Some(advertiser) = &self.advertiser {
+            return advertiser.clone();
+        }
+        let advertiser = MdnsAdvertiser::new_alloc();
+        self.adopt(advertiser.clone().upcast(), "advertise_error");
+        self.advertiser = Some(advertiser.clone());
+        advertiser
+    }
+
+    /// The browser child, created on first use.
+    fn browser(&mut self) -> Gd<MdnsBrowser> {
+        if let Some(browser) = &self.browser {
+            return browser.clone();
+        }
+        let browser = MdnsBrowser::new_alloc();
+        self.adopt(browser.clone().upcast(), "browse_error");
+        self.browser = Some(browser.clone());
+        browser
+    }
+
+    /// Adds `child` as an internal child whose `error_signal` is re-emitted
+    /// as `lobby_error`.
+    ///
+    /// The child processes before this node, so each frame's changes are
+    /// reported the same frame.  The error is forwarded by emitting on the
+    /// base `Node`, which does not bind this object: the child may report
+    /// while a method of this node is still running.
+    fn adopt(&mut self, mut child: Gd<Node>, error_signal: &str) {
+        child.set_process_priority(-1);
+        let mut lobby = self.to_gd().upcast::<Node>();
+        let forward = Callable::from_fn("forward_lobby_error", move |args: &[&Variant]| {
+            let message = args.first().map(|message| (*message).clone()).unwrap_or_default();
+            lobby.emit_signal("lobby_error", &[message]);
+            Variant::nil()
+        });
+        child.connect(error_signal, &forward);
+        self.base_mut().add_child_ex(&child).internal(InternalMode::FRONT).done();
+    }
+
+    /// Remembers the name and host the advertiser registered, to leave the
+    /// lobby out.
+    fn note_own_name(&mut self) {
+        let Some(advertiser) = &self.advertiser else {
+            return;
+        };
+        let advertiser = advertiser.bind();
+        let name = advertiser.get_registered_name().to_string();
+        if let (false, Some(host)) = (name.is_empty(), advertiser.registered_host()) {
+            self.own.insert(own_key(&name), own_key(host));
+        }
+    }
+
+    /// Returns `true` if `service` is a lobby this node hosted: its name is
+    /// one of `own` and it lives on the host announced with it, or on an
+    /// address of this machine.  Another machine's lobby that later takes
+    /// the same name is listed.  `local` caches this machine's addresses.
+    fn is_own(&self, service: &CachedService, local: &mut Option<Vec<IpAddr>>) -> bool {
+        let Some(host) = self.own.get(&own_key(&service.fullname)) else {
+            return false;
+        };
+        if own_key(&service.host) == *host {
+            return true;
+        }
+        let local = local.get_or_insert_with(interfaces::local_addresses);
+        service.addresses.iter().any(|address| local.contains(address))
+    }
+
+    /// Compares the browser's list with the reported lobbies and emits the
+    /// difference: removals first, then additions and updates.
+    fn sync_lobbies(&mut self) {
+        let listed = self
+            .browser
+            .as_ref()
+            .map(|browser| browser.bind().listed_services())
+            .unwrap_or_default();
+        self.note_own_name();
+        let hosted = self.advertiser.as_ref().map(|advertiser| {
+            own_key(&advertiser.bind().get_registered_name().to_string())
+        });
+        self.own.retain(|name, _| {
+            hosted.as_ref() == Some(name)
+                || listed.iter().any(|service| own_key(&service.fullname) == *name)
+        });
+        if self.browser.is_none() {
+            return;
+        }
+        let mut local = None;
+        let current: BTreeMap<String, CachedService> = listed
+            .into_iter()
+            .filter(|service| !self.is_own(service, &mut local))
+            .map(|service| (service.fullname.clone(), service))
+            .collect();
+        let removed: Vec<CachedService> = self
+            .lobbies
+            .iter()
+            .filter(|(fullname, _)| !current.contains_key(*fullname))
+            .map(|(_, service)| service.clone())
+            .collect();
+        let mut changes: Vec<(&str, CachedService)> = Vec::new();
+        for (fullname, service) in &current {
+            match self.lobbies.get(fullname) {
+                None => changes.push(("lobby_added", service.clone())),
+                Some(previous) if !previous.same_records(service) => {
+                    changes.push(("lobby_updated", service.clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        self.lobbies = current;
+        for service in removed {
+            let lobby = lobby_dict(&service);
+            main_thread::emit(&mut self.base_mut(), "lobby_removed", &[lobby.to_variant()]);
+        }
+        for (signal, service) in changes {
+            let lobby = lobby_dict(&service);
+            main_thread::emit(&mut self.base_mut(), signal, &[lobby.to_variant()]);
+        }
+    }
+}
+
+/// `fullname` (or a host name) as kept in `LanLobby::own`.
+fn own_key(fullname: &str) -> String {
+    fullname.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// `service` as `LanLobby` reports it.
+fn lobby_dict(service: &CachedService) -> VarDictionary {
+    let cut = service.fullname.len().saturating_sub(service.service_type.len() + 1);
+    let name = service.fullname.get(..cut).filter(|name| !name.is_empty());
+    let addresses = browser::address_array(service);
+    let address = addresses.as_slice().first().cloned().unwrap_or_default();
+    let mut dict = VarDictionary::new();
+    dict.set("name", name.unwrap_or(&service.fullname));
+    dict.set("fullname", service.fullname.as_str());
+    dict.set("host", service.host.as_str());
+    dict.set("address", address);
+    dict.set("addresses", addresses);
+    dict.set("port", service.port as i64);
+    dict.set("metadata", browser::txt_dict(service, false));
+    dict.set("available", service.is_available());
+    dict
+}
@@ -0,0 +1,115 @@
+//! Detection of a denied Local Network permission on Apple platforms, behind
+//! `MdnsManager.get_local_network_permission()`.
+//!
+//! iOS 14+ (and macOS 15+) ask the player before an app may use the LAN.
+//! Apple offers no call that returns the answer, and a denied app's daemon
+//! just sends queries into a black hole, so the game shows an empty list.
+//! Two observations stand in for the missing API:
+//!
+//! - while access is denied, sending to the mDNS multicast group fails with
+//!   `EHOSTUNREACH`, so a one-question probe sent every few seconds tells;
+//! - once any browse has heard an answer, access is evidently granted and
+//!   probing stops.
+//!
+//! A successful send proves nothing (the prompt may still be showing), so the
+//! state stays unknown until one of the two happens.  Elsewhere there is no
+//! such permission and the state is always granted.
+//!
+//! Free of Godot types so it can be tested without an engine
+//! (see `tests/local_network.rs`).
+
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+/// How often the probe is sent while the answer is not known.
+pub const PROBE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Name the probe asks for; nobody is expected to answer it.
+const PROBE_NAME: &str = "godot-mdns-permission-probe.local";
+
+/// What is known about the Local Network permission.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Permission {
+    #[default]
+    Unknown,
+    Granted,
+    Denied,
+}
+
+/// Returns `true` on the platforms that have the permission.
+pub fn applies() -> bool {
+    cfg!(any(target_os = "ios", target_os = "macos"))
+}
+
+/// The permission as inferred from the probe's send result and whether any
+/// browse has heard an answer.
+pub fn classify(sent: &io::Result<()>, heard: bool) -> Permission {
+    match sent {
+        _ if heard => Permission::Granted,
+        Err(e) if e.kind() == io::ErrorKind::HostUnreachable => Permission::Denied,
+        _ => Permission::Unknown,
+    }
+}
+
+/// What the probes and browses have shown so far.
+#[derive(Debug, Default)]
+pub struct LocalNetwork {
+    permission: Permission,
+    heard: bool,
+    next_probe: Option<Instant>,
+}
+
+impl LocalNetwork {
+    /// The permission as known now; always `Granted` where it does not apply.
+    pub fn permission(&self) -> Permission {
+        if applies() {
+            self.permission
+        } else {
+            Permission::Granted
+        }
+    }
+
+    /// Records that a browse heard an answer from the network.
+    pub fn note_heard(&mut self) {
+        self.heard = true;
+        self.permission = Permission::Granted;
+    }
+
+    /// Sends the probe to `port` if due and mDNS is in use (`active`), and
+    /// returns the new permission if it changed.
+    pub fn poll(&mut self, now: Instant, active: bool, port: u16) -> Option<Permission> {
+        if !applies() || !active || self.heard || self.next_probe.is_some_and(|due| now < due) {
+            return None;
+        }
+        self.next_probe = Some(now + PROBE_INTERVAL);
+        let permission = classify(&send_probe(port), self.heard);
+        (permission != self.permission).then(|| {
+            self.permission = permission;
+            permission
+        })
+    }
+}
+
+/// A one-question mDNS query for [`PROBE_NAME`]'s A record.
+pub fn probe_query() -> Vec<u8> {
+    // ID 0, standard query, one question.
+    let mut packet = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in PROBE_NAME.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    // Root label, QTYPE A, QCLASS IN.
+    packet.extend_from_slice(&[0, 0, 1, 0, 1]);
+    packet
+}
+
+/// Sends [`probe_query`] to the IPv4 mDNS group on `port` from an ephemeral
+/// socket.
+fn send_probe(port: u16) -> io::Result<()> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    socket.set_multicast_ttl_v4(255)?;
+    let group: SockAddr = SocketAddr::from((Ipv4Addr::new(224, 0, 0, 251), port)).into();
+    socket.send_to(&probe_query(), &group).map(drop)
+}
@@ -0,0 +1,109 @@
+//! Bridge from the `log` crate (which `mdns-sd` logs through) to the Godot
+//! output panel, gated by `MdnsManager.set_log_level()`.
+//!
+//! Records may be produced on the daemon thread, where calling into Godot is
+//! not safe, so [`log::Log::log`] only queues them; `MdnsManager` prints the
+//! queue on the main thread once per frame via [`flush`].
+
+use godot::prelude::*;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::VecDeque;
+use std::sync::{Mutex, Once};
+
+/// Records held between two frames; older ones are dropped beyond this so a
+/// chatty `TRACE` level cannot grow the queue without bound.
+const QUEUE_CAPACITY: usize = 512;
+
+struct GodotLogger {
+    queue: Mutex<VecDeque<(Level, String)>>,
+}
+
+static LOGGER: GodotLogger = GodotLogger {
+    queue: Mutex::new(VecDeque::new()),
+};
+
+static INSTALL: Once = Once::new();
+
+impl Log for GodotLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let Ok(mut queue) = self.queue.lock() else {
+            return;
+        };
+        if queue.len() == QUEUE_CAPACITY {
+            queue.pop_front();
+        }
+        queue.push_back((record.level(), format!("[{}] {}", record.target(), record.args())));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs the bridge as the process logger, with logging off.
+///
+/// `log` accepts a logger only once per library instance; the `Once` keeps a
+/// re-initialised extension from trying again, and an error (another logger
+/// already set, e.g. by a test harness) is ignored.
+pub fn install() {
+    INSTALL.call_once(|| {
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(LevelFilter::Off);
+        }
+    });
+}
+
+/// Maps `MdnsManager.LOG_LEVEL_*` (0 = off … 5 = trace) to a filter.
+pub fn level_from_int(level: i64) -> Option<LevelFilter> {
+    match level {
+        0 => Some(LevelFilter::Off),
+        1 => Some(LevelFilter::Error),
+        2 => Some(LevelFilter::Warn),
+        3 => Some(LevelFilter::Info),
+        4 => Some(LevelFilter::Debug),
+        5 => Some(LevelFilter::Trace),
+        _ => None,
+    }
+}
+
+/// Sets the most verbose level forwarded to Godot.
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+    if level == LevelFilter::Off {
+        if let Ok(mut queue) = LOGGER.queue.lock() {
+            queue.clear();
+        }
+    }
+}
+
+/// The level set by [`set_level`], as a `LOG_LEVEL_*` int.
+pub fn level() -> i64 {
+    log::max_level() as i64
+}
+
+/// Prints every queued record: errors via `godot_error!`, warnings via
+/// `godot_warn!`, everything else via `godot_print!`.  Main thread only.
+pub fn flush() {
+    let records: Vec<(Level, String)> = match LOGGER.queue.lock() {
+        Ok(mut queue) => queue.drain(..).collect(),
+        Err(_) => return,
+    };
+    for (level, message) in records {
+        match level {
+            Level::Error => {
+                godot_error!("{message}");
+            }
+            Level::Warn => {
+                godot_warn!("{message}");
+            }
+            _ => {
+                godot_print!("{message}");
+            }
+        }
+    }
+}
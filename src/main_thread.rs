@@ -0,0 +1,25 @@
+//! Signal emission for nodes whose methods may be called from a GDScript
+//! `Thread`.
+//!
+//! The crate is built with gdext's `experimental-threads`, so a method called
+//! from another thread waits for the node's `process()` (or any other call in
+//! progress) to return instead of racing it.  Godot still expects signals to
+//! reach scripts on the main thread; [`emit`] defers the emission to the next
+//! idle time of the main loop when it is called from anywhere else.
+
+use godot::classes::Node;
+use godot::init::is_main_thread;
+use godot::prelude::*;
+
+/// Emits `signal` on `node` now if this is the main thread, or through
+/// `call_deferred` otherwise.
+pub fn emit(node: &mut Gd<Node>, signal: &str, args: &[Variant]) {
+    if is_main_thread() {
+        node.emit_signal(signal, args);
+        return;
+    }
+    let mut deferred = Vec::with_capacity(args.len() + 1);
+    deferred.push(signal.to_variant());
+    deferred.extend_from_slice(args);
+    node.call_deferred("emit_signal", &deferred);
+}
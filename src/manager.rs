@@ -0,0 +1,1746 @@
+//! `MdnsManager` — engine singleton exposing the shared daemon layer to GDScript.
+
+use crate::advertiser::{self, MdnsAdvertiser};
+use crate::backend::{self, Backend};
+use crate::browser::{self, MdnsBrowser};
+use crate::cache::CachedService;
+use crate::daemon::{self, WatchdogAlarm};
+use crate::environment::{self, EnvironmentReport};
+use crate::firewall;
+use crate::interfaces::{self, InterfaceSelection, IpVersion, LocalInterface};
+use crate::local_network::Permission;
+use crate::logging;
+use crate::multicast_lock::MulticastLock;
+use crate::platform;
+use godot::global::Error;
+use godot::prelude::*;
+use godot::classes::{Engine, SceneTree};
+use mdns_sd::{DaemonEvent, ServiceEvent, VERIFY_TIMEOUT_DEFAULT};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Engine singleton wrapping the process-global shared mDNS daemon.
+///
+/// Available from GDScript as `MdnsManager` without adding anything to the
+/// scene tree.  Configuration setters only take effect before the daemon is
+/// created (in the background, by the first `browse()` / `advertise()` call
+/// or by `warm_up()`) and return `ERR_ALREADY_IN_USE` afterwards.
+///
+/// ## GDScript example
+/// ```gdscript
+/// func _ready() -> void:
+///     MdnsManager.set_service_name_len_max(30)   # before any browse/advertise
+///     print("daemon up: ", MdnsManager.is_daemon_created())
+///
+/// func _exit_multiplayer() -> void:
+///     MdnsManager.shutdown_all()
+/// ```
+#[derive(GodotClass)]
+#[class(singleton)]
+pub struct MdnsManager {
+    /// `flush_cache()` is waiting for the replacement daemon to come up.
+    flush_pending: bool,
+    /// `verify_cache()` calls waiting for their verification window to end.
+    verifications: Vec<CacheVerification>,
+    /// Reset the shared daemon when the watchdog reports it unresponsive.
+    auto_recover: bool,
+    /// Snapshot `get_metrics_rate()` last compared against.
+    metrics_previous: Option<MetricsSample>,
+    /// Android's `MulticastLock`, held while anything browses or advertises.
+    multicast_lock: MulticastLock,
+    /// `run_environment_diagnostics()` in progress.
+    environment_probe: Option<mpsc::Receiver<EnvironmentReport>>,
+    base: Base<Object>,
+}
+
+/// Largest `set_event_history_size()`.
+const MAX_EVENT_HISTORY: i64 = 10_000;
+
+/// One `verify_cache()`, or one type of a `refresh_all()`, in flight.
+struct CacheVerification {
+    service_type: String,
+    /// Instances asked to re-confirm.
+    fullnames: Vec<String>,
+    /// When the daemon has dropped every instance that did not answer.
+    deadline: Instant,
+}
+
+/// A metrics snapshot kept by `get_metrics_rate()`.
+struct MetricsSample {
+    counters: BTreeMap<String, i64>,
+    taken: Instant,
+    /// Daemon generation the counters belong to; a new daemon starts at zero.
+    generation: u64,
+}
+
+/// `mdns-sd` counters that each stand for one query sent.
+const QUERY_COUNTERS: [&str; 5] = [
+    "browse",
+    "resolve-hostname",
+    "cache-refresh-ptr",
+    "cache-refresh-srv-txt",
+    "cache-refresh-addr",
+];
+
+/// Extra time after `mdns-sd`'s verification timeout before `cache_verified`
+/// counts the survivors, so the daemon's removals have reached the browsers.
+const VERIFY_SETTLE: Duration = Duration::from_secs(1);
+
+#[godot_api]
+impl IObject for MdnsManager {
+    fn init(base: Base<Object>) -> Self {
+        Self {
+            flush_pending: false,
+            verifications: Vec::new(),
+            auto_recover: false,
+            metrics_previous: None,
+            multicast_lock: MulticastLock::default(),
+            environment_probe: None,
+            base,
+        }
+    }
+}
+
+#[godot_api]
+impl MdnsManager {
+    // ── Constants ────────────────────────────────────────────────────────────
+
+    /// `set_log_level()`: forward nothing (default).
+    #[constant]
+    const LOG_LEVEL_OFF: i64 = 0;
+    /// `set_log_level()`: errors only.
+    #[constant]
+    const LOG_LEVEL_ERROR: i64 = 1;
+    /// `set_log_level()`: errors and warnings.
+    #[constant]
+    const LOG_LEVEL_WARN: i64 = 2;
+    /// `set_log_level()`: up to informational messages.
+    #[constant]
+    const LOG_LEVEL_INFO: i64 = 3;
+    /// `set_log_level()`: up to debug messages, e.g. why a packet was ignored.
+    #[constant]
+    const LOG_LEVEL_DEBUG: i64 = 4;
+    /// `set_log_level()`: everything, including per-packet traces.
+    #[constant]
+    const LOG_LEVEL_TRACE: i64 = 5;
+
+    /// `set_ip_version()`: use IPv4 and IPv6 (default).
+    #[constant]
+    const IP_VERSION_BOTH: i64 = 0;
+    /// `set_ip_version()`: IPv4 only; the IPv6 sockets are closed.
+    #[constant]
+    const IP_VERSION_IPV4_ONLY: i64 = 1;
+    /// `set_ip_version()`: IPv6 only; the IPv4 sockets are closed.
+    #[constant]
+    const IP_VERSION_IPV6_ONLY: i64 = 2;
+
+    /// `get_local_network_permission()`: not known yet.
+    #[constant]
+    const LOCAL_NETWORK_PERMISSION_UNKNOWN: i64 = 0;
+    /// `get_local_network_permission()`: granted, or not needed on this platform.
+    #[constant]
+    const LOCAL_NETWORK_PERMISSION_GRANTED: i64 = 1;
+    /// `get_local_network_permission()`: the player denied Local Network access.
+    #[constant]
+    const LOCAL_NETWORK_PERMISSION_DENIED: i64 = 2;
+
+    /// `set_backend()`: the bundled `mdns-sd` daemon (default).
+    #[constant]
+    const BACKEND_MDNS_SD: i64 = 0;
+    /// `set_backend()`: the operating system's DNS-SD service (macOS / iOS).
+    #[constant]
+    const BACKEND_SYSTEM: i64 = 1;
+
+    // ── Signals ──────────────────────────────────────────────────────────────
+
+    /// Emitted when the shared daemon reports an internal error on its monitor
+    /// channel (socket failures, send errors, rejected registrations, …).
+    ///
+    /// A daemon that keeps emitting this is likely not discovering anything;
+    /// show "LAN discovery unavailable" rather than an empty list.
+    #[signal]
+    fn daemon_error(message: GString);
+
+    /// Emitted when the shared daemon's thread has not answered a watchdog
+    /// ping for 10 seconds — e.g. a platform socket bug deadlocked it, and
+    /// browsers would otherwise just stay empty.  `is_daemon_healthy()` is
+    /// `false` from then on until the daemon answers again or is replaced.
+    /// With `set_auto_recover(true)` the daemon is then reset automatically.
+    #[signal]
+    fn daemon_unresponsive();
+
+    /// Emitted when `set_allowed_interfaces()`, `set_denied_interfaces()`,
+    /// the virtual-adapter filter, `enable_interface()`, `disable_interface()`
+    /// or `set_ip_version()` reconfigured the live shared daemon.  Services seen on interfaces that
+    /// are now excluded will not be refreshed; browsers may want to re-browse.
+    #[signal]
+    fn interfaces_changed();
+
+    /// Emitted on iOS (and macOS 15+) when the probe finds that the player
+    /// denied the app Local Network access: every query goes nowhere, so
+    /// show "enable Local Network access in Settings" rather than an empty
+    /// list.  See `get_local_network_permission()`.
+    #[signal]
+    fn local_network_permission_denied();
+
+    /// Emitted when local interface addresses appeared or went away — a
+    /// laptop docked, airplane mode toggled, another WiFi joined.  The
+    /// interfaces are re-enumerated every 3 seconds by default (see
+    /// `set_network_poll_interval_ms()`).  `added` and `removed` hold one
+    /// `Dictionary` per address: `name` (String), `address` (String) and
+    /// `loopback` (bool).  `mdns-sd` follows the change on its own; this is
+    /// for the game, e.g. to re-browse or tell the player.
+    #[signal]
+    fn network_changed(added: Array<VarDictionary>, removed: Array<VarDictionary>);
+
+    /// Emitted by `reset_shared_daemon()` after the old daemon was shut down,
+    /// and when a failed daemon was replaced automatically (see
+    /// `set_auto_recover()`).  Browsers and advertisers re-establish themselves
+    /// on their next frame and emit their own `daemon_reset` when done.
+    #[signal]
+    fn daemon_reset();
+
+    /// Emitted when a shared daemon created in the background (by `warm_up()`
+    /// or the first `browse()` / `advertise()`) is up.  A failed creation is
+    /// reported through `daemon_error` instead.
+    #[signal]
+    fn daemon_ready();
+
+    /// Emitted at most once per run, when the check made before the shared
+    /// daemon was first created found a setup that breaks discovery — e.g.
+    /// another responder holding port 5353 exclusively, or Avahi running as a
+    /// reflector or cache only.  The message says how to fix it; the details are in
+    /// `get_startup_diagnostics()`.
+    #[signal]
+    fn diagnostics_warning(message: GString);
+
+    /// Emitted when `run_environment_diagnostics()` has finished, with the
+    /// report described there.
+    #[signal]
+    fn diagnostics_completed(report: VarDictionary);
+
+    /// Emitted once the fresh daemon requested by `flush_cache()` is up
+    /// (`success == true`) or failed to start.
+    #[signal]
+    fn cache_flushed(success: bool);
+
+    /// Emitted when a `verify_cache()` window has ended, and for each browsed
+    /// type when a `refresh_all()` one has.  `confirmed` instances
+    /// answered and are still listed; `removed` ones did not and were reported
+    /// through `service_removed`.
+    #[signal]
+    fn cache_verified(service_type: GString, confirmed: i64, removed: i64);
+
+    /// Catch-all for logging and analytics, emitted for every browse and
+    /// daemon event in the process while `set_event_bus_enabled(true)`.
+    /// Each daemon event is reported once, however many browsers receive it;
+    /// the per-node signals are unaffected.
+    ///
+    /// `kind` is one of `search_started`, `service_found`, `service_resolved`,
+    /// `service_removed`, `search_stopped` (with `service_type`, and
+    /// `node_paths`: the browsers that received it), or `daemon_error`,
+    /// `announce`, `ip_added`, `ip_removed`, `name_changed`, `respond` (from
+    /// the shared daemon's monitor).  See the README for each kind's `data`.
+    #[signal]
+    fn event(kind: GString, data: VarDictionary);
+
+    // ── Methods ──────────────────────────────────────────────────────────────
+
+    /// Forward `mdns-sd`'s own diagnostics (interface join failures, ignored
+    /// packets, …) up to `level` to the Godot output: errors as errors,
+    /// warnings as warnings, the rest as plain prints.  One of the
+    /// `LOG_LEVEL_*` constants; `LOG_LEVEL_OFF` (the default) prints nothing.
+    ///
+    /// Messages are printed on the main thread at the end of each frame, so
+    /// they may appear a frame late.
+    #[func]
+    fn set_log_level(&mut self, level: i64) -> Error {
+        match logging::level_from_int(level) {
+            Some(filter) => {
+                logging::set_level(filter);
+                Error::OK
+            }
+            None => {
+                godot_error!("set_log_level: unknown level {level}");
+                Error::ERR_INVALID_PARAMETER
+            }
+        }
+    }
+
+    /// Returns the current `LOG_LEVEL_*`.
+    #[func]
+    fn get_log_level(&self) -> i64 {
+        logging::level()
+    }
+
+    /// Returns `true` if the shared daemon currently exists.
+    #[func]
+    fn is_daemon_created(&self) -> bool {
+        daemon::is_created()
+    }
+
+    /// Returns `false` on platforms without UDP multicast sockets, such as
+    /// the web export, so projects can hide LAN features up front.  There
+    /// the nodes still exist, but `browse()` / `advertise()` fail at once
+    /// with an error starting with `"unsupported_platform"`, and no daemon,
+    /// thread or socket is ever created.
+    #[func]
+    fn is_supported(&self) -> bool {
+        platform::is_supported()
+    }
+
+    /// Choose what `MdnsBrowser` and `MdnsAdvertiser` browse and advertise
+    /// through: one of the `BACKEND_*` constants.  `BACKEND_SYSTEM` uses the
+    /// operating system's DNS-SD service (mDNSResponder) instead of the
+    /// bundled daemon, so an iOS app needs only `NSBonjourServices`, not the
+    /// multicast entitlement.  It exists on macOS and iOS builds with the
+    /// `dnssd` feature; elsewhere this returns `ERR_UNAVAILABLE`.
+    ///
+    /// Takes effect on the next `browse()` / `advertise()`; running ones stay
+    /// on their backend.  Signals and methods are the same on both, but the
+    /// system backend ignores what only the bundled daemon can do: pinned
+    /// interfaces, `set_daemon_port()`, dual daemons, explicit advertised
+    /// addresses and host names.  This singleton's cache, metrics and capture
+    /// tools always concern the bundled daemon.
+    #[func]
+    fn set_backend(&mut self, backend: i64) -> Error {
+        let backend = match backend {
+            Self::BACKEND_MDNS_SD => Backend::MdnsSd,
+            Self::BACKEND_SYSTEM => Backend::System,
+            _ => {
+                godot_error!("set_backend: unknown backend {backend}");
+                return Error::ERR_INVALID_PARAMETER;
+            }
+        };
+        match backend::set(backend) {
+            Ok(()) => Error::OK,
+            Err(e) => {
+                godot_error!("set_backend: {e}");
+                Error::ERR_UNAVAILABLE
+            }
+        }
+    }
+
+    /// Returns the `BACKEND_*` last given to `set_backend()`.
+    #[func]
+    fn get_backend(&self) -> i64 {
+        match backend::current() {
+            Backend::MdnsSd => Self::BACKEND_MDNS_SD,
+            Backend::System => Self::BACKEND_SYSTEM,
+        }
+    }
+
+    /// Start creating the shared daemon in the background now, e.g. on the
+    /// title screen, so the first `browse()` / `advertise()` finds it ready.
+    /// Never blocks; `daemon_ready` is emitted once it is up.  Does nothing if
+    /// the daemon exists or is already being created.
+    ///
+    /// Configuration setters return `ERR_ALREADY_IN_USE` once this was called,
+    /// so call them first.  Returns `ERR_UNAVAILABLE` (without logging) where
+    /// `is_supported()` is `false`.
+    #[func]
+    fn warm_up(&mut self) -> Error {
+        if !platform::is_supported() {
+            return Error::ERR_UNAVAILABLE;
+        }
+        match daemon::warm_up() {
+            Ok(()) => Error::OK,
+            Err(e) => {
+                godot_error!("{e}");
+                Error::FAILED
+            }
+        }
+    }
+
+    /// Stop every browse, unregister every service, and shut the shared daemon
+    /// down, waiting up to half a second for the goodbyes and for its thread
+    /// to exit.  The next `browse()` / `advertise()` lazily creates a new one.
+    /// `OK` means the thread confirmed, so its port-5353 socket is closed and
+    /// e.g. an advertiser with `set_daemon_port()` can bind it at once.
+    ///
+    /// Nodes still using the shared daemon go idle on their next frame: each
+    /// `MdnsBrowser` first reports the events its browse delivered, then both
+    /// kinds emit their error signal with a message starting with
+    /// `"shutdown"`.  Browsers on a private daemon (`set_interface()`) keep
+    /// running.  Configuration and passive capture carry over.
+    #[func]
+    fn shutdown_all(&mut self) -> Error {
+        match daemon::shutdown_all() {
+            Ok(()) => Error::OK,
+            Err(e) => {
+                godot_error!("{e}");
+                Error::FAILED
+            }
+        }
+    }
+
+    /// Shut down the shared daemon (if any) and let the next use create a fresh
+    /// one — e.g. after its sockets died because the network went away.
+    ///
+    /// Unlike `shutdown_all()`, active nodes are not left stranded: every
+    /// `MdnsBrowser` re-issues its browse and every `MdnsAdvertiser` re-registers
+    /// its service on the new daemon during its next `_process`.  Configuration
+    /// (interfaces, name length, …) carries over.
+    ///
+    /// Like `shutdown_all()`, blocks for up to half a second until the goodbyes
+    /// are sent and the old thread has exited, so its sockets are closed when
+    /// this returns `OK`.  `FAILED` means the old thread did not confirm in
+    /// time; the new daemon is created regardless.
+    #[func]
+    fn reset_shared_daemon(&mut self) -> Error {
+        let result = daemon::reset();
+        self.base_mut().emit_signal("daemon_reset", &[]);
+        match result {
+            Ok(()) => Error::OK,
+            Err(e) => {
+                godot_error!("{e}");
+                Error::FAILED
+            }
+        }
+    }
+
+    /// Turn the catch-all `event` signal on or off.  Off by default; while off,
+    /// no events are copied for it.
+    #[func]
+    fn set_event_bus_enabled(&mut self, enabled: bool) {
+        daemon::set_event_bus(enabled);
+    }
+
+    /// Returns `true` if `set_event_bus_enabled()` is on.
+    #[func]
+    fn is_event_bus_enabled(&self) -> bool {
+        daemon::is_event_bus_enabled()
+    }
+
+    /// Record the last `size` browse and daemon events (clamped to 10 000)
+    /// for `get_recent_events()` — e.g. to attach to a bug report when a
+    /// player says a server "wasn't in the list".  `0` (the default) records
+    /// nothing and drops the history.  Multicast responses the daemon sends
+    /// are not recorded; they would crowd out everything else.
+    #[func]
+    fn set_event_history_size(&mut self, size: i64) {
+        daemon::set_history_size(size.clamp(0, MAX_EVENT_HISTORY) as usize);
+    }
+
+    /// The size last given to `set_event_history_size()`.
+    #[func]
+    fn get_event_history_size(&self) -> i64 {
+        daemon::history_size() as i64
+    }
+
+    /// Cap the number of distinct service types browsed at once by all
+    /// `MdnsBrowser` nodes together (default 16); `0` lifts it.  Each type is
+    /// a standing multicast query, so a game browsing whatever a server list
+    /// hands it cannot flood the LAN.  A `browse()` of one type too many
+    /// fails with a `browse_error` starting with `"too_many_types"`; browsers
+    /// of a type already browsed are not counted twice.  Lowering the cap
+    /// leaves running browses alone.
+    #[func]
+    fn set_max_browse_types(&mut self, max: i64) {
+        daemon::set_max_browse_types(max.max(0) as usize);
+    }
+
+    /// The cap last given to `set_max_browse_types()`.
+    #[func]
+    fn get_max_browse_types(&self) -> i64 {
+        daemon::max_browse_types() as i64
+    }
+
+    /// The recorded events, oldest first, one `Dictionary` each:
+    ///
+    /// - `timestamp` (float) — Unix time, as `Time.get_unix_time_from_system()`
+    /// - `kind` (String) — as in the `event` signal, e.g. `"service_resolved"`
+    /// - `service_type` (String) — `""` for daemon events
+    /// - `fullname` (String) — the instance concerned, or `""`
+    /// - `summary` (String) — short details, e.g. `"host.local.:7350 [192.168.1.42] txt=3"`
+    #[func]
+    fn get_recent_events(&self) -> Array<VarDictionary> {
+        daemon::recent_events()
+            .iter()
+            .map(|entry| {
+                let timestamp = entry
+                    .at
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map_or(0.0, |d| d.as_secs_f64());
+                let mut dict = VarDictionary::new();
+                dict.set("timestamp", timestamp);
+                dict.set("kind", entry.kind);
+                dict.set("service_type", entry.service_type.as_str());
+                dict.set("fullname", entry.fullname.as_str());
+                dict.set("summary", entry.summary.as_str());
+                dict
+            })
+            .collect()
+    }
+
+    /// Empties the event history, keeping its size.
+    #[func]
+    fn clear_event_history(&mut self) {
+        daemon::clear_history();
+    }
+
+    /// Reset the shared daemon automatically (as `reset_shared_daemon()`,
+    /// including `daemon_reset`) right after `daemon_unresponsive`.  Off by
+    /// default.  The stuck thread itself cannot be stopped; it is abandoned.
+    ///
+    /// A daemon whose thread died outright is always replaced, without waiting
+    /// for this.  Either way nodes emit `recovered` once re-established.
+    #[func]
+    fn set_auto_recover(&mut self, enabled: bool) {
+        self.auto_recover = enabled;
+    }
+
+    /// Returns `true` if `set_auto_recover()` is on.
+    #[func]
+    fn is_auto_recover_enabled(&self) -> bool {
+        self.auto_recover
+    }
+
+    /// Returns `true` while the extension holds Android's
+    /// `WifiManager.MulticastLock`, without which the WiFi driver drops every
+    /// mDNS packet.  It is acquired when the first browse or advertisement
+    /// starts and released when the last one stops; a failure (usually the
+    /// missing `CHANGE_WIFI_MULTICAST_STATE` permission) is reported once
+    /// through `daemon_error`, starting with `"multicast_lock_failed"`.
+    /// Always `false` on other platforms.
+    #[func]
+    fn is_multicast_lock_held(&self) -> bool {
+        self.multicast_lock.is_held()
+    }
+
+    /// What is known about the Local Network permission of iOS 14+ (and
+    /// macOS 15+): `LOCAL_NETWORK_PERMISSION_UNKNOWN`, `_GRANTED` or
+    /// `_DENIED`.  Always `_GRANTED` on other platforms.
+    ///
+    /// Apple has no call that reports it, so while anything browses or
+    /// advertises the extension sends a one-question probe every 5 seconds:
+    /// the OS rejects it while access is denied.  Once any browse has heard
+    /// an answer the permission counts as granted and probing stops.  Until
+    /// then it stays unknown, e.g. while the prompt is still showing.
+    #[func]
+    fn get_local_network_permission(&self) -> i64 {
+        match daemon::local_network_permission() {
+            Permission::Unknown => Self::LOCAL_NETWORK_PERMISSION_UNKNOWN,
+            Permission::Granted => Self::LOCAL_NETWORK_PERMISSION_GRANTED,
+            Permission::Denied => Self::LOCAL_NETWORK_PERMISSION_DENIED,
+        }
+    }
+
+    /// How often to re-enumerate the local interfaces for `network_changed`,
+    /// in milliseconds (default 3000).  Each poll costs one OS call on the
+    /// main thread.  `0` turns the monitor off; turning it back on does not
+    /// report what changed in between.
+    #[func]
+    fn set_network_poll_interval_ms(&mut self, ms: i64) {
+        let interval = (ms > 0).then(|| Duration::from_millis(ms as u64));
+        daemon::set_network_poll_interval(interval);
+    }
+
+    /// The interval set with `set_network_poll_interval_ms()`, `0` while off.
+    #[func]
+    fn get_network_poll_interval_ms(&self) -> i64 {
+        daemon::network_poll_interval().map_or(0, |interval| interval.as_millis() as i64)
+    }
+
+    /// Drop everything the shared daemon has cached, e.g. after switching
+    /// networks so records from the old one do not leak into new browses.
+    ///
+    /// `mdns-sd` cannot clear its cache in place, so this replaces the daemon
+    /// like `reset_shared_daemon()` (emitting `daemon_reset`), and additionally
+    /// makes every `MdnsBrowser` report its cached services as removed before
+    /// re-browsing.  The new daemon is only started once the old one has
+    /// confirmed its shutdown, so the two never hold the port at once;
+    /// `cache_flushed` is emitted when it is up.  Returns `FAILED`, without
+    /// `cache_flushed`, if the old daemon did not confirm in time.
+    #[func]
+    fn flush_cache(&mut self) -> Error {
+        let result = daemon::flush().and_then(|()| daemon::warm_up());
+        self.base_mut().emit_signal("daemon_reset", &[]);
+        match result {
+            Ok(()) => {
+                self.flush_pending = true;
+                Error::OK
+            }
+            Err(e) => {
+                godot_error!("flush_cache: {e}");
+                Error::FAILED
+            }
+        }
+    }
+
+    /// Ask every instance of `service_type` that the `MdnsBrowser` nodes in the
+    /// scene tree currently list to re-confirm itself (RFC 6762 §10.4).  Those
+    /// that do not answer within 10 seconds are dropped from the daemon's
+    /// cache and reported through `service_removed`; `cache_verified` follows
+    /// with the tally.
+    ///
+    /// Each browser's instances are queried on the daemon serving its browse,
+    /// shared or its own (`set_interface()`, `set_daemon_port()`).  Returns
+    /// `FAILED` if some browser's instances could not be queried, e.g.
+    /// because the shared daemon is not running; the others are still
+    /// verified and counted.  With nothing to verify,
+    /// `cache_verified(service_type, 0, 0)` is emitted next frame.
+    #[func]
+    fn verify_cache(&mut self, service_type: GString) -> Error {
+        let service_type = service_type.to_string();
+        let mut fullnames = BTreeSet::new();
+        let mut result = Error::OK;
+        for browser in scene_browsers() {
+            let browser = browser.bind();
+            let names = browser.cached_fullnames_of_type(&service_type);
+            if names.is_empty() {
+                continue;
+            }
+            if let Err(e) = browser.verify_on_daemon(&names, VERIFY_TIMEOUT_DEFAULT) {
+                godot_error!("verify_cache: {e}");
+                result = Error::FAILED;
+                continue;
+            }
+            fullnames.extend(names);
+        }
+        let fullnames: Vec<String> = fullnames.into_iter().collect();
+        let deadline = if fullnames.is_empty() {
+            Instant::now()
+        } else {
+            Instant::now() + VERIFY_TIMEOUT_DEFAULT + VERIFY_SETTLE
+        };
+        self.verifications.push(CacheVerification {
+            service_type,
+            fullnames,
+            deadline,
+        });
+        result
+    }
+
+    /// Ask every service listed by the `MdnsBrowser` nodes in the scene tree to
+    /// re-confirm itself, for all browsed types at once — e.g. after a network
+    /// hiccup, instead of waiting for re-announcements.  Each browser queries
+    /// on the daemon serving its browse, shared or its own.  Services that
+    /// answer with changed records are reported through `service_updated`;
+    /// those silent for 10 seconds are removed (`service_removed`).  Nothing
+    /// is cleared up front and signal connections are untouched, so services
+    /// still on the LAN stay listed throughout.  `cache_verified` follows for
+    /// each browsed type, as for `verify_cache()`.
+    ///
+    /// Returns `FAILED` if some browser's services could not be queried, e.g.
+    /// because the shared daemon is not running; the others are still
+    /// refreshed.
+    #[func]
+    fn refresh_all(&mut self) -> Error {
+        let mut by_type: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut result = Error::OK;
+        for browser in scene_browsers() {
+            let browser = browser.bind();
+            let Some(service_type) = browser.browsing_type() else {
+                continue;
+            };
+            if let Err(e) = browser.refresh_listed(VERIFY_TIMEOUT_DEFAULT) {
+                godot_error!("refresh_all: {e}");
+                result = Error::FAILED;
+                continue;
+            }
+            by_type
+                .entry(service_type.to_string())
+                .or_default()
+                .extend(browser.cached_fullnames_of_type(service_type));
+        }
+        let deadline = Instant::now() + VERIFY_TIMEOUT_DEFAULT + VERIFY_SETTLE;
+        for (service_type, fullnames) in by_type {
+            self.verifications.push(CacheVerification {
+                service_type,
+                fullnames: fullnames.into_iter().collect(),
+                deadline,
+            });
+        }
+        result
+    }
+
+    /// Start (or stop) recording the mDNS announcements this machine overhears
+    /// from the LAN, for a network overview tool.  Uses a separate
+    /// receive-only socket on the mDNS port that never sends anything, so no
+    /// extra multicast traffic is generated.  IPv4, default interface only;
+    /// answers unicast to another host's query are not seen.
+    ///
+    /// **Warning:** the capture socket shares the daemon's port through
+    /// address/port reuse, so it is a second socket on 5353 in this process.
+    /// Unicast packets to that port, such as answers to a query that asked
+    /// for a unicast reply, are delivered to only one of the sockets, so
+    /// while capture is on the daemon may miss some of them and take longer
+    /// to resolve; multicast traffic reaches both.  Leave it off outside
+    /// diagnostics tools.
+    ///
+    /// Independent of the daemon: survives `shutdown_all()` and resets.
+    /// Stopping discards what was recorded.
+    #[func]
+    fn enable_passive_capture(&mut self, enabled: bool) -> Error {
+        match daemon::set_passive_capture(enabled) {
+            Ok(()) => Error::OK,
+            Err(e) => {
+                godot_error!("enable_passive_capture: {e}");
+                Error::FAILED
+            }
+        }
+    }
+
+    /// Every service instance heard announced since `enable_passive_capture(true)`,
+    /// sorted by fullname, each as a `Dictionary` with:
+    ///
+    /// - `service_type` (String) — e.g. `"_http._tcp.local."`
+    /// - `fullname` (String) — e.g. `"Printer._http._tcp.local."`
+    /// - `hostname` (String) — `""` until the instance's SRV record is heard
+    /// - `port` (int) — `-1` until then
+    /// - `seconds_since_seen` (float)
+    ///
+    /// Instances that sent a goodbye are dropped.  Empty while capture is off.
+    #[func]
+    fn get_overheard_services(&self) -> Array<VarDictionary> {
+        let mut out = Array::new();
+        for service in daemon::overheard_services().unwrap_or_default() {
+            let mut dict = VarDictionary::new();
+            dict.set("service_type", service.service_type.as_str());
+            dict.set("fullname", service.fullname.as_str());
+            dict.set("hostname", service.hostname.as_deref().unwrap_or(""));
+            dict.set("port", service.port.map_or(-1, i64::from));
+            dict.set("seconds_since_seen", service.last_seen.elapsed().as_secs_f64());
+            out.push(&dict);
+        }
+        out
+    }
+
+    /// Latest counters reported by the shared daemon (queries sent, responses
+    /// sent, …) as a `Dictionary` of `String → int`, for diagnostics screens.
+    ///
+    /// Keys are passed through verbatim from `mdns-sd`.  Snapshots are requested
+    /// asynchronously, so values may lag one call behind.  Returns an empty
+    /// dictionary — without creating the daemon — if it does not exist yet.
+    #[func]
+    fn get_daemon_metrics(&self) -> VarDictionary {
+        let mut dict = VarDictionary::new();
+        for (name, value) in daemon::metrics_snapshot().unwrap_or_default() {
+            dict.set(GString::from(&name), value);
+        }
+        dict
+    }
+
+    /// Per-second change of each counter of `get_daemon_metrics()` since the
+    /// previous call, for a live network overlay.  Keys are the counter names
+    /// with dashes turned into underscores plus `_per_sec`
+    /// (`respond_per_sec`, `cache_refresh_ptr_per_sec`, …), and:
+    ///
+    /// - `queries_per_sec` (float) — browse, hostname and cache-refresh queries
+    /// - `responses_per_sec` (float) — same as `respond_per_sec`
+    /// - `interval_sec` (float) — time since the previous call
+    ///
+    /// The first call, and the first after the daemon was replaced, reports
+    /// zeros.  Counters that are gauges (`cached-*`, `dns-registry-*`) can go
+    /// negative.  Like `get_daemon_metrics()`, values may lag one call behind,
+    /// and the result is empty if the daemon does not exist.
+    #[func]
+    fn get_metrics_rate(&mut self) -> VarDictionary {
+        let mut dict = VarDictionary::new();
+        let Some(counters) = daemon::metrics_snapshot() else {
+            self.metrics_previous = None;
+            return dict;
+        };
+        let now = Instant::now();
+        let generation = daemon::generation();
+        let previous = self
+            .metrics_previous
+            .take()
+            .filter(|previous| previous.generation == generation);
+        let interval = previous.as_ref().map_or(0.0, |p| (now - p.taken).as_secs_f64());
+        let rate = |name: &str| -> f64 {
+            match &previous {
+                Some(previous) if interval > 0.0 => {
+                    let before = previous.counters.get(name).copied().unwrap_or(0);
+                    let after = counters.get(name).copied().unwrap_or(0);
+                    (after - before) as f64 / interval
+                }
+                _ => 0.0,
+            }
+        };
+        for name in counters.keys() {
+            let key = format!("{}_per_sec", name.replace('-', "_"));
+            dict.set(GString::from(&key), rate(name));
+        }
+        dict.set("queries_per_sec", QUERY_COUNTERS.iter().map(|name| rate(name)).sum::<f64>());
+        dict.set("responses_per_sec", rate("respond"));
+        dict.set("interval_sec", interval);
+        self.metrics_previous = Some(MetricsSample {
+            counters,
+            taken: now,
+            generation,
+        });
+        dict
+    }
+
+    /// Returns `true` if mDNS is actually functional: the shared daemon exists,
+    /// its thread answers promptly, it has not reported an error in the last
+    /// 10 seconds, and neither the watchdog (`daemon_unresponsive`) nor a
+    /// browse left without any event (`MdnsBrowser.set_search_timeout_ms()`)
+    /// has flagged it.  Does not create the daemon.  Cheap enough to poll
+    /// every few seconds (blocks for at most ~50 ms if the daemon is stuck).
+    #[func]
+    fn is_daemon_healthy(&self) -> bool {
+        daemon::health().healthy
+    }
+
+    /// Detailed version of `is_daemon_healthy()`:
+    ///
+    /// - `healthy` (bool)
+    /// - `reason` (String) — `"ok"`, `"not started"`, `"shut down"`,
+    ///   `"unresponsive"` or `"recent error"`
+    /// - `last_error` (String) — most recent `daemon_error` message, or `""`
+    /// - `seconds_since_last_error` (float) — `-1.0` if there was none
+    /// - `active_browses` / `active_registrations` (int)
+    #[func]
+    fn get_daemon_status(&self) -> VarDictionary {
+        let health = daemon::health();
+        let (last_error, since) = match &health.last_error {
+            Some((e, age)) => (e.as_str(), age.as_secs_f64()),
+            None => ("", -1.0),
+        };
+        let mut dict = VarDictionary::new();
+        dict.set("healthy", health.healthy);
+        dict.set("reason", health.reason);
+        dict.set("last_error", last_error);
+        dict.set("seconds_since_last_error", since);
+        dict.set("active_browses", daemon::active_browse_count() as i64);
+        dict.set("active_registrations", daemon::active_registration_count() as i64);
+        dict
+    }
+
+    /// How the daemon's multicast sockets share port 5353 with other mDNS
+    /// responders on this machine, for debugging one-way discovery:
+    ///
+    /// - `reuse_address` (bool) — `SO_REUSEADDR`, always set
+    /// - `reuse_port` (bool) — `SO_REUSEPORT`, set on Unix-like platforms
+    ///   (macOS, Linux, Android, iOS); not used on Windows
+    /// - `configurable` (bool) — always `false`
+    ///
+    /// `mdns-sd` sets these unconditionally when it binds each interface
+    /// socket and offers no option to change them, so there is no setter.
+    /// Running a second daemon (e.g. `MdnsBrowser.set_interface()`) or another
+    /// responder on the same port is what lets `SO_REUSEPORT` split incoming
+    /// packets between sockets on macOS.
+    #[func]
+    fn get_socket_reuse(&self) -> VarDictionary {
+        let mut dict = VarDictionary::new();
+        dict.set("reuse_address", true);
+        dict.set("reuse_port", cfg!(unix));
+        dict.set("configurable", false);
+        dict
+    }
+
+    /// Receive and send buffer sizes of the daemon's sockets, for diagnosing
+    /// dropped responses under burst load (a crowded LAN event):
+    ///
+    /// - `recv_bytes` / `send_bytes` (int) — as the OS reports them for a new
+    ///   UDP socket (Linux reports twice the configured value); `-1` if they
+    ///   could not be read
+    /// - `configurable` (bool) — always `false`
+    ///
+    /// `mdns-sd` creates its sockets inside the daemon thread and neither sets
+    /// the buffer sizes nor exposes the sockets, so there is no setter, before
+    /// or after creation.  The remedy is the OS default, e.g.
+    /// `net.core.rmem_default` on Linux.  Also part of `get_debug_info()`.
+    #[func]
+    fn get_socket_buffer_sizes(&self) -> VarDictionary {
+        socket_buffer_dict()
+    }
+
+    /// The interfaces a daemon is bound to, one `Dictionary` each:
+    ///
+    /// - `name` (String) — interface name, e.g. `"wlan0"`
+    /// - `addresses` (PackedStringArray) — its addresses the daemon uses
+    /// - `ipv4` / `ipv6` (bool) — whether the daemon's IPv4 / IPv6 socket
+    ///   serves this interface
+    /// - `loopback` (bool)
+    ///
+    /// Reports the shared daemon for `null` (empty while it does not exist), or, given a
+    /// `browser` browsing on its own daemon (`MdnsBrowser.set_interface()`),
+    /// that private daemon.  Any other browser uses the shared daemon.
+    ///
+    /// `mdns-sd` cannot be asked which interfaces it joined, so this is
+    /// reconstructed from the allow/deny lists, exclusions, toggles and IP
+    /// version — including changes made at runtime — applied to the
+    /// interfaces that are up now, the way the daemon applies them.
+    #[func]
+    fn get_bound_addresses(&self, browser: Option<Gd<MdnsBrowser>>) -> Array<VarDictionary> {
+        let selection = browser.and_then(|browser| browser.bind().private_daemon_selection());
+        let mut by_name: BTreeMap<String, Vec<LocalInterface>> = BTreeMap::new();
+        for interface in daemon::bound_interfaces(selection) {
+            by_name.entry(interface.name.clone()).or_default().push(interface);
+        }
+        by_name
+            .into_iter()
+            .map(|(name, addresses)| {
+                let mut dict = VarDictionary::new();
+                dict.set("name", name.as_str());
+                dict.set(
+                    "addresses",
+                    addresses
+                        .iter()
+                        .map(|a| GString::from(&a.ip.to_string()))
+                        .collect::<PackedStringArray>(),
+                );
+                dict.set("ipv4", addresses.iter().any(|a| a.ip.is_ipv4()));
+                dict.set("ipv6", addresses.iter().any(|a| a.ip.is_ipv6()));
+                dict.set("loopback", addresses.iter().any(|a| a.loopback));
+                dict
+            })
+            .collect()
+    }
+
+    /// Probe whether this machine can discover its own services, on a
+    /// background thread, and emit `diagnostics_completed` with the report a
+    /// few seconds later (up to about 10 s).  Windows with Hyper-V or WSL
+    /// virtual switches, and some VPN adapters, never deliver mDNS back to
+    /// the sending machine, so hosting and joining on one PC finds nothing
+    /// while LAN discovery works; call this before a same-PC session to warn
+    /// the player instead.  The probes are those the integration tests run:
+    ///
+    /// - `multicast_loopback` (bool) — a raw UDP multicast packet came back
+    /// - `multicast_error` (String) — why it did not, or `""`
+    /// - `interfaces` (Array) — one `Dictionary` per IPv4 interface: `name`,
+    ///   `address`, `looped` (bool) and `error` (String), the same probe
+    ///   joined to the mDNS group on that interface
+    /// - `self_resolve_ms` (int) — how long a throwaway service on a private
+    ///   daemon (port 15353, multicast loopback on) took to discover itself,
+    ///   or -1 if it never did
+    /// - `same_machine_discovery_expected` (bool) — it did
+    /// - `summary` (String) — what works and what to do, for the player or QA
+    /// - `duration_ms` (int) — how long the probes took
+    ///
+    /// Does not touch the shared daemon.  `ERR_BUSY` if a run is already in
+    /// progress, `FAILED` if the thread could not be started.
+    #[func]
+    fn run_environment_diagnostics(&mut self) -> Error {
+        if self.environment_probe.is_some() {
+            return Error::ERR_BUSY;
+        }
+        match environment::spawn() {
+            Ok(receiver) => {
+                self.environment_probe = Some(receiver);
+                Error::OK
+            }
+            Err(e) => {
+                godot_error!("run_environment_diagnostics: {e}");
+                Error::FAILED
+            }
+        }
+    }
+
+    /// Whether Windows Defender Firewall lets inbound mDNS reach this game —
+    /// the usual cause of one-way discovery on Windows, where the game is
+    /// found by others but finds nothing.  Queries the firewall through
+    /// PowerShell, which blocks for a second or more, so call it from a help
+    /// or settings screen rather than every frame:
+    ///
+    /// - `supported` (bool) — `false` except on Windows; the other keys are
+    ///   then missing
+    /// - `checked` (bool) — the firewall could be queried; if not, `error`
+    ///   says why and the remaining keys are missing
+    /// - `program` (String) — this executable, as firewall rules name it
+    /// - `port` (int) — the mDNS port checked, 5353 unless `set_daemon_port()`
+    /// - `profile` (String) — the active network's category: `"Public"`,
+    ///   `"Private"`, `"Domain"`, or `""` without a network
+    /// - `firewall_enabled` (bool) — the firewall is on for that profile
+    /// - `allow_rule` / `block_rule` (String) — the name of the first enabled
+    ///   rule for this executable allowing / blocking inbound UDP on the port
+    ///   for that profile, or `""`
+    /// - `inbound_blocked` (bool) — inbound mDNS is dropped
+    /// - `message` (String) — the cause and remedy when blocked, or `""`
+    ///
+    /// Only rules naming this executable count; an allow rule for every
+    /// program is not seen, so `inbound_blocked` may be a false alarm there.
+    #[func]
+    fn check_firewall(&self) -> VarDictionary {
+        let mut dict = VarDictionary::new();
+        dict.set("supported", cfg!(windows));
+        if !cfg!(windows) {
+            return dict;
+        }
+        let program = current_exe();
+        let port = daemon::configured_port();
+        let report = match firewall::query(&program) {
+            Ok(report) => report,
+            Err(e) => {
+                dict.set("checked", false);
+                dict.set("error", e.as_str());
+                return dict;
+            }
+        };
+        let rule_name = |rule: Option<&firewall::FirewallRule>| {
+            rule.map_or_else(String::new, |rule| rule.name.clone())
+        };
+        dict.set("checked", true);
+        dict.set("error", "");
+        dict.set("program", program.as_str());
+        dict.set("port", port as i64);
+        dict.set("profile", report.profile.as_str());
+        dict.set("firewall_enabled", report.enabled);
+        dict.set("allow_rule", rule_name(report.allow_rule(port)).as_str());
+        dict.set("block_rule", rule_name(report.block_rule(port)).as_str());
+        dict.set("inbound_blocked", report.inbound_blocked(port));
+        dict.set("message", report.message(port, &program).as_str());
+        dict
+    }
+
+    /// Ask Windows to add a firewall rule allowing inbound UDP on the mDNS
+    /// port to this executable on every network profile.  **Needs
+    /// elevation:** Windows shows a UAC prompt, and nothing changes if the
+    /// player declines.  Returns once the prompt is up, without waiting for
+    /// the answer; call `check_firewall()` again afterwards.  `FAILED` if the
+    /// prompt could not be started, `ERR_UNAVAILABLE` except on Windows.
+    #[func]
+    fn request_firewall_rule(&mut self) -> Error {
+        if !cfg!(windows) {
+            return Error::ERR_UNAVAILABLE;
+        }
+        match firewall::request_rule(&current_exe(), daemon::configured_port()) {
+            Ok(()) => Error::OK,
+            Err(e) => {
+                godot_error!("request_firewall_rule: {e}");
+                Error::FAILED
+            }
+        }
+    }
+
+    /// What the check made just before the shared daemon was first created
+    /// found about the mDNS port (a few milliseconds, once per run):
+    ///
+    /// - `checked` (bool) — `false` until the shared daemon has been created;
+    ///   the other keys are then missing
+    /// - `port` (int) — the port checked, 5353 unless `set_daemon_port()` was used
+    /// - `port_free` (bool) — no other program had it bound
+    /// - `shareable` (bool) — the daemon could share it with whoever had it;
+    ///   `false` means discovery will miss packets
+    /// - `bind_error` (String) — why sharing failed, or `""`
+    /// - `responders` (PackedStringArray) — well-known responders that look
+    ///   installed or running: `"Avahi"`, `"mDNSResponder"`, `"Bonjour for Windows"`
+    /// - `avahi` (Dictionary) — on Linux with Avahi installed, otherwise empty:
+    ///   `running`, `reflector` (`enable-reflector`), `publishing_disabled`
+    ///   (`disable-publishing`, i.e. cache only) and `user_publishing_disabled`
+    ///   (`disable-user-service-publishing`, which does not affect this extension)
+    /// - `warning` (String) — as sent with `diagnostics_warning`, or `""`
+    #[func]
+    fn get_startup_diagnostics(&self) -> VarDictionary {
+        let mut dict = VarDictionary::new();
+        let Some(report) = daemon::startup_report() else {
+            dict.set("checked", false);
+            return dict;
+        };
+        let responders: PackedStringArray = report.responders.iter().map(GString::from).collect();
+        dict.set("checked", true);
+        dict.set("port", report.port as i64);
+        dict.set("port_free", report.port_free);
+        dict.set("shareable", report.shareable);
+        dict.set("bind_error", report.bind_error.as_str());
+        dict.set("responders", responders);
+        let mut avahi = VarDictionary::new();
+        if let Some(state) = &report.avahi {
+            avahi.set("running", state.running);
+            avahi.set("reflector", state.reflector);
+            avahi.set("publishing_disabled", state.publishing_disabled);
+            avahi.set("user_publishing_disabled", state.user_publishing_disabled);
+        }
+        dict.set("avahi", avahi);
+        dict.set("warning", report.warning().unwrap_or_default().as_str());
+        dict
+    }
+
+    /// Everything the extension knows about its own state, for bug reports.
+    /// Contains only strings, numbers and arrays/dictionaries of them, so it
+    /// can be `print()`ed or saved as JSON as is:
+    ///
+    /// - `shared_daemon` (bool) — whether the shared daemon exists
+    /// - `pinned_daemons` (int) — private daemons of `MdnsBrowser.set_interface()`
+    /// - `browses` (Array) — one `Dictionary` per type browsed on the shared
+    ///   daemon: `service_type`, `subscribers` (int) and `owners` (node paths)
+    /// - `registrations` (Array) — one `Dictionary` per service registered on
+    ///   the shared daemon: `fullname`, `state` and `owner` (node path)
+    /// - `browsers` / `advertisers` (Array) — one `Dictionary` per node in the
+    ///   scene tree: `path`, `state`, and `service_type`, `queued_events`,
+    ///   `cached_services` (browsers) or `fullname` (advertisers)
+    /// - `excluded_interfaces` (Array) — one `Dictionary` (`name`, `reason`) per
+    ///   interface the virtual-adapter filter switches off right now
+    /// - `socket_buffers` (Dictionary) — as `get_socket_buffer_sizes()`
+    /// - `recent_daemon_events` (PackedStringArray) — the last 16 daemon monitor
+    ///   events, oldest first, as `"<seconds>s ago: <event>"`
+    ///
+    /// A browse or registration with no owner belongs to a node outside the
+    /// scene tree, or leaked.  Never creates the daemon.
+    #[func]
+    fn get_debug_info(&self) -> VarDictionary {
+        let snapshot = daemon::debug_snapshot();
+        let browsers: Vec<VarDictionary> = scene_browsers()
+            .iter()
+            .map(|browser| browser.bind().debug_info())
+            .collect();
+        let advertisers: Vec<VarDictionary> = scene_advertisers()
+            .iter()
+            .map(|advertiser| advertiser.bind().debug_info())
+            .collect();
+        let text = |dict: &VarDictionary, key: &str| -> String {
+            dict.get(key).map(|v| v.to_string()).unwrap_or_default()
+        };
+
+        let mut browses = Array::<VarDictionary>::new();
+        for (service_type, subscribers) in &snapshot.browses {
+            let owners: PackedStringArray = browsers
+                .iter()
+                .filter(|b| {
+                    text(b, "state") == "browsing" && text(b, "service_type") == *service_type
+                })
+                .map(|b| GString::from(&text(b, "path")))
+                .collect();
+            let mut dict = VarDictionary::new();
+            dict.set("service_type", service_type.as_str());
+            dict.set("subscribers", *subscribers as i64);
+            dict.set("owners", owners);
+            browses.push(&dict);
+        }
+        let mut registrations = Array::<VarDictionary>::new();
+        for fullname in &snapshot.registrations {
+            let owner = advertisers.iter().find(|a| text(a, "fullname") == *fullname);
+            let mut dict = VarDictionary::new();
+            dict.set("fullname", fullname.as_str());
+            dict.set("state", owner.map_or("registered".to_string(), |a| text(a, "state")));
+            dict.set("owner", owner.map(|a| text(a, "path")).unwrap_or_default());
+            registrations.push(&dict);
+        }
+        let recent: PackedStringArray = snapshot
+            .recent_events
+            .iter()
+            .map(|(event, age)| GString::from(&format!("{:.1}s ago: {event}", age.as_secs_f64())))
+            .collect();
+
+        let mut excluded = Array::<VarDictionary>::new();
+        for interface in daemon::excluded_interfaces() {
+            let mut dict = VarDictionary::new();
+            dict.set("name", interface.name.as_str());
+            dict.set("reason", interface.reason.as_str());
+            excluded.push(&dict);
+        }
+
+        let mut dict = VarDictionary::new();
+        dict.set("shared_daemon", snapshot.shared_daemon);
+        dict.set("pinned_daemons", snapshot.private_browses as i64);
+        dict.set("browses", browses);
+        dict.set("registrations", registrations);
+        dict.set("browsers", browsers.iter().cloned().collect::<Array<VarDictionary>>());
+        dict.set("advertisers", advertisers.iter().cloned().collect::<Array<VarDictionary>>());
+        dict.set("excluded_interfaces", excluded);
+        dict.set("socket_buffers", socket_buffer_dict());
+        dict.set("recent_daemon_events", recent);
+        dict
+    }
+
+    /// Number of live browse subscriptions across all `MdnsBrowser` nodes.
+    #[func]
+    fn get_active_browse_count(&self) -> i64 {
+        daemon::active_browse_count() as i64
+    }
+
+    /// Number of services currently registered by `MdnsAdvertiser` nodes.
+    #[func]
+    fn get_active_registration_count(&self) -> i64 {
+        daemon::active_registration_count() as i64
+    }
+
+    /// Service types at least one `MdnsBrowser` is browsing right now, on the
+    /// shared daemon or a private one, sorted.  A type several browsers share
+    /// is listed once; it goes when the last of them stops or is freed.
+    #[func]
+    fn get_active_browse_types(&self) -> PackedStringArray {
+        daemon::active_browse_types().iter().map(GString::from).collect()
+    }
+
+    /// Fullnames of the services `MdnsAdvertiser` nodes have registered right
+    /// now, sorted.  Briefly empty after a daemon reset, until the advertisers
+    /// have re-registered.
+    #[func]
+    fn get_active_registrations(&self) -> PackedStringArray {
+        daemon::active_registrations().iter().map(GString::from).collect()
+    }
+
+    /// Raise the maximum service-name length from the RFC 6763 default of 15
+    /// bytes (`mdns-sd` caps it at 30).  Must be called before the daemon exists.
+    #[func]
+    fn set_service_name_len_max(&mut self, len_max: i64) -> Error {
+        if !(1..=30).contains(&len_max) {
+            godot_error!("set_service_name_len_max: {len_max} is outside 1..=30");
+            return Error::ERR_INVALID_PARAMETER;
+        }
+        configure(|config| config.service_name_len_max = Some(len_max as u8))
+    }
+
+    /// Use `port` instead of the standard mDNS port 5353, e.g. to run several
+    /// copies of a game on one machine as an isolated "fake LAN" in CI.  Must be
+    /// called before the daemon exists; `5353` restores the default.
+    ///
+    /// A daemon on a non-standard port only interoperates with peers configured
+    /// the same way — regular mDNS responders (and other games) will neither
+    /// see it nor be seen by it.
+    ///
+    /// The port is the only isolation knob: `mdns-sd` hard-codes the multicast
+    /// groups (224.0.0.251 and ff02::fb), so there is no setter for a custom
+    /// group.  Harnesses that need a private group should pick a private port.
+    #[func]
+    fn set_daemon_port(&mut self, port: i64) -> Error {
+        if !(1..=65535).contains(&port) {
+            godot_error!("set_daemon_port: {port} is outside 1..=65535");
+            return Error::ERR_INVALID_PARAMETER;
+        }
+        let port = port as u16;
+        configure(|config| config.port = (port != 5353).then_some(port))
+    }
+
+    /// Enable or disable multicast loopback on the shared daemon's IPv4 and
+    /// IPv6 sockets.  Loopback is what lets a browser see services advertised
+    /// by the same machine (e.g. a host that also browses).
+    ///
+    /// Applies to the shared daemon if it is running, and is remembered for
+    /// daemons created later — including one still being created, those after
+    /// `reset_shared_daemon()` and the private daemon of
+    /// `MdnsBrowser.set_interface()`.  Never creates the daemon or waits for
+    /// it.  Returns `FAILED` if the running daemon rejects the setting.
+    ///
+    /// Development aid: with the environment variable
+    /// `GODOT_MDNS_FORCE_LOOPBACK=1`, every daemon is created with loopback on,
+    /// whatever was configured here.
+    #[func]
+    fn set_multicast_loopback(&mut self, enabled: bool) -> Error {
+        match daemon::set_multicast_loopback(enabled) {
+            Ok(()) => Error::OK,
+            Err(e) => {
+                godot_error!("set_multicast_loopback: {e}");
+                Error::FAILED
+            }
+        }
+    }
+
+    /// Restrict the shared daemon to the given interfaces, each given by name
+    /// (`"eth0"`) or by one of its IP addresses (`"192.168.1.42"`).  An empty
+    /// array lifts the restriction.
+    ///
+    /// Unlike the other setters this may be called at any time: before the
+    /// daemon exists the list is applied at creation, afterwards the live
+    /// daemon is reconfigured and `interfaces_changed` is emitted.
+    #[func]
+    fn set_allowed_interfaces(&mut self, names_or_ips: PackedStringArray) -> Error {
+        self.set_interfaces("set_allowed_interfaces", names_or_ips, |selection, kinds| {
+            selection.allowed = kinds
+        })
+    }
+
+    /// Exclude the given interfaces (names or IP addresses) from the shared
+    /// daemon.  Applied after the allow-list, so a denied interface stays off
+    /// even if it is also allowed.  Same timing rules as
+    /// `set_allowed_interfaces()`.
+    #[func]
+    fn set_denied_interfaces(&mut self, names_or_ips: PackedStringArray) -> Error {
+        self.set_interfaces("set_denied_interfaces", names_or_ips, |selection, kinds| {
+            selection.denied = kinds
+        })
+    }
+
+    /// Replace the interface-name patterns of the virtual-adapter filter
+    /// (`*` matches any run of characters, ASCII case is ignored).  By default
+    /// `utun*`, `tun*`, `tap*`, `vEthernet*`, `docker*`, `br-*`, `vboxnet*` and
+    /// `zt*` are excluded, together with any interface holding a CGNAT /
+    /// Tailscale address (100.64.0.0/10, fd7a:115c:a1e0::/48), so tunnels,
+    /// container bridges and VM networks neither receive queries nor have
+    /// their unreachable addresses advertised.  An empty array keeps only the
+    /// address rule.
+    ///
+    /// Interfaces named in `set_allowed_interfaces()` are never excluded, and
+    /// `enable_interface()` overrides the filter.  It is evaluated whenever the
+    /// daemon is created or its interfaces are reconfigured; `get_debug_info()`
+    /// lists what it excluded.  Same timing rules as `set_allowed_interfaces()`.
+    #[func]
+    fn set_interface_exclusions(&mut self, patterns: PackedStringArray) -> Error {
+        let patterns: Vec<String> = patterns
+            .as_slice()
+            .iter()
+            .map(|p| p.to_string().trim().to_string())
+            .filter(|p| !p.is_empty())
+            .collect();
+        self.update_selection("set_interface_exclusions", |selection| {
+            selection.exclusions.patterns = patterns
+        })
+    }
+
+    /// The patterns set by `set_interface_exclusions()`.
+    #[func]
+    fn get_interface_exclusions(&self) -> PackedStringArray {
+        daemon::exclusions()
+            .patterns
+            .iter()
+            .map(GString::from)
+            .collect()
+    }
+
+    /// Turn the virtual-adapter filter of `set_interface_exclusions()` off
+    /// (or back on, the default) as a whole, e.g. for a game that is meant to
+    /// be played over a VPN.
+    #[func]
+    fn set_exclude_virtual_interfaces(&mut self, enabled: bool) -> Error {
+        self.update_selection("set_exclude_virtual_interfaces", |selection| {
+            selection.exclusions.enabled = enabled
+        })
+    }
+
+    /// Returns `true` if the virtual-adapter filter is on.
+    #[func]
+    fn is_excluding_virtual_interfaces(&self) -> bool {
+        daemon::exclusions().enabled
+    }
+
+    /// Switch one interface of the shared daemon on, e.g. from a settings
+    /// screen.  `name_or_ip` is an interface name (`"eth0"`), one of its IP
+    /// addresses, or `"all"`, `"ipv4"` or `"ipv6"`.
+    ///
+    /// Toggles are applied in the order they were made, after the allow/deny
+    /// lists and before `set_ip_version()`; the latest one for an interface
+    /// wins, and `"all"` supersedes every earlier toggle.  They are kept and
+    /// re-applied whenever the daemon is recreated.  Same timing rules as
+    /// `set_allowed_interfaces()`: a live daemon is reconfigured and
+    /// `interfaces_changed` is emitted.
+    ///
+    /// Returns `ERR_INVALID_PARAMETER` for an unparsable spec, `FAILED` if the
+    /// daemon rejects the change.
+    #[func]
+    fn enable_interface(&mut self, name_or_ip: GString) -> Error {
+        self.toggle_interface("enable_interface", name_or_ip, true)
+    }
+
+    /// Switch one interface of the shared daemon off, e.g. a corporate VPN
+    /// adapter.  Same rules as `enable_interface()`.
+    #[func]
+    fn disable_interface(&mut self, name_or_ip: GString) -> Error {
+        self.toggle_interface("disable_interface", name_or_ip, false)
+    }
+
+    /// Restrict every daemon — the shared one and the private ones used by
+    /// `MdnsBrowser.set_interface()` and the self-checks — to one IP family
+    /// (`IP_VERSION_*`).  Useful on networks where IPv6 multicast is broken
+    /// and only produces send errors.  Applied after the allow/deny lists.
+    ///
+    /// Same timing rules as `set_allowed_interfaces()`: a live shared daemon
+    /// is reconfigured in place and `interfaces_changed` is emitted.  Private
+    /// daemons pick the setting up the next time they are created.
+    #[func]
+    fn set_ip_version(&mut self, mode: i64) -> Error {
+        let version = match mode {
+            Self::IP_VERSION_BOTH => IpVersion::Both,
+            Self::IP_VERSION_IPV4_ONLY => IpVersion::V4Only,
+            Self::IP_VERSION_IPV6_ONLY => IpVersion::V6Only,
+            _ => {
+                godot_error!("set_ip_version: unknown mode {mode}");
+                return Error::ERR_INVALID_PARAMETER;
+            }
+        };
+        self.update_selection("set_ip_version", |selection| selection.ip_version = version)
+    }
+
+    /// The mode last passed to `set_ip_version()` (`IP_VERSION_BOTH` by default).
+    #[func]
+    fn get_ip_version(&self) -> i64 {
+        match daemon::ip_version() {
+            IpVersion::Both => Self::IP_VERSION_BOTH,
+            IpVersion::V4Only => Self::IP_VERSION_IPV4_ONLY,
+            IpVersion::V6Only => Self::IP_VERSION_IPV6_ONLY,
+        }
+    }
+}
+
+impl MdnsManager {
+    /// Replaces a failed shared daemon.  Nodes re-establish themselves as
+    /// after `reset_shared_daemon()` and then emit `recovered`.
+    fn recover_shared_daemon(&mut self) {
+        if let Err(e) = daemon::recover() {
+            godot_warn!("godot-mdns: {e}");
+        }
+        self.base_mut().emit_signal("daemon_reset", &[]);
+    }
+
+    /// Called once per frame from the extension's main-loop hook.  Finishes a
+    /// background daemon creation, drains the shared daemon's monitor channel,
+    /// re-emits errors as `daemon_error` and prints queued log records.
+    pub(crate) fn poll(&mut self) {
+        if let Some(created) = daemon::progress_creation() {
+            if std::mem::take(&mut self.flush_pending) {
+                self.base_mut()
+                    .emit_signal("cache_flushed", &[created.is_ok().to_variant()]);
+            }
+            match created {
+                Ok(()) => {
+                    self.base_mut().emit_signal("daemon_ready", &[]);
+                }
+                Err(e) => {
+                    self.base_mut()
+                        .emit_signal("daemon_error", &[GString::from(&e).to_variant()]);
+                }
+            }
+        }
+        if let Some(warning) = daemon::take_startup_warning() {
+            godot_warn!("godot-mdns: {warning}");
+            self.base_mut()
+                .emit_signal("diagnostics_warning", &[GString::from(&warning).to_variant()]);
+        }
+        match daemon::watchdog_tick() {
+            Some(WatchdogAlarm::Stalled) => {
+                self.base_mut().emit_signal("daemon_unresponsive", &[]);
+                if self.auto_recover {
+                    self.recover_shared_daemon();
+                }
+            }
+            // A dead thread cannot come back, so this does not wait for opt-in.
+            Some(WatchdogAlarm::Died) => {
+                godot_warn!("godot-mdns: the shared daemon's thread died; recreating it");
+                self.recover_shared_daemon();
+            }
+            None => {}
+        }
+        if let Some(change) = daemon::network_tick() {
+            self.base_mut().emit_signal(
+                "network_changed",
+                &[
+                    network_change_array(&change.added).to_variant(),
+                    network_change_array(&change.removed).to_variant(),
+                ],
+            );
+        }
+        if daemon::local_network_tick() == Some(Permission::Denied) {
+            godot_warn!(
+                "godot-mdns: Local Network access is denied; mDNS finds nothing until the \
+                 player allows it in Settings"
+            );
+            self.base_mut().emit_signal("local_network_permission_denied", &[]);
+        }
+        if daemon::take_multicast_relock() {
+            self.multicast_lock.reacquire();
+        }
+        if let Some(e) = self.multicast_lock.update() {
+            godot_error!("godot-mdns: {e}");
+            self.base_mut().emit_signal("daemon_error", &[GString::from(&e).to_variant()]);
+        }
+        self.finish_verifications();
+        self.finish_environment_probe();
+        let bus = daemon::is_event_bus_enabled();
+        for event in daemon::drain_monitor() {
+            if bus {
+                self.emit_bus_event(monitor_event_data(&event));
+            }
+            if let DaemonEvent::Error(e) = event {
+                self.base_mut()
+                    .emit_signal("daemon_error", &[GString::from(&e.to_string()).to_variant()]);
+            }
+        }
+        if bus {
+            self.emit_browse_bus_events();
+        }
+        logging::flush();
+    }
+
+    /// Emits `diagnostics_completed` once the `run_environment_diagnostics()`
+    /// thread has reported.
+    fn finish_environment_probe(&mut self) {
+        let Some(receiver) = &self.environment_probe else {
+            return;
+        };
+        let report = match receiver.try_recv() {
+            Ok(report) => report,
+            Err(mpsc::TryRecvError::Empty) => return,
+            Err(mpsc::TryRecvError::Disconnected) => {
+                self.environment_probe = None;
+                godot_error!("godot-mdns: the environment diagnostics thread died");
+                return;
+            }
+        };
+        self.environment_probe = None;
+        self.base_mut()
+            .emit_signal("diagnostics_completed", &[environment_dict(&report).to_variant()]);
+    }
+
+    /// Emits `event` for the browse events the shared layer copied since the
+    /// last frame.
+    fn emit_browse_bus_events(&mut self) {
+        let events = daemon::take_bus_events();
+        if events.is_empty() {
+            return;
+        }
+        let browsers: Vec<(String, String)> = scene_browsers()
+            .iter()
+            .filter_map(|browser| {
+                let browser = browser.bind();
+                let service_type = browser.shared_browse_type()?.to_string();
+                Some((service_type, browser.base().get_path().to_string()))
+            })
+            .collect();
+        for bus_event in events {
+            let node_paths: PackedStringArray = match &bus_event.owner {
+                Some(owner) => [GString::from(owner)].into_iter().collect(),
+                None => browsers
+                    .iter()
+                    .filter(|(service_type, _)| *service_type == bus_event.service_type)
+                    .map(|(_, path)| GString::from(path))
+                    .collect(),
+            };
+            if let Some((kind, mut data)) = browse_event_data(bus_event.event) {
+                data.set("service_type", bus_event.service_type.as_str());
+                data.set("node_paths", node_paths);
+                self.emit_bus_event(Some((kind, data)));
+            }
+        }
+    }
+
+    fn emit_bus_event(&mut self, event: Option<(&str, VarDictionary)>) {
+        if let Some((kind, data)) = event {
+            self.base_mut()
+                .emit_signal("event", &[GString::from(kind).to_variant(), data.to_variant()]);
+        }
+    }
+
+    /// Emits `cache_verified` for every verification whose window ended,
+    /// counting the instances some browser still lists as confirmed.
+    fn finish_verifications(&mut self) {
+        let now = Instant::now();
+        let (done, waiting) = std::mem::take(&mut self.verifications)
+            .into_iter()
+            .partition(|v| v.deadline <= now);
+        self.verifications = waiting;
+        for verification in done {
+            let browsers = scene_browsers();
+            let confirmed = verification
+                .fullnames
+                .iter()
+                .filter(|name| {
+                    browsers
+                        .iter()
+                        .any(|browser| browser.bind().cached_service(name).is_some())
+                })
+                .count();
+            let removed = verification.fullnames.len() - confirmed;
+            self.base_mut().emit_signal(
+                "cache_verified",
+                &[
+                    GString::from(&verification.service_type).to_variant(),
+                    (confirmed as i64).to_variant(),
+                    (removed as i64).to_variant(),
+                ],
+            );
+        }
+    }
+
+    /// Shared body of the allow/deny setters: validates every entry, then
+    /// hands over to `update_selection()`.
+    fn set_interfaces(
+        &mut self,
+        caller: &str,
+        names_or_ips: PackedStringArray,
+        update: impl FnOnce(&mut InterfaceSelection, Vec<mdns_sd::IfKind>),
+    ) -> Error {
+        let specs: Vec<String> = names_or_ips.as_slice().iter().map(GString::to_string).collect();
+        let kinds = match interfaces::parse_interfaces(&specs) {
+            Ok(kinds) => kinds,
+            Err(e) => {
+                godot_error!("{caller}: {e}");
+                return Error::ERR_INVALID_PARAMETER;
+            }
+        };
+        self.update_selection(caller, |selection| update(selection, kinds))
+    }
+
+    /// Shared part of `enable_interface()` and `disable_interface()`.
+    fn toggle_interface(&mut self, caller: &str, name_or_ip: GString, enabled: bool) -> Error {
+        let kind = match interfaces::parse_interface_or_group(&name_or_ip.to_string()) {
+            Ok(kind) => kind,
+            Err(e) => {
+                godot_error!("{caller}: {e}");
+                return Error::ERR_INVALID_PARAMETER;
+            }
+        };
+        self.update_selection(caller, |selection| selection.toggle(kind, enabled))
+    }
+
+    /// Stores a selection change and emits `interfaces_changed` if a live
+    /// daemon was updated.
+    fn update_selection(
+        &mut self,
+        caller: &str,
+        update: impl FnOnce(&mut InterfaceSelection),
+    ) -> Error {
+        match daemon::set_interface_selection(update) {
+            Ok(true) => {
+                self.base_mut().emit_signal("interfaces_changed", &[]);
+                Error::OK
+            }
+            Ok(false) => Error::OK,
+            Err(e) => {
+                godot_error!("{caller}: {e}");
+                Error::FAILED
+            }
+        }
+    }
+}
+
+/// Every `MdnsBrowser` in the running scene tree.
+fn scene_browsers() -> Vec<Gd<MdnsBrowser>> {
+    Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+        .map(|tree| browser::browsers_in_tree(&tree))
+        .unwrap_or_default()
+}
+
+/// Kind and data of a browse event for the `event` signal; `service_type`
+/// and `node_paths` are added by the caller.
+fn browse_event_data(event: ServiceEvent) -> Option<(&'static str, VarDictionary)> {
+    let mut data = VarDictionary::new();
+    let kind = match event {
+        ServiceEvent::SearchStarted(_) => "search_started",
+        ServiceEvent::SearchStopped(_) => "search_stopped",
+        ServiceEvent::ServiceFound(_, fullname) => {
+            data.set("fullname", fullname.as_str());
+            "service_found"
+        }
+        ServiceEvent::ServiceRemoved(_, fullname) => {
+            data.set("fullname", fullname.as_str());
+            "service_removed"
+        }
+        ServiceEvent::ServiceResolved(info) => {
+            let service = CachedService::from_resolved(&info);
+            data.set("fullname", service.fullname.as_str());
+            data.set("host", service.host.as_str());
+            data.set("addresses", browser::address_array(&service));
+            data.set("port", service.port as i64);
+            data.set("txt", browser::txt_dict(&service, false));
+            data.set("description", service.description());
+            data.set("available", service.is_available());
+            "service_resolved"
+        }
+        _ => return None,
+    };
+    Some((kind, data))
+}
+
+/// Kind and data of a shared-daemon monitor event for the `event` signal.
+fn monitor_event_data(event: &DaemonEvent) -> Option<(&'static str, VarDictionary)> {
+    let mut data = VarDictionary::new();
+    let kind = match event {
+        DaemonEvent::Error(e) => {
+            data.set("message", e.to_string().as_str());
+            "daemon_error"
+        }
+        DaemonEvent::Announce(fullname, detail) => {
+            data.set("fullname", fullname.as_str());
+            // Where it went out, as `mdns-sd` words it (addresses or `host:interface`).
+            data.set("detail", detail.as_str());
+            "announce"
+        }
+        DaemonEvent::IpAdd(ip) => {
+            data.set("address", ip.to_string().as_str());
+            "ip_added"
+        }
+        DaemonEvent::IpDel(ip) => {
+            data.set("address", ip.to_string().as_str());
+            "ip_removed"
+        }
+        DaemonEvent::NameChange(change) => {
+            data.set("original", change.original.as_str());
+            data.set("new_name", change.new_name.as_str());
+            "name_changed"
+        }
+        DaemonEvent::Respond(interface) => {
+            data.set("interface", interface.as_str());
+            "respond"
+        }
+        _ => return None,
+    };
+    Some((kind, data))
+}
+
+/// The dictionary of `get_socket_buffer_sizes()`.
+fn socket_buffer_dict() -> VarDictionary {
+    let (recv, send) = daemon::default_socket_buffer_sizes()
+        .map_or((-1, -1), |(recv, send)| (recv as i64, send as i64));
+    let mut dict = VarDictionary::new();
+    dict.set("recv_bytes", recv);
+    dict.set("send_bytes", send);
+    dict.set("configurable", false);
+    dict
+}
+
+/// The addresses of a `network_changed` parameter.
+fn network_change_array(interfaces: &[LocalInterface]) -> Array<VarDictionary> {
+    interfaces
+        .iter()
+        .map(|interface| {
+            let mut dict = VarDictionary::new();
+            dict.set("name", interface.name.as_str());
+            dict.set("address", interface.ip.to_string().as_str());
+            dict.set("loopback", interface.loopback);
+            dict
+        })
+        .collect()
+}
+
+/// The path of the running executable, or `""` if it cannot be found.
+fn current_exe() -> String {
+    std::env::current_exe()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default()
+}
+
+/// The `diagnostics_completed` report.
+fn environment_dict(report: &EnvironmentReport) -> VarDictionary {
+    let interfaces: Array<VarDictionary> = report
+        .interfaces
+        .iter()
+        .map(|probe| {
+            let mut dict = VarDictionary::new();
+            dict.set("name", probe.name.as_str());
+            dict.set("address", probe.address.to_string().as_str());
+            dict.set("looped", probe.looped);
+            dict.set("error", probe.error.as_str());
+            dict
+        })
+        .collect();
+    let mut dict = VarDictionary::new();
+    dict.set("multicast_loopback", report.multicast_loopback);
+    dict.set("multicast_error", report.multicast_error.as_str());
+    dict.set("interfaces", interfaces);
+    dict.set(
+        "self_resolve_ms",
+        report.self_resolve.map_or(-1, |took| took.as_millis() as i64),
+    );
+    dict.set("same_machine_discovery_expected", report.same_machine_discovery_expected());
+    dict.set("summary", report.summary().as_str());
+    dict.set("duration_ms", report.duration.as_millis() as i64);
+    dict
+}
+
+/// Every `MdnsAdvertiser` in the running scene tree.
+fn scene_advertisers() -> Vec<Gd<MdnsAdvertiser>> {
+    Engine::singleton()
+        .get_main_loop()
+        .and_then(|main_loop| main_loop.try_cast::<SceneTree>().ok())
+        .map(|tree| advertiser::advertisers_in_tree(&tree))
+        .unwrap_or_default()
+}
+
+/// Applies `f` to the daemon configuration, mapping "daemon already exists"
+/// to `ERR_ALREADY_IN_USE`.
+fn configure(f: impl FnOnce(&mut daemon::DaemonConfig)) -> Error {
+    match daemon::configure(f) {
+        Ok(()) => Error::OK,
+        Err(e) => {
+            godot_error!("{e}");
+            Error::ERR_ALREADY_IN_USE
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Android `WifiManager.MulticastLock`, held while anything browses or
+//! advertises.
+//!
+//! Android drops inbound multicast at the WiFi driver unless an app holds
+//! this lock, so without it discovery silently receives nothing.  The lock
+//! is taken through the `AndroidRuntime` singleton of Godot 4.2+ Android
+//! templates, which already bridges to JNI, so no Java plugin is needed.
+//! `MdnsManager` calls [`MulticastLock::update`] once per frame: the lock is
+//! acquired when the first browse or registration starts and released when
+//! the daemon layer counts none, which makes the layer's own bookkeeping the
+//! reference count.  Everywhere but Android this does nothing.
+
+use crate::daemon;
+use godot::classes::Engine;
+use godot::prelude::*;
+
+/// Leading word of the error reported when the lock cannot be acquired.
+pub const MULTICAST_LOCK_ERROR: &str = "multicast_lock_failed";
+
+/// The manifest permission the lock needs.
+const PERMISSION: &str = "android.permission.CHANGE_WIFI_MULTICAST_STATE";
+
+/// Tag Android shows for the lock in `dumpsys wifi`.
+const LOCK_TAG: &str = "godot-mdns";
+
+/// The lock, if held.
+#[derive(Default)]
+pub struct MulticastLock {
+    /// The `MulticastLock` Java object while it is held.
+    lock: Option<Variant>,
+    /// Acquiring failed; not retried until nothing needs the lock any more.
+    failed: bool,
+}
+
+impl MulticastLock {
+    pub fn is_held(&self) -> bool {
+        self.lock.is_some()
+    }
+
+    /// Acquires or releases the lock so it is held exactly while a browse or
+    /// registration is active.  Returns the error the first time acquiring
+    /// fails.
+    pub fn update(&mut self) -> Option<String> {
+        if !cfg!(target_os = "android") {
+            return None;
+        }
+        let needed = daemon::active_browse_count() + daemon::active_registration_count() > 0;
+        if !needed {
+            self.release();
+            self.failed = false;
+            return None;
+        }
+        if self.lock.is_some() || self.failed {
+            return None;
+        }
+        match acquire() {
+            Ok(lock) => {
+                self.lock = Some(lock);
+                None
+            }
+            Err(e) => {
+                self.failed = true;
+                Some(e)
+            }
+        }
+    }
+
+    /// Releases the lock so the next [`update`](Self::update) acquires a new
+    /// one, retrying even after a failure.
+    pub fn reacquire(&mut self) {
+        self.release();
+        self.failed = false;
+    }
+
+    /// Releases the lock if held.
+    pub fn release(&mut self) {
+        if let Some(lock) = self.lock.take() {
+            lock.call("release", &[]);
+        }
+    }
+}
+
+/// Creates and acquires a non-reference-counted lock (Android would
+/// otherwise need one `release()` per `acquire()`).
+fn acquire() -> Result<Variant, String> {
+    let runtime = Engine::singleton().get_singleton("AndroidRuntime").ok_or_else(|| {
+        format!(
+            "{MULTICAST_LOCK_ERROR}: the AndroidRuntime singleton is missing (it needs a \
+             Godot 4.2+ Android template); acquire a WifiManager.MulticastLock yourself \
+             or discovery receives nothing"
+        )
+    })?;
+    let context = runtime.upcast::<Object>().call("getApplicationContext", &[]);
+    let granted = context.call("checkSelfPermission", &[PERMISSION.to_variant()]);
+    // PackageManager.PERMISSION_GRANTED
+    if granted.try_to::<i64>().ok() != Some(0) {
+        return Err(format!(
+            "{MULTICAST_LOCK_ERROR}: the app lacks the {PERMISSION} permission, so Android \
+             drops every mDNS packet. Tick \"Change Wifi Multicast State\" under \
+             Permissions in the Android export preset, or add \
+             <uses-permission android:name=\"{PERMISSION}\"/> to the manifest"
+        ));
+    }
+    let wifi = context.call("getSystemService", &["wifi".to_variant()]);
+    if wifi.is_nil() {
+        return Err(format!("{MULTICAST_LOCK_ERROR}: this device has no WifiManager"));
+    }
+    let lock = wifi.call("createMulticastLock", &[LOCK_TAG.to_variant()]);
+    if lock.is_nil() {
+        return Err(format!("{MULTICAST_LOCK_ERROR}: WifiManager.createMulticastLock() failed"));
+    }
+    lock.call("setReferenceCounted", &[false.to_variant()]);
+    lock.call("acquire", &[]);
+    if !lock.call("isHeld", &[]).booleanize() {
+        return Err(format!("{MULTICAST_LOCK_ERROR}: MulticastLock.acquire() did not take"));
+    }
+    Ok(lock)
+}
@@ -0,0 +1,174 @@
+//! Instance-name suffixes behind `MdnsAdvertiser.set_name_suffix_scheme()`,
+//! for fleets that advertise the same label from many machines, the host
+//! name used when the OS reports none or is overridden, and `LanLobby`'s
+//! default service type.
+//!
+//! Free of Godot types so the generated shapes can be tested without an
+//! engine (see `tests/naming.rs`).
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+
+/// Longest DNS label, and so the longest instance name `mdns-sd` accepts.
+pub const MAX_INSTANCE_LEN: usize = 63;
+
+/// Source of [`NameSuffix::Counter`] suffixes; shared by every advertiser of
+/// the process so no two get the same number.
+static COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Start of the host name used when the OS reports none; see
+/// [`hostname_or_fallback`].
+pub const FALLBACK_HOSTNAME: &str = "unknown-host";
+
+/// Service name of `LanLobby` for a project whose name has no letter in it.
+pub const FALLBACK_LOBBY_SERVICE: &str = "godot-lobby";
+
+/// Longest service name, the label after the `_` (RFC 6763 §7.2).
+pub const MAX_SERVICE_NAME_LEN: usize = 15;
+
+/// What to append to an instance name before it is registered.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NameSuffix {
+    /// Register the name as given.
+    #[default]
+    None,
+    /// Four random hex digits: `"Server (3fa9)"`.
+    Random,
+    /// The short host name (up to the first dot): `"Server (build-07)"`.
+    Hostname,
+    /// A per-process counter, starting at 1: `"Server (2)"`.
+    Counter,
+}
+
+impl NameSuffix {
+    /// Returns `name` with this scheme's suffix, in the `"name (suffix)"` form
+    /// DNS-SD uses when renaming on conflicts.  `hostname` is only used by
+    /// [`NameSuffix::Hostname`]; a domain part is dropped.
+    ///
+    /// `name` is shortened (on a character boundary) if the result would
+    /// exceed [`MAX_INSTANCE_LEN`] bytes, so the suffix always survives.
+    pub fn apply(self, name: &str, hostname: &str) -> String {
+        let suffix = match self {
+            Self::None => return name.to_string(),
+            Self::Random => format!("{:04x}", random_u16()),
+            Self::Hostname => hostname.split('.').next().unwrap_or_default().to_string(),
+            Self::Counter => COUNTER.fetch_add(1, Ordering::Relaxed).to_string(),
+        };
+        if suffix.is_empty() {
+            return name.to_string();
+        }
+        let tail = format!(" ({suffix})");
+        let room = MAX_INSTANCE_LEN.saturating_sub(tail.len());
+        let mut cut = name.len().min(room);
+        while !name.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        format!("{}{tail}", name[..cut].trim_end())
+    }
+}
+
+/// Returns `os_hostname` unchanged, or [`fallback_hostname`] if the OS could
+/// not report one (or reported an empty name).
+pub fn hostname_or_fallback(os_hostname: Option<String>) -> String {
+    match os_hostname {
+        Some(name) if !name.trim().is_empty() => name,
+        _ => fallback_hostname(),
+    }
+}
+
+/// The host name of a machine whose OS reports none, e.g. `"unknown-host-3fa9c2e1"`.
+///
+/// A bare `unknown-host` would make every such device advertise
+/// `unknown-host.local.` and answer for the others' address records, so eight
+/// hex digits are appended.  They are drawn once per process, so every
+/// advertiser of the game agrees on the name while it runs.
+pub fn fallback_hostname() -> String {
+    static NAME: OnceLock<String> = OnceLock::new();
+    NAME.get_or_init(|| fallback_hostname_for(process_seed())).clone()
+}
+
+/// The fallback host name for `seed`; different seeds give different names
+/// unless their low 32 bits match.
+pub fn fallback_hostname_for(seed: u64) -> String {
+    format!("{FALLBACK_HOSTNAME}-{:08x}", seed as u32)
+}
+
+/// The service type `LanLobby` browses and advertises for a project called
+/// `project_name`: `"My Game!"` gives `"_my-game._udp.local."`.
+///
+/// Letters and digits are kept, lowercased; every other run of characters
+/// becomes one hyphen, and the result is cut to the 15 characters a service
+/// name may have.  A name without an ASCII letter gives
+/// [`FALLBACK_LOBBY_SERVICE`], since a service name needs one.
+pub fn lobby_service_type(project_name: &str) -> String {
+    let mut name = String::new();
+    for c in project_name.chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c.to_ascii_lowercase());
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+    name.truncate(MAX_SERVICE_NAME_LEN);
+    let name = name.trim_end_matches('-');
+    let name = if name.chars().any(|c| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        FALLBACK_LOBBY_SERVICE
+    };
+    format!("_{name}._udp.local.")
+}
+
+/// Checks a host name given to `MdnsAdvertiser.set_custom_host_fqdn()` and
+/// returns it lowercased with its trailing dot, e.g. `"Arcade-01.local"`
+/// gives `"arcade-01.local."`.
+///
+/// The name needs at least two labels of letters, digits and inner hyphens,
+/// 63 bytes each, and 253 bytes in all.  It must end in `.local.`, since
+/// `mdns-sd` refuses to register any other host name.
+pub fn check_host_fqdn(fqdn: &str) -> Result<String, String> {
+    let fqdn = fqdn.trim().to_ascii_lowercase();
+    let name = fqdn.strip_suffix('.').unwrap_or(&fqdn);
+    if name.is_empty() || name.len() > 253 {
+        return Err(format!("\"{fqdn}\" must be 1 to 253 bytes long"));
+    }
+    let labels: Vec<&str> = name.split('.').collect();
+    if labels.len() < 2 {
+        return Err(format!("\"{fqdn}\" is not fully qualified"));
+    }
+    for label in &labels {
+        let valid = (1..=MAX_INSTANCE_LEN).contains(&label.len())
+            && label.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            && !label.starts_with('-')
+            && !label.ends_with('-');
+        if !valid {
+            return Err(format!("\"{fqdn}\": \"{label}\" is not a valid DNS label"));
+        }
+    }
+    if labels.last() != Some(&"local") {
+        return Err(format!("\"{fqdn}\" is not under .local. (mdns-sd registers no other)"));
+    }
+    Ok(format!("{name}."))
+}
+
+/// Random bits from the per-process hash seed, the process ID and the clock.
+fn process_seed() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(std::process::id());
+    hasher.write_u16(random_u16());
+    hasher.finish()
+}
+
+/// 16 random bits from the standard library's per-process hash seed, mixed
+/// with the clock so consecutive calls differ.
+fn random_u16() -> u16 {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    hasher.write_u128(nanos);
+    hasher.finish() as u16
+}
@@ -0,0 +1,101 @@
+//! Interface monitor behind `MdnsManager.network_changed`.
+//!
+//! Docking a laptop, toggling airplane mode or joining another WiFi changes
+//! which interfaces are up.  `mdns-sd` follows such changes itself, but only
+//! for its own sockets; nothing told the game.  The shared layer re-enumerates
+//! the interfaces every few seconds, compares the result with the previous
+//! snapshot and reports the difference, so every feature that reacts to the
+//! network hangs off this one poll instead of running its own.
+//!
+//! Free of Godot types so it can be tested without an engine
+//! (see `tests/netwatch.rs`).
+
+use crate::interfaces::LocalInterface;
+use std::collections::BTreeSet;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+/// Default time between two enumerations.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+/// Addresses that appeared or went away between two snapshots, each list
+/// sorted by interface name and then address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkChange {
+    pub added: Vec<LocalInterface>,
+    pub removed: Vec<LocalInterface>,
+}
+
+/// The last snapshot and when to take the next one.
+#[derive(Debug)]
+pub struct NetworkMonitor {
+    /// `None` turns the monitor off.
+    interval: Option<Duration>,
+    next_poll: Option<Instant>,
+    /// The interfaces of the previous poll; `None` until the first one.
+    snapshot: Option<BTreeSet<(String, IpAddr, bool)>>,
+}
+
+impl Default for NetworkMonitor {
+    fn default() -> Self {
+        Self {
+            interval: Some(DEFAULT_POLL_INTERVAL),
+            next_poll: None,
+            snapshot: None,
+        }
+    }
+}
+
+impl NetworkMonitor {
+    /// The time between two enumerations, or `None` while off.
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    /// Changes the time between two enumerations; `None` turns the monitor
+    /// off and forgets the snapshot, so turning it back on reports nothing
+    /// that happened in between.
+    pub fn set_interval(&mut self, interval: Option<Duration>) {
+        self.interval = interval;
+        self.next_poll = None;
+        if interval.is_none() {
+            self.snapshot = None;
+        }
+    }
+
+    /// Enumerates the interfaces if the interval has passed since the last
+    /// poll, and reports what changed.  The first poll only takes the
+    /// snapshot.
+    pub fn poll(&mut self, now: Instant) -> Option<NetworkChange> {
+        let interval = self.interval?;
+        if self.next_poll.is_some_and(|due| now < due) {
+            return None;
+        }
+        self.next_poll = Some(now + interval);
+        self.observe(LocalInterface::enumerate())
+    }
+
+    /// Replaces the snapshot with `interfaces` and returns the difference, or
+    /// `None` if nothing changed or there was no snapshot yet.
+    pub fn observe(&mut self, interfaces: Vec<LocalInterface>) -> Option<NetworkChange> {
+        let current: BTreeSet<(String, IpAddr, bool)> = interfaces
+            .into_iter()
+            .map(|i| (i.name, i.ip, i.loopback))
+            .collect();
+        let previous = self.snapshot.replace(current.clone())?;
+        let listed = |set: &BTreeSet<_>, other: &BTreeSet<_>| -> Vec<LocalInterface> {
+            set.difference(other)
+                .map(|(name, ip, loopback): &(String, IpAddr, bool)| LocalInterface {
+                    name: name.clone(),
+                    ip: *ip,
+                    loopback: *loopback,
+                })
+                .collect()
+        };
+        let change = NetworkChange {
+            added: listed(&current, &previous),
+            removed: listed(&previous, &current),
+        };
+        (!change.added.is_empty() || !change.removed.is_empty()).then_some(change)
+    }
+}
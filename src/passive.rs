@@ -0,0 +1,283 @@
+//! Opt-in passive capture of mDNS announcements, behind
+//! `MdnsManager.enable_passive_capture()` / `get_overheard_services()`.
+//!
+//! `mdns-sd` does not expose its record cache, and its cache-only browse
+//! needs the service type up front and would take over the single listener
+//! per type.  So this runs a separate, receive-only socket on the mDNS group
+//! and keeps a table of the instances it hears announced (PTR) and where they
+//! live (SRV).  It never sends anything: no extra multicast traffic.
+//!
+//! The packet parsing is free of Godot types and socket state so it can be
+//! tested without a network (see `tests/passive.rs`).
+
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+const TYPE_PTR: u16 = 12;
+const TYPE_SRV: u16 = 33;
+
+/// PTR owner that lists service *types* rather than instances (RFC 6763 §9).
+const SERVICE_TYPE_ENUMERATION: &str = "_services._dns-sd._udp.local.";
+
+/// Compression pointers followed per name before giving up on a looping packet.
+const MAX_POINTER_HOPS: usize = 32;
+
+/// A resource record this module cares about.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Record {
+    /// `name` (a service type) → `target` (an instance fullname).
+    Ptr { name: String, target: String, ttl: u32 },
+    /// `name` (an instance fullname) → `target` (a hostname) and `port`.
+    Srv {
+        name: String,
+        target: String,
+        port: u16,
+        ttl: u32,
+    },
+}
+
+/// Parses every PTR and SRV record in the answer, authority and additional
+/// sections of an mDNS response.  Queries yield no records.
+pub fn parse_records(packet: &[u8]) -> Result<Vec<Record>, String> {
+    let header = packet.get(..12).ok_or("packet shorter than a DNS header")?;
+    let flags = u16::from_be_bytes([header[2], header[3]]);
+    if flags & 0x8000 == 0 {
+        return Ok(Vec::new());
+    }
+    let count = |at: usize| u16::from_be_bytes([header[at], header[at + 1]]) as usize;
+    let questions = count(4);
+    let records = count(6) + count(8) + count(10);
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = read_name(packet, pos)?.1 + 4;
+    }
+    let mut out = Vec::new();
+    for _ in 0..records {
+        let (name, after_name) = read_name(packet, pos)?;
+        let fixed = packet
+            .get(after_name..after_name + 10)
+            .ok_or("truncated resource record")?;
+        let rtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+        let ttl = u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]);
+        let rdlen = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let rdata = after_name + 10;
+        if packet.len() < rdata + rdlen {
+            return Err("truncated record data".to_string());
+        }
+        match rtype {
+            TYPE_PTR => out.push(Record::Ptr {
+                name,
+                target: read_name(packet, rdata)?.0,
+                ttl,
+            }),
+            TYPE_SRV if rdlen >= 6 => out.push(Record::Srv {
+                name,
+                target: read_name(packet, rdata + 6)?.0,
+                port: u16::from_be_bytes([packet[rdata + 4], packet[rdata + 5]]),
+                ttl,
+            }),
+            _ => {}
+        }
+        pos = rdata + rdlen;
+    }
+    Ok(out)
+}
+
+/// Reads a possibly compressed name at `pos`, returning it with a trailing
+/// dot and the position just past it in the original stream.
+fn read_name(packet: &[u8], mut pos: usize) -> Result<(String, usize), String> {
+    let mut name = String::new();
+    let mut end = None;
+    let mut hops = 0;
+    loop {
+        let len = *packet.get(pos).ok_or("name runs past the packet")? as usize;
+        match len {
+            0 => {
+                if name.is_empty() {
+                    name.push('.');
+                }
+                return Ok((name, end.unwrap_or(pos + 1)));
+            }
+            l if l & 0xC0 == 0xC0 => {
+                let low = *packet.get(pos + 1).ok_or("truncated compression pointer")? as usize;
+                end.get_or_insert(pos + 2);
+                hops += 1;
+                if hops > MAX_POINTER_HOPS {
+                    return Err("compression pointer loop".to_string());
+                }
+                pos = ((l & 0x3F) << 8) | low;
+            }
+            l if l & 0xC0 != 0 => return Err(format!("unsupported label type {l:#x}")),
+            l => {
+                let label = packet.get(pos + 1..pos + 1 + l).ok_or("label runs past the packet")?;
+                name.push_str(&String::from_utf8_lossy(label));
+                name.push('.');
+                pos += 1 + l;
+            }
+        }
+    }
+}
+
+/// One instance heard on the network.
+#[derive(Debug, Clone)]
+pub struct OverheardService {
+    /// e.g. `"_http._tcp.local."`
+    pub service_type: String,
+    /// e.g. `"Printer._http._tcp.local."`
+    pub fullname: String,
+    /// From the instance's SRV record, once heard.
+    pub hostname: Option<String>,
+    pub port: Option<u16>,
+    pub last_seen: Instant,
+}
+
+/// Instances heard so far, keyed by lower-cased fullname.
+#[derive(Debug, Default)]
+pub struct Overheard {
+    services: BTreeMap<String, OverheardService>,
+    /// SRV data heard before the matching PTR, keyed like `services`.
+    orphan_srv: BTreeMap<String, (String, u16)>,
+}
+
+impl Overheard {
+    /// Applies the records of one packet.  A PTR with TTL 0 (a goodbye)
+    /// forgets the instance.
+    pub fn record(&mut self, records: Vec<Record>, now: Instant) {
+        for record in records {
+            match record {
+                Record::Ptr { name, target, ttl } => {
+                    if name.eq_ignore_ascii_case(SERVICE_TYPE_ENUMERATION) {
+                        continue;
+                    }
+                    let key = target.to_ascii_lowercase();
+                    if ttl == 0 {
+                        self.services.remove(&key);
+                        continue;
+                    }
+                    let (hostname, port) = self
+                        .orphan_srv
+                        .remove(&key)
+                        .map_or((None, None), |(host, port)| (Some(host), Some(port)));
+                    let entry = self.services.entry(key).or_insert(OverheardService {
+                        service_type: name,
+                        fullname: target,
+                        hostname,
+                        port,
+                        last_seen: now,
+                    });
+                    entry.last_seen = now;
+                }
+                Record::Srv {
+                    name, target, port, ..
+                } => {
+                    let key = name.to_ascii_lowercase();
+                    match self.services.get_mut(&key) {
+                        Some(entry) => {
+                            entry.hostname = Some(target);
+                            entry.port = Some(port);
+                            entry.last_seen = now;
+                        }
+                        None => {
+                            self.orphan_srv.insert(key, (target, port));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Every instance heard, sorted by fullname.
+    pub fn snapshot(&self) -> Vec<OverheardService> {
+        self.services.values().cloned().collect()
+    }
+}
+
+/// The receive-only socket and its thread.
+pub struct PassiveCapture {
+    stop: Arc<AtomicBool>,
+    table: Arc<Mutex<Overheard>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// How often the capture thread checks whether it should stop.
+const RECV_POLL: Duration = Duration::from_millis(200);
+
+const MDNS_GROUP_V4: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+
+impl PassiveCapture {
+    /// Binds `port` (shared with the daemon via address/port reuse), joins the
+    /// IPv4 mDNS group on the default interface and starts recording.
+    ///
+    /// Unicast packets to `port` reach only one of the sockets sharing it, so
+    /// this socket may take replies meant for the daemon; they are recorded
+    /// here but the daemon never sees them.
+    pub fn start(port: u16) -> Result<Self, String> {
+        use socket2::{Domain, Protocol, Socket, Type};
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))
+            .map_err(|e| format!("passive capture socket: {e}"))?;
+        socket
+            .set_reuse_address(true)
+            .map_err(|e| format!("passive capture SO_REUSEADDR: {e}"))?;
+        #[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+        socket
+            .set_reuse_port(true)
+            .map_err(|e| format!("passive capture SO_REUSEPORT: {e}"))?;
+        socket
+            .bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)).into())
+            .map_err(|e| format!("passive capture bind to port {port}: {e}"))?;
+        socket
+            .join_multicast_v4(&MDNS_GROUP_V4, &Ipv4Addr::UNSPECIFIED)
+            .map_err(|e| format!("passive capture multicast join: {e}"))?;
+        socket
+            .set_read_timeout(Some(RECV_POLL))
+            .map_err(|e| format!("passive capture read timeout: {e}"))?;
+        let socket: std::net::UdpSocket = socket.into();
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let table = Arc::new(Mutex::new(Overheard::default()));
+        let thread = {
+            let (stop, table) = (stop.clone(), table.clone());
+            std::thread::Builder::new()
+                .name("godot-mdns-passive".to_string())
+                .spawn(move || {
+                    let mut buf = [0u8; 9000];
+                    while !stop.load(Ordering::Relaxed) {
+                        let Ok((len, _)) = socket.recv_from(&mut buf) else {
+                            continue;
+                        };
+                        // Malformed packets are someone else's problem; skip them.
+                        if let Ok(records) = parse_records(&buf[..len]) {
+                            if let Ok(mut table) = table.lock() {
+                                table.record(records, Instant::now());
+                            }
+                        }
+                    }
+                })
+                .map_err(|e| format!("passive capture thread: {e}"))?
+        };
+        Ok(Self {
+            stop,
+            table,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn snapshot(&self) -> Vec<OverheardService> {
+        self.table.lock().map(|t| t.snapshot()).unwrap_or_default()
+    }
+}
+
+impl Drop for PassiveCapture {
+    /// Stops the thread; takes at most one `RECV_POLL`.
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! `MdnsPeerList` — an always-current `Array` of the services a browser lists.
+
+use crate::browser::MdnsBrowser;
+use godot::prelude::*;
+
+/// Browser signals after which the list may have changed.  Their arities
+/// differ, hence the closures rather than a `#[func]` per signal.
+/// `tree_exited` covers a browser leaving the tree, which stops it without
+/// a signal of its own.
+const WATCHED_SIGNALS: [&str; 8] = [
+    "service_discovered",
+    "service_updated",
+    "service_removed",
+    "service_discovered_compact",
+    "services_cleared",
+    "daemon_reset",
+    "recovered",
+    "tree_exited",
+];
+
+/// Follows an `MdnsBrowser` and keeps the list of its services, for code
+/// that only wants "the servers there are now" without tracking
+/// `service_discovered` / `service_updated` / `service_removed` itself.
+///
+/// ## GDScript example
+/// ```gdscript
+/// var peers := MdnsPeerList.create($MdnsBrowser)
+/// peers.changed.connect(func(): server_list.show(peers.get_peers()))
+/// $MdnsBrowser.browse("_mygame._udp.local.")
+/// ```
+///
+/// GDExtension classes cannot take constructor arguments, so
+/// `MdnsPeerList.new(browser)` is spelled `MdnsPeerList.create(browser)`
+/// (or `MdnsPeerList.new()` then `attach(browser)`).
+///
+/// The list is the browser's own (`get_discovered_services()`): one entry
+/// per service, `set_dedup_by_host()` applied, and services dropped when
+/// they say goodbye, their records expire, or the browser's
+/// `set_stale_eviction_ms()` window passes.  That window is off by default,
+/// and record TTLs run to 75 minutes, so a host that vanishes without a
+/// goodbye (a crash, a pulled cable) stays listed that long unless the
+/// browser sets one.  `stop_browsing()` and the browser leaving the tree
+/// empty the list.  However many services change in a frame, `changed` is
+/// emitted once, at the end of the frame, and only if the list differs
+/// from the one last announced.  The list holds no
+/// reference to itself through the browser: dropping the last reference
+/// frees it and disconnects it.
+#[derive(GodotClass)]
+#[class(base = RefCounted)]
+pub struct MdnsPeerList {
+    browser: Option<Gd<MdnsBrowser>>,
+    /// Connected callables, by signal, to disconnect in `detach()`.
+    connections: Vec<(&'static str, Callable)>,
+    /// The list as of the last `changed`.
+    announced: Array<VarDictionary>,
+    /// Whether a comparison is already queued for the end of the frame.
+    refresh_queued: bool,
+    base: Base<RefCounted>,
+}
+
+#[godot_api]
+impl IRefCounted for MdnsPeerList {
+    fn init(base: Base<RefCounted>) -> Self {
+        Self {
+            browser: None,
+            connections: Vec::new(),
+            announced: Array::new(),
+            refresh_queued: false,
+            base,
+        }
+    }
+}
+
+#[godot_api]
+impl MdnsPeerList {
+    // ── Signals ──────────────────────────────────────────────────────────────
+
+    /// Emitted at most once per frame when `get_peers()` changed: a service
+    /// was added, removed, or re-announced with different records.
+    #[signal]
+    fn changed();
+
+    // ── Methods ──────────────────────────────────────────────────────────────
+
+    /// A list following `browser`; the same as `new()` then `attach()`.
+    #[func]
+    fn create(browser: Gd<MdnsBrowser>) -> Gd<MdnsPeerList> {
+        let mut list = MdnsPeerList::new_gd();
+        list.bind_mut().attach(browser);
+        list
+    }
+
+    /// Follow `browser`, leaving the previous one.  Services it already
+    /// lists are announced by a `changed` at the end of the frame.
+    #[func]
+    fn attach(&mut self, mut browser: Gd<MdnsBrowser>) {
+        self.detach();
+        let id = self.base().instance_id();
+        for signal in WATCHED_SIGNALS {
+            let callable = Callable::from_fn("mdns_peer_list_changed", move |_args: &[&Variant]| {
+                if let Ok(mut list) = Gd::<MdnsPeerList>::try_from_instance_id(id) {
+                    list.bind_mut().queue_refresh();
+                }
+                Variant::nil()
+            });
+            browser.connect(signal, &callable);
+            self.connections.push((signal, callable));
+        }
+        self.browser = Some(browser);
+        self.queue_refresh();
+    }
+
+    /// Stop following the browser.  The list is empty afterwards; `changed`
+    /// is emitted at the end of the frame if it was not.
+    #[func]
+    fn detach(&mut self) {
+        let Some(mut browser) = self.browser.take() else {
+            return;
+        };
+        if browser.is_instance_valid() {
+            for (signal, callable) in self.connections.drain(..) {
+                browser.disconnect(signal, &callable);
+            }
+        }
+        self.connections.clear();
+        self.queue_refresh();
+    }
+
+    /// The browser followed, or `null`.
+    #[func]
+    fn get_browser(&self) -> Option<Gd<MdnsBrowser>> {
+        self.live_browser()
+    }
+
+    /// The current services, sorted by fullname, each a `Dictionary` as in
+    /// `MdnsBrowser.get_discovered_services()`.  Empty while detached.
+    #[func]
+    fn get_peers(&self) -> Array<VarDictionary> {
+        self.live_browser()
+            .map(|browser| browser.bind().get_discovered_services())
+            .unwrap_or_default()
+    }
+
+    /// Number of entries in `get_peers()`.
+    #[func]
+    fn size(&self) -> i64 {
+        self.get_peers().len() as i64
+    }
+
+    // ── Internal helpers ─────────────────────────────────────────────────────
+
+    /// The browser, unless it has been freed.
+    fn live_browser(&self) -> Option<Gd<MdnsBrowser>> {
+        self.browser.clone().filter(|browser| browser.is_instance_valid())
+    }
+
+    /// Compares the list with the announced one at the end of the frame,
+    /// once however many signals arrive meanwhile.
+    fn queue_refresh(&mut self) {
+        if self.refresh_queued {
+            return;
+        }
+        self.refresh_queued = true;
+        let id = self.base().instance_id();
+        let refresh = Callable::from_fn("mdns_peer_list_refresh", move |_args: &[&Variant]| {
+            if let Ok(mut list) = Gd::<MdnsPeerList>::try_from_instance_id(id) {
+                list.bind_mut().refresh();
+            }
+            Variant::nil()
+        });
+        refresh.call_deferred(&[]);
+    }
+
+    /// Emits `changed` if the list differs from the announced one.
+    fn refresh(&mut self) {
+        self.refresh_queued = false;
+        let peers = self.get_peers();
+        if peers == self.announced {
+            return;
+        }
+        self.announced = peers;
+        self.base_mut().emit_signal("changed", &[]);
+    }
+}
+
+impl Drop for MdnsPeerList {
+    fn drop(&mut self) {
+        if let Some(mut browser) = self.browser.take() {
+            if browser.is_instance_valid() {
+                for (signal, callable) in self.connections.drain(..) {
+                    browser.disconnect(signal, &callable);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Whether mDNS can work here at all, behind `MdnsManager.is_supported()`.
+//!
+//! The web export has no UDP sockets, let alone multicast, and threads only
+//! with special server headers.  The classes still register there so scenes
+//! load unchanged, but the daemon layer refuses to create anything and nodes
+//! report [`UNSUPPORTED_PLATFORM_ERROR`] straight away.  WebAssembly builds
+//! are known to be unsupported at compile time; the extension marks other
+//! hosts unsupported at start-up from the engine's feature tags.
+//!
+//! Free of Godot types so it can be tested without an engine
+//! (see `tests/platform.rs`).
+
+use std::sync::OnceLock;
+
+/// Leading word of the error nodes report on an unsupported platform.
+pub const UNSUPPORTED_PLATFORM_ERROR: &str = "unsupported_platform";
+
+/// Name of the platform found unsupported at run time, if any.
+static UNSUPPORTED: OnceLock<String> = OnceLock::new();
+
+/// Marks the running platform unsupported, e.g. `"web"`.  The first call wins.
+pub fn mark_unsupported(platform: &str) {
+    let _ = UNSUPPORTED.set(platform.to_string());
+}
+
+/// Returns `true` if mDNS sockets can be opened on this platform.
+pub fn is_supported() -> bool {
+    !cfg!(target_family = "wasm") && UNSUPPORTED.get().is_none()
+}
+
+/// The error to report instead of creating a daemon or socket; `None` where
+/// mDNS is supported.
+pub fn unsupported_error() -> Option<String> {
+    if is_supported() {
+        return None;
+    }
+    let platform = UNSUPPORTED.get().map_or("WebAssembly", String::as_str);
+    Some(format!(
+        "{UNSUPPORTED_PLATFORM_ERROR}: mDNS needs UDP multicast sockets, which {platform} does not provide"
+    ))
+}
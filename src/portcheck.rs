@@ -0,0 +1,220 @@
+//! One-off check of the mDNS port, run just before the shared daemon is first
+//! created and reported by `MdnsManager.get_startup_diagnostics()`.
+//!
+//! `mdns-sd` binds with `SO_REUSEADDR` (plus `SO_REUSEPORT` on Unix), so it
+//! coexists with Avahi, Bonjour and other games that do the same.  A
+//! responder that holds the port *exclusively* leaves the daemon half-working
+//! (on Windows its bind may even "succeed" without receiving anything), and
+//! nothing else tells the player why.  Two binds show which case applies:
+//! a plain one succeeds only if nobody holds the port, and one with the
+//! daemon's own socket options fails only if the holder refuses to share.
+//!
+//! On Linux the check also reads Avahi's configuration: sharing the port is
+//! not enough when `avahi-daemon` runs as a reflector or cache only, and
+//! the player is told how to fix those setups.
+//!
+//! Free of Godot types so it can be tested without an engine
+//! (see `tests/portcheck.rs`).
+
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::path::Path;
+
+/// What the probe found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortReport {
+    pub port: u16,
+    /// Nobody else had the port bound.
+    pub port_free: bool,
+    /// A bind with the daemon's socket options worked: the port can be shared.
+    pub shareable: bool,
+    /// The OS error of the failed shared bind, if any.
+    pub bind_error: String,
+    /// Well-known mDNS responders that look installed or running.
+    pub responders: Vec<String>,
+    /// Avahi's state, if it is installed (Linux only).
+    pub avahi: Option<AvahiReport>,
+}
+
+/// What `avahi-daemon` is doing, from its pid file and configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AvahiReport {
+    /// The pid file names a live process.
+    pub running: bool,
+    /// `enable-reflector=yes`: Avahi repeats every mDNS packet on its other
+    /// interfaces.
+    pub reflector: bool,
+    /// `disable-publishing=yes`: Avahi only browses and caches.
+    pub publishing_disabled: bool,
+    /// `disable-user-service-publishing=yes`: Avahi's own clients may not
+    /// publish.  This extension answers on its own socket and is unaffected.
+    pub user_publishing_disabled: bool,
+}
+
+impl AvahiReport {
+    /// The warning for a running Avahi set up in a way that makes our
+    /// registrations answer inconsistently, with the remedy; `None` otherwise.
+    pub fn warning(&self) -> Option<String> {
+        if !self.running {
+            return None;
+        }
+        let mut problems = Vec::new();
+        if self.reflector {
+            problems.push(
+                "avahi-daemon runs as a reflector (enable-reflector=yes), so peers on \
+                 other networks see our services with addresses they cannot reach and \
+                 our own announcements come back as conflicts. Set enable-reflector=no, \
+                 or limit it with reflect-filters",
+            );
+        }
+        if self.publishing_disabled {
+            problems.push(
+                "avahi-daemon runs cache only (disable-publishing=yes), and its known-answer \
+                 lists can keep other programs on this machine from seeing our services. \
+                 Set disable-publishing=no",
+            );
+        }
+        if problems.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "{}. Edit {AVAHI_CONF} and restart avahi-daemon.",
+            problems.join("; ")
+        ))
+    }
+}
+
+impl PortReport {
+    /// The warning to show when discovery is likely to suffer, if any.
+    pub fn warning(&self) -> Option<String> {
+        let avahi = self.avahi.as_ref().and_then(AvahiReport::warning);
+        match (self.port_warning(), avahi) {
+            (Some(port), Some(avahi)) => Some(format!("{port} {avahi}")),
+            (port, avahi) => port.or(avahi),
+        }
+    }
+
+    /// The part of [`warning`](Self::warning) about the port itself.
+    fn port_warning(&self) -> Option<String> {
+        if self.shareable {
+            return None;
+        }
+        let holders = if self.responders.is_empty() {
+            "another program".to_string()
+        } else {
+            self.responders.join(" or ")
+        };
+        Some(format!(
+            "UDP port {} is held exclusively (probably by {holders}): {}. \
+             mDNS discovery will miss packets until that program is stopped \
+             or shares the port.",
+            self.port, self.bind_error
+        ))
+    }
+}
+
+/// Probes `port` on `0.0.0.0`.  Takes a few milliseconds; the sockets are
+/// closed before returning.
+pub fn probe(port: u16) -> PortReport {
+    let addr: SockAddr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)).into();
+    let port_free = bind(&addr, false).is_ok();
+    let shared = if port_free { Ok(()) } else { bind(&addr, true) };
+    PortReport {
+        port,
+        port_free,
+        shareable: shared.is_ok(),
+        bind_error: shared.err().map(|e| e.to_string()).unwrap_or_default(),
+        responders: known_responders(),
+        avahi: avahi_report(),
+    }
+}
+
+/// Avahi's configuration file.
+const AVAHI_CONF: &str = "/etc/avahi/avahi-daemon.conf";
+
+/// Where `avahi-daemon` writes its pid, depending on the distribution.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+const AVAHI_PID_FILES: [&str; 2] = ["/run/avahi-daemon/pid", "/var/run/avahi-daemon/pid"];
+
+/// Reads the settings that matter to us from the contents of
+/// `avahi-daemon.conf`.  `running` is left `false`.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub fn parse_avahi_conf(text: &str) -> AvahiReport {
+    let mut report = AvahiReport::default();
+    let mut section = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_ascii_lowercase();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let yes = value.trim().eq_ignore_ascii_case("yes");
+        match (section.as_str(), key.trim()) {
+            ("reflector", "enable-reflector") => report.reflector = yes,
+            ("publish", "disable-publishing") => report.publishing_disabled = yes,
+            ("publish", "disable-user-service-publishing") => {
+                report.user_publishing_disabled = yes
+            }
+            _ => {}
+        }
+    }
+    report
+}
+
+/// Avahi's state, or `None` if it has neither a configuration file nor a
+/// pid file here.
+#[cfg(target_os = "linux")]
+fn avahi_report() -> Option<AvahiReport> {
+    let running = AVAHI_PID_FILES.iter().any(|path| {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|pid| pid.trim().parse::<u32>().ok())
+            .is_some_and(|pid| Path::new(&format!("/proc/{pid}")).exists())
+    });
+    let conf = std::fs::read_to_string(AVAHI_CONF).ok();
+    if conf.is_none() && !running {
+        return None;
+    }
+    Some(AvahiReport { running, ..parse_avahi_conf(&conf.unwrap_or_default()) })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn avahi_report() -> Option<AvahiReport> {
+    None
+}
+
+/// Binds a UDP socket to `addr`, with `mdns-sd`'s reuse options if `reuse`.
+fn bind(addr: &SockAddr, reuse: bool) -> std::io::Result<()> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+    if reuse {
+        socket.set_reuse_address(true)?;
+        #[cfg(unix)]
+        socket.set_reuse_port(true)?;
+    }
+    socket.bind(addr)
+}
+
+/// Names of the well-known responders whose control socket or install
+/// location exists on this machine.
+fn known_responders() -> Vec<String> {
+    let candidates: &[(&str, &str)] = &[
+        ("Avahi", "/run/avahi-daemon/socket"),
+        ("Avahi", "/var/run/avahi-daemon/socket"),
+        ("mDNSResponder", "/var/run/mDNSResponder"),
+        ("Bonjour for Windows", r"C:\Program Files\Bonjour\mDNSResponder.exe"),
+        ("Bonjour for Windows", r"C:\Program Files (x86)\Bonjour\mDNSResponder.exe"),
+    ];
+    let mut found: Vec<String> = Vec::new();
+    for (name, path) in candidates {
+        if Path::new(path).exists() && !found.iter().any(|f| f == name) {
+            found.push(name.to_string());
+        }
+    }
+    found
+}
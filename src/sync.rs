@@ -0,0 +1,26 @@
+//! Poison recovery for the process-global mutexes.
+//!
+//! Free of Godot types so the recovery path can be tested without an engine
+//! (see `tests/sync.rs`).
+
+use std::sync::{Mutex, MutexGuard};
+
+/// Locks `mutex`, recovering it if a thread panicked while holding it.
+///
+/// The poisoned guard is taken over, `recover` resets whatever the panic may
+/// have left half-updated, and the poison flag is cleared so later locks
+/// succeed normally.  Returns the guard and whether recovery ran.
+pub fn lock_or_recover<T>(
+    mutex: &Mutex<T>,
+    recover: impl FnOnce(&mut T),
+) -> (MutexGuard<'_, T>, bool) {
+    match mutex.lock() {
+        Ok(guard) => (guard, false),
+        Err(poisoned) => {
+            let mut guard = poisoned.into_inner();
+            recover(&mut guard);
+            mutex.clear_poison();
+            (guard, true)
+        }
+    }
+}
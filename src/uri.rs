@@ -0,0 +1,73 @@
+//! Connect targets and URIs built from discovery payloads, behind
+//! `MdnsUtil.build_uri()` and `MdnsUtil.extract_host_and_port()`.
+//!
+//! Free of Godot types so the formatting can be tested without an engine
+//! (see `tests/uri.rs`); `MdnsUtil` only reads the payload `Dictionary`.
+
+/// What a discovery payload offers to connect to.
+#[derive(Debug, Default, Clone)]
+pub struct Payload {
+    /// A `best_address` entry, if the payload has one.
+    pub best_address: Option<String>,
+    /// `addresses` (IPv4 first, as the browser emits them), followed by a
+    /// `LanLobby` payload's `address`.
+    pub addresses: Vec<String>,
+    /// `host`, e.g. `"marks-pc.local."`.
+    pub hostname: String,
+    pub port: i64,
+}
+
+/// The host and port to connect to, without brackets or scheme.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    /// An address (`"192.168.1.42"`, `"fe80::1%en0"`) or a host name without
+    /// its trailing dot.
+    pub host: String,
+    pub port: u16,
+}
+
+impl Payload {
+    /// The target: `best_address` if set, else the first address, else the
+    /// host name — or the host name first with `prefer_hostname`.  `None`
+    /// without a valid port or anything to connect to.
+    pub fn target(&self, prefer_hostname: bool) -> Option<Target> {
+        let port = u16::try_from(self.port).ok().filter(|port| *port != 0)?;
+        let hostname = self.hostname.trim().trim_end_matches('.');
+        let address = self
+            .best_address
+            .iter()
+            .chain(&self.addresses)
+            .map(|address| address.trim())
+            .find(|address| !address.is_empty());
+        let host = match (prefer_hostname, address) {
+            (true, _) if !hostname.is_empty() => hostname,
+            (_, Some(address)) => address,
+            _ if !hostname.is_empty() => hostname,
+            _ => return None,
+        };
+        Some(Target { host: host.to_string(), port })
+    }
+}
+
+impl Target {
+    /// `"host:port"`, the host bracketed if it is an IPv6 address
+    /// (`"[fe80::1%en0]:7350"`), as `MdnsBrowser` connect strings are.
+    pub fn authority(&self) -> String {
+        if self.host.contains(':') {
+            format!("[{}]:{}", self.host, self.port)
+        } else {
+            format!("{}:{}", self.host, self.port)
+        }
+    }
+
+    /// `"scheme://host:port"`; a `"://"` already on `scheme` is not doubled,
+    /// and an empty `scheme` gives the bare [`authority`](Self::authority).
+    pub fn uri(&self, scheme: &str) -> String {
+        let scheme = scheme.trim().trim_end_matches("://");
+        if scheme.is_empty() {
+            self.authority()
+        } else {
+            format!("{scheme}://{}", self.authority())
+        }
+    }
+}
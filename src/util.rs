@@ -0,0 +1,93 @@
+//! `MdnsUtil` — static helpers for using discovery results.
+
+use crate::uri::Payload;
+use godot::prelude::*;
+
+/// Turns a discovered service into something to connect to, so every
+/// project does not re-implement address picking and IPv6 bracketing.
+///
+/// ## GDScript example
+/// ```gdscript
+/// func _on_service_discovered(name, host, addresses, port, txt):
+///     var service := $MdnsBrowser.get_service(name)
+///     var ws := WebSocketPeer.new()
+///     ws.connect_to_url(MdnsUtil.build_uri(service, "ws"))
+///
+///     var target := MdnsUtil.extract_host_and_port(service)
+///     var enet := ENetMultiplayerPeer.new()
+///     enet.create_client(target.host, target.port)
+/// ```
+///
+/// Both take a service `Dictionary` as `MdnsBrowser.get_service()` /
+/// `get_discovered_services()` return them, or a `LanLobby` lobby.  The
+/// address used is `best_address` if the dictionary has one, else the first
+/// of `addresses` (IPv4 when there is one), else the host name.  An
+/// IPv6-only service's link-local address keeps its zone (`fe80::1%en0`),
+/// which the socket needs to pick the interface; see
+/// `MdnsBrowser.get_scoped_connect_string()` for the platform differences.
+#[derive(GodotClass)]
+#[class(no_init, base = Object)]
+pub struct MdnsUtil {
+    base: Base<Object>,
+}
+
+#[godot_api]
+impl MdnsUtil {
+    /// `"<scheme>://<host>:<port>"` for `service`, e.g.
+    /// `"ws://192.168.1.42:7350"` or `"ws://[fe80::1%en0]:7350"`, IPv6
+    /// addresses bracketed.  With `prefer_hostname` the host name
+    /// (`"marks-pc.local"`) is used instead of an address when the service
+    /// has one.  An empty `scheme` gives `"<host>:<port>"`.  Returns `""`
+    /// if `service` has no valid port or nothing to connect to.
+    #[func]
+    fn build_uri(
+        service: VarDictionary,
+        scheme: GString,
+        #[opt(default = false)] prefer_hostname: bool,
+    ) -> GString {
+        payload(&service)
+            .target(prefer_hostname)
+            .map(|target| GString::from(&target.uri(&scheme.to_string())))
+            .unwrap_or_default()
+    }
+
+    /// `{ "host": String, "port": int }` for APIs that take them apart, e.g.
+    /// `ENetMultiplayerPeer.create_client()`.  `host` is picked as in
+    /// `build_uri()` and never bracketed.  Returns an empty `Dictionary` if
+    /// `service` has no valid port or nothing to connect to.
+    #[func]
+    fn extract_host_and_port(
+        service: VarDictionary,
+        #[opt(default = false)] prefer_hostname: bool,
+    ) -> VarDictionary {
+        let mut dict = VarDictionary::new();
+        if let Some(target) = payload(&service).target(prefer_hostname) {
+            dict.set("host", target.host.as_str());
+            dict.set("port", target.port as i64);
+        }
+        dict
+    }
+}
+
+/// Reads the entries of a service or lobby `Dictionary` that
+/// [`Payload::target`] uses; missing or mistyped ones are left empty.
+fn payload(service: &VarDictionary) -> Payload {
+    let string = |key: &str| {
+        service
+            .get(key)
+            .and_then(|value| value.try_to::<GString>().ok())
+            .map(|value| value.to_string())
+    };
+    let mut addresses: Vec<String> = service
+        .get("addresses")
+        .and_then(|value| value.try_to::<PackedStringArray>().ok())
+        .map(|addresses| addresses.as_slice().iter().map(GString::to_string).collect())
+        .unwrap_or_default();
+    addresses.extend(string("address"));
+    Payload {
+        best_address: string("best_address"),
+        addresses,
+        hostname: string("host").unwrap_or_default(),
+        port: service.get("port").and_then(|port| port.try_to::<i64>().ok()).unwrap_or(0),
+    }
+}
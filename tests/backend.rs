@@ -0,0 +1,167 @@
+//! Tests for the backend switch behind `MdnsManager.set_backend()` and the
+//! record helpers the system DNS-SD backend builds its events with.
+//!
+//! The Apple backend itself needs mDNSResponder; a stand-in backend checks
+//! the selection here.
+//!
+//! Run with:
+//!   cargo test --test backend
+
+#[allow(dead_code)]
+#[path = "../src/backend.rs"]
+mod backend;
+
+use backend::{Advertisement, Backend, Browse, DnsSd};
+use mdns_sd::{ServiceEvent, ServiceInfo, TxtProperty};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+
+const SERVICE_TYPE: &str = "_mygame._tcp.local.";
+
+/// A backend that reports one `SearchStarted` per browse and counts stops.
+struct StandIn {
+    stopped: &'static AtomicUsize,
+}
+
+impl DnsSd for StandIn {
+    fn browse(&self, service_type: &str) -> Result<Browse, String> {
+        let (sender, receiver) = flume::unbounded();
+        let _ = sender.send(ServiceEvent::SearchStarted(service_type.to_string()));
+        let stopped = self.stopped;
+        Ok(Browse::new(receiver, move || {
+            stopped.fetch_add(1, Ordering::SeqCst);
+        }))
+    }
+
+    fn register(&self, info: &ServiceInfo) -> Result<Advertisement, String> {
+        let (outcome, outcomes) = mpsc::channel();
+        let _ = outcome.send(Ok(info.get_fullname().to_string()));
+        let stopped = self.stopped;
+        Ok(Advertisement::new(outcomes, |_: &ServiceInfo| Ok(()), move || {
+            stopped.fetch_add(1, Ordering::SeqCst);
+        }))
+    }
+}
+
+fn info(name: &str) -> ServiceInfo {
+    ServiceInfo::new(SERVICE_TYPE, name, "host.local.", "", 7350, &[("k", "v")][..])
+        .expect("ServiceInfo::new failed")
+}
+
+#[test]
+fn d0_system_backend_is_selected_only_once_installed() {
+    static STOPPED: AtomicUsize = AtomicUsize::new(0);
+    static STAND_IN: StandIn = StandIn { stopped: &STOPPED };
+
+    assert!(backend::set(Backend::System).is_err());
+    assert_eq!(backend::current(), Backend::MdnsSd);
+    assert!(backend::active_system().is_none());
+
+    backend::install_system(&STAND_IN);
+    backend::set(Backend::System).expect("installed backend is selectable");
+    assert_eq!(backend::current(), Backend::System);
+    let system = backend::active_system().expect("system backend active");
+
+    let browse = system.browse(SERVICE_TYPE).expect("browse");
+    assert!(matches!(
+        browse.receiver().try_recv(),
+        Ok(ServiceEvent::SearchStarted(ty)) if ty == SERVICE_TYPE
+    ));
+    drop(browse);
+    let advertisement = system.register(&info("Lobby")).expect("register");
+    assert_eq!(advertisement.next_outcome(), Some(Ok(format!("Lobby.{SERVICE_TYPE}"))));
+    assert_eq!(advertisement.next_outcome(), None);
+    advertisement.update(&info("Lobby")).expect("update");
+    drop(advertisement);
+    assert_eq!(STOPPED.load(Ordering::SeqCst), 2, "each handle stops once, on drop");
+
+    backend::set(Backend::MdnsSd).expect("bundled daemon is always selectable");
+    assert!(backend::active_system().is_none());
+}
+
+#[test]
+fn d1_service_types_split_into_registration_type_and_domain() {
+    let split = |ty: &str| backend::split_service_type(ty);
+    assert_eq!(split("_mygame._tcp.local."), Some(("_mygame._tcp".into(), "local.".into())));
+    assert_eq!(split("_mygame._udp.local"), Some(("_mygame._udp".into(), "local.".into())));
+    assert_eq!(
+        split("_ctf._sub._mygame._tcp.local."),
+        Some(("_mygame._tcp,_ctf".into(), "local.".into()))
+    );
+    assert_eq!(split("mygame._tcp.local."), None);
+    assert_eq!(split("_mygame._sctp.local."), None);
+    assert_eq!(split("_mygame._tcp."), None);
+}
+
+#[test]
+fn d2_fullnames_match_mdns_sd_and_round_trip() {
+    for name in ["Lobby", "Bob's game v1.2", r"back\slash", "Ünïcode room"] {
+        let fullname = backend::fullname(name, SERVICE_TYPE);
+        assert_eq!(fullname, info(name).get_fullname(), "{name}");
+        assert_eq!(backend::instance_name(&fullname, SERVICE_TYPE).as_deref(), Some(name));
+    }
+    assert_eq!(
+        backend::fullname("Lobby", "_ctf._sub._mygame._tcp.local."),
+        format!("Lobby.{SERVICE_TYPE}")
+    );
+    assert_eq!(backend::instance_name("Lobby._other._tcp.local.", SERVICE_TYPE), None);
+}
+
+#[test]
+fn d3_txt_records_round_trip() {
+    let service = ServiceInfo::new(
+        SERVICE_TYPE,
+        "Lobby",
+        "host.local.",
+        "",
+        7350,
+        vec![
+            TxtProperty::from(("version", "1.0")),
+            TxtProperty::from(("empty", "")),
+            TxtProperty::from("flag"),
+        ],
+    )
+    .expect("ServiceInfo::new failed")
+    .as_resolved_service();
+    let record = backend::encode_txt(&service.txt_properties);
+    assert_eq!(record, b"\x0bversion=1.0\x06empty=\x04flag");
+
+    let decoded = backend::decode_txt(&record);
+    let pairs: Vec<(&str, Option<&[u8]>)> = decoded.iter().map(|p| (p.key(), p.val())).collect();
+    assert_eq!(
+        pairs,
+        [("version", Some(&b"1.0"[..])), ("empty", Some(&b""[..])), ("flag", None)]
+    );
+}
+
+#[test]
+fn d4_malformed_txt_strings_are_skipped() {
+    // Empty string, empty key, duplicate key (case-insensitively), a
+    // non-ASCII key, then a length running past the end.
+    let record = b"\x00\x02=x\x03a=1\x03A=2\x04\xc3\xa9=1\x09b=cut";
+    let decoded = backend::decode_txt(record);
+    let pairs: Vec<(&str, Option<&[u8]>)> = decoded.iter().map(|p| (p.key(), p.val())).collect();
+    assert_eq!(pairs, [("a", Some(&b"1"[..])), ("b", Some(&b"cut"[..]))]);
+}
+
+#[test]
+fn d5_resolved_services_look_like_the_bundled_daemons() {
+    let address = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+    let service = backend::resolved(
+        SERVICE_TYPE,
+        "Bob's game v1.2",
+        "bobs-mac.local.",
+        7350,
+        &[address],
+        b"\x0bversion=1.0",
+    )
+    .expect("resolved");
+    assert_eq!(service.fullname, info("Bob's game v1.2").get_fullname());
+    assert_eq!(service.ty_domain, SERVICE_TYPE);
+    assert_eq!(service.host, "bobs-mac.local.");
+    assert_eq!(service.port, 7350);
+    let addresses: Vec<IpAddr> = service.addresses.iter().map(|a| a.to_ip_addr()).collect();
+    assert_eq!(addresses, [address]);
+    assert_eq!(service.txt_properties.get_property_val_str("version"), Some("1.0"));
+}
@@ -0,0 +1,32 @@
+//! Tests for the iOS `NSBonjourServices` check run by `MdnsBrowser.browse()`.
+//!
+//! Run with:
+//!   cargo test --test bonjour_services
+
+#[allow(dead_code)]
+#[path = "../src/bonjour_services.rs"]
+mod bonjour_services;
+
+use bonjour_services::{plist_entry, undeclared_error, BONJOUR_SERVICE_UNDECLARED_ERROR};
+
+#[test]
+fn b0_plist_entry_drops_domain_and_subtype() {
+    assert_eq!(plist_entry("_mygame._tcp.local."), "_mygame._tcp");
+    assert_eq!(plist_entry("_mygame._udp.LOCAL"), "_mygame._udp");
+    assert_eq!(plist_entry("_lobby._sub._mygame._tcp.local."), "_mygame._tcp");
+    assert_eq!(plist_entry("_mygame._tcp"), "_mygame._tcp");
+}
+
+#[test]
+fn b1_undeclared_type_names_the_item_to_add() {
+    let declared = ["_other._udp".to_string(), "_MyGame._tcp.".to_string()];
+    assert_eq!(undeclared_error(Some(&declared), "_mygame._tcp.local."), None);
+
+    let error = undeclared_error(Some(&declared), "_mygame._udp.local.").unwrap();
+    assert!(error.starts_with(BONJOUR_SERVICE_UNDECLARED_ERROR));
+    assert!(error.contains("<string>_mygame._udp</string>"), "{error}");
+
+    let error = undeclared_error(None, "_mygame._tcp.local.").unwrap();
+    assert!(error.contains("<key>NSBonjourServices</key>"), "{error}");
+    assert!(error.contains("<string>_mygame._tcp</string>"), "{error}");
+}
@@ -0,0 +1,44 @@
+//! Browse events and labels shared by the tests of the Godot-free event
+//! handling (`fanout`, `lifecycle`, `history`).  Each test binary uses only
+//! some of them.
+#![allow(dead_code)]
+
+use mdns_sd::{ServiceEvent, ServiceInfo};
+
+pub const TY: &str = "_mygame._tcp.local.";
+
+pub fn found(instance: &str) -> ServiceEvent {
+    ServiceEvent::ServiceFound(TY.to_string(), format!("{instance}.{TY}"))
+}
+
+pub fn resolved(instance: &str, port: u16) -> ServiceEvent {
+    let info = ServiceInfo::new(
+        TY,
+        instance,
+        "test-host.local.",
+        "192.168.1.42",
+        port,
+        None::<std::collections::HashMap<String, String>>,
+    )
+    .expect("ServiceInfo::new failed");
+    ServiceEvent::ServiceResolved(Box::new(info.as_resolved_service()))
+}
+
+pub fn removed(instance: &str) -> ServiceEvent {
+    ServiceEvent::ServiceRemoved(TY.to_string(), format!("{instance}.{TY}"))
+}
+
+/// Short labels so event sequences can be compared with `assert_eq!`.
+pub fn labels(events: &[ServiceEvent]) -> Vec<String> {
+    events
+        .iter()
+        .map(|event| match event {
+            ServiceEvent::ServiceFound(_, name) => format!("found {name}"),
+            ServiceEvent::ServiceResolved(info) => {
+                format!("resolved {}:{}", info.get_fullname(), info.get_port())
+            }
+            ServiceEvent::ServiceRemoved(_, name) => format!("removed {name}"),
+            other => format!("{other:?}"),
+        })
+        .collect()
+}
@@ -0,0 +1,64 @@
+//! Tests for the report of `MdnsManager.run_environment_diagnostics()`.
+//!
+//! The probes themselves depend on the machine (see `tests/mdns_loopback.rs`
+//! for them as tests); these check what the report makes of their results.
+//!
+//! Run with:
+//!   cargo test --test environment
+
+#![allow(dead_code)]
+
+#[path = "../src/environment.rs"]
+mod environment;
+#[path = "../src/interfaces.rs"]
+mod interfaces;
+
+use environment::{EnvironmentReport, InterfaceProbe};
+use std::time::Duration;
+
+fn probe(name: &str, address: &str, looped: bool) -> InterfaceProbe {
+    InterfaceProbe {
+        name: name.to_string(),
+        address: address.parse().unwrap(),
+        looped,
+        error: if looped { String::new() } else { "timed out".to_string() },
+    }
+}
+
+#[test]
+fn e0_self_resolve_decides_same_machine_discovery() {
+    let mut report = EnvironmentReport {
+        multicast_loopback: true,
+        interfaces: vec![probe("eth0", "192.168.1.20", true)],
+        self_resolve: Some(Duration::from_millis(420)),
+        ..Default::default()
+    };
+    assert!(report.same_machine_discovery_expected());
+    assert!(report.summary().contains("works"), "{}", report.summary());
+    assert!(report.summary().contains("420 ms"), "{}", report.summary());
+
+    report.self_resolve = None;
+    assert!(!report.same_machine_discovery_expected());
+    assert!(report.summary().contains("Hyper-V"), "{}", report.summary());
+}
+
+#[test]
+fn e1_summary_names_failing_interfaces_and_dead_multicast() {
+    let mut report = EnvironmentReport {
+        multicast_loopback: true,
+        interfaces: vec![
+            probe("Ethernet", "192.168.1.20", true),
+            probe("vEthernet (WSL)", "172.22.0.1", false),
+        ],
+        ..Default::default()
+    };
+    let summary = report.summary();
+    assert!(summary.contains("vEthernet (WSL) (172.22.0.1)"), "{summary}");
+    assert!(!summary.contains("Ethernet (192.168.1.20)"), "{summary}");
+
+    report.multicast_loopback = false;
+    report.multicast_error = "network unreachable".to_string();
+    let summary = report.summary();
+    assert!(summary.contains("network unreachable"), "{summary}");
+    assert!(summary.contains("firewall"), "{summary}");
+}
@@ -0,0 +1,98 @@
+//! Tests for the per-type fan-out that lets several `MdnsBrowser` nodes
+//! browse the same service type on the shared daemon.
+//!
+//! Run with:
+//!   cargo test --test fanout
+
+mod common;
+
+#[path = "../src/fanout.rs"]
+mod fanout;
+
+use common::{found, labels, removed, resolved, TY};
+use fanout::BrowseFanOut;
+
+#[test]
+fn f0_two_browsers_both_receive_every_event() {
+    let mut fanout = BrowseFanOut::new();
+    fanout.subscribe(1);
+    fanout.subscribe(2);
+    fanout.dispatch(found("a"));
+    fanout.dispatch(resolved("a", 7350));
+
+    let expected = vec![
+        format!("found a.{TY}"),
+        format!("resolved a.{TY}:7350"),
+    ];
+    assert_eq!(labels(&fanout.take(1)), expected);
+    assert_eq!(labels(&fanout.take(2)), expected);
+    assert!(fanout.take(1).is_empty(), "take drains the queue");
+}
+
+#[test]
+fn f1_one_browser_leaving_keeps_the_other_fed() {
+    let mut fanout = BrowseFanOut::new();
+    fanout.subscribe(1);
+    fanout.subscribe(2);
+    assert!(!fanout.unsubscribe(1), "the browse must outlive the first subscriber");
+    assert_eq!(fanout.len(), 1);
+
+    fanout.dispatch(resolved("a", 7350));
+    assert!(fanout.take(1).is_empty(), "a departed subscriber gets nothing");
+    assert_eq!(labels(&fanout.take(2)), vec![format!("resolved a.{TY}:7350")]);
+
+    assert!(fanout.unsubscribe(2), "the last subscriber stops the browse");
+    assert!(fanout.is_empty());
+}
+
+#[test]
+fn f2_late_browser_gets_live_instances_replayed() {
+    let mut fanout = BrowseFanOut::new();
+    fanout.subscribe(1);
+    fanout.dispatch(found("a"));
+    fanout.dispatch(resolved("a", 7350));
+    fanout.dispatch(resolved("a", 7351));
+    fanout.dispatch(found("b"));
+    fanout.dispatch(found("gone"));
+    fanout.dispatch(resolved("gone", 9));
+    fanout.dispatch(removed("gone"));
+
+    fanout.subscribe(2);
+    assert_eq!(fanout.queued(2), 3);
+    assert_eq!(
+        labels(&fanout.take(2)),
+        vec![
+            format!("found a.{TY}"),
+            format!("found b.{TY}"),
+            format!("resolved a.{TY}:7351"),
+        ],
+        "only live instances, with their latest resolution"
+    );
+    assert_eq!(fanout.take(1).len(), 7, "the first browser saw everything live");
+}
+
+/// A browser freed mid-frame drops its subscription with events still
+/// queued; the other browsers of the type must not lose theirs.
+#[test]
+fn f3_freeing_a_browser_with_queued_events() {
+    let mut fanout = BrowseFanOut::new();
+    fanout.subscribe(1);
+    fanout.subscribe(2);
+    fanout.dispatch(found("a"));
+    fanout.dispatch(resolved("a", 7350));
+    assert_eq!(fanout.queued(1), 2);
+
+    assert!(!fanout.unsubscribe(1));
+    assert_eq!(fanout.queued(1), 0, "the freed browser's queue is gone");
+    assert!(fanout.take(1).is_empty());
+
+    fanout.dispatch(removed("a"));
+    assert_eq!(
+        labels(&fanout.take(2)),
+        vec![
+            format!("found a.{TY}"),
+            format!("resolved a.{TY}:7350"),
+            format!("removed a.{TY}"),
+        ]
+    );
+}
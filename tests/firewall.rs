@@ -0,0 +1,72 @@
+//! Tests for the Windows Firewall evaluation behind
+//! `MdnsManager.check_firewall()`.
+//!
+//! Feeds the PowerShell output by hand, so they run on every platform.
+//!
+//! Run with:
+//!   cargo test --test firewall
+
+#[allow(dead_code)]
+#[path = "../src/firewall.rs"]
+mod firewall;
+
+use firewall::parse_report;
+
+const PUBLIC_NETWORK: &str = "\
+profile=Public
+enabled=True
+default_inbound=NotConfigured
+allow_inbound_rules=True
+";
+
+#[test]
+fn w0_private_only_prompt_answer_blocks_public_networks() {
+    // What ticking only "Private networks" in Windows' first-run prompt leaves.
+    let text = format!(
+        "{PUBLIC_NETWORK}\
+         rule=mygame.exe|Allow|UDP|Any|Private\n\
+         rule=mygame.exe|Block|UDP|Any|Public\n\
+         junk line\n"
+    );
+    let report = parse_report(&text);
+    assert_eq!(report.profile, "Public");
+    assert_eq!(report.rules.len(), 2);
+    assert!(report.allow_rule(5353).is_none());
+    assert!(report.inbound_blocked(5353));
+    let message = report.message(5353, "C:\\Games\\mygame.exe");
+    assert!(message.contains("\"mygame.exe\" blocks it on Public"), "{message}");
+
+    // The same rules on a Private network let mDNS in.
+    let report = parse_report(&text.replace("profile=Public", "profile=Private"));
+    assert_eq!(report.allow_rule(5353).map(|rule| rule.protocol.as_str()), Some("UDP"));
+    assert!(!report.inbound_blocked(5353));
+    assert_eq!(report.message(5353, "mygame.exe"), "");
+}
+
+#[test]
+fn w1_rules_must_cover_protocol_port_and_profile() {
+    let allow = |protocol: &str, ports: &str, profiles: &str| {
+        parse_report(&format!(
+            "{PUBLIC_NETWORK}rule=game|Allow|{protocol}|{ports}|{profiles}\n"
+        ))
+    };
+    assert!(!allow("UDP", "5353", "Public").inbound_blocked(5353));
+    assert!(!allow("Any", "Any", "Any").inbound_blocked(5353));
+    assert!(!allow("UDP", "7000,5300-5400", "Private, Public").inbound_blocked(5353));
+    assert!(allow("TCP", "Any", "Any").inbound_blocked(5353));
+    assert!(allow("UDP", "7000-7100", "Any").inbound_blocked(5353));
+    assert!(allow("UDP", "Any", "Domain, Private").inbound_blocked(5353));
+
+    // "Block all incoming connections" ignores allow rules; a disabled
+    // firewall blocks nothing.
+    let report = parse_report(&format!(
+        "{}rule=game|Allow|UDP|Any|Any\n",
+        PUBLIC_NETWORK.replace("allow_inbound_rules=True", "allow_inbound_rules=False")
+    ));
+    assert!(report.inbound_blocked(5353));
+    assert!(report.message(5353, "game").contains("blocks all incoming"));
+    let report = parse_report(&PUBLIC_NETWORK.replace("enabled=True", "enabled=False"));
+    assert!(!report.inbound_blocked(5353));
+
+    assert_eq!(parse_report("profile=DomainAuthenticated").profile, "Domain");
+}
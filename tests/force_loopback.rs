@@ -0,0 +1,37 @@
+//! Tests for the `GODOT_MDNS_FORCE_LOOPBACK` development override.
+//!
+//! Kept in its own test binary because it sets a process-wide environment
+//! variable.
+//!
+//! Run with:
+//!   cargo test --test force_loopback
+
+#[allow(dead_code)]
+#[path = "../src/devloop.rs"]
+mod devloop;
+
+use devloop::{effective, forced, is_enabled_value, FORCE_LOOPBACK_VAR};
+
+#[test]
+fn l0_env_var_forces_loopback_on() {
+    for value in ["1", "true", " Yes ", "ON"] {
+        assert!(is_enabled_value(value), "{value:?}");
+    }
+    for value in ["", "0", "false", "off", "2"] {
+        assert!(!is_enabled_value(value), "{value:?}");
+    }
+
+    std::env::remove_var(FORCE_LOOPBACK_VAR);
+    assert!(!forced());
+    assert_eq!(effective(None), None, "mdns-sd default kept");
+    assert_eq!(effective(Some(false)), Some(false));
+
+    std::env::set_var(FORCE_LOOPBACK_VAR, "1");
+    assert!(forced());
+    assert_eq!(effective(None), Some(true));
+    assert_eq!(effective(Some(false)), Some(true), "the override wins");
+
+    std::env::set_var(FORCE_LOOPBACK_VAR, "0");
+    assert_eq!(effective(Some(false)), Some(false));
+    std::env::remove_var(FORCE_LOOPBACK_VAR);
+}
@@ -0,0 +1,51 @@
+//! Tests for the event ring buffer behind `MdnsManager.get_recent_events()`.
+//!
+//! Run with:
+//!   cargo test --test history
+
+mod common;
+
+#[allow(dead_code)]
+#[path = "../src/history.rs"]
+mod history;
+
+use common::{found, TY};
+use history::{EventHistory, MAX_SUMMARY_LEN};
+use mdns_sd::{ServiceEvent, ServiceInfo};
+
+#[test]
+fn h0_keeps_the_last_n_events() {
+    let mut history = EventHistory::default();
+    history.record_service(TY, &found("ignored"));
+    assert_eq!(history.entries().count(), 0, "size 0 records nothing");
+
+    history.set_limit(2);
+    for instance in ["a", "b", "c"] {
+        history.record_service(TY, &found(instance));
+    }
+    let names: Vec<&str> = history.entries().map(|e| e.fullname.as_str()).collect();
+    assert_eq!(names, [format!("b.{TY}"), format!("c.{TY}")]);
+    assert!(history.entries().all(|e| e.kind == "service_found" && e.service_type == TY));
+
+    history.set_limit(1);
+    assert_eq!(history.entries().next().unwrap().fullname, format!("c.{TY}"));
+    history.clear();
+    assert_eq!((history.entries().count(), history.limit()), (0, 1));
+}
+
+#[test]
+fn h1_resolutions_are_summarised_briefly() {
+    let mut history = EventHistory::default();
+    history.set_limit(4);
+    let info = ServiceInfo::new(TY, "a", "host.local.", "192.168.1.42", 7350, &[("k", "v")][..])
+        .expect("ServiceInfo::new failed");
+    history.record_service(TY, &ServiceEvent::ServiceResolved(Box::new(info.as_resolved_service())));
+
+    let long = "x".repeat(400);
+    history.record_service(TY, &ServiceEvent::SearchStarted(long));
+
+    let entries: Vec<_> = history.entries().collect();
+    assert_eq!(entries[0].summary, "host.local.:7350 [192.168.1.42] txt=1");
+    assert!(entries[1].summary.len() <= MAX_SUMMARY_LEN);
+    assert!(entries[1].summary.ends_with('…'));
+}
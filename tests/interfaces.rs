@@ -0,0 +1,270 @@
+//! Tests for interface-spec parsing shared by `MdnsManager` and the Android
+//! private-daemon path of `MdnsBrowser`.
+//!
+//! Run with:
+//!   cargo test --test interfaces
+
+#[allow(dead_code)]
+#[path = "../src/interfaces.rs"]
+mod interfaces;
+
+use interfaces::{
+    parse_interface, parse_interface_or_group, parse_interfaces, parse_scoped_address,
+    parse_scoped_addresses, parse_subnet, parse_subnets, glob_match, is_vpn_overlay_address,
+    parse_valid_interfaces, pick_wifi_address,
+    InterfaceSelection, IpVersion, LocalInterface,
+};
+use mdns_sd::IfKind;
+
+#[test]
+fn i0_ip_strings_select_by_address() {
+    match parse_interface(" 192.168.1.42 ") {
+        Ok(IfKind::Addr(ip)) => assert_eq!(ip.to_string(), "192.168.1.42"),
+        other => panic!("expected IfKind::Addr, got {other:?}"),
+    }
+    assert!(matches!(parse_interface("fe80::1"), Ok(IfKind::Addr(ip)) if ip.is_ipv6()));
+}
+
+#[test]
+fn i1_other_strings_select_by_name() {
+    match parse_interface("eth0") {
+        Ok(IfKind::Name(name)) => assert_eq!(name, "eth0"),
+        other => panic!("expected IfKind::Name, got {other:?}"),
+    }
+    // Windows adapter names may contain spaces.
+    assert!(matches!(parse_interface("Ethernet 2"), Ok(IfKind::Name(_))));
+}
+
+#[test]
+fn i2_invalid_entries_are_rejected() {
+    assert!(parse_interface("").is_err());
+    assert!(parse_interface("   ").is_err());
+    assert!(parse_interface("eth\n0").is_err());
+
+    let err = parse_interfaces(&["eth0", "", "wlan0"]).unwrap_err();
+    assert!(err.contains("empty"), "unexpected error: {err}");
+    assert_eq!(parse_interfaces(&["eth0", "10.0.0.1"]).unwrap().len(), 2);
+}
+
+#[test]
+fn i3_default_selection() {
+    // The virtual-adapter filter is on out of the box.
+    assert!(!InterfaceSelection::default().is_default());
+    let mut plain = InterfaceSelection::default();
+    plain.exclusions.enabled = false;
+    assert!(plain.is_default());
+    let selection = InterfaceSelection {
+        denied: parse_interfaces(&["docker0"]).unwrap(),
+        ..Default::default()
+    };
+    assert!(!selection.is_default());
+}
+
+#[test]
+fn i4_scoped_addresses() {
+    let plain = parse_scoped_address("192.168.1.42").unwrap();
+    assert_eq!(plain.scope, None);
+    assert!(matches!(plain.interface(), IfKind::Addr(_)));
+
+    let zoned = parse_scoped_address("fe80::1%en0").unwrap();
+    assert_eq!(zoned.ip.to_string(), "fe80::1");
+    assert_eq!(zoned.scope.as_deref(), Some("en0"));
+    assert!(matches!(zoned.interface(), IfKind::Name(name) if name == "en0"));
+
+    assert!(parse_scoped_address("fe80::1%").is_err());
+    assert!(parse_scoped_address("fe80::1%12").is_err(), "numeric zones are rejected");
+    assert!(parse_scoped_address("not-an-ip%en0").is_err());
+}
+
+#[test]
+fn i5_scoped_address_errors_list_every_bad_entry() {
+    let err = parse_scoped_addresses(&["10.0.0.1", "bogus", "fe80::1%", "fe80::2%eth0"]).unwrap_err();
+    assert!(err.contains("'bogus'"), "unexpected error: {err}");
+    assert!(err.contains("'fe80::1%'"), "unexpected error: {err}");
+    assert!(!err.contains("10.0.0.1"), "valid entries must not be listed: {err}");
+
+    assert_eq!(parse_scoped_addresses(&["10.0.0.1", "fe80::2%eth0"]).unwrap().len(), 2);
+}
+
+#[test]
+fn i6_ip_version_disables_the_other_family() {
+    assert_eq!(IpVersion::default(), IpVersion::Both);
+    assert!(IpVersion::Both.disabled_family().is_none());
+    assert!(matches!(IpVersion::V4Only.disabled_family(), Some(IfKind::IPv6)));
+    assert!(matches!(IpVersion::V6Only.disabled_family(), Some(IfKind::IPv4)));
+
+    let selection = InterfaceSelection {
+        ip_version: IpVersion::V4Only,
+        ..Default::default()
+    };
+    assert!(!selection.is_default());
+}
+
+#[test]
+fn i7_toggles_accept_groups_and_coalesce() {
+    assert!(matches!(parse_interface_or_group(" ALL "), Ok(IfKind::All)));
+    assert!(matches!(parse_interface_or_group("ipv4"), Ok(IfKind::IPv4)));
+    assert!(matches!(parse_interface_or_group("IPv6"), Ok(IfKind::IPv6)));
+    assert!(matches!(parse_interface_or_group("utun3"), Ok(IfKind::Name(n)) if n == "utun3"));
+    assert!(parse_interface_or_group("").is_err());
+
+    let mut selection = InterfaceSelection::default();
+    selection.toggle(IfKind::Name("utun3".into()), false);
+    selection.toggle(IfKind::Name("eth0".into()), true);
+    selection.toggle(IfKind::Name("utun3".into()), true);
+    assert!(!selection.is_default());
+    let order: Vec<String> = selection
+        .toggles
+        .iter()
+        .map(|t| format!("{:?}={}", t.kind, t.enabled))
+        .collect();
+    assert_eq!(order, ["Name(\"eth0\")=true", "Name(\"utun3\")=true"]);
+
+    // "all" supersedes every earlier toggle.
+    selection.toggle(IfKind::All, true);
+    assert_eq!(selection.toggles.len(), 1);
+}
+
+#[test]
+fn i8_virtual_adapters_are_excluded_unless_allowed() {
+    assert!(glob_match("utun*", "utun3"));
+    assert!(glob_match("vEthernet*", "VETHERNET (WSL)"));
+    assert!(glob_match("br-*", "br-"));
+    assert!(glob_match("*docker*", "my-docker0"));
+    assert!(!glob_match("tun*", "eth0"));
+    assert!(!glob_match("zt", "zt0"));
+
+    assert!(is_vpn_overlay_address(&"100.101.1.2".parse().unwrap()));
+    assert!(!is_vpn_overlay_address(&"100.128.0.1".parse().unwrap()));
+    assert!(is_vpn_overlay_address(&"fd7a:115c:a1e0::1".parse().unwrap()));
+    assert!(!is_vpn_overlay_address(&"192.168.1.42".parse().unwrap()));
+
+    let interfaces: Vec<(String, std::net::IpAddr)> = [
+        ("eth0", "192.168.1.42"),
+        ("utun3", "fe80::1"),
+        ("utun3", "10.8.0.2"),
+        ("docker0", "172.17.0.1"),
+        ("tailscale0", "100.101.1.2"),
+        ("wlan0", "10.0.0.5"),
+    ]
+    .iter()
+    .map(|(name, ip)| (name.to_string(), ip.parse().unwrap()))
+    .collect();
+
+    let mut selection = InterfaceSelection::default();
+    let excluded = selection.excluded(&interfaces);
+    let names: Vec<&str> = excluded.iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, ["utun3", "docker0", "tailscale0"], "once per interface");
+    assert_eq!(excluded[0].reason, "pattern utun*");
+    assert_eq!(excluded[2].reason, "VPN address 100.101.1.2");
+
+    // Explicitly allowed adapters are kept, by name or by address.
+    selection.allowed = parse_interfaces(&["docker0", "100.101.1.2"]).unwrap();
+    let names: Vec<String> = selection.excluded(&interfaces).into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["utun3"]);
+
+    selection.exclusions.enabled = false;
+    assert!(selection.excluded(&interfaces).is_empty());
+}
+
+#[test]
+fn i9_bound_interfaces_follow_the_selection() {
+    let local = |name: &str, ip: &str| LocalInterface {
+        name: name.to_string(),
+        ip: ip.parse().unwrap(),
+        loopback: name == "lo",
+    };
+    let interfaces = [
+        local("lo", "127.0.0.1"),
+        local("eth0", "192.168.1.42"),
+        local("eth0", "fe80::1"),
+        local("wlan0", "10.0.0.7"),
+        local("tailscale0", "100.101.1.2"),
+    ];
+
+    let mut selection = InterfaceSelection::default();
+    assert_eq!(
+        selection.binds(&interfaces),
+        [true, true, true, true, false],
+        "only the VPN overlay is excluded by default"
+    );
+
+    selection.toggle(IfKind::Name("wlan0".into()), false);
+    selection.ip_version = IpVersion::V4Only;
+    assert_eq!(selection.binds(&interfaces), [true, true, false, false, false]);
+
+    // The Android private daemon: one interface only.
+    let pinned = InterfaceSelection {
+        allowed: vec![IfKind::Addr("10.0.0.7".parse().unwrap())],
+        ..Default::default()
+    };
+    assert_eq!(pinned.binds(&interfaces), [false, false, false, true, false]);
+}
+
+#[test]
+fn i10_android_wifi_address_is_picked_by_name_and_range() {
+    let iface = |name: &str, ip: &str| LocalInterface {
+        name: name.to_string(),
+        ip: ip.parse().unwrap(),
+        loopback: name == "lo",
+    };
+    let mut interfaces = vec![
+        iface("lo", "127.0.0.1"),
+        iface("rmnet_data0", "10.120.4.7"),
+        iface("swlan0", "192.168.43.1"),
+        iface("wlan0", "fe80::1"),
+        iface("wlan1", "192.168.1.30"),
+    ];
+    assert_eq!(pick_wifi_address(&interfaces), Some("192.168.1.30".parse().unwrap()));
+
+    interfaces.push(iface("wlan0", "192.168.1.20"));
+    assert_eq!(
+        pick_wifi_address(&interfaces),
+        Some("192.168.1.20".parse().unwrap()),
+        "wlan0 comes first"
+    );
+
+    // Only the hotspot, or only mobile data / a public address.
+    assert_eq!(pick_wifi_address(&interfaces[2..3]), Some("192.168.43.1".parse().unwrap()));
+    let public = [iface("rmnet_data0", "10.120.4.7"), iface("wlan0", "203.0.113.5")];
+    assert_eq!(pick_wifi_address(&public), None);
+}
+
+#[test]
+fn i11_subnets_parse_and_match_by_prefix() {
+    let lan = parse_subnet(" 192.168.1.77/24 ").unwrap();
+    assert_eq!(lan.to_string(), "192.168.1.0/24", "host bits are cleared");
+    assert!(lan.contains(&"192.168.1.200".parse().unwrap()));
+    assert!(!lan.contains(&"192.168.2.1".parse().unwrap()));
+    assert!(!lan.contains(&"::ffff:192.168.1.1".parse().unwrap()), "families never mix");
+
+    let ula = parse_subnet("fd00::/8").unwrap();
+    assert!(ula.contains(&"fd12:3456::1".parse().unwrap()));
+    assert!(!ula.contains(&"fe80::1".parse().unwrap()));
+    assert_eq!(parse_subnet("10.0.0.5").unwrap().to_string(), "10.0.0.5/32");
+    assert!(parse_subnet("0.0.0.0/0").unwrap().contains(&"8.8.8.8".parse().unwrap()));
+
+    let both = parse_subnets("192.168.1.0/24, fd00::/8").unwrap();
+    assert_eq!(both, [lan, ula]);
+    assert!(parse_subnets("  ").unwrap().is_empty());
+    for bad in ["192.168.1.0/33", "192.168.1/24", "lan/24", "10.0.0.0/", "fd00::/129"] {
+        assert!(parse_subnet(bad).is_err(), "{bad} must be rejected");
+    }
+    assert!(parse_subnets("192.168.1.0/24, nope").is_err());
+}
+
+#[test]
+fn i12_invalid_interfaces_are_reported_one_by_one() {
+    let (valid, errors) =
+        parse_valid_interfaces(&["192.168.1.20", "", "eth\n1", "eth1", " 192.168.1.20"]);
+    assert_eq!(valid.len(), 2, "the repeated address counts once");
+    assert!(matches!(&valid[0], IfKind::Addr(ip) if ip.to_string() == "192.168.1.20"));
+    assert!(matches!(&valid[1], IfKind::Name(name) if name == "eth1"));
+    assert_eq!(errors.len(), 2);
+    assert!(errors[0].starts_with("'': "), "unexpected error: {}", errors[0]);
+    assert!(errors[1].starts_with("'eth\\n1': "), "unexpected error: {}", errors[1]);
+
+    let (valid, errors) = parse_valid_interfaces(&["wlan0", "10.0.0.2"]);
+    assert_eq!(valid.len(), 2);
+    assert!(errors.is_empty());
+}
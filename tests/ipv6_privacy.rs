@@ -0,0 +1,38 @@
+//! Tests for the temporary IPv6 address detection behind `MdnsAdvertiser`'s
+//! automatic addresses (`src/ipv6_privacy.rs`).
+//!
+//! Run with:
+//!   cargo test --test ipv6_privacy
+
+#[path = "../src/ipv6_privacy.rs"]
+mod ipv6_privacy;
+
+use std::net::Ipv6Addr;
+
+#[test]
+fn v0_if_inet6_lists_temporary_and_deprecated_addresses() {
+    let text = "\
+20010db800000000021122fffe334455 02 40 00 00     eth0
+20010db80000000071a3c9d2e4f50617 02 40 00 01     eth0
+20010db800000000a1b2c3d4e5f60708 02 40 00 21     eth0
+fe80000000000000021122fffe334455 02 40 20 80     eth0
+00000000000000000000000000000001 01 80 10 80       lo
+not-an-address 02 40 00 01 eth0
+20010db8 02 40 00 01 eth0
+";
+    let temporary = ipv6_privacy::parse_if_inet6(text);
+    assert_eq!(
+        temporary,
+        [
+            "2001:db8::71a3:c9d2:e4f5:617".parse::<Ipv6Addr>().unwrap(),
+            "2001:db8::a1b2:c3d4:e5f6:708".parse().unwrap(),
+        ],
+        "stable (EUI-64), link-local and loopback addresses are kept; bad lines skipped"
+    );
+}
+
+#[test]
+fn v1_detection_never_fails() {
+    // Whatever this host has, asking must not panic or error out.
+    let _ = ipv6_privacy::temporary_addresses();
+}
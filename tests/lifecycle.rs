@@ -0,0 +1,52 @@
+//! Tests for the backlog coalescing the browser applies when the app resumes
+//! from the background (`set_handle_app_lifecycle()`).
+//!
+//! Run with:
+//!   cargo test --test lifecycle
+
+mod common;
+
+#[allow(dead_code)]
+#[path = "../src/lifecycle.rs"]
+mod lifecycle;
+
+use common::{found, labels, removed, resolved, TY};
+use mdns_sd::ServiceEvent;
+
+#[test]
+fn a0_updates_collapse_into_the_latest_one() {
+    let backlog = vec![
+        ServiceEvent::SearchStarted(TY.to_string()),
+        resolved("a", 7000),
+        resolved("b", 7100),
+        resolved("a", 7001),
+        removed("b"),
+        resolved("b", 7101),
+        resolved("a", 7002),
+    ];
+    let known = |fullname: &str| fullname == format!("a.{TY}");
+
+    let expected = vec![
+        format!("SearchStarted({TY:?})"),
+        format!("resolved b.{TY}:7101"),
+        format!("resolved a.{TY}:7002"),
+    ];
+    assert_eq!(labels(&lifecycle::coalesce(backlog, known)), expected);
+}
+
+#[test]
+fn a1_services_that_came_and_went_are_not_reported() {
+    let backlog = vec![
+        found("new"),
+        resolved("new", 7000),
+        found("listed"),
+        resolved("listed", 7100),
+        removed("new"),
+        removed("listed"),
+    ];
+    let known = |fullname: &str| fullname == format!("listed.{TY}");
+
+    // The listed service's removal is kept; the new one never shows.
+    let expected = vec![format!("found listed.{TY}"), format!("removed listed.{TY}")];
+    assert_eq!(labels(&lifecycle::coalesce(backlog, known)), expected);
+}
@@ -0,0 +1,43 @@
+//! Tests for the Local Network permission probe behind
+//! `MdnsManager.get_local_network_permission()`.
+//!
+//! Run with:
+//!   cargo test --test local_network
+
+#[allow(dead_code)]
+#[path = "../src/local_network.rs"]
+mod local_network;
+
+use local_network::{classify, probe_query, LocalNetwork, Permission};
+use std::io::{Error, ErrorKind};
+use std::time::Instant;
+
+#[test]
+fn l0_unreachable_group_means_denied() {
+    assert_eq!(classify(&Err(Error::from(ErrorKind::HostUnreachable)), false), Permission::Denied);
+    // A send that goes out proves nothing: the prompt may still be up.
+    assert_eq!(classify(&Ok(()), false), Permission::Unknown);
+    assert_eq!(classify(&Err(Error::from(ErrorKind::WouldBlock)), false), Permission::Unknown);
+    // An answer heard beats everything.
+    assert_eq!(classify(&Err(Error::from(ErrorKind::HostUnreachable)), true), Permission::Granted);
+}
+
+#[test]
+fn l1_probe_is_one_question() {
+    let packet = probe_query();
+    assert_eq!(&packet[..12], &[0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(&packet[packet.len() - 5..], &[0, 0, 1, 0, 1], "root, A, IN");
+    assert_eq!(usize::from(packet[12]), "godot-mdns-permission-probe".len());
+}
+
+#[test]
+fn l2_heard_answer_grants_and_stops_probing() {
+    let mut state = LocalNetwork::default();
+    let expected = if local_network::applies() { Permission::Unknown } else { Permission::Granted };
+    assert_eq!(state.permission(), expected);
+    // Nothing browses: no probe is sent.
+    assert_eq!(state.poll(Instant::now(), false, 5353), None);
+    state.note_heard();
+    assert_eq!(state.permission(), Permission::Granted);
+    assert_eq!(state.poll(Instant::now(), true, 5353), None);
+}
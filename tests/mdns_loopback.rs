@@ -28,6 +28,13 @@
 //!
 //! This does NOT mean the library is broken — it means same-machine loopback
 //! testing is not possible in this network environment.
+//!
+//! Run with `GODOT_MDNS_FORCE_LOOPBACK=1` to turn multicast loopback on for
+//! the shared test daemon, as the extension does for its own daemons.
+
+#[allow(dead_code)]
+#[path = "../src/devloop.rs"]
+mod devloop;
 
 use mdns_sd::{ResolvedService, ServiceDaemon, ServiceEvent, ServiceInfo};
 use std::collections::HashMap;
@@ -49,6 +56,11 @@ fn shared_test_daemon() -> ServiceDaemon {
     if guard.is_none() {
         let d = ServiceDaemon::new().expect("failed to create mDNS daemon");
         println!("[daemon] created with default (all-interface) binding");
+        if devloop::forced() {
+            let _ = d.set_multicast_loop_v4(true);
+            let _ = d.set_multicast_loop_v6(true);
+            println!("[daemon] multicast loopback forced on ({})", devloop::FORCE_LOOPBACK_VAR);
+        }
         *guard = Some(d);
     }
     guard.as_ref().unwrap().clone()
@@ -649,3 +661,147 @@ fn t14_multiple_services_resolved() {
     }
     println!("[t14] PASS — all {} services resolved", names.len());
 }
+
+#[test]
+fn t15_hostname_resolve_loopback() {
+    if !require_mdns_loopback("t15") {
+        return;
+    }
+
+    let svc_type = unique_service_type("hn");
+    let ts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .subsec_nanos();
+    let hostname_local = format!("hn{ts}.local.");
+    let daemon = shared_test_daemon();
+
+    let info = ServiceInfo::new(
+        &svc_type, "hostname-test", &hostname_local, "", 7350,
+        &[] as &[(&str, &str)],
+    )
+    .expect("ServiceInfo::new failed")
+    .enable_addr_auto();
+    let fullname = info.get_fullname().to_string();
+    daemon.register(info).expect("register failed");
+    std::thread::sleep(Duration::from_millis(500));
+
+    let receiver = daemon
+        .resolve_hostname(&hostname_local, Some(10_000))
+        .expect("resolve_hostname failed");
+
+    let mut found = None;
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while Instant::now() < deadline && found.is_none() {
+        match receiver.try_recv() {
+            Ok(mdns_sd::HostnameResolutionEvent::AddressesFound(host, addrs)) => {
+                println!("  [addresses] {host}: {addrs:?}");
+                found = Some(addrs);
+            }
+            Ok(mdns_sd::HostnameResolutionEvent::SearchTimeout(_)) => break,
+            Ok(ev) => println!("  [event] {ev:?}"),
+            Err(_) => std::thread::sleep(Duration::from_millis(50)),
+        }
+    }
+    let _ = daemon.stop_resolve_hostname(&hostname_local);
+    let _ = daemon.unregister(&fullname);
+
+    let addrs = found.expect("AddressesFound not received within 10 seconds");
+    assert!(!addrs.is_empty());
+    println!("[t15] PASS");
+}
+
+/// Mirrors `MdnsManager.set_multicast_loopback()`: both setters must be
+/// accepted (off then on), and with loopback on a daemon resolves its own
+/// service — the same round trip `mdns_self_resolve_works()` probes.
+#[test]
+fn t16_multicast_loopback_toggle() {
+    let daemon = ServiceDaemon::new_with_port(15354).expect("new_with_port failed");
+    for enabled in [false, true] {
+        daemon
+            .set_multicast_loop_v4(enabled)
+            .expect("set_multicast_loop_v4 rejected");
+        daemon
+            .set_multicast_loop_v6(enabled)
+            .expect("set_multicast_loop_v6 rejected");
+    }
+
+    if !require_mdns_loopback("t16") {
+        let _ = daemon.shutdown();
+        return;
+    }
+
+    let svc_type = unique_service_type("ml");
+    let receiver = daemon.browse(&svc_type).expect("browse failed");
+    std::thread::sleep(Duration::from_millis(300));
+
+    let hostname = format!("{}.local.", get_hostname());
+    let info = ServiceInfo::new(&svc_type, "loop", &hostname, "", 1234, &[] as &[(&str, &str)])
+        .expect("ServiceInfo::new failed");
+    let fullname = info.get_fullname().to_string();
+    daemon.register(info).expect("register failed");
+
+    let resolved = wait_for_resolved(&receiver, &fullname, Duration::from_secs(10));
+    let _ = daemon.unregister(&fullname);
+    let _ = daemon.shutdown();
+    assert!(resolved.is_some(), "service not resolved with multicast loopback enabled");
+    println!("[t16] PASS");
+}
+
+/// Mirrors `MdnsAdvertiser.bump_txt()`: re-registering the same fullname with
+/// one TXT value changed must reach a subscribed browser as a fresh
+/// `ServiceResolved` carrying the new value — an in-place update, with no
+/// `ServiceRemoved` in between.
+#[test]
+fn t17_txt_bump_is_an_in_place_update() {
+    if !require_mdns_loopback("t17") {
+        return;
+    }
+
+    let svc_type = unique_service_type("tb");
+    let hostname_local = format!("{}.local.", get_hostname());
+    let daemon = shared_test_daemon();
+
+    let receiver = daemon.browse(&svc_type).expect("browse failed");
+    std::thread::sleep(Duration::from_millis(500));
+
+    let build = |players: &str| {
+        ServiceInfo::new(
+            &svc_type,
+            "bump-test",
+            &hostname_local,
+            "",
+            7350,
+            &[("version", "1.0"), ("players", players)] as &[(&str, &str)],
+        )
+        .expect("ServiceInfo::new failed")
+    };
+    let info = build("1");
+    let fullname = info.get_fullname().to_string();
+    daemon.register(info).expect("register failed");
+    assert!(
+        wait_for_resolved(&receiver, &fullname, Duration::from_secs(15)).is_some(),
+        "service must be discovered before bumping its TXT"
+    );
+
+    daemon.register(build("2")).expect("re-register failed");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let mut updated = false;
+    let mut removed = false;
+    while Instant::now() < deadline && !updated {
+        match receiver.try_recv() {
+            Ok(ServiceEvent::ServiceResolved(info)) if info.get_fullname() == fullname => {
+                updated = info.get_property_val_str("players") == Some("2");
+            }
+            Ok(ServiceEvent::ServiceRemoved(_, name)) if name == fullname => removed = true,
+            Ok(_) => {}
+            Err(_) => std::thread::sleep(Duration::from_millis(50)),
+        }
+    }
+    let _ = daemon.unregister(&fullname);
+
+    assert!(!removed, "a TXT bump must not look like remove + add");
+    assert!(updated, "updated TXT value not received within 10 seconds");
+    println!("[t17] PASS");
+}
@@ -0,0 +1,130 @@
+//! Tests for the instance-name suffix schemes of
+//! `MdnsAdvertiser.set_name_suffix_scheme()`.
+//!
+//! Run with:
+//!   cargo test --test naming
+
+#[allow(dead_code)]
+#[path = "../src/naming.rs"]
+mod naming;
+
+use mdns_sd::ServiceInfo;
+use naming::{NameSuffix, FALLBACK_HOSTNAME, MAX_INSTANCE_LEN};
+
+const SERVICE_TYPE: &str = "_mygame._tcp.local.";
+
+/// The fullname `mdns-sd` builds for `instance`, as `get_registered_name()` reports it.
+fn fullname(instance: &str) -> String {
+    ServiceInfo::new(SERVICE_TYPE, instance, "host.local.", "", 7350, &[] as &[(&str, &str)])
+        .expect("ServiceInfo::new failed")
+        .get_fullname()
+        .to_string()
+}
+
+/// Splits `"<base> (<suffix>)._mygame._tcp.local."` into base and suffix.
+fn split_suffix(fullname: &str) -> (&str, &str) {
+    let instance = fullname
+        .strip_suffix(&format!(".{SERVICE_TYPE}"))
+        .expect("fullname ends in the service type");
+    let (base, rest) = instance.rsplit_once(" (").expect("instance has a suffix");
+    (base, rest.strip_suffix(')').expect("suffix is parenthesised"))
+}
+
+#[test]
+fn n0_none_keeps_the_name() {
+    let name = NameSuffix::None.apply("Server", "build-07.example.com");
+    assert_eq!(fullname(&name), "Server._mygame._tcp.local.");
+}
+
+#[test]
+fn n1_random_appends_four_hex_digits() {
+    let name = NameSuffix::Random.apply("Server", "build-07");
+    let full = fullname(&name);
+    let (base, suffix) = split_suffix(&full);
+    assert_eq!(base, "Server");
+    assert_eq!(suffix.len(), 4, "{full}");
+    assert!(suffix.bytes().all(|b| b.is_ascii_hexdigit()), "{full}");
+}
+
+#[test]
+fn n2_hostname_appends_the_short_host_name() {
+    let name = NameSuffix::Hostname.apply("Server", "build-07.example.com");
+    assert_eq!(fullname(&name), "Server (build-07)._mygame._tcp.local.");
+    // Nothing to append: the name is kept as is.
+    assert_eq!(NameSuffix::Hostname.apply("Server", ""), "Server");
+}
+
+#[test]
+fn n3_counter_increases_per_call() {
+    let first = fullname(&NameSuffix::Counter.apply("Server", "build-07"));
+    let second = fullname(&NameSuffix::Counter.apply("Server", "build-07"));
+    let (base, a) = split_suffix(&first);
+    assert_eq!(base, "Server");
+    let (_, b) = split_suffix(&second);
+    let (a, b): (u64, u64) = (a.parse().unwrap(), b.parse().unwrap());
+    assert!(a >= 1 && b > a, "{first} then {second}");
+}
+
+#[test]
+fn n4_long_names_are_shortened_to_keep_the_suffix() {
+    let long = "é".repeat(40); // 80 bytes
+    let name = NameSuffix::Hostname.apply(&long, "build-07");
+    assert!(name.len() <= MAX_INSTANCE_LEN, "{} bytes", name.len());
+    assert!(name.ends_with(" (build-07)"), "{name}");
+    assert!(name.starts_with('é'));
+}
+
+#[test]
+fn n5_missing_hostname_falls_back_to_a_unique_name() {
+    assert_eq!(naming::hostname_or_fallback(Some("build-07".into())), "build-07");
+
+    // The OS failing to report a name, as `hostname::get()` erroring would.
+    let fallback = naming::hostname_or_fallback(None);
+    let suffix = fallback
+        .strip_prefix(&format!("{FALLBACK_HOSTNAME}-"))
+        .unwrap_or_else(|| panic!("{fallback} lacks the fallback prefix"));
+    assert_eq!(suffix.len(), 8, "{fallback}");
+    assert!(suffix.chars().all(|c| c.is_ascii_hexdigit()), "{fallback}");
+    assert_eq!(naming::hostname_or_fallback(Some(" ".into())), fallback, "stable per process");
+
+    // Two hostname-less devices (different seeds) do not share a name.
+    assert_ne!(naming::fallback_hostname_for(1), naming::fallback_hostname_for(2));
+    assert_eq!(naming::fallback_hostname_for(0x1234_abcd), "unknown-host-1234abcd");
+}
+
+#[test]
+fn n6_lobby_service_type_follows_the_project_name() {
+    assert_eq!(naming::lobby_service_type("My Game!"), "_my-game._udp.local.");
+    assert_eq!(naming::lobby_service_type("  Space -- Race 2 "), "_space-race-2._udp.local.");
+    // Cut to 15 characters, without a hyphen left dangling.
+    assert_eq!(
+        naming::lobby_service_type("Super Long Project Name"),
+        "_super-long-proj._udp.local."
+    );
+    assert_eq!(naming::lobby_service_type("Tower Defense X"), "_tower-defense-x._udp.local.");
+    assert_eq!(naming::lobby_service_type("abcdefghijklmn op"), "_abcdefghijklmn._udp.local.");
+    // No letter to keep: the fallback.
+    assert_eq!(naming::lobby_service_type(""), "_godot-lobby._udp.local.");
+    assert_eq!(naming::lobby_service_type("2048"), "_godot-lobby._udp.local.");
+    assert_eq!(naming::lobby_service_type("ゲーム"), "_godot-lobby._udp.local.");
+}
+
+#[test]
+fn n7_custom_host_fqdn_must_be_a_dns_name_under_local() {
+    assert_eq!(naming::check_host_fqdn("Arcade-01.local").as_deref(), Ok("arcade-01.local."));
+    assert_eq!(naming::check_host_fqdn(" cab.lan.local. ").as_deref(), Ok("cab.lan.local."));
+
+    let rejected = ["", "local.", "arcade", "-arcade.local.", "arc ade.local.", "a..local."];
+    for fqdn in rejected {
+        assert!(naming::check_host_fqdn(fqdn).is_err(), "{fqdn:?} accepted");
+    }
+    let long_label = format!("{}.local.", "a".repeat(64));
+    assert!(naming::check_host_fqdn(&long_label).is_err());
+    let err = naming::check_host_fqdn("router.home.arpa.").unwrap_err();
+    assert!(err.contains(".local."), "{err}");
+
+    // mdns-sd accepts what passes.
+    let host = naming::check_host_fqdn("arcade-01.local").unwrap();
+    let info = ServiceInfo::new(SERVICE_TYPE, "Server", &host, "192.168.1.42", 7350, None);
+    assert_eq!(info.unwrap().get_hostname(), "arcade-01.local.");
+}
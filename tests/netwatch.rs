@@ -0,0 +1,66 @@
+//! Tests for the interface monitor behind `MdnsManager.network_changed`.
+//!
+//! Feeds the snapshots by hand, so the result does not depend on the
+//! interfaces of the test machine.
+//!
+//! Run with:
+//!   cargo test --test netwatch
+
+#![allow(dead_code)]
+
+#[path = "../src/interfaces.rs"]
+mod interfaces;
+#[path = "../src/netwatch.rs"]
+mod netwatch;
+
+use interfaces::LocalInterface;
+use netwatch::{NetworkChange, NetworkMonitor};
+use std::time::{Duration, Instant};
+
+fn iface(name: &str, ip: &str) -> LocalInterface {
+    LocalInterface {
+        name: name.to_string(),
+        ip: ip.parse().unwrap(),
+        loopback: name == "lo",
+    }
+}
+
+#[test]
+fn n0_reports_added_and_removed_addresses() {
+    let mut monitor = NetworkMonitor::default();
+    let docked = vec![iface("lo", "127.0.0.1"), iface("wlan0", "192.168.1.20")];
+    assert_eq!(monitor.observe(docked.clone()), None, "the first snapshot reports nothing");
+    assert_eq!(monitor.observe(docked.into_iter().rev().collect()), None, "order does not matter");
+
+    let change = monitor.observe(vec![
+        iface("lo", "127.0.0.1"),
+        iface("eth0", "10.0.0.5"),
+        iface("eth0", "fe80::1"),
+    ]);
+    assert_eq!(
+        change,
+        Some(NetworkChange {
+            added: vec![iface("eth0", "10.0.0.5"), iface("eth0", "fe80::1")],
+            removed: vec![iface("wlan0", "192.168.1.20")],
+        })
+    );
+}
+
+#[test]
+fn n1_interval_and_off() {
+    let mut monitor = NetworkMonitor::default();
+    assert_eq!(monitor.interval(), Some(netwatch::DEFAULT_POLL_INTERVAL));
+    let start = Instant::now();
+    // The first poll only takes the snapshot.
+    assert_eq!(monitor.poll(start), None);
+    // Not due yet: the snapshot is left alone even though it is stale.
+    monitor.observe(Vec::new());
+    assert_eq!(monitor.poll(start + Duration::from_millis(10)), None);
+
+    monitor.set_interval(None);
+    assert_eq!(monitor.poll(start + Duration::from_secs(60)), None, "off");
+    // Turned back on, the first poll is a fresh snapshot again.
+    monitor.set_interval(Some(Duration::from_secs(1)));
+    assert_eq!(monitor.poll(start + Duration::from_secs(61)), None);
+    assert!(monitor.observe(vec![iface("tun9", "10.99.0.1")]).is_some());
+}
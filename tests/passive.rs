@@ -0,0 +1,96 @@
+//! Tests for the packet parsing behind `MdnsManager.enable_passive_capture()`.
+//!
+//! Run with:
+//!   cargo test --test passive
+
+#[allow(dead_code)]
+#[path = "../src/passive.rs"]
+mod passive;
+
+use passive::{parse_records, Overheard, Record};
+use std::time::Instant;
+
+/// Encodes `name` as uncompressed labels.
+fn labels(name: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    for label in name.trim_end_matches('.').split('.') {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    out
+}
+
+fn record(name: &[u8], rtype: u16, ttl: u32, rdata: &[u8]) -> Vec<u8> {
+    let mut out = name.to_vec();
+    out.extend_from_slice(&rtype.to_be_bytes());
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&ttl.to_be_bytes());
+    out.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+    out.extend_from_slice(rdata);
+    out
+}
+
+/// A response announcing `Printer._http._tcp.local.` with a PTR and an SRV,
+/// the SRV owner compressed as a pointer to the PTR's target.
+fn announcement(ptr_ttl: u32) -> Vec<u8> {
+    let mut packet = vec![0, 0, 0x84, 0x00, 0, 0, 0, 2, 0, 0, 0, 0];
+    let ptr_name = labels("_http._tcp.local.");
+    let target_at = 12 + ptr_name.len() + 10;
+    packet.extend(record(&ptr_name, 12, ptr_ttl, &labels("Printer._http._tcp.local.")));
+
+    let pointer = [0xC0 | (target_at >> 8) as u8, target_at as u8];
+    let mut srv = vec![0, 0, 0, 0];
+    srv.extend_from_slice(&631u16.to_be_bytes());
+    srv.extend(labels("printer-host.local."));
+    packet.extend(record(&pointer, 33, 120, &srv));
+    packet
+}
+
+#[test]
+fn p0_parses_ptr_and_compressed_srv() {
+    let records = parse_records(&announcement(4500)).unwrap();
+    assert_eq!(
+        records,
+        vec![
+            Record::Ptr {
+                name: "_http._tcp.local.".to_string(),
+                target: "Printer._http._tcp.local.".to_string(),
+                ttl: 4500,
+            },
+            Record::Srv {
+                name: "Printer._http._tcp.local.".to_string(),
+                target: "printer-host.local.".to_string(),
+                port: 631,
+                ttl: 120,
+            },
+        ]
+    );
+}
+
+#[test]
+fn p1_queries_and_garbage_yield_nothing() {
+    let mut query = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+    query.extend(labels("_http._tcp.local."));
+    query.extend_from_slice(&[0, 12, 0, 1]);
+    assert!(parse_records(&query).unwrap().is_empty());
+
+    assert!(parse_records(&[0, 1, 2]).is_err());
+    // An answer whose name points at itself must not hang.
+    let looping = [0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0xC0, 12];
+    assert!(parse_records(&looping).is_err());
+}
+
+#[test]
+fn p2_table_tracks_instances_and_goodbyes() {
+    let mut table = Overheard::default();
+    table.record(parse_records(&announcement(4500)).unwrap(), Instant::now());
+    let heard = table.snapshot();
+    assert_eq!(heard.len(), 1);
+    assert_eq!(heard[0].service_type, "_http._tcp.local.");
+    assert_eq!(heard[0].hostname.as_deref(), Some("printer-host.local."));
+    assert_eq!(heard[0].port, Some(631));
+
+    table.record(parse_records(&announcement(0)).unwrap(), Instant::now());
+    assert!(table.snapshot().is_empty(), "a goodbye forgets the instance");
+}
@@ -0,0 +1,21 @@
+//! Tests for the platform check behind `MdnsManager.is_supported()`
+//! (`src/platform.rs`).
+//!
+//! Run with:
+//!   cargo test --test platform
+
+#[path = "../src/platform.rs"]
+mod platform;
+
+#[test]
+fn u0_marking_unsupported_yields_the_error() {
+    assert!(platform::is_supported(), "this host has sockets");
+    assert_eq!(platform::unsupported_error(), None);
+
+    platform::mark_unsupported("the web platform");
+    platform::mark_unsupported("something else");
+    assert!(!platform::is_supported());
+    let error = platform::unsupported_error().expect("an error once unsupported");
+    assert!(error.starts_with(platform::UNSUPPORTED_PLATFORM_ERROR), "{error}");
+    assert!(error.contains("the web platform"), "the first mark wins: {error}");
+}
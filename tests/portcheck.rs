@@ -0,0 +1,95 @@
+//! Tests for the mDNS port check behind `MdnsManager.get_startup_diagnostics()`.
+//!
+//! Uses a free ephemeral port rather than 5353, and drops the Avahi part of
+//! the report, so the result does not depend on the responders running on
+//! the test machine.
+//!
+//! Run with:
+//!   cargo test --test portcheck
+
+#[allow(dead_code)]
+#[path = "../src/portcheck.rs"]
+mod portcheck;
+
+use socket2::{Domain, Protocol, SockAddr, Socket, Type};
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+
+/// Probes `port`, ignoring whatever Avahi is installed here.
+fn probe(port: u16) -> portcheck::PortReport {
+    portcheck::PortReport { avahi: None, ..portcheck::probe(port) }
+}
+
+/// A UDP port nobody is using right now.
+fn free_port() -> u16 {
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .and_then(|socket| socket.local_addr())
+        .expect("bind an ephemeral port")
+        .port()
+}
+
+#[test]
+fn p0_free_port() {
+    let port = free_port();
+    let report = probe(port);
+    assert_eq!(report.port, port);
+    assert!(report.port_free && report.shareable, "{report:?}");
+    assert_eq!(report.warning(), None);
+}
+
+#[test]
+fn p1_exclusive_holder_is_reported() {
+    let holder = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).expect("bind");
+    let port = holder.local_addr().unwrap().port();
+    let report = probe(port);
+    assert!(!report.port_free);
+    assert!(!report.shareable, "a plain bind does not share: {report:?}");
+    assert!(!report.bind_error.is_empty());
+    let warning = report.warning().expect("a warning");
+    assert!(warning.contains(&format!("UDP port {port}")), "{warning}");
+}
+
+#[test]
+fn p2_sharing_holder_is_fine() {
+    let holder = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP)).unwrap();
+    holder.set_reuse_address(true).unwrap();
+    #[cfg(unix)]
+    holder.set_reuse_port(true).unwrap();
+    let port = free_port();
+    let addr: SockAddr = SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)).into();
+    holder.bind(&addr).expect("bind with reuse");
+
+    let report = probe(port);
+    assert!(!report.port_free);
+    assert!(report.shareable, "{report:?}");
+    assert_eq!(report.warning(), None);
+}
+
+#[test]
+fn p3_avahi_conf() {
+    let conf = "\
+[server]
+use-ipv4=yes
+# enable-reflector=yes
+[publish]
+disable-publishing=no
+disable-user-service-publishing = YES
+[reflector]
+enable-reflector=yes
+";
+    let mut avahi = portcheck::parse_avahi_conf(conf);
+    assert!(avahi.reflector && avahi.user_publishing_disabled, "{avahi:?}");
+    assert!(!avahi.publishing_disabled && !avahi.running);
+    assert_eq!(avahi.warning(), None, "Avahi is not running");
+
+    avahi.running = true;
+    let warning = avahi.warning().expect("a reflector warning");
+    assert!(warning.contains("enable-reflector=no"), "{warning}");
+    assert!(!warning.contains("disable-publishing"), "{warning}");
+
+    let report =
+        portcheck::PortReport { shareable: true, avahi: Some(avahi), ..Default::default() };
+    assert_eq!(report.warning(), Some(warning));
+
+    let quiet = portcheck::parse_avahi_conf("[reflector]\nenable-reflector=no\n");
+    assert_eq!(quiet, portcheck::AvahiReport::default());
+}
@@ -0,0 +1,597 @@
+//! Tests for the browser's discovered-service cache.
+//!
+//! `src/cache.rs` has no Godot dependency, so it is compiled straight into this
+//! test crate (the cdylib itself cannot be linked by the test harness).
+//!
+//! Run with:
+//!   cargo test --test service_cache
+
+#[allow(dead_code)]
+#[path = "../src/cache.rs"]
+mod cache;
+
+use cache::{
+    CachedService, Change, ExportedService, ServiceCache, ServiceDiff, ServiceIds, ID_REUSE_WINDOW,
+};
+use mdns_sd::{ScopedIp, ServiceInfo, TxtProperty};
+use std::time::{Duration, Instant};
+
+fn cached(service_type: &str, instance: &str) -> CachedService {
+    CachedService {
+        service_type: service_type.to_string(),
+        fullname: format!("{instance}.{service_type}"),
+        host: "test-host.local.".to_string(),
+        port: 7350,
+        addresses: vec!["192.168.1.42".parse().unwrap()],
+        zones: Default::default(),
+        txt: Vec::new(),
+        txt_flags: Vec::new(),
+    }
+}
+
+#[test]
+fn c0_count_by_type() {
+    let mut cache = ServiceCache::new();
+    cache.insert(cached("_a._tcp.local.", "one"));
+    cache.insert(cached("_a._tcp.local.", "two"));
+    cache.insert(cached("_b._tcp.local.", "three"));
+
+    assert_eq!(cache.len(), 3);
+    assert_eq!(cache.count_of_type("_a._tcp.local."), 2);
+    assert_eq!(cache.count_of_type("_A._TCP.local."), 2, "type match is case-insensitive");
+    assert_eq!(cache.count_of_type("_b._tcp.local."), 1);
+    assert_eq!(cache.count_of_type("_c._tcp.local."), 0);
+
+    let listed: Vec<&str> = cache.services().iter().map(|s| s.fullname.as_str()).collect();
+    assert_eq!(
+        listed,
+        ["one._a._tcp.local.", "three._b._tcp.local.", "two._a._tcp.local."],
+        "sorted by fullname"
+    );
+}
+
+#[test]
+fn c1_reinsert_replaces_and_remove_drops() {
+    let mut cache = ServiceCache::new();
+    assert!(cache.insert(cached("_a._tcp.local.", "one")).is_none());
+    assert!(cache.insert(cached("_a._tcp.local.", "one")).is_some());
+    assert_eq!(cache.len(), 1, "re-announcement must not add a second entry");
+
+    assert!(cache.remove("one._a._tcp.local.").is_some());
+    assert!(cache.remove("one._a._tcp.local.").is_none());
+    assert_eq!(cache.len(), 0);
+}
+
+#[test]
+fn c2_from_resolved_sorts_ipv4_first() {
+    let info = ServiceInfo::new(
+        "_mygame._tcp.local.",
+        "sorted",
+        "test-host.local.",
+        "fe80::1,192.168.1.42,::1,10.0.0.1",
+        7350,
+        &[("version", "1.0")] as &[(&str, &str)],
+    )
+    .expect("ServiceInfo::new failed");
+
+    let entry = CachedService::from_resolved(&info.as_resolved_service());
+    assert_eq!(entry.fullname, "sorted._mygame._tcp.local.");
+    assert_eq!(entry.service_type, "_mygame._tcp.local.");
+    assert_eq!(entry.port, 7350);
+    assert!(entry.addresses[0].is_ipv4());
+    assert!(entry.addresses[1].is_ipv4());
+    assert!(entry.addresses[2].is_ipv6());
+    assert!(entry.addresses[3].is_ipv6());
+    assert_eq!(entry.txt, vec![("version".to_string(), "1.0".to_string())]);
+}
+
+#[test]
+fn c3_evict_stale_drops_only_old_entries() {
+    let mut cache = ServiceCache::new();
+    cache.insert(cached("_a._tcp.local.", "b"));
+    cache.insert(cached("_a._tcp.local.", "a"));
+
+    let now = Instant::now();
+    assert!(cache.evict_stale(now, Duration::from_secs(60)).is_empty());
+    assert_eq!(cache.len(), 2);
+
+    let evicted = cache.evict_stale(now + Duration::from_secs(61), Duration::from_secs(60));
+    let names: Vec<&str> = evicted.iter().map(|s| s.fullname.as_str()).collect();
+    assert_eq!(names, ["a._a._tcp.local.", "b._a._tcp.local."], "sorted by fullname");
+    assert_eq!(cache.len(), 0);
+}
+
+#[test]
+fn c4_reordered_addresses_are_unchanged() {
+    let resolve = |addresses: &str| {
+        let info = ServiceInfo::new(
+            "_mygame._tcp.local.",
+            "reordered",
+            "test-host.local.",
+            addresses,
+            7350,
+            &[("version", "1.0")] as &[(&str, &str)],
+        )
+        .expect("ServiceInfo::new failed");
+        CachedService::from_resolved(&info.as_resolved_service())
+    };
+    let first = resolve("10.0.0.1,192.168.1.42,fe80::1,fe80::2");
+    let second = resolve("fe80::2,192.168.1.42,fe80::1,10.0.0.1");
+    assert_eq!(first.addresses, second.addresses, "same set must yield the same order");
+
+    let mut cache = ServiceCache::new();
+    assert_eq!(cache.upsert(first.clone()), Change::Discovered);
+    assert_eq!(cache.upsert(second), Change::Unchanged);
+
+    // Even if the Vec order differs, the comparison is set-based.
+    let mut reversed = first.clone();
+    reversed.addresses.reverse();
+    assert_eq!(cache.upsert(reversed), Change::Unchanged);
+
+    let mut moved = first;
+    moved.addresses.push("192.168.1.99".parse().unwrap());
+    assert_eq!(cache.upsert(moved), Change::Updated);
+}
+
+/// A link-local address as `mdns-sd` reports it: scoped to the interface the
+/// packet arrived on.
+fn link_local(ip: &str, if_name: &str, if_index: u32) -> ScopedIp {
+    let iface = if_addrs::Interface {
+        name: if_name.to_string(),
+        addr: if_addrs::IfAddr::V6(if_addrs::Ifv6Addr {
+            ip: ip.parse().unwrap(),
+            netmask: "ffff:ffff:ffff:ffff::".parse().unwrap(),
+            prefixlen: 64,
+            broadcast: None,
+        }),
+        index: Some(if_index),
+        oper_status: if_addrs::IfOperStatus::Up,
+        is_p2p: false,
+        #[cfg(windows)]
+        adapter_name: String::new(),
+    };
+    ScopedIp::from(&iface)
+}
+
+#[test]
+fn c5_ipv6_only_service_keeps_link_local_zone() {
+    let info = ServiceInfo::new(
+        "_iot._udp.local.",
+        "sensor",
+        "sensor.local.",
+        "",
+        5683,
+        &[] as &[(&str, &str)],
+    )
+    .expect("ServiceInfo::new failed");
+    let mut resolved = info.as_resolved_service();
+    resolved.addresses = [link_local("fe80::1", "en0", 4)].into_iter().collect();
+
+    let entry = CachedService::from_resolved(&resolved);
+    assert_eq!(entry.addresses.len(), 1, "IPv6-only services must not be dropped");
+    let zone = if cfg!(windows) { "4" } else { "en0" };
+    assert_eq!(entry.address_strings(), vec![format!("fe80::1%{zone}")]);
+
+    // With an IPv4 address present the strings stay plain.
+    let ipv4: std::net::IpAddr = "192.168.1.42".parse().unwrap();
+    resolved.addresses.insert(ScopedIp::from(ipv4));
+    let entry = CachedService::from_resolved(&resolved);
+    assert_eq!(entry.address_strings(), vec!["192.168.1.42", "fe80::1"]);
+    // ...but the scoped form keeps every zone.
+    assert_eq!(
+        entry.scoped_address_strings(),
+        vec!["192.168.1.42".to_string(), format!("fe80::1%{zone}")]
+    );
+}
+
+#[test]
+fn c6_diff_lists_only_changed_fields() {
+    let mut old = cached("_a._tcp.local.", "one");
+    old.txt = vec![
+        ("players".into(), "1".into()),
+        ("map".into(), "dust".into()),
+        ("mode".into(), "ffa".into()),
+    ];
+    let mut new = old.clone();
+    assert_eq!(old.diff(&new), ServiceDiff::default(), "same records");
+
+    new.txt = vec![
+        ("players".into(), "2".into()),
+        ("map".into(), "dust".into()),
+        ("ping".into(), "".into()),
+    ];
+    let diff = old.diff(&new);
+    assert_eq!(diff.host, None);
+    assert_eq!(diff.port, None);
+    assert_eq!(diff.addresses, None);
+    assert_eq!(
+        diff.txt,
+        [
+            ("players".to_string(), Some("2".to_string())),
+            ("ping".to_string(), Some(String::new())),
+            ("mode".to_string(), None),
+        ]
+    );
+
+    new.addresses = vec!["192.168.1.43".parse().unwrap(), "192.168.1.42".parse().unwrap()];
+    new.port = 7351;
+    let diff = old.diff(&new);
+    assert_eq!(diff.port, Some(7351));
+    assert_eq!(
+        diff.addresses.as_deref(),
+        Some(&["192.168.1.43".to_string(), "192.168.1.42".to_string()][..]),
+        "the whole new address array"
+    );
+
+    let mut reordered = old.clone();
+    reordered.addresses.reverse();
+    assert_eq!(old.diff(&reordered).addresses, None);
+}
+
+#[test]
+fn c7_description_reads_the_desc_entry() {
+    let mut service = cached("_a._tcp.local.", "one");
+    assert_eq!(service.description(), "");
+
+    service.txt = vec![
+        ("map".into(), "dust".into()),
+        ("DESC".into(), "Capture the flag, no mods".into()),
+        ("desc".into(), "ignored".into()),
+    ];
+    assert_eq!(
+        service.description(),
+        "Capture the flag, no mods",
+        "key matched case-insensitively, first entry wins"
+    );
+    assert_eq!(cache::DESCRIPTION_KEY.len() + 1 + cache::MAX_DESCRIPTION_LEN, 255);
+}
+
+#[test]
+fn c8_rotated_privacy_address_is_only_churn() {
+    let mut old = cached("_a._tcp.local.", "one");
+    old.addresses = vec![
+        "192.168.1.42".parse().unwrap(),
+        "2001:db8::211:22ff:fe33:4455".parse().unwrap(),
+        "2001:db8::71a3:c9d2:e4f5:617".parse().unwrap(),
+    ];
+    let mut rotated = old.clone();
+    rotated.addresses[2] = "2001:db8::9e0c:1f2a:b3d4:5e6f".parse().unwrap();
+    assert!(old.only_ipv6_rotated(&rotated));
+    assert!(!old.only_ipv6_rotated(&old.clone()), "nothing changed");
+
+    let mut other_prefix = old.clone();
+    other_prefix.addresses[2] = "2001:db8:0:1::1".parse().unwrap();
+    assert!(!old.only_ipv6_rotated(&other_prefix), "moved to another /64");
+
+    let mut dropped = old.clone();
+    dropped.addresses.pop();
+    assert!(!old.only_ipv6_rotated(&dropped), "an address went away without replacement");
+
+    let mut ipv4_moved = rotated.clone();
+    ipv4_moved.addresses[0] = "192.168.1.43".parse().unwrap();
+    assert!(!old.only_ipv6_rotated(&ipv4_moved));
+
+    let mut txt_changed = rotated;
+    txt_changed.txt = vec![("players".into(), "2".into())];
+    assert!(!old.only_ipv6_rotated(&txt_changed));
+}
+
+#[test]
+fn c9_identical_caches_list_in_identical_order() {
+    // Each `HashMap` gets its own random hasher, so two caches holding the
+    // same services iterate differently; everything the browser emits in
+    // bulk must be sorted regardless.
+    let names = ["Zeta", "alpha", "Beta", "gamma", "Delta", "epsilon", "Eta", "theta"];
+    let build = |order: &mut dyn Iterator<Item = &&str>| {
+        let mut cache = ServiceCache::new();
+        for name in order {
+            cache.insert(cached("_a._tcp.local.", name));
+        }
+        cache
+    };
+    let mut first = build(&mut names.iter());
+    let mut second = build(&mut names.iter().rev());
+
+    let listed = |cache: &ServiceCache| -> Vec<String> {
+        cache.services().iter().map(|s| s.fullname.clone()).collect()
+    };
+    let order = listed(&first);
+    assert_eq!(order, listed(&second));
+    let mut sorted = order.clone();
+    sorted.sort();
+    assert_eq!(order, sorted, "sorted by fullname");
+    assert_eq!(
+        first.fullnames_of_type("_a._tcp.local."),
+        second.fullnames_of_type("_a._tcp.local.")
+    );
+
+    let later = Instant::now() + Duration::from_secs(60);
+    let evicted = |cache: &mut ServiceCache| -> Vec<String> {
+        cache
+            .evict_stale(later, Duration::from_secs(1))
+            .into_iter()
+            .map(|s| s.fullname)
+            .collect()
+    };
+    assert_eq!(evicted(&mut first), order);
+    assert_eq!(evicted(&mut second), order);
+
+    let mut first = build(&mut names.iter());
+    let mut second = build(&mut names.iter().rev());
+    let taken = |cache: &mut ServiceCache| -> Vec<String> {
+        cache.take_all().into_iter().map(|s| s.fullname).collect()
+    };
+    assert_eq!(taken(&mut first), taken(&mut second));
+}
+
+#[test]
+fn c10_contains_ignores_case_and_trailing_dot() {
+    let mut cache = ServiceCache::new();
+    cache.insert(cached("_game._udp.local.", "Host"));
+
+    assert!(cache.contains("Host._game._udp.local."));
+    assert!(cache.contains("host._GAME._udp.local"), "names are case-insensitive");
+    assert!(!cache.contains("Other._game._udp.local."));
+    assert!(!cache.contains("Host._game._tcp.local."));
+
+    cache.remove("Host._game._udp.local.");
+    assert!(!cache.contains("Host._game._udp.local."));
+}
+
+#[test]
+fn c11_valueless_key_is_kept_apart_from_empty_value() {
+    let resolve = |properties: Vec<TxtProperty>| {
+        let info = ServiceInfo::new(
+            "_mygame._tcp.local.",
+            "flags",
+            "test-host.local.",
+            "192.168.1.42",
+            7350,
+            properties,
+        )
+        .expect("ServiceInfo::new failed");
+        CachedService::from_resolved(&info.as_resolved_service())
+    };
+    let flagged = resolve(vec![TxtProperty::from("private"), ("motd", "").into()]);
+    assert_eq!(flagged.txt, [("private".into(), "".into()), ("motd".into(), "".into())]);
+    assert_eq!(flagged.txt_flags, ["private"]);
+    assert!(flagged.is_txt_flag("PRIVATE") && !flagged.is_txt_flag("motd"));
+
+    // `private=` is a different record from `private`.
+    let valued = resolve(vec![("private", "").into(), ("motd", "").into()]);
+    assert!(valued.txt_flags.is_empty());
+    assert!(!flagged.same_records(&valued));
+    let diff = flagged.diff(&valued);
+    assert_eq!(diff.txt, [("private".to_string(), Some(String::new()))]);
+
+    let mut cache = ServiceCache::new();
+    assert_eq!(cache.upsert(flagged.clone()), Change::Discovered);
+    assert_eq!(cache.upsert(flagged), Change::Unchanged);
+    assert_eq!(cache.upsert(valued), Change::Updated);
+}
+
+#[test]
+fn c12_retain_ports_drops_services_outside_the_range() {
+    let mut cache = ServiceCache::new();
+    for (instance, port) in [("low", 6999), ("first", 7000), ("last", 7999), ("high", 8000)] {
+        let mut service = cached("_game._udp.local.", instance);
+        service.port = port;
+        cache.insert(service);
+    }
+
+    let removed: Vec<String> =
+        cache.retain_ports(7000, 7999).into_iter().map(|s| s.fullname).collect();
+    assert_eq!(removed, ["high._game._udp.local.", "low._game._udp.local."]);
+    assert_eq!(cache.len(), 2, "both ends are inclusive");
+    assert!(cache.retain_ports(7000, 7999).is_empty());
+}
+
+#[test]
+fn c13_connect_string_prefers_ipv4_and_brackets_ipv6() {
+    let mut service = cached("_game._udp.local.", "Host");
+    service.addresses.push("fe80::1".parse().unwrap());
+    assert_eq!(service.connect_string(), "192.168.1.42:7350");
+
+    service.addresses.remove(0);
+    assert_eq!(service.connect_string(), "[fe80::1]:7350");
+
+    service.addresses.clear();
+    assert_eq!(service.connect_string(), "test-host.local:7350", "falls back to the host");
+
+    let mut cache = ServiceCache::new();
+    cache.insert(service);
+    assert!(cache.find("host._game._udp.local").is_some_and(|s| s.port == 7350));
+}
+
+#[test]
+fn c14_txt_byte_size_counts_rfc_6763_framing() {
+    let mut service = cached("_game._udp.local.", "Host");
+    assert_eq!(service.txt_byte_size(), 1, "an empty TXT is one empty string");
+
+    service.txt = vec![("players".into(), "12".into()), ("private".into(), "".into())];
+    // 1 + "players=12", then 1 + "private=".
+    assert_eq!(service.txt_byte_size(), 11 + 9);
+    service.txt_flags = vec!["private".into()];
+    assert_eq!(service.txt_byte_size(), 11 + 8, "a valueless key has no '='");
+}
+
+#[test]
+fn c15_service_ids_are_stable_and_reused_within_the_window() {
+    let start = Instant::now();
+    let mut ids = ServiceIds::new();
+    assert_eq!(ids.assign("A._game._udp.local.", start), 1);
+    assert_eq!(ids.assign("B._game._udp.local.", start), 2);
+    assert_eq!(ids.assign("a._game._udp.local", start), 1, "names are case-insensitive");
+
+    assert_eq!(ids.release("A._game._udp.local.", start), Some(1));
+    assert_eq!(ids.get("A._game._udp.local."), Some(1), "readable while it may return");
+    let soon = start + ID_REUSE_WINDOW;
+    assert_eq!(ids.assign("A._game._udp.local.", soon), 1, "reappeared within the window");
+
+    ids.release("B._game._udp.local.", start);
+    let late = start + ID_REUSE_WINDOW + Duration::from_secs(1);
+    ids.prune(late);
+    assert_eq!(ids.get("B._game._udp.local."), None);
+    assert_eq!(ids.assign("B._game._udp.local.", late), 3, "retired IDs are not reused");
+    assert_eq!(ids.release("C._game._udp.local.", late), None);
+
+    ids.clear();
+    assert_eq!(ids.assign("C._game._udp.local.", late), 1);
+}
+
+#[test]
+fn c16_exported_services_are_checked_and_restored_stale() {
+    let exported = ExportedService {
+        service_type: "_game._udp.local.".into(),
+        fullname: "Host._game._udp.local.".into(),
+        host: "host.local.".into(),
+        port: 7350,
+        addresses: vec!["fe80::1%en0".into(), "192.168.1.20".into()],
+        txt: vec![("players".into(), "3".into()), ("private".into(), "".into())],
+        txt_flags: vec!["private".into()],
+    };
+    let service = CachedService::from_exported(exported.clone()).expect("valid entry");
+    assert_eq!(service.scoped_address_strings(), ["192.168.1.20", "fe80::1%en0"]);
+
+    let invalid = [
+        ExportedService { port: 70000, ..exported.clone() },
+        ExportedService { fullname: "Host._other._udp.local.".into(), ..exported.clone() },
+        ExportedService { addresses: vec!["not-an-ip".into()], ..exported.clone() },
+        ExportedService { addresses: vec!["192.168.1.20%en0".into()], ..exported.clone() },
+        ExportedService { txt_flags: vec!["missing".into()], ..exported.clone() },
+        ExportedService { service_type: "game".into(), ..exported.clone() },
+    ];
+    for entry in invalid {
+        assert!(CachedService::from_exported(entry.clone()).is_err(), "{entry:?}");
+    }
+
+    let mut cache = ServiceCache::new();
+    let long_ago = Instant::now() - Duration::from_secs(60);
+    assert!(cache.restore(service.clone(), long_ago, true));
+    assert!(cache.is_stale(&service.fullname));
+    assert!(!cache.restore(service.clone(), Instant::now(), false), "already listed");
+    assert_eq!(cache.evict_stale(Instant::now(), Duration::from_secs(30)).len(), 1);
+
+    cache.restore(service.clone(), long_ago, true);
+    assert_eq!(cache.upsert(service.clone()), Change::Unchanged);
+    assert!(!cache.is_stale(&service.fullname), "seen on the network again");
+}
+
+#[test]
+fn c17_status_entry_marks_a_service_unavailable() {
+    let mut service = cached("_a._tcp.local.", "one");
+    assert!(service.is_available(), "no status entry");
+
+    service.txt = vec![("status".into(), cache::STATUS_MAINTENANCE.into())];
+    assert!(!service.is_available());
+    service.txt = vec![("Status".into(), "UP".into())];
+    assert!(service.is_available(), "key and value matched case-insensitively");
+    service.txt = vec![("status".into(), "away".into())];
+    assert!(!service.is_available(), "any other value means unavailable");
+}
+
+#[test]
+fn c18_one_representative_per_host() {
+    const TY: &str = "_a._tcp.local.";
+    let on = |instance: &str, host: &str, players: &str| {
+        let mut service = cached(TY, instance);
+        service.host = host.to_string();
+        service.txt = vec![("players".into(), players.into())];
+        service
+    };
+    let mut cache = ServiceCache::new();
+    cache.insert(on("room-b", "box.local.", "2"));
+    cache.insert(on("room-a", "BOX.local", "5"));
+    cache.insert(on("other", "pc.local.", "1"));
+    cache.insert(on("nameless", "", "9"));
+
+    let group = cache::host_group(cache.get(&format!("room-a.{TY}")).unwrap());
+    assert_eq!(group, "box.local", "case and trailing dot do not matter");
+    let representative = |cache: &ServiceCache, prefer| {
+        cache.host_representative(&group, prefer).map(|s| s.fullname.clone())
+    };
+    assert_eq!(representative(&cache, None), Some(format!("room-b.{TY}")), "first seen");
+    assert_eq!(representative(&cache, Some("players")), Some(format!("room-a.{TY}")));
+    assert_eq!(representative(&cache, Some("missing")), Some(format!("room-b.{TY}")));
+
+    // An update keeps the arrival order; the preference follows the value.
+    cache.insert(on("room-b", "box.local.", "7"));
+    assert_eq!(representative(&cache, None), Some(format!("room-b.{TY}")));
+    assert_eq!(representative(&cache, Some("players")), Some(format!("room-b.{TY}")));
+
+    assert_eq!(cache.host_siblings(&format!("room-b.{TY}")), [format!("room-a.{TY}")]);
+    assert!(cache.host_siblings(&format!("other.{TY}")).is_empty());
+    assert!(cache.host_siblings(&format!("nameless.{TY}")).is_empty(), "no host, no group");
+
+    cache.remove(&format!("room-b.{TY}"));
+    assert_eq!(representative(&cache, None), Some(format!("room-a.{TY}")));
+    cache.remove(&format!("room-a.{TY}"));
+    assert_eq!(representative(&cache, None), None);
+}
+
+#[test]
+fn c19_scoped_connect_string_overrides_the_zone() {
+    let mut service = cached("_game._udp.local.", "Host");
+    assert_eq!(service.scoped_connect_string("en0"), None, "no IPv6 link-local address");
+
+    service.addresses.push("2001:db8::1".parse().unwrap());
+    assert_eq!(service.scoped_connect_string("en0"), None, "global IPv6 does not count");
+
+    let link_local = "fe80::1".parse().unwrap();
+    service.addresses.push(link_local);
+    assert_eq!(service.scoped_connect_string("").as_deref(), Some("[fe80::1]:7350"));
+    service.zones.insert(link_local, "en0".to_string());
+    assert_eq!(service.scoped_connect_string("").as_deref(), Some("[fe80::1%en0]:7350"));
+    assert_eq!(service.scoped_connect_string("12").as_deref(), Some("[fe80::1%12]:7350"));
+    assert_eq!(service.scoped_connect_string("%eth0").as_deref(), Some("[fe80::1%eth0]:7350"));
+}
+
+/// The browser reports a removal from `mdns-sd` only for a service still
+/// listed, so one evicted or cleared first is reported once, not twice.
+#[test]
+fn c20_evicted_service_is_not_removed_again() {
+    let mut cache = ServiceCache::new();
+    cache.insert(cached("_a._tcp.local.", "a"));
+    cache.insert(cached("_a._tcp.local.", "b"));
+
+    let later = Instant::now() + Duration::from_secs(61);
+    let mut removals = cache.evict_stale(later, Duration::from_secs(60)).len();
+    assert_eq!(removals, 2);
+    // The TTL runs out in `mdns-sd`, which knows nothing of the eviction.
+    if cache.remove("a._a._tcp.local.").is_some() {
+        removals += 1;
+    }
+    assert_eq!(removals, 2, "one service_removed per service");
+
+    cache.insert(cached("_a._tcp.local.", "a"));
+    assert_eq!(cache.take_all().len(), 1);
+    assert!(cache.remove("a._a._tcp.local.").is_none(), "cleared, so not listed");
+}
+
+#[test]
+fn c21_services_are_confirmed_before_eviction() {
+    let mut cache = ServiceCache::new();
+    cache.insert(cached("_a._tcp.local.", "a"));
+    cache.insert(cached("_a._tcp.local.", "b"));
+    let old = cached("_a._tcp.local.", "restored");
+    let t0 = Instant::now();
+    cache.restore(old, t0 - Duration::from_secs(55), true);
+    let (max_age, lead) = (Duration::from_secs(60), Duration::from_secs(10));
+    let at = |secs: u64| t0 + Duration::from_secs(secs);
+
+    assert!(cache.due_for_confirmation(at(49), max_age, lead).is_empty());
+    let due = cache.due_for_confirmation(at(51), max_age, lead);
+    assert_eq!(due, ["a._a._tcp.local.", "b._a._tcp.local."], "restored ones are not asked");
+    cache.start_confirmation(&due, at(61));
+    assert!(cache.due_for_confirmation(at(52), max_age, lead).is_empty(), "asked once");
+
+    let evicted: Vec<String> =
+        cache.evict_stale(at(61), max_age).into_iter().map(|s| s.fullname).collect();
+    assert_eq!(evicted, ["restored._a._tcp.local."], "kept while being confirmed");
+
+    // `b` stays silent and `mdns-sd` removes it; `a` answers.
+    assert!(cache.remove("b._a._tcp.local.").is_some());
+    cache.finish_confirmations(at(61));
+    assert!(cache.evict_stale(at(120), max_age).is_empty(), "the window started over");
+    assert_eq!(cache.evict_stale(at(122), max_age).len(), 1);
+}
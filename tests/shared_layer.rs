@@ -0,0 +1,125 @@
+//! Tests for the bookkeeping of the shared daemon layer (`src/daemon.rs`)
+//! behind `MdnsManager.get_active_browse_types()` / `get_active_registrations()`.
+//!
+//! The layer is compiled into this test crate together with the modules it
+//! uses, and drives a real `ServiceDaemon`; no packets need to arrive.  The
+//! shared daemon is process-global, so everything runs in one test.
+//!
+//! Run with:
+//!   cargo test --test shared_layer
+
+#![allow(dead_code)]
+
+#[path = "../src/daemon.rs"]
+mod daemon;
+#[path = "../src/devloop.rs"]
+mod devloop;
+#[path = "../src/fanout.rs"]
+mod fanout;
+#[path = "../src/history.rs"]
+mod history;
+#[path = "../src/interfaces.rs"]
+mod interfaces;
+#[path = "../src/local_network.rs"]
+mod local_network;
+#[path = "../src/netwatch.rs"]
+mod netwatch;
+#[path = "../src/passive.rs"]
+mod passive;
+#[path = "../src/platform.rs"]
+mod platform;
+#[path = "../src/portcheck.rs"]
+mod portcheck;
+#[path = "../src/sync.rs"]
+mod sync;
+
+use mdns_sd::ServiceInfo;
+
+const A: &str = "_slayer-a._tcp.local.";
+const B: &str = "_slayer-b._tcp.local.";
+const C: &str = "_slayer-c._tcp.local.";
+
+fn info(instance: &str) -> ServiceInfo {
+    ServiceInfo::new(A, instance, "slayer.local.", "127.0.0.1", 7350, &[] as &[(&str, &str)])
+        .expect("ServiceInfo::new failed")
+}
+
+#[test]
+fn s0_active_types_and_registrations_follow_start_stop_and_free() {
+    let shared = match daemon::shared_daemon() {
+        Ok(shared) => shared,
+        Err(e) => {
+            println!("SKIP: no mDNS daemon in this environment: {e}");
+            return;
+        }
+    };
+    assert!(daemon::active_browse_types().is_empty());
+
+    // Two browsers of A share one browse; one of B.
+    let first = daemon::subscribe(A).expect("subscribe");
+    let second = daemon::subscribe(A).expect("subscribe");
+    let other = daemon::subscribe(B).expect("subscribe");
+    assert_eq!(daemon::active_browse_types(), [A, B]);
+    assert_eq!(daemon::active_browse_count(), 3);
+
+    // A browser freed without stop_browsing() drops its subscription.
+    drop(first);
+    assert_eq!(daemon::active_browse_types(), [A, B], "A is still browsed");
+    drop(second);
+    assert_eq!(daemon::active_browse_types(), [B]);
+
+    // A private (interface-pinned) browse of A counts too.
+    let private = daemon::private_daemon(Default::default()).expect("private daemon");
+    let _receiver = daemon::browse_private(&private, A).expect("private browse");
+    assert_eq!(daemon::active_browse_types(), [A, B]);
+    daemon::stop_browse_private(&private, A);
+    assert_eq!(daemon::active_browse_types(), [B]);
+
+    // The type cap counts distinct types, shared and private alike.
+    assert_eq!(daemon::max_browse_types(), daemon::DEFAULT_MAX_BROWSE_TYPES);
+    daemon::set_max_browse_types(2);
+    let capped = daemon::subscribe(A).expect("second type");
+    let joined = daemon::subscribe(A).expect("joining a browsed type is free");
+    let refused = daemon::subscribe(C).err().expect("third type refused");
+    assert!(refused.starts_with("too_many_types"), "{refused}");
+    let refused = daemon::browse_private(&private, C).expect_err("third type refused");
+    assert!(refused.starts_with("too_many_types"), "{refused}");
+    assert_eq!(daemon::active_browse_types(), [A, B]);
+    daemon::set_max_browse_types(0);
+    let third = daemon::subscribe(C).expect("no cap");
+    assert_eq!(daemon::active_browse_types(), [A, B, C]);
+    daemon::set_max_browse_types(daemon::DEFAULT_MAX_BROWSE_TYPES);
+    drop((capped, joined, third));
+    assert_eq!(daemon::active_browse_types(), [B]);
+    let _ = private.shutdown();
+
+    daemon::register(&shared, info("one")).expect("register");
+    daemon::register(&shared, info("two")).expect("register");
+    let one = format!("one.{A}");
+    let two = format!("two.{A}");
+    assert_eq!(daemon::active_registrations(), [one.clone(), two.clone()]);
+    daemon::unregister(&shared, &one);
+    assert_eq!(daemon::active_registrations(), [two.as_str()]);
+
+    // A registration on a node's own daemon (set_daemon_port) is listed, and
+    // survives a reset of the shared daemon.
+    let own = daemon::private_daemon_on(Default::default(), Some(15356)).expect("own daemon");
+    daemon::register_private(&own, info("own")).expect("register");
+    let own_name = format!("own.{A}");
+    assert_eq!(daemon::active_registrations(), [own_name.as_str(), two.as_str()]);
+
+    // A reset drops everything else; nodes re-establish on their next frame.
+    daemon::reset().expect("reset");
+    assert!(daemon::active_browse_types().is_empty());
+    assert_eq!(daemon::active_registrations(), [own_name.as_str()]);
+    // Once its shutdown is confirmed the port can be taken again at once.
+    daemon::shutdown_private(&own, Some(&own_name)).expect("own daemon confirmed shutdown");
+    assert!(daemon::active_registrations().is_empty());
+    let again = daemon::private_daemon_on(Default::default(), Some(15356)).expect("same port");
+    daemon::shutdown_private(&again, None).expect("second daemon confirmed shutdown");
+    assert!(daemon::node_daemon_port(70000).is_err());
+    assert_eq!(daemon::node_daemon_port(0), Ok(None));
+    drop(other);
+    assert!(daemon::active_browse_types().is_empty());
+    assert_eq!(daemon::active_browse_count(), 0);
+}
@@ -0,0 +1,57 @@
+//! Tests for the poison recovery behind the shared daemon mutex.
+//!
+//! Run with:
+//!   cargo test --test sync
+
+#[path = "../src/sync.rs"]
+mod sync;
+
+use std::sync::{Arc, Mutex};
+use sync::lock_or_recover;
+
+/// Stand-in for the shared state: a daemon slot plus some configuration.
+#[derive(Default)]
+struct State {
+    daemon: Option<u32>,
+    port: u16,
+}
+
+fn poison(mutex: &Arc<Mutex<State>>) {
+    let mutex = Arc::clone(mutex);
+    let _ = std::thread::spawn(move || {
+        let mut state = mutex.lock().unwrap();
+        state.daemon = Some(1);
+        panic!("simulated panic while holding the lock");
+    })
+    .join();
+}
+
+#[test]
+fn s0_healthy_lock_skips_recovery() {
+    let mutex = Mutex::new(State::default());
+    let (guard, recovered) = lock_or_recover(&mutex, |_| panic!("must not run"));
+    assert!(!recovered);
+    assert!(guard.daemon.is_none());
+}
+
+#[test]
+fn s1_poisoned_lock_is_recovered_once() {
+    let mutex = Arc::new(Mutex::new(State {
+        daemon: None,
+        port: 5354,
+    }));
+    poison(&mutex);
+    assert!(mutex.is_poisoned());
+
+    let (mut guard, recovered) = lock_or_recover(&mutex, |state| state.daemon = None);
+    assert!(recovered);
+    assert!(guard.daemon.is_none(), "the half-updated slot must be cleared");
+    assert_eq!(guard.port, 5354, "untouched fields survive");
+    guard.daemon = Some(2);
+    drop(guard);
+
+    assert!(!mutex.is_poisoned());
+    let (guard, recovered) = lock_or_recover(&mutex, |_| panic!("must not run again"));
+    assert!(!recovered);
+    assert_eq!(guard.daemon, Some(2));
+}
@@ -0,0 +1,106 @@
+//! Stress test for browsing from a second thread, as a GDScript `Thread`
+//! calling `MdnsBrowser.browse()` / `stop_browsing()` does, while the main
+//! loop drains the shared browse every frame.
+//!
+//! The nodes themselves need an engine; this drives the shared daemon layer
+//! (`src/daemon.rs`) they share, which is where the threads meet.  Runs in a
+//! test binary of its own because the shared daemon is process-global.
+//!
+//! Run with:
+//!   cargo test --test thread_safety
+
+#![allow(dead_code)]
+
+#[path = "../src/daemon.rs"]
+mod daemon;
+#[path = "../src/devloop.rs"]
+mod devloop;
+#[path = "../src/fanout.rs"]
+mod fanout;
+#[path = "../src/history.rs"]
+mod history;
+#[path = "../src/interfaces.rs"]
+mod interfaces;
+#[path = "../src/local_network.rs"]
+mod local_network;
+#[path = "../src/netwatch.rs"]
+mod netwatch;
+#[path = "../src/passive.rs"]
+mod passive;
+#[path = "../src/platform.rs"]
+mod platform;
+#[path = "../src/portcheck.rs"]
+mod portcheck;
+#[path = "../src/sync.rs"]
+mod sync;
+
+use mdns_sd::ServiceInfo;
+use std::time::{Duration, Instant};
+
+const TY: &str = "_threads._tcp.local.";
+
+/// Browse/stop cycles of the hammering thread.
+const CYCLES: usize = 2000;
+
+/// Pause between cycles: `mdns-sd`'s command channel is bounded, and a
+/// thread that never yields only measures how fast it fills up.
+const CYCLE_PAUSE: Duration = Duration::from_micros(200);
+
+#[test]
+fn r0_browse_and_stop_from_a_thread_while_the_main_loop_drains() {
+    let shared = match daemon::shared_daemon() {
+        Ok(shared) => shared,
+        Err(e) => {
+            println!("SKIP: no mDNS daemon in this environment: {e}");
+            return;
+        }
+    };
+    // The main loop's browser, kept for the whole test.
+    let main_browse = daemon::subscribe(TY).expect("subscribe");
+
+    let worker = {
+        let shared = shared.clone();
+        std::thread::spawn(move || {
+            for cycle in 0..CYCLES {
+                let browse = daemon::subscribe(TY).expect("subscribe from the worker");
+                browse.take_events();
+                if cycle % 10 == 0 {
+                    // Registration churn so the drained browse has events.
+                    let instance = format!("worker-{cycle}");
+                    let info = ServiceInfo::new(
+                        TY,
+                        &instance,
+                        "threads.local.",
+                        "127.0.0.1",
+                        7350,
+                        &[] as &[(&str, &str)],
+                    )
+                    .expect("ServiceInfo::new failed");
+                    daemon::register(&shared, info).expect("register");
+                    daemon::unregister(&shared, &format!("{instance}.{TY}"));
+                }
+                drop(browse);
+                std::thread::sleep(CYCLE_PAUSE);
+            }
+        })
+    };
+
+    // The main loop: drain every "frame" until the worker is finished.
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while !worker.is_finished() {
+        assert!(Instant::now() < deadline, "the worker did not finish; deadlock?");
+        main_browse.take_events();
+        assert!(daemon::active_browse_count() >= 1, "the main browse was cut off");
+        assert_eq!(daemon::active_browse_types(), [TY]);
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    worker.join().expect("the worker panicked");
+
+    // Every worker browse was released; only the main one is left.
+    assert_eq!(daemon::active_browse_count(), 1);
+    assert!(daemon::active_registrations().is_empty());
+    main_browse.take_events();
+    drop(main_browse);
+    assert!(daemon::active_browse_types().is_empty());
+    assert_eq!(daemon::active_browse_count(), 0);
+}
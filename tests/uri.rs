@@ -0,0 +1,77 @@
+//! Tests for the connect targets and URIs of `MdnsUtil.build_uri()` and
+//! `MdnsUtil.extract_host_and_port()`.
+//!
+//! Run with:
+//!   cargo test --test uri
+
+#[path = "../src/uri.rs"]
+mod uri;
+
+use uri::{Payload, Target};
+
+fn payload(addresses: &[&str]) -> Payload {
+    Payload {
+        best_address: None,
+        addresses: addresses.iter().map(|a| a.to_string()).collect(),
+        hostname: "marks-pc.local.".to_string(),
+        port: 7350,
+    }
+}
+
+fn target(host: &str) -> Option<Target> {
+    Some(Target { host: host.to_string(), port: 7350 })
+}
+
+#[test]
+fn k0_ipv4_is_used_as_is() {
+    let service = payload(&["192.168.1.42", "fe80::1"]);
+    assert_eq!(service.target(false), target("192.168.1.42"));
+    let target = service.target(false).unwrap();
+    assert_eq!(target.uri("ws"), "ws://192.168.1.42:7350");
+    assert_eq!(target.uri("wss://"), "wss://192.168.1.42:7350", "no doubled separator");
+    assert_eq!(target.uri(""), "192.168.1.42:7350");
+}
+
+#[test]
+fn k1_global_ipv6_is_bracketed() {
+    let target = payload(&["2001:db8::42"]).target(false).unwrap();
+    assert_eq!(target.host, "2001:db8::42", "extract_host_and_port() leaves it bare");
+    assert_eq!(target.uri("ws"), "ws://[2001:db8::42]:7350");
+}
+
+#[test]
+fn k2_scoped_link_local_ipv6_keeps_its_zone() {
+    let target = payload(&["fe80::1%en0"]).target(false).unwrap();
+    assert_eq!(target.host, "fe80::1%en0");
+    assert_eq!(target.uri("ws"), "ws://[fe80::1%en0]:7350");
+    assert_eq!(target.authority(), "[fe80::1%en0]:7350");
+}
+
+#[test]
+fn k3_best_address_and_hostname_preference() {
+    let mut service = payload(&["192.168.1.42"]);
+    service.best_address = Some("10.0.0.7".to_string());
+    assert_eq!(service.target(false), target("10.0.0.7"));
+    assert_eq!(service.target(true), target("marks-pc.local"), "trailing dot dropped");
+
+    service.best_address = Some(String::new());
+    assert_eq!(service.target(false), target("192.168.1.42"), "an empty one is skipped");
+
+    // Without addresses the host name is all there is, and the other way round.
+    assert_eq!(payload(&[]).target(false), target("marks-pc.local"));
+    let mut nameless = payload(&["192.168.1.42"]);
+    nameless.hostname.clear();
+    assert_eq!(nameless.target(true), target("192.168.1.42"));
+}
+
+#[test]
+fn k4_nothing_to_connect_to() {
+    let mut nameless = payload(&[]);
+    nameless.hostname.clear();
+    assert_eq!(nameless.target(false), None);
+
+    for port in [0, -1, 65536] {
+        let service = Payload { port, ..payload(&["192.168.1.42"]) };
+        assert_eq!(service.target(false), None, "port {port}");
+    }
+}